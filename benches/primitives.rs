@@ -0,0 +1,118 @@
+//! Micro-benchmarks of the primitives the search spends its time in, on boards from the early, middle and late game.
+//!
+//! Run with `cargo bench`, or `cargo bench -- eval` for the benchmarks whose name contains `eval`.
+
+use std::hint::black_box;
+
+use ai_2048::board::{self, Board};
+use ai_2048::eval;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+
+/// Boards at different stages of a game, with their names
+fn boards() -> [(&'static str, Board); 3] {
+    [
+        (
+            "early",
+            Board {
+                cells: [[0, 0, 0, 1], [0, 0, 1, 2], [0, 0, 0, 1], [0, 0, 0, 0]],
+            },
+        ),
+        (
+            "middle",
+            Board {
+                cells: [[7, 6, 4, 2], [3, 4, 2, 1], [1, 2, 0, 0], [0, 1, 0, 0]],
+            },
+        ),
+        (
+            "late",
+            Board {
+                cells: [[11, 10, 8, 7], [4, 6, 7, 5], [3, 2, 4, 1], [1, 2, 0, 1]],
+            },
+        ),
+    ]
+}
+
+fn bench_push_left(c: &mut Criterion) {
+    let rows: Vec<[u8; board::N]> = boards().iter().flat_map(|(_, board)| board.cells).collect();
+    let mut group = c.benchmark_group("push_left");
+    group.throughput(Throughput::Elements(rows.len() as u64));
+    group.bench_function("rows", |b| {
+        b.iter(|| {
+            for row in &rows {
+                let mut row = *row;
+                black_box(board::push_left(black_box(&mut row)));
+            }
+        })
+    });
+    group.finish();
+}
+
+fn bench_apply(c: &mut Criterion) {
+    let mut group = c.benchmark_group("apply");
+    group.throughput(Throughput::Elements(board::ALL_ACTIONS.len() as u64));
+    for (name, board) in boards() {
+        group.bench_with_input(BenchmarkId::from_parameter(name), &board, |b, board| {
+            b.iter(|| {
+                for action in board::ALL_ACTIONS {
+                    black_box(black_box(board).apply(action));
+                }
+            })
+        });
+    }
+    group.finish();
+}
+
+/// Same as `bench_apply` with the scalar implementation, to compare with the feature `simd`
+fn bench_apply_scalar(c: &mut Criterion) {
+    let mut group = c.benchmark_group("apply_scalar");
+    group.throughput(Throughput::Elements(board::ALL_ACTIONS.len() as u64));
+    for (name, board) in boards() {
+        group.bench_with_input(BenchmarkId::from_parameter(name), &board, |b, board| {
+            b.iter(|| {
+                for action in board::ALL_ACTIONS {
+                    black_box(black_box(board).apply_scored_scalar(action));
+                }
+            })
+        });
+    }
+    group.finish();
+}
+
+fn bench_random_successors(c: &mut Criterion) {
+    let mut group = c.benchmark_group("random_successors");
+    for (name, board) in boards() {
+        group.throughput(Throughput::Elements(
+            board.random_successors().count() as u64
+        ));
+        group.bench_with_input(BenchmarkId::from_parameter(name), &board, |b, board| {
+            b.iter(|| {
+                for successor in black_box(board).random_successors() {
+                    black_box(successor);
+                }
+            })
+        });
+    }
+    group.finish();
+}
+
+fn bench_eval(c: &mut Criterion) {
+    let mut group = c.benchmark_group("eval");
+    // build the lookup tables outside of the measurements
+    eval::eval(&boards()[0].1);
+    for (name, board) in boards() {
+        group.bench_with_input(BenchmarkId::from_parameter(name), &board, |b, board| {
+            b.iter(|| eval::eval(black_box(board)))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    bench_push_left,
+    bench_apply,
+    bench_apply_scalar,
+    bench_random_successors,
+    bench_eval
+);
+criterion_main!(benches);
@@ -0,0 +1,16 @@
+//! Generates the code of the gRPC service of `proto/ai2048.proto` (see `src/grpc.rs`) with the `server` feature.
+//!
+//! The proto file is parsed by `protox`, in Rust, so that building needs no `protoc`.
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    println!("cargo::rerun-if-changed=proto/ai2048.proto");
+    #[cfg(feature = "server")]
+    {
+        let descriptors = protox::compile(["proto/ai2048.proto"], ["proto"])?;
+        tonic_prost_build::configure()
+            // the client, e.g. for the tests, is given a channel rather than making one
+            .build_transport(false)
+            .compile_fds(descriptors)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,79 @@
+/*
+ * C API of the 2048 engine (`src/ffi.rs`), in the shared library built by `cargo build --release`
+ * (`target/release/libai_2048.so`, `.dylib` or `ai_2048.dll`).
+ *
+ * A board is an array of 16 exponents of its tiles, row by row (0 for an empty cell, 1 for a 2, 2 for a 4, ...).
+ * Actions are numbered as below.
+ *
+ * Generated by cbindgen from `src/ffi.rs` (see `cbindgen.toml`): do not edit.
+ */
+
+#ifndef AI_2048_H
+#define AI_2048_H
+
+#include <stdint.h>
+
+/**
+ * Number of the action moving the tiles up (the actions are numbered in the order of `ALL_ACTIONS`)
+ */
+#define AI_2048_UP 0
+
+/**
+ * Number of the action moving the tiles down
+ */
+#define AI_2048_DOWN 1
+
+/**
+ * Number of the action moving the tiles left
+ */
+#define AI_2048_LEFT 2
+
+/**
+ * Number of the action moving the tiles right
+ */
+#define AI_2048_RIGHT 3
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Applies the action to the board (without placing a random tile) and writes the resulting board to `out`.
+ *
+ * Returns the score of the action in the classic 2048 game, -1 if the action does not move any tile (`out` is then
+ * left unchanged), or -2 if an argument is invalid.
+ *
+ * # Safety
+ * `cells` must point to 16 readable bytes and `out` to 16 writable bytes (they may be the same array).
+ */
+int32_t board_apply(const uint8_t *cells,
+                    int32_t action,
+                    uint8_t *out);
+
+/**
+ * Evaluation of the board by the heuristic of the engine, or NaN if the board is invalid.
+ *
+ * # Safety
+ * `cells` must point to 16 readable bytes.
+ */
+float board_eval(const uint8_t *cells);
+
+/**
+ * Action selected by the strategy (e.g. `"expectimax:depth=3"`, or `NULL` for the default one) on the board, within
+ * `budget_ms` milliseconds if it is not 0.
+ *
+ * Returns the action, -1 if no action is applicable (the game is lost), -2 if an argument is invalid, or -3 if the
+ * strategy failed.
+ *
+ * # Safety
+ * `cells` must point to 16 readable bytes and `strategy` must be null or a nul-terminated string.
+ */
+int32_t select_action(const uint8_t *cells,
+                      const char *strategy,
+                      uint64_t budget_ms);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* AI_2048_H */
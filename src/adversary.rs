@@ -0,0 +1,127 @@
+//! Opponents placing the new tiles, for games where the AI plays against an adversary rather than against chance
+//! (`main adversarial`).
+//!
+//! Expectimax assumes that tiles appear at random, as in the real game. Against an adversary choosing the worst
+//! tile for the player, that assumption no longer holds and a minimax search would be the sound one: comparing both
+//! opponents shows the difference.
+
+use std::io::{BufRead, Write};
+
+use anyhow::{bail, ensure, Context};
+use rand::Rng;
+
+use crate::board::{Board, PlayableBoard, N};
+use crate::replay::Spawn;
+
+/// An opponent choosing where the new tile appears and its value
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum Adversary {
+    /// The random tiles of the real game (a 2 with probability 0.9, a 4 otherwise, on a uniformly random cell)
+    Random,
+    /// The tile leading to the board of lowest value for the player, according to the evaluation function
+    Worst,
+    /// A person, typing the row, the column and the value of each tile
+    Human,
+}
+
+impl Adversary {
+    /// Tile placed on the board, which has at least one empty cell.
+    pub fn place(&self, board: &Board, rng: &mut impl Rng) -> anyhow::Result<Spawn> {
+        match self {
+            Adversary::Random => {
+                let mut next = *board;
+                next.add_random_with(rng);
+                Ok(Spawn::between(board, &next).expect("a single tile is added"))
+            }
+            Adversary::Worst => Ok(worst(board)),
+            Adversary::Human => ask(board, &mut std::io::stdin().lock(), &mut std::io::stdout()),
+        }
+    }
+}
+
+/// Tile minimizing the value of the resulting board for the player.
+fn worst(board: &Board) -> Spawn {
+    board
+        .random_successors()
+        .map(|(_, next)| {
+            let value = PlayableBoard::from(next).evaluate();
+            (
+                Spawn::between(board, &next).expect("a single tile is added"),
+                value,
+            )
+        })
+        .min_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map(|(spawn, _)| spawn)
+        .expect("the board has an empty cell")
+}
+
+/// Asks for a tile as `<row> <col> <value>` (rows and columns numbered from 1, a value of 2 or 4), until a valid
+/// one is given.
+fn ask(board: &Board, input: &mut impl BufRead, output: &mut impl Write) -> anyhow::Result<Spawn> {
+    loop {
+        write!(output, "Tile to place (`<row> <col> <2|4>`): ")?;
+        output.flush()?;
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            bail!("No more input from the adversary");
+        }
+        match parse_spawn(board, &line) {
+            Ok(spawn) => return Ok(spawn),
+            Err(e) => writeln!(output, "{e:#}")?,
+        }
+    }
+}
+
+fn parse_spawn(board: &Board, line: &str) -> anyhow::Result<Spawn> {
+    let numbers = line
+        .split_whitespace()
+        .map(str::parse::<usize>)
+        .collect::<Result<Vec<_>, _>>()
+        .context("Expected three numbers")?;
+    let [row, col, value] = numbers[..] else {
+        bail!("Expected three numbers: the row, the column and the value of the tile");
+    };
+    ensure!(
+        (1..=N).contains(&row) && (1..=N).contains(&col),
+        "Rows and columns are numbered from 1 to {N}"
+    );
+    ensure!(
+        board.cells[row - 1][col - 1] == 0,
+        "The cell ({row}, {col}) is not empty"
+    );
+    let tile = match value {
+        2 => 1,
+        4 => 2,
+        _ => bail!("The tile is a 2 or a 4"),
+    };
+    Ok(Spawn {
+        row: row - 1,
+        col: col - 1,
+        tile,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_adversaries() {
+        let mut board = Board::EMPTY;
+        board.cells[0] = [1, 2, 3, 0];
+        let worst = Adversary::Worst.place(&board, &mut rand::rng()).unwrap();
+        assert_eq!(board.cells[worst.row][worst.col], 0);
+
+        // invalid lines are rejected until a valid tile is given
+        let mut input = "1 1 2\n2 2 8\n2 2 4\n".as_bytes();
+        let spawn = ask(&board, &mut input, &mut Vec::new()).unwrap();
+        assert_eq!(
+            spawn,
+            Spawn {
+                row: 1,
+                col: 1,
+                tile: 2
+            }
+        );
+    }
+}
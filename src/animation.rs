@@ -0,0 +1,146 @@
+//! Animation of the tiles sliding and merging in the terminal, between the board before an action and the board after
+//! it, as in the original game (used by `main play` and `main auto`).
+//!
+//! Each frame is drawn as the boards are (see the `Display` of `Board`): tiles slide smoothly along the rows, and
+//! line by line along the columns.
+
+use std::fmt::Write as _;
+use std::io::Write;
+use std::time::Duration;
+
+use colored::*;
+use crossterm::cursor::MoveUp;
+use crossterm::queue;
+
+use crate::board::{styled_tile, Action, Board, N};
+
+/// Number of frames of an animation, including the last one showing the board after the action
+pub const NUM_FRAMES: usize = 6;
+
+/// Width of a cell in characters: a tile and the space after it
+const CELL: usize = 8;
+
+/// Frames of the tiles of the board sliding with the action, the last one being the board after the action.
+///
+/// Returns no frame if the action is not applicable.
+pub fn frames(board: &Board, action: Action, num_frames: usize) -> Vec<String> {
+    let Some((next, moves)) = board.apply_with_events(action) else {
+        return Vec::new();
+    };
+    let mut frames: Vec<String> = (1..num_frames)
+        .map(|i| {
+            let progress = i as f32 / num_frames as f32;
+            // position of the left of each tile, in characters, and its row
+            let tiles: Vec<(usize, usize, u8)> = moves
+                .iter()
+                .map(|m| {
+                    let row = m.from.0 as f32 + (m.to.0 as f32 - m.from.0 as f32) * progress;
+                    let col = m.from.1 as f32 + (m.to.1 as f32 - m.from.1 as f32) * progress;
+                    (
+                        row.round() as usize,
+                        (col * CELL as f32).round() as usize,
+                        m.tile,
+                    )
+                })
+                .collect();
+            render(&tiles)
+        })
+        .collect();
+    frames.push(next.to_string());
+    frames
+}
+
+/// A board with the tiles at the given positions, each given by its row, its first character and its exponent
+fn render(tiles: &[(usize, usize, u8)]) -> String {
+    let mut text = String::new();
+    let _ = writeln!(text, "{}", format!("╔═{}╗", "═".repeat(CELL * N)).bold());
+    for row in 0..N {
+        // tile drawn on each character of the line, if any, starting with the empty cells
+        let mut line: Vec<Option<u8>> = (0..CELL * N)
+            .map(|x| (x % CELL < CELL - 1).then_some(0))
+            .collect();
+        for &(_, x, tile) in tiles.iter().filter(|&&(r, _, _)| r == row) {
+            line[x..x + CELL - 1].fill(Some(tile));
+        }
+        let _ = write!(text, "{}", "║ ".bold());
+        let mut x = 0;
+        while x < line.len() {
+            match line[x] {
+                // the first character of a tile, drawn whole
+                Some(tile)
+                    if line[x..].len() >= CELL - 1
+                        && line[x..x + CELL - 1].iter().all(|&t| t == Some(tile)) =>
+                {
+                    let _ = write!(text, "{}", styled_tile(tile));
+                    x += CELL - 1;
+                }
+                // part of a tile covered by another one
+                Some(tile) => {
+                    let (r, g, b) = crate::board::tile_color(tile);
+                    let _ = write!(text, "{}", " ".on_truecolor(r, g, b));
+                    x += 1;
+                }
+                None => {
+                    text.push(' ');
+                    x += 1;
+                }
+            }
+        }
+        let _ = writeln!(text, "{} ", "║".bold());
+    }
+    let _ = writeln!(text, "{}", format!("╚═{}╝", "═".repeat(CELL * N)).bold());
+    text
+}
+
+/// Shows the frames of the action on a terminal whose cursor is just below the board, drawing each frame over the board
+/// and the previous frame, for `duration` in total.
+pub fn play(
+    out: &mut impl Write,
+    board: &Board,
+    action: Action,
+    duration: Duration,
+) -> anyhow::Result<()> {
+    let frames = frames(board, action, NUM_FRAMES);
+    for frame in &frames {
+        queue!(out, MoveUp(N as u16 + 2))?;
+        write!(out, "{frame}")?;
+        out.flush()?;
+        std::thread::sleep(duration / NUM_FRAMES as u32);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frames() {
+        // the frames without the colors
+        let plain = |text: &str| {
+            let mut plain = String::new();
+            let mut escape = false;
+            for c in text.chars() {
+                match c {
+                    '\x1b' => escape = true,
+                    'm' if escape => escape = false,
+                    _ if !escape => plain.push(c),
+                    _ => {}
+                }
+            }
+            plain
+        };
+        let board = Board {
+            cells: [[1, 0, 0, 1], [0, 0, 0, 0], [0, 2, 0, 0], [0, 0, 0, 0]],
+        };
+        let right = frames(&board, Action::Right, 4);
+        assert_eq!(right.len(), 4);
+        assert_eq!(right[0].lines().count(), N + 2);
+        assert_eq!(right[3], board.apply(Action::Right).unwrap().to_string());
+        // the 4 of the third row slides by a quarter of its distance at each frame
+        let row = plain(right[0].lines().nth(3).unwrap());
+        assert_eq!(row.chars().position(|c| c == '4'), Some(2 + CELL + 4 + 3));
+        assert_eq!(frames(&board, Action::Up, 4).len(), 4);
+        assert!(frames(&Board::EMPTY, Action::Up, 4).is_empty());
+    }
+}
@@ -0,0 +1,1975 @@
+//! Benchmark of a strategy over many games, run by the `bench` binary and by `main bench`.
+
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use anyhow::Context;
+use clap::builder::PossibleValuesParser;
+use clap::{Subcommand, ValueEnum};
+use indicatif::{ProgressBar, ProgressDrawTarget, ProgressStyle};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use rayon::prelude::*;
+
+use crate::board::{PlayableBoard, ALL_ACTIONS, WIN_TILE};
+use crate::checkpoint::{self, Checkpoint};
+use crate::collect::{Collector, Sample};
+#[cfg(feature = "tui")]
+use crate::dashboard::Dashboard;
+use crate::eval::spec::EvaluatorSpec;
+use crate::interrupt::{self, Interrupted};
+use crate::replay::{Event, ReplayWriter, Spawn};
+#[cfg(feature = "db")]
+use crate::results_db;
+use crate::strategy::Strategy;
+use crate::{eval, search, stats};
+
+/// Prints a line of information on the run: on stdout, unless stdout is reserved for the JSON or Markdown summary.
+macro_rules! info {
+    ($args:expr, $($arg:tt)*) => {
+        if $args.format != Format::Text {
+            eprintln!($($arg)*)
+        } else {
+            println!($($arg)*)
+        }
+    };
+}
+
+/// Exit code when all games were played but some of the thresholds (`--min-*`) were not met
+const EXIT_BELOW_THRESHOLD: i32 = 3;
+
+/// Exit code after Ctrl-C, once the games finished so far are reported (as for a process killed by SIGINT)
+const EXIT_INTERRUPTED: i32 = 130;
+
+/// Options of a benchmark
+#[derive(clap::Args, Debug)]
+pub struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// Time in seconds allowed for a single game
+    #[arg(short, long, default_value = "600", global = true)]
+    timeout: u64,
+
+    /// Time budget in milliseconds for each decision. Searches able to stop early (expectimax) stop a deeper search
+    /// when it runs out and keep the last decision completed in time: decisions exceeding it are counted as overruns
+    #[arg(long, global = true)]
+    time_per_move: Option<u64>,
+
+    /// Tile to reach (e.g. 2048): reports the fraction of games reaching it and the number of moves needed
+    #[arg(long, global = true, value_parser = parse_tile)]
+    target: Option<u8>,
+
+    /// Stops each game as soon as the target tile is reached
+    #[arg(long, global = true, requires = "target")]
+    stop_at_target: bool,
+
+    /// Number of games played by each worker before the measured games, and left out of all statistics, so that
+    /// lazily initialized tables do not weigh on the first measured decisions
+    #[arg(long, global = true, default_value = "0")]
+    warmup: u64,
+
+    /// Number of games to play
+    #[arg(short, long, default_value = "8", global = true)]
+    num_games: u64,
+
+    /// Strategy selecting the actions, with optional parameters (`default`, `random`, `greedy`, `expectimax:depth=4`)
+    #[arg(short, long, default_value = "default")]
+    strategy: Strategy,
+
+    /// Named preset of weights for the evaluation function (default weights if absent)
+    #[arg(long, global = true, value_parser = PossibleValuesParser::new(eval::presets::names()))]
+    eval_preset: Option<String>,
+
+    /// File of weights for the evaluation function, instead of a named preset
+    #[arg(long, global = true, conflicts_with = "eval_preset")]
+    weights: Option<PathBuf>,
+
+    /// Evaluation function at the leaves of the search, with optional parameters (`linear` with the weights above,
+    /// `phased`, `ntuple:file=ntuple.weights`, `nn:file=net.safetensors`)
+    #[arg(long, global = true, default_value = "linear")]
+    evaluator: EvaluatorSpec,
+
+    /// Comma-separated heuristics to switch off (a prefix such as `monotonicity` disables all `monotonicity_*` terms)
+    #[arg(long, global = true, value_delimiter = ',')]
+    disable: Vec<String>,
+
+    /// Seed of the first game (game `i` uses the seed `seed + i`). A random seed is picked if absent
+    #[arg(long, global = true)]
+    seed: Option<u64>,
+
+    /// Number of games played in parallel (number of physical CPUs if absent)
+    #[arg(long, global = true)]
+    threads: Option<usize>,
+
+    /// File listing the seeds of the games to play, one per line (blank lines and `#` comments are ignored)
+    #[arg(long, global = true, conflicts_with_all = ["seed", "num_games"])]
+    seeds: Option<PathBuf>,
+
+    /// Directory where the replay of each game is written, as `game-<seed>.jsonl`, with the value of each action
+    /// played for the strategy. Valuing the action takes another search on each position
+    #[arg(long, global = true)]
+    replays: Option<PathBuf>,
+
+    /// File where each position of the games is written, with the value of each action for the strategy and the
+    /// action selected (see `collect`). Valuing all actions takes another search on each position
+    #[arg(long, conflicts_with_all = ["depth_sweep", "eval_sweep", "replay_seed"])]
+    collect: Option<PathBuf>,
+
+    /// File where the result of each game is saved as soon as it ends, so that an interrupted run can be resumed
+    #[arg(long)]
+    checkpoint: Option<PathBuf>,
+
+    /// Continues the run saved in the checkpoint file, only playing the games that did not end
+    #[arg(long, requires = "checkpoint")]
+    resume: bool,
+
+    /// File to which the result of each game is appended, with the strategy and configuration of the run, so that
+    /// an experiment played in several runs can be aggregated with `bench report`
+    #[arg(long)]
+    results: Option<PathBuf>,
+
+    /// Shows the games being played and statistics over the finished ones in a live dashboard
+    /// (requires building with `--features tui`)
+    #[arg(long)]
+    dashboard: bool,
+
+    /// SQLite database where the run and all its games are recorded (requires building with `--features db`)
+    #[arg(long, global = true)]
+    db: Option<PathBuf>,
+
+    /// Plays the strategy at each depth of the range (`2..=6`, `2..7` or `4`) on the same seeds, and reports the score
+    /// and time per move at each depth
+    #[arg(long, value_parser = parse_depths, conflicts_with = "replay_seed")]
+    depth_sweep: Option<RangeInclusive<usize>>,
+
+    /// Comma-separated evaluations (preset names or weights files) with which the strategy is played on the same
+    /// seeds, reporting the score and time per move with each one
+    #[arg(long, value_delimiter = ',', conflicts_with_all = ["eval_preset", "weights", "evaluator", "depth_sweep", "replay_seed"])]
+    eval_sweep: Vec<String>,
+
+    /// CSV file where the survival curves are written: the estimated probability of a game still running after each
+    /// number of moves and each 2048 score (games stopped by the timeout or at the target are censored)
+    #[arg(long)]
+    survival: Option<PathBuf>,
+
+    /// How games stopped by the timeout are counted in the statistics (they are always reported separately)
+    #[arg(long, value_enum, global = true, default_value = "include")]
+    timeouts: Timeouts,
+
+    /// Format of the summary of the games. With `json` or `md` (a Markdown table of the strategies and their
+    /// statistics), stdout only receives the summary and other messages go to stderr
+    #[arg(long, value_enum, default_value = "text")]
+    format: Format,
+
+    /// Minimum average score (#actions): the exit code is 3 if it is not reached
+    #[arg(long)]
+    min_average_moves: Option<f64>,
+
+    /// Minimum average 2048 score: the exit code is 3 if it is not reached
+    #[arg(long)]
+    min_average_score: Option<f64>,
+
+    /// Minimum percentage of games reaching the target tile: the exit code is 3 if it is not reached
+    #[arg(long, requires = "target")]
+    min_target_rate: Option<f64>,
+
+    /// Plays a single game with the given seed (as printed in the results), showing each move.
+    /// The game is identical to the original one, unless decisions depend on the time (`--time-per-move`)
+    #[arg(long, conflicts_with_all = ["seed", "seeds"])]
+    replay_seed: Option<u64>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum Format {
+    Text,
+    Json,
+    Md,
+}
+
+/// How games stopped by the timeout are counted in the statistics
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum Timeouts {
+    /// Counted with the score reached at the timeout (a lower bound of the score of the complete game)
+    Include,
+    /// Left out of the statistics
+    Exclude,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Plays two strategies on the same seeds, and reports the differences of scores seed by seed
+    Compare(CompareArgs),
+    /// Plays several strategies on the same seeds, and rates them from the pairwise comparisons of their scores
+    Rate(RateArgs),
+    /// Prints the runs recorded in the results database (`--db`), from the oldest to the most recent
+    History(HistoryArgs),
+    /// Aggregates the games of several runs, appended to results files (`--results`) or recorded in the results
+    /// database (`--db`), grouping them by strategy and configuration
+    Report(ReportArgs),
+}
+
+#[derive(clap::Args, Debug)]
+struct CompareArgs {
+    /// First strategy
+    #[arg(short, long)]
+    a: Strategy,
+
+    /// Second strategy
+    #[arg(short, long)]
+    b: Strategy,
+}
+
+#[derive(clap::Args, Debug)]
+struct RateArgs {
+    /// Strategies to rate (repeat the flag for each strategy)
+    #[arg(short, long = "strategy", required = true, num_args = 1)]
+    strategies: Vec<Strategy>,
+}
+
+#[derive(clap::Args, Debug)]
+struct ReportArgs {
+    /// Results files to aggregate (the results database is used if none is given)
+    files: Vec<PathBuf>,
+}
+
+#[derive(clap::Args, Debug)]
+struct HistoryArgs {
+    /// Number of runs to print
+    #[arg(long, default_value = "20")]
+    last: usize,
+}
+
+/// Outcome of a single game
+#[derive(serde::Serialize, serde::Deserialize)]
+struct GameResult {
+    /// Seed of the generator of random tiles
+    seed: u64,
+    /// Number of actions played
+    score: f32,
+    /// Score of the classic 2048 game: sum of the values of all tiles created by merges
+    merge_score: u32,
+    /// Board at the end of the game
+    board: PlayableBoard,
+    /// Whether the game was stopped by the timeout
+    timed_out: bool,
+    /// Time taken by the strategy to select each action, in seconds
+    move_times: Vec<f64>,
+    /// Number of decisions that exceeded the time budget per move
+    overruns: usize,
+    /// Statistics of the searches made to select the actions
+    search: search::SearchTotals,
+    /// Largest memory used by the evaluation cache during the game, in bytes
+    peak_cache_memory: usize,
+    /// Number of actions played before reaching the target tile, if it was reached
+    target_moves: Option<usize>,
+}
+
+/// Time allowed for games and decisions, and tile at which games may stop
+#[derive(Clone, Copy, Debug)]
+struct Limits {
+    /// Time allowed for a whole game
+    game: Duration,
+    /// Time allowed for a single decision, if limited
+    per_move: Option<Duration>,
+    /// Tile (exponent) whose first appearance is recorded
+    target: Option<u8>,
+    /// Whether games stop as soon as the target tile appears
+    stop_at_target: bool,
+}
+
+impl Limits {
+    fn from_args(args: &Args) -> Limits {
+        Limits {
+            game: Duration::from_secs(args.timeout),
+            per_move: args.time_per_move.map(Duration::from_millis),
+            target: args.target,
+            stop_at_target: args.stop_at_target,
+        }
+    }
+}
+
+/// Runs the benchmark described by the command line arguments.
+pub fn run(args: Args) -> anyhow::Result<()> {
+    #[cfg(feature = "db")]
+    let started_at = results_db::now();
+
+    #[cfg(not(feature = "db"))]
+    anyhow::ensure!(
+        args.db.is_none() && !matches!(args.command, Some(Command::History(_))),
+        "The results database is not available in this build, rebuild with `--features db`"
+    );
+    #[cfg(feature = "db")]
+    if let Some(Command::History(history)) = &args.command {
+        return print_history(&args, history);
+    }
+    if let Some(Command::Report(report)) = &args.command {
+        return print_aggregate(&args, report);
+    }
+
+    // maximum allow runtime for each game
+    let limits = Limits::from_args(&args);
+    // weights of the evaluation function used by the search
+    let mut weights = eval::load_weights(args.weights.as_deref(), args.eval_preset.as_deref())?;
+    for name in &args.disable {
+        weights.disable(name)?;
+    }
+    if args.evaluator.uses_weights() {
+        info!(
+            args,
+            "Active heuristics: {}",
+            weights.active().collect::<Vec<_>>().join(", ")
+        );
+    } else {
+        anyhow::ensure!(
+            args.eval_preset.is_none() && args.weights.is_none() && args.disable.is_empty(),
+            "The evaluator {} does not use the weights of the linear evaluation",
+            args.evaluator
+        );
+        info!(args, "Evaluator: {}", args.evaluator);
+    }
+    eval::set_default_evaluator(args.evaluator.load(weights)?)?;
+
+    #[cfg(not(feature = "tui"))]
+    anyhow::ensure!(
+        !args.dashboard,
+        "The dashboard is not available in this build, rebuild with `--features tui`"
+    );
+
+    // configure the global thread pool of rayon, by default with as many threads as we have *physical* CPUs
+    let threads = args.threads.unwrap_or_else(num_cpus::get_physical);
+    anyhow::ensure!(threads > 0, "At least one thread is needed");
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .build_global()?;
+
+    warm_up(&args)?;
+
+    match &args.command {
+        Some(Command::Compare(compare)) => return compare_strategies(&args, compare),
+        Some(Command::Rate(rate)) => return rate_strategies(&args, rate),
+        _ => {}
+    }
+    if let Some(depths) = &args.depth_sweep {
+        return sweep_depths(&args, depths.clone());
+    }
+    if !args.eval_sweep.is_empty() {
+        return sweep_evals(&args, &args.eval_sweep);
+    }
+    info!(args, "Strategy: {}", args.strategy);
+
+    if let Some(seed) = args.replay_seed {
+        // the moves are shown along with the boards
+        log::set_max_level(log::max_level().max(log::LevelFilter::Info));
+        let result = play(
+            &args.strategy,
+            seed,
+            limits,
+            true,
+            args.replays.as_deref(),
+            None,
+            None,
+        )?;
+        if result.timed_out {
+            println!("Timeout");
+        }
+        println!(
+            "score (#actions): {}   2048 score: {}",
+            result.score, result.merge_score
+        );
+        return Ok(());
+    }
+    let mut header = checkpoint::Header {
+        seeds: game_seeds(&args)?,
+        strategy: args.strategy.to_string(),
+    };
+    // results of the games completed by a previous run
+    let mut finished: Vec<GameResult> = Vec::new();
+    let checkpoint = match &args.checkpoint {
+        Some(path) if args.resume => {
+            let (checkpoint, saved, results) = Checkpoint::resume(path)?;
+            if args.seed.is_none() && args.seeds.is_none() {
+                // the seeds are picked at random, use the ones of the interrupted run
+                header.seeds = saved.seeds.clone();
+            }
+            saved.check_compatible(&header)?;
+            finished = results;
+            info!(
+                args,
+                "Resuming from {}: {} games already played",
+                path.display(),
+                finished.len()
+            );
+            Some(checkpoint)
+        }
+        Some(path) => Some(Checkpoint::create(path, &header)?),
+        None => None,
+    };
+    let checkpoint = checkpoint.map(Mutex::new);
+    let seeds = header.seeds;
+    let num_games = seeds.len();
+    info!(args, "Seeds: {}", describe_seeds(&seeds));
+
+    let collector = args.collect.as_deref().map(Collector::create).transpose()?;
+    let progress = progress_bar(num_games as u64);
+    #[cfg(feature = "tui")]
+    let dashboard = args.dashboard.then(|| Dashboard::new(threads, num_games));
+    #[cfg(not(feature = "tui"))]
+    let dashboard: Option<Dashboard> = None;
+    if let Some(dashboard) = &dashboard {
+        progress.set_draw_target(ProgressDrawTarget::hidden());
+        for result in &finished {
+            dashboard.finish(result.score, &result.board);
+        }
+    }
+    progress.inc(finished.len() as u64);
+    let total_score = AtomicU64::new(finished.iter().map(|result| result.score as u64).sum());
+
+    // run all remaining games on the thread pool and collect the results
+    let remaining: Vec<u64> = seeds
+        .iter()
+        .copied()
+        .filter(|seed| !finished.iter().any(|result| result.seed == *seed))
+        .collect();
+    // on Ctrl-C, the games in progress stop and the remaining ones are not started
+    interrupt::install()?;
+    let run_games = || -> Vec<_> {
+        remaining
+            .into_par_iter()
+            .filter(|_| !interrupt::interrupted())
+            .map(|seed| {
+                let mut result = play(
+                    &args.strategy,
+                    seed,
+                    limits,
+                    false,
+                    args.replays.as_deref(),
+                    dashboard.as_ref(),
+                    collector.as_ref(),
+                );
+                if let (Ok(game), Some(checkpoint)) = (&result, &checkpoint) {
+                    if let Err(e) = checkpoint.lock().unwrap().append(game) {
+                        result = Err(e.context("Cannot write the checkpoint"));
+                    }
+                }
+                if let Ok(result) = &result {
+                    let outcome = if result.timed_out {
+                        "Timeout"
+                    } else if args.stop_at_target && result.target_moves.is_some() {
+                        "Target reached"
+                    } else {
+                        "End game"
+                    };
+                    match &dashboard {
+                        Some(dashboard) => dashboard.finish(result.score, &result.board),
+                        None => progress.suspend(|| {
+                            info!(
+                                args,
+                                "{outcome} (seed {seed}) // num moves {}", result.score
+                            )
+                        }),
+                    }
+                    total_score.fetch_add(result.score as u64, Ordering::Relaxed);
+                }
+                progress.inc(1);
+                let average =
+                    total_score.load(Ordering::Relaxed) as f64 / progress.position() as f64;
+                progress.set_message(format!("average score (#actions): {average:.1}"));
+                result
+            })
+            .collect()
+    };
+    let mut results = match &dashboard {
+        // draw the dashboard on this thread while the games are played
+        #[cfg(feature = "tui")]
+        Some(dashboard) => std::thread::scope(|scope| {
+            let games = scope.spawn(run_games);
+            dashboard.run(|| games.is_finished())?;
+            anyhow::Ok(games.join().unwrap())
+        })?,
+        _ => run_games(),
+    };
+    progress.finish_and_clear();
+    results.retain(|result| !result.as_ref().is_err_and(|e| e.is::<Interrupted>()));
+    results.extend(finished.into_iter().map(Ok));
+    let interrupted = interrupt::interrupted();
+    if interrupted {
+        info!(
+            args,
+            "Interrupted: statistics over the {} games finished out of {num_games}",
+            results.len()
+        );
+    }
+    results.sort_by_key(|result| result.as_ref().map_or(u64::MAX, |result| result.seed));
+
+    let valid_results: Vec<_> = results.iter().filter_map(|x| x.as_ref().ok()).collect();
+    let thresholds = thresholds(&args, &counted(&args, &results));
+    match args.format {
+        Format::Text => print_report(&args, &results, &thresholds),
+        Format::Json => println!("{:#}", json_summary(&args, &seeds, &results, &thresholds)),
+        Format::Md => print_markdown_table(
+            &["strategy"],
+            vec![(
+                vec![args.strategy.to_string()],
+                Overview::of(&counted(&args, &results)),
+            )],
+        ),
+    }
+    if let Some(path) = &args.results {
+        append_results(path, &args, &valid_results)?;
+        info!(args, "Results appended to {}", path.display());
+    }
+    if let Some(path) = &args.survival {
+        write_survival_curves(path, &valid_results, args.stop_at_target)?;
+        info!(args, "Survival curves written to {}", path.display());
+    }
+    if let (Some(collector), Some(path)) = (collector, &args.collect) {
+        let count = collector.finish()?;
+        info!(args, "{count} positions written to {}", path.display());
+    }
+
+    #[cfg(feature = "db")]
+    if let Some(path) = &args.db {
+        let id = record_run(path, &args, started_at, &seeds, &valid_results)?;
+        info!(args, "Recorded as run #{id} in {}", path.display());
+    }
+
+    if interrupted {
+        if let Some(path) = &args.checkpoint {
+            info!(
+                args,
+                "Continue the run with `--checkpoint {} --resume`",
+                path.display()
+            );
+        }
+        std::process::exit(EXIT_INTERRUPTED);
+    }
+    if thresholds.iter().any(|threshold| !threshold.is_met()) {
+        std::process::exit(EXIT_BELOW_THRESHOLD);
+    }
+    Ok(())
+}
+
+/// Prints the results of all games, and statistics over the successful ones.
+fn print_report(args: &Args, results: &[anyhow::Result<GameResult>], thresholds: &[Threshold]) {
+    let played: Vec<_> = results.iter().filter_map(|x| x.as_ref().ok()).collect();
+    let valid = counted(args, results);
+    // print all results
+    for res in results {
+        match res {
+            Ok(GameResult {
+                seed,
+                score,
+                merge_score,
+                board,
+                overruns,
+                timed_out,
+                ..
+            }) => println!(
+                "seed: {seed}   score (#actions): {score}   2048 score: {merge_score}   overruns: {overruns}{}\n{board}\n",
+                if *timed_out { "   (timeout)" } else { "" }
+            ),
+            Err(e) => println!("{e}"),
+        }
+    }
+    let num_timeouts = played.iter().filter(|result| result.timed_out).count();
+    let num_excluded = played.len() - valid.len();
+
+    // print statistic over the valid runs
+    if results.len() > num_excluded {
+        println!("How many time a tile was reached:");
+        for tile in 3..=15 {
+            let mut count = 0;
+            for result in &valid {
+                if result.board.has_at_least_tile(tile) {
+                    count += 1;
+                }
+            }
+            println!(
+                "{:>6}: {:>6.2}%",
+                2u32.pow(tile as u32),
+                (count as f32) / ((results.len() - num_excluded) as f32) * 100.0
+            );
+        }
+    }
+    println!("\nMax tile at the end of the game:");
+    let histogram = max_tile_histogram(&valid);
+    let largest_count = histogram.iter().map(|&(_, count)| count).max().unwrap_or(0);
+    for (tile, count) in histogram {
+        let bar = "█".repeat((count * HISTOGRAM_WIDTH).div_ceil(largest_count.max(1)));
+        println!(
+            "{:>6}: {:>6.2}% {bar}",
+            2u32.pow(tile as u32),
+            (count as f32) / (valid.len() as f32) * 100.0
+        );
+    }
+    println!(
+        "\nNumber of completed games:  {}",
+        played.len() - num_timeouts
+    );
+    println!(
+        "Number of timed-out games:  {num_timeouts}{}",
+        match args.timeouts {
+            _ if num_timeouts == 0 => "",
+            Timeouts::Include => " (included in the statistics)",
+            Timeouts::Exclude => " (excluded from the statistics)",
+        }
+    );
+    println!(
+        "Number of game with error:  {}",
+        results.len() - played.len()
+    );
+    let scores: Vec<f64> = valid.iter().map(|result| result.score as f64).collect();
+    if let Some(summary) = stats::Summary::of(&scores) {
+        println!("Score (#actions):\n{summary}");
+        print_confidence_intervals(&scores);
+    }
+    let merge_scores: Vec<f64> = valid
+        .iter()
+        .map(|result| result.merge_score as f64)
+        .collect();
+    if let Some(summary) = stats::Summary::of(&merge_scores) {
+        println!("2048 score (sum of merged tiles):\n{summary}");
+    }
+    if let Some(target) = args.target {
+        print_target_stats(target, &valid);
+    }
+    print_latencies(&played);
+    print_search_stats(&played);
+
+    for threshold in thresholds {
+        println!("{threshold}");
+    }
+}
+
+/// Successful games counted in the statistics, according to `--timeouts`
+fn counted<'a>(args: &Args, results: &'a [anyhow::Result<GameResult>]) -> Vec<&'a GameResult> {
+    results
+        .iter()
+        .filter_map(|result| result.as_ref().ok())
+        .filter(|result| args.timeouts == Timeouts::Include || !result.timed_out)
+        .collect()
+}
+
+/// A minimum required on a statistic of the games
+struct Threshold {
+    name: &'static str,
+    value: f64,
+    minimum: f64,
+}
+
+impl Threshold {
+    fn is_met(&self) -> bool {
+        self.value >= self.minimum
+    }
+}
+
+impl std::fmt::Display for Threshold {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let verdict = if self.is_met() { "met" } else { "NOT met" };
+        write!(
+            f,
+            "Threshold {verdict}: {} = {:.2} (minimum {:.2})",
+            self.name, self.value, self.minimum
+        )
+    }
+}
+
+/// Thresholds given on the command line, with the values reached by the games.
+fn thresholds(args: &Args, results: &[&GameResult]) -> Vec<Threshold> {
+    let mean_of = |value: fn(&GameResult) -> f64| {
+        stats::mean(
+            &results
+                .iter()
+                .map(|result| value(result))
+                .collect::<Vec<_>>(),
+        )
+    };
+    let mut thresholds = Vec::new();
+    if let Some(minimum) = args.min_average_moves {
+        thresholds.push(Threshold {
+            name: "average_moves",
+            value: mean_of(|result| result.score as f64),
+            minimum,
+        });
+    }
+    if let Some(minimum) = args.min_average_score {
+        thresholds.push(Threshold {
+            name: "average_score",
+            value: mean_of(|result| result.merge_score as f64),
+            minimum,
+        });
+    }
+    if let Some(minimum) = args.min_target_rate {
+        thresholds.push(Threshold {
+            name: "target_rate",
+            value: mean_of(|result| f64::from(u8::from(result.target_moves.is_some()))) * 100.0,
+            minimum,
+        });
+    }
+    thresholds
+}
+
+/// Summary of the run as JSON: configuration, statistics, thresholds and the outcome of each game.
+fn json_summary(
+    args: &Args,
+    seeds: &[u64],
+    results: &[anyhow::Result<GameResult>],
+    thresholds: &[Threshold],
+) -> serde_json::Value {
+    let played: Vec<_> = results.iter().filter_map(|x| x.as_ref().ok()).collect();
+    let valid = counted(args, results);
+    let values = |value: fn(&GameResult) -> f64| -> Vec<f64> {
+        valid.iter().map(|result| value(result)).collect()
+    };
+    let mut times: Vec<f64> = played
+        .iter()
+        .flat_map(|result| result.move_times.iter().copied())
+        .collect();
+    times.sort_by(f64::total_cmp);
+    let ms = |q: f64| (!times.is_empty()).then(|| stats::quantile(&times, q) * 1000.0);
+    let target = args.target.map(|target| {
+        let moves: Vec<f64> = valid
+            .iter()
+            .filter_map(|result| result.target_moves)
+            .map(|moves| moves as f64)
+            .collect();
+        serde_json::json!({
+            "tile": 1u32 << target,
+            "rate": moves.len() as f64 / valid.len() as f64,
+            "moves": stats::Summary::of(&moves),
+        })
+    });
+    let survival: serde_json::Map<_, _> = survival_curves(&played, args.stop_at_target)
+        .into_iter()
+        .map(|(metric, curve)| (metric.to_string(), serde_json::json!(curve)))
+        .collect();
+    serde_json::json!({
+        "strategy": args.strategy.to_string(),
+        "seeds": seeds,
+        "num_games": results.len(),
+        "num_errors": results.len() - played.len(),
+        "num_timeouts": played.iter().filter(|result| result.timed_out).count(),
+        "timeouts": if args.timeouts == Timeouts::Include { "include" } else { "exclude" },
+        "score": stats::Summary::of(&values(|result| result.score as f64)),
+        "merge_score": stats::Summary::of(&values(|result| result.merge_score as f64)),
+        "max_tiles": max_tile_histogram(&valid)
+            .into_iter()
+            .map(|(tile, count)| ((1u32 << tile).to_string(), count.into()))
+            .collect::<serde_json::Map<_, _>>(),
+        "target": target,
+        "time_per_move_ms": {
+            "mean": (!times.is_empty()).then(|| stats::mean(&times) * 1000.0),
+            "p95": ms(0.95),
+            "p99": ms(0.99),
+            "max": ms(1.0),
+            "overruns": played.iter().map(|result| result.overruns).sum::<usize>(),
+        },
+        "survival": survival,
+        "thresholds": thresholds
+            .iter()
+            .map(|threshold| serde_json::json!({
+                "name": threshold.name,
+                "value": threshold.value,
+                "minimum": threshold.minimum,
+                "met": threshold.is_met(),
+            }))
+            .collect::<Vec<_>>(),
+        "passed": thresholds.iter().all(Threshold::is_met),
+        "games": played
+            .iter()
+            .map(|result| serde_json::json!({
+                "seed": result.seed,
+                "score": result.score,
+                "merge_score": result.merge_score,
+                "max_tile": 1u32 << result.board.board().max_tile(),
+                "timed_out": result.timed_out,
+                "target_moves": result.target_moves,
+            }))
+            .collect::<Vec<_>>(),
+        "errors": results
+            .iter()
+            .filter_map(|result| result.as_ref().err())
+            .map(|e| format!("{e:#}"))
+            .collect::<Vec<_>>(),
+    })
+}
+
+/// Plays `--warmup` games with the strategy on every worker of the thread pool, discarding their results.
+fn warm_up(args: &Args) -> anyhow::Result<()> {
+    if args.warmup == 0 {
+        return Ok(());
+    }
+    let limits = Limits::from_args(args);
+    info!(args, "Warming up with {} games per worker", args.warmup);
+    rayon::broadcast(|context| {
+        // any seeds would do, these ones differ between workers
+        let first_seed = context.index() as u64 * args.warmup;
+        (first_seed..first_seed + args.warmup).try_for_each(|seed| {
+            play(&args.strategy, seed, limits, false, None, None, None).map(|_| ())
+        })
+    })
+    .into_iter()
+    .collect::<anyhow::Result<()>>()
+    .context("Failure of a warm-up game")
+}
+
+/// Seeds of the games to play: read from the seed file, or `num_games` consecutive seeds from `seed`
+/// (picked at random if absent).
+fn game_seeds(args: &Args) -> anyhow::Result<Vec<u64>> {
+    if let Some(path) = &args.seeds {
+        return read_seeds(path);
+    }
+    let first_seed = args.seed.unwrap_or_else(rand::random);
+    Ok((first_seed..first_seed + args.num_games).collect())
+}
+
+/// Reads a seed file: one seed per line, ignoring blank lines and comments starting with `#`.
+fn read_seeds(path: &Path) -> anyhow::Result<Vec<u64>> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Cannot read the seeds in {}", path.display()))?;
+    let mut seeds = Vec::new();
+    for (line_number, line) in content.lines().enumerate() {
+        let line = line.split('#').next().unwrap().trim();
+        if !line.is_empty() {
+            let seed = line.parse().with_context(|| {
+                format!("Invalid seed at {}:{}", path.display(), line_number + 1)
+            })?;
+            seeds.push(seed);
+        }
+    }
+    anyhow::ensure!(!seeds.is_empty(), "No seed in {}", path.display());
+    Ok(seeds)
+}
+
+/// Short description of the seeds: a range if they are consecutive, their number otherwise
+fn describe_seeds(seeds: &[u64]) -> String {
+    let consecutive = seeds.windows(2).all(|pair| pair[1] == pair[0] + 1);
+    match (seeds.first(), seeds.last()) {
+        (Some(first), Some(last)) if consecutive => format!("{first}..{}", last + 1),
+        _ => format!("{} seeds", seeds.len()),
+    }
+}
+
+/// Records the run and its successful games in the results database, returning the id of the run.
+#[cfg(feature = "db")]
+fn record_run(
+    path: &Path,
+    args: &Args,
+    started_at: i64,
+    seeds: &[u64],
+    results: &[&GameResult],
+) -> anyhow::Result<i64> {
+    let mut config = run_config(args);
+    config["strategy"] = args.strategy.to_string().into();
+    config["seeds"] = describe_seeds(seeds).into();
+    config["threads"] = rayon::current_num_threads().into();
+    let games: Vec<results_db::Game> = results
+        .iter()
+        .map(|result| results_db::Game {
+            seed: result.seed,
+            score: result.score,
+            merge_score: result.merge_score,
+            max_tile: result.board.board().max_tile(),
+            timed_out: result.timed_out,
+            mean_move_time: stats::mean(&result.move_times),
+        })
+        .collect();
+    let mut db = results_db::ResultsDb::open(path)?;
+    db.insert(
+        &results_db::Run {
+            started_at,
+            git_commit: crate::submission::git_commit(),
+            strategy: &args.strategy.to_string(),
+            config: &config.to_string(),
+        },
+        &games,
+    )
+}
+
+/// Prints the last runs of the results database, with the change of mean score since the previous run of the same
+/// strategy.
+#[cfg(feature = "db")]
+fn print_history(args: &Args, history: &HistoryArgs) -> anyhow::Result<()> {
+    let path = args
+        .db
+        .as_deref()
+        .context("The database is given with `--db <FILE>`")?;
+    let runs = results_db::ResultsDb::open(path)?.history(history.last)?;
+    println!(
+        "{:>5}  {:<19}  {:<14} {:>6} {:>10} {:>8} {:>10} {:>6}  strategy",
+        "run", "started (UTC)", "commit", "games", "score", "change", "2048 score", "2048%"
+    );
+    for (i, run) in runs.iter().enumerate() {
+        let previous = runs[..i]
+            .iter()
+            .rev()
+            .find(|previous| previous.strategy == run.strategy);
+        let change = match previous {
+            Some(previous) => format!("{:+.1}", run.mean_score - previous.mean_score),
+            None => String::new(),
+        };
+        println!(
+            "{:>5}  {:<19}  {:<14} {:>6} {:>10.1} {:>8} {:>10.1} {:>5.1}%  {}",
+            run.id,
+            run.started_at,
+            run.git_commit.as_deref().unwrap_or("-"),
+            run.num_games,
+            run.mean_score,
+            change,
+            run.mean_merge_score,
+            run.win_rate * 100.0,
+            run.strategy
+        );
+    }
+    Ok(())
+}
+
+/// Parameters of the run on which the outcome of its games depends, besides the strategy and the seeds
+fn run_config(args: &Args) -> serde_json::Value {
+    serde_json::json!({
+        "timeout": args.timeout,
+        "time_per_move": args.time_per_move,
+        "eval_preset": args.eval_preset,
+        "weights": args.weights,
+        "evaluator": args.evaluator.to_string(),
+        "disable": args.disable,
+        "target": args.target,
+        "stop_at_target": args.stop_at_target,
+    })
+}
+
+/// Line of a results file: the result of a game and the run it belongs to
+#[derive(serde::Serialize, serde::Deserialize)]
+struct ResultRecord<R> {
+    strategy: String,
+    config: serde_json::Value,
+    result: R,
+}
+
+/// Appends the results of the games to the results file, creating it if needed.
+fn append_results(path: &Path, args: &Args, results: &[&GameResult]) -> anyhow::Result<()> {
+    let file = File::options()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("Cannot open {}", path.display()))?;
+    let mut out = BufWriter::new(file);
+    let config = run_config(args);
+    for result in results {
+        let record = ResultRecord {
+            strategy: args.strategy.to_string(),
+            config: config.clone(),
+            result,
+        };
+        serde_json::to_writer(&mut out, &record)?;
+        writeln!(out)?;
+    }
+    out.flush()?;
+    Ok(())
+}
+
+/// A game of a past run, as aggregated by `bench report`
+struct PastGame {
+    strategy: String,
+    /// Configuration of the run, as JSON
+    config: String,
+    score: f32,
+    max_tile: u8,
+    timed_out: bool,
+    /// Mean time to select an action, in seconds
+    mean_move_time: f64,
+}
+
+/// Reads the games of all results files.
+fn read_results(paths: &[PathBuf]) -> anyhow::Result<Vec<PastGame>> {
+    let mut games = Vec::new();
+    for path in paths {
+        let file = File::open(path).with_context(|| format!("Cannot open {}", path.display()))?;
+        for (i, line) in BufReader::new(file).lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let record: ResultRecord<GameResult> = serde_json::from_str(&line)
+                .with_context(|| format!("Invalid record at {}:{}", path.display(), i + 1))?;
+            games.push(PastGame {
+                strategy: record.strategy,
+                config: record.config.to_string(),
+                score: record.result.score,
+                max_tile: record.result.board.board().max_tile(),
+                timed_out: record.result.timed_out,
+                mean_move_time: stats::mean(&record.result.move_times),
+            });
+        }
+    }
+    Ok(games)
+}
+
+/// Reads the games of all runs of the results database.
+#[cfg(feature = "db")]
+fn read_db(args: &Args) -> anyhow::Result<Vec<PastGame>> {
+    let path = args
+        .db
+        .as_deref()
+        .context("No results file given, and no database given with `--db <FILE>`")?;
+    let games = results_db::ResultsDb::open(path)?.games()?;
+    Ok(games
+        .into_iter()
+        .map(|(strategy, config, game)| PastGame {
+            strategy,
+            config,
+            score: game.score,
+            max_tile: game.max_tile,
+            timed_out: game.timed_out,
+            mean_move_time: game.mean_move_time,
+        })
+        .collect())
+}
+
+#[cfg(not(feature = "db"))]
+fn read_db(_args: &Args) -> anyhow::Result<Vec<PastGame>> {
+    anyhow::bail!("No results file given (reading the results database requires building with `--features db`)")
+}
+
+/// Prints statistics over the games of past runs, for each strategy and configuration.
+fn print_aggregate(args: &Args, report: &ReportArgs) -> anyhow::Result<()> {
+    let games = if report.files.is_empty() {
+        read_db(args)?
+    } else {
+        read_results(&report.files)?
+    };
+    // groups in the order of their first game
+    let mut groups: Vec<((&str, &str), Vec<&PastGame>)> = Vec::new();
+    for game in &games {
+        if args.timeouts == Timeouts::Exclude && game.timed_out {
+            continue;
+        }
+        let key = (game.strategy.as_str(), game.config.as_str());
+        match groups.iter_mut().find(|(k, _)| *k == key) {
+            Some((_, group)) => group.push(game),
+            None => groups.push((key, vec![game])),
+        }
+    }
+    let overviews = groups
+        .into_iter()
+        .map(|((strategy, config), games)| ((strategy, config), Overview::of_past(&games)));
+    match args.format {
+        Format::Text => {
+            println!(
+                "{:>6} {:>10} {:>10} {:>8} {:>8}  strategy  config",
+                "games", "mean score", "median", "2048%", "ms/move"
+            );
+            for ((strategy, config), overview) in overviews {
+                println!(
+                    "{:>6} {:>10.1} {:>10.1} {:>7.1}% {:>8.3}  {strategy}  {config}",
+                    overview.num_games,
+                    overview.mean_score,
+                    overview.median_score,
+                    overview.win_rate * 100.0,
+                    overview.ms_per_move
+                );
+            }
+        }
+        Format::Md => print_markdown_table(
+            &["strategy", "config"],
+            overviews
+                .map(|((strategy, config), overview)| {
+                    (vec![strategy.to_string(), config.to_string()], overview)
+                })
+                .collect(),
+        ),
+        Format::Json => {
+            let groups: Vec<_> = overviews
+                .map(|((strategy, config), overview)| {
+                    serde_json::json!({
+                        "strategy": strategy,
+                        "config": serde_json::from_str::<serde_json::Value>(config).unwrap_or_default(),
+                        "statistics": overview,
+                    })
+                })
+                .collect();
+            println!("{:#}", serde_json::Value::from(groups));
+        }
+    }
+    Ok(())
+}
+
+/// Progress bar over games, drawn on stderr (hidden if it is not a terminal)
+fn progress_bar(num_games: u64) -> ProgressBar {
+    ProgressBar::new(num_games).with_style(
+        ProgressStyle::with_template(
+            "[{elapsed_precise}] {bar:40} {pos}/{len} games   {msg}   ETA {eta}",
+        )
+        .unwrap(),
+    )
+}
+
+/// Plays both strategies on the same seeds and prints the paired differences of scores.
+///
+/// As both strategies face the same sequence of random numbers, the difference on a seed is much less noisy
+/// than the difference between two independent games, and fewer games are needed to tell strategies apart.
+fn compare_strategies(args: &Args, compare: &CompareArgs) -> anyhow::Result<()> {
+    let limits = Limits::from_args(args);
+    let seeds = game_seeds(args)?;
+    info!(args, "A: {}\nB: {}", compare.a, compare.b);
+    info!(args, "Seeds: {}", describe_seeds(&seeds));
+
+    let progress = progress_bar(seeds.len() as u64);
+    let pairs: Vec<(GameResult, GameResult)> = seeds
+        .into_par_iter()
+        .map(|seed| {
+            let pair = (
+                play(&compare.a, seed, limits, false, None, None, None)?,
+                play(&compare.b, seed, limits, false, None, None, None)?,
+            );
+            progress.inc(1);
+            Ok(pair)
+        })
+        .collect::<anyhow::Result<_>>()?;
+    progress.finish_and_clear();
+
+    if args.format == Format::Md {
+        print_markdown_table(
+            &["", "strategy"],
+            vec![
+                (
+                    vec!["A".to_string(), compare.a.to_string()],
+                    Overview::of(&pairs.iter().map(|(a, _)| a).collect::<Vec<_>>()),
+                ),
+                (
+                    vec!["B".to_string(), compare.b.to_string()],
+                    Overview::of(&pairs.iter().map(|(_, b)| b).collect::<Vec<_>>()),
+                ),
+            ],
+        );
+        return Ok(());
+    }
+    println!("\n{:>20} {:>8} {:>8} {:>8}", "seed", "A", "B", "A - B");
+    for (a, b) in &pairs {
+        println!(
+            "{:>20} {:>8} {:>8} {:>8}",
+            a.seed,
+            a.score,
+            b.score,
+            a.score - b.score
+        );
+    }
+    let differences: Vec<f64> = pairs
+        .iter()
+        .map(|(a, b)| (a.score - b.score) as f64)
+        .collect();
+    let wins = differences.iter().filter(|&&d| d > 0.0).count();
+    let losses = differences.iter().filter(|&&d| d < 0.0).count();
+    println!(
+        "\nA wins on {wins} seeds, B wins on {losses} seeds, {} ties",
+        differences.len() - wins - losses
+    );
+    for (name, results) in [
+        ("A", pairs.iter().map(|(a, _)| a).collect::<Vec<_>>()),
+        ("B", pairs.iter().map(|(_, b)| b).collect()),
+    ] {
+        print!("{name}: ");
+        print_latencies(&results);
+        print!("{name}: ");
+        print_search_stats(&results);
+    }
+    if let Some(summary) = stats::Summary::of(&differences) {
+        println!("Score difference A - B (#actions):\n{summary}");
+        print_confidence_intervals(&differences);
+        let (p_a, p_b) = (
+            stats::paired_p_value(&differences),
+            stats::paired_p_value(&differences.iter().map(|d| -d).collect::<Vec<_>>()),
+        );
+        if p_a < SIGNIFICANCE {
+            println!("A better than B with p < {p_a:.4}");
+        } else if p_b < SIGNIFICANCE {
+            println!("B better than A with p < {p_b:.4}");
+        } else {
+            println!(
+                "No significant difference (p = {:.4}), more games are needed to tell A and B apart",
+                p_a.min(p_b)
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Rating of a strategy whose strength is the geometric mean of all strengths
+const BASE_RATING: f64 = 1500.0;
+
+/// Plays all strategies on the same seeds and prints their Elo ratings.
+///
+/// On each seed, every pair of strategies is compared: the one with the higher score wins. A Bradley-Terry model is
+/// fitted on these outcomes and its strengths are shown on the Elo scale, where a difference of 400 points means
+/// that the stronger strategy wins 10 times more often than it loses.
+fn rate_strategies(args: &Args, rate: &RateArgs) -> anyhow::Result<()> {
+    let strategies = &rate.strategies;
+    anyhow::ensure!(strategies.len() >= 2, "At least two strategies are needed");
+    let limits = Limits::from_args(args);
+    let seeds = game_seeds(args)?;
+    for (i, strategy) in strategies.iter().enumerate() {
+        info!(args, "{i}: {strategy}");
+    }
+    info!(args, "Seeds: {}", describe_seeds(&seeds));
+
+    let games = play_on_shared_seeds(seeds, strategies.len(), |i, seed| {
+        play(&strategies[i], seed, limits, false, None, None, None)
+    })?;
+
+    // wins[i][j]: number of seeds on which strategy i scored more than strategy j, ties counting for half
+    let n = strategies.len();
+    let mut wins = vec![vec![0.0; n]; n];
+    for results in &games {
+        for i in 0..n {
+            for j in 0..n {
+                if i != j {
+                    wins[i][j] += match results[i].score.total_cmp(&results[j].score) {
+                        std::cmp::Ordering::Greater => 1.0,
+                        std::cmp::Ordering::Equal => 0.5,
+                        std::cmp::Ordering::Less => 0.0,
+                    };
+                }
+            }
+        }
+    }
+    let ratings: Vec<f64> = stats::bradley_terry(&wins)
+        .iter()
+        .map(|strength| BASE_RATING + 400.0 * strength.log10())
+        .collect();
+    let mut ranking: Vec<usize> = (0..n).collect();
+    ranking.sort_by(|&a, &b| ratings[b].total_cmp(&ratings[a]));
+
+    if args.format == Format::Md {
+        let rows = ranking
+            .iter()
+            .enumerate()
+            .map(|(rank, &i)| {
+                let labels = vec![
+                    (rank + 1).to_string(),
+                    format!("{:.0}", ratings[i]),
+                    strategies[i].to_string(),
+                ];
+                let results: Vec<&GameResult> = games.iter().map(|results| &results[i]).collect();
+                (labels, Overview::of(&results))
+            })
+            .collect();
+        print_markdown_table(&["rank", "rating", "strategy"], rows);
+        return Ok(());
+    }
+    println!("\nWins of the strategy of each row against the strategy of each column:");
+    print!("{:>4}", "");
+    for j in 0..n {
+        print!(" {j:>6}");
+    }
+    println!();
+    for (i, row) in wins.iter().enumerate() {
+        print!("{i:>4}");
+        for (j, w) in row.iter().enumerate() {
+            if i == j {
+                print!(" {:>6}", "-");
+            } else {
+                print!(" {w:>6.1}");
+            }
+        }
+        println!();
+    }
+
+    println!(
+        "\n{:>4} {:>8} {:>12}  strategy",
+        "rank", "rating", "mean score"
+    );
+    for (rank, &i) in ranking.iter().enumerate() {
+        let scores: Vec<f64> = games
+            .iter()
+            .map(|results| results[i].score as f64)
+            .collect();
+        println!(
+            "{:>4} {:>8.0} {:>12.1}  {}",
+            rank + 1,
+            ratings[i],
+            stats::mean(&scores),
+            strategies[i]
+        );
+    }
+    Ok(())
+}
+
+/// Parses a range of depths: `2..=6`, `2..7` or a single depth.
+fn parse_depths(s: &str) -> anyhow::Result<RangeInclusive<usize>> {
+    let parse = |depth: &str| {
+        depth
+            .trim()
+            .parse::<usize>()
+            .with_context(|| format!("Invalid depth: {depth}"))
+    };
+    let range = if let Some((low, high)) = s.split_once("..=") {
+        parse(low)?..=parse(high)?
+    } else if let Some((low, high)) = s.split_once("..") {
+        parse(low)?
+            ..=parse(high)?
+                .checked_sub(1)
+                .context("Empty range of depths")?
+    } else {
+        let depth = parse(s)?;
+        depth..=depth
+    };
+    anyhow::ensure!(!range.is_empty(), "Empty range of depths: {s}");
+    Ok(range)
+}
+
+/// Plays the strategy at each depth on the same seeds, and prints the score and time per move at each depth.
+fn sweep_depths(args: &Args, depths: RangeInclusive<usize>) -> anyhow::Result<()> {
+    let strategies: Vec<Strategy> = depths
+        .clone()
+        .map(|depth| args.strategy.with_depth(depth))
+        .collect::<Option<_>>()
+        .with_context(|| {
+            format!(
+                "The strategy `{}` has no depth, use for instance `--strategy expectimax`",
+                args.strategy
+            )
+        })?;
+    let limits = Limits::from_args(args);
+    let seeds = game_seeds(args)?;
+    info!(args, "Strategy: {}", args.strategy);
+    info!(args, "Seeds: {}", describe_seeds(&seeds));
+
+    let games = play_on_shared_seeds(seeds, strategies.len(), |i, seed| {
+        play(&strategies[i], seed, limits, false, None, None, None)
+    })?;
+
+    let labels: Vec<String> = depths.map(|depth| depth.to_string()).collect();
+    print_sweep(args, "depth", &labels, &games);
+    Ok(())
+}
+
+/// Plays `n` variants on each seed, with `play_variant(i, seed)` playing the variant `i`.
+///
+/// Returns the results of each seed, in the order of the variants.
+fn play_on_shared_seeds(
+    seeds: Vec<u64>,
+    n: usize,
+    play_variant: impl Fn(usize, u64) -> anyhow::Result<GameResult> + Sync,
+) -> anyhow::Result<Vec<Vec<GameResult>>> {
+    let progress = progress_bar(seeds.len() as u64);
+    let games = seeds
+        .into_par_iter()
+        .map(|seed| {
+            let results = (0..n)
+                .map(|i| play_variant(i, seed))
+                .collect::<anyhow::Result<_>>()?;
+            progress.inc(1);
+            Ok(results)
+        })
+        .collect::<anyhow::Result<_>>()?;
+    progress.finish_and_clear();
+    Ok(games)
+}
+
+/// Prints a table of the score and time per move of each variant, whose results are given seed by seed.
+fn print_sweep(args: &Args, header: &str, labels: &[String], games: &[Vec<GameResult>]) {
+    if args.format == Format::Md {
+        let rows = labels
+            .iter()
+            .enumerate()
+            .map(|(i, label)| {
+                let results: Vec<&GameResult> = games.iter().map(|results| &results[i]).collect();
+                (vec![label.clone()], Overview::of(&results))
+            })
+            .collect();
+        print_markdown_table(&[header], rows);
+        return;
+    }
+    let width = labels
+        .iter()
+        .map(|label| label.len())
+        .chain([header.len()])
+        .max()
+        .unwrap_or(0);
+    println!(
+        "\n{header:>width$} {:>12} {:>10} {:>12} {:>10} {:>10} {:>9}",
+        "mean score", "median", "2048 score", "ms/move", "p95 ms", "overruns"
+    );
+    for (i, label) in labels.iter().enumerate() {
+        let results: Vec<&GameResult> = games.iter().map(|results| &results[i]).collect();
+        let scores: Vec<f64> = results.iter().map(|result| result.score as f64).collect();
+        let merge_scores: Vec<f64> = results
+            .iter()
+            .map(|result| result.merge_score as f64)
+            .collect();
+        let mut times: Vec<f64> = results
+            .iter()
+            .flat_map(|result| result.move_times.iter().copied())
+            .collect();
+        times.sort_by(f64::total_cmp);
+        let overruns: usize = results.iter().map(|result| result.overruns).sum();
+        println!(
+            "{label:>width$} {:>12.1} {:>10.1} {:>12.1} {:>10.3} {:>10.3} {overruns:>9}",
+            stats::mean(&scores),
+            stats::median(&scores),
+            stats::mean(&merge_scores),
+            stats::mean(&times) * 1000.0,
+            stats::quantile(&times, 0.95) * 1000.0,
+        );
+    }
+}
+
+/// Plays the strategy with each evaluation on the same seeds, and prints the score and time per move with each one.
+///
+/// An evaluation is either the name of a preset or a weights file, and the heuristics of `--disable` are switched
+/// off in all of them.
+fn sweep_evals(args: &Args, evals: &[String]) -> anyhow::Result<()> {
+    let evaluators: Vec<Arc<dyn eval::Evaluate>> = evals
+        .iter()
+        .map(|name| {
+            let mut weights = if eval::presets::names().any(|preset| preset == name) {
+                eval::load_weights(None, Some(name))?
+            } else {
+                eval::load_weights(Some(Path::new(name)), None)?
+            };
+            for heuristic in &args.disable {
+                weights.disable(heuristic)?;
+            }
+            Ok(Arc::new(eval::Evaluator::new(weights)) as Arc<dyn eval::Evaluate>)
+        })
+        .collect::<anyhow::Result<_>>()?;
+    let limits = Limits::from_args(args);
+    let seeds = game_seeds(args)?;
+    info!(args, "Strategy: {}", args.strategy);
+    info!(args, "Seeds: {}", describe_seeds(&seeds));
+
+    let games = play_on_shared_seeds(seeds, evaluators.len(), |i, seed| {
+        eval::with_evaluator(evaluators[i].clone(), || {
+            // evaluations memoized with another evaluator are wrong for this one
+            search::clear_eval_cache();
+            play(&args.strategy, seed, limits, false, None, None, None)
+        })
+    })?;
+    print_sweep(args, "eval", evals, &games);
+    Ok(())
+}
+
+/// Statistics of the games of a strategy, as shown in the tables comparing strategies
+#[derive(serde::Serialize)]
+struct Overview {
+    num_games: usize,
+    mean_score: f64,
+    median_score: f64,
+    /// Fraction of the games reaching the 2048 tile
+    win_rate: f64,
+    /// Mean time to select an action, in milliseconds
+    ms_per_move: f64,
+}
+
+impl Overview {
+    fn of(results: &[&GameResult]) -> Overview {
+        let times: Vec<f64> = results
+            .iter()
+            .flat_map(|result| result.move_times.iter().copied())
+            .collect();
+        Self::new(
+            results
+                .iter()
+                .map(|result| (result.score, result.board.board().max_tile())),
+            stats::mean(&times) * 1000.0,
+        )
+    }
+
+    /// Overview of games aggregated from past runs, where the time per move is weighted by the length of each game
+    fn of_past(games: &[&PastGame]) -> Overview {
+        let num_moves: f64 = games.iter().map(|game| game.score as f64).sum();
+        let total_time: f64 = games
+            .iter()
+            .map(|game| game.mean_move_time * game.score as f64)
+            .sum();
+        Self::new(
+            games.iter().map(|game| (game.score, game.max_tile)),
+            total_time / num_moves * 1000.0,
+        )
+    }
+
+    /// Overview of games given by their score and max tile
+    fn new(games: impl Iterator<Item = (f32, u8)>, ms_per_move: f64) -> Overview {
+        let (scores, max_tiles): (Vec<f64>, Vec<u8>) = games
+            .map(|(score, max_tile)| (score as f64, max_tile))
+            .unzip();
+        let wins = max_tiles.iter().filter(|&&tile| tile >= WIN_TILE).count();
+        Overview {
+            num_games: scores.len(),
+            mean_score: stats::mean(&scores),
+            median_score: stats::median(&scores),
+            win_rate: wins as f64 / scores.len().max(1) as f64,
+            ms_per_move,
+        }
+    }
+}
+
+/// Prints a Markdown table with a row per strategy: its labels (one per column of `label_columns`), followed by
+/// statistics over its games.
+fn print_markdown_table(label_columns: &[&str], rows: Vec<(Vec<String>, Overview)>) {
+    let markdown_row = |cells: &[String]| {
+        let cells: Vec<String> = cells.iter().map(|cell| cell.replace('|', "\\|")).collect();
+        format!("| {} |", cells.join(" | "))
+    };
+    let metrics = ["games", "mean score", "median", "win rate", "ms/move"];
+    let header: Vec<String> = label_columns
+        .iter()
+        .chain(&metrics)
+        .map(|column| column.to_string())
+        .collect();
+    // labels are aligned to the left and numbers to the right
+    let alignment: Vec<String> = label_columns
+        .iter()
+        .map(|_| ":---")
+        .chain(metrics.iter().map(|_| "---:"))
+        .map(str::to_string)
+        .collect();
+    println!("{}", markdown_row(&header));
+    println!("{}", markdown_row(&alignment));
+    for (mut cells, overview) in rows {
+        cells.extend([
+            overview.num_games.to_string(),
+            format!("{:.1}", overview.mean_score),
+            format!("{:.1}", overview.median_score),
+            format!("{:.1}%", overview.win_rate * 100.0),
+            format!("{:.3}", overview.ms_per_move),
+        ]);
+        println!("{}", markdown_row(&cells));
+    }
+}
+
+/// Level of the confidence intervals
+const CONFIDENCE: f64 = 0.95;
+
+/// Threshold on the p-value for a difference between strategies to be reported as significant
+const SIGNIFICANCE: f64 = 0.05;
+
+/// Prints bootstrap confidence intervals of the mean and median of the values.
+fn print_confidence_intervals(values: &[f64]) {
+    for (name, statistic) in [
+        ("mean", stats::mean as fn(&[f64]) -> f64),
+        ("median", stats::median),
+    ] {
+        let (low, high) = stats::bootstrap_ci(values, statistic, CONFIDENCE);
+        println!(
+            "  {:.0}% confidence interval of the {name}: [{low:.2}, {high:.2}]",
+            CONFIDENCE * 100.0
+        );
+    }
+}
+
+/// Parses a tile given by its value (a power of two, e.g. 2048) into its exponent.
+fn parse_tile(s: &str) -> anyhow::Result<u8> {
+    let value: u32 = s.parse().with_context(|| format!("Invalid tile: {s}"))?;
+    anyhow::ensure!(
+        value >= 2 && value.is_power_of_two(),
+        "A tile is a power of two (e.g. 2048), got {value}"
+    );
+    Ok(value.trailing_zeros() as u8)
+}
+
+/// Prints the fraction of games reaching the target tile, and the number of moves needed to reach it.
+fn print_target_stats(target: u8, results: &[&GameResult]) {
+    let reached: Vec<f64> = results
+        .iter()
+        .map(|result| f64::from(u8::from(result.target_moves.is_some())))
+        .collect();
+    if reached.is_empty() {
+        return;
+    }
+    let (low, high) = stats::bootstrap_ci(&reached, stats::mean, CONFIDENCE);
+    println!(
+        "Target {}: reached in {}/{} games ({:.1}%, {:.0}% confidence interval [{:.1}%, {:.1}%])",
+        1u32 << target,
+        reached.iter().filter(|&&r| r > 0.0).count(),
+        reached.len(),
+        stats::mean(&reached) * 100.0,
+        CONFIDENCE * 100.0,
+        low * 100.0,
+        high * 100.0
+    );
+    let moves: Vec<f64> = results
+        .iter()
+        .filter_map(|result| result.target_moves)
+        .map(|moves| moves as f64)
+        .collect();
+    if let Some(summary) = stats::Summary::of(&moves) {
+        println!("Number of moves to reach {}:\n{summary}", 1u32 << target);
+    }
+}
+
+/// Survival curves of the games, by number of moves and by 2048 score (see `stats::survival_curve`).
+fn survival_curves(
+    results: &[&GameResult],
+    stop_at_target: bool,
+) -> [(&'static str, Vec<(f64, f64)>); 2] {
+    // whether the game was interrupted, rather than ending because no action was applicable
+    let interrupted =
+        |result: &GameResult| result.timed_out || (stop_at_target && result.target_moves.is_some());
+    let by = |value: fn(&GameResult) -> f64| {
+        let samples: Vec<(f64, bool)> = results
+            .iter()
+            .map(|result| (value(result), !interrupted(result)))
+            .collect();
+        stats::survival_curve(&samples)
+    };
+    [
+        ("moves", by(|result| result.score as f64)),
+        ("merge_score", by(|result| result.merge_score as f64)),
+    ]
+}
+
+/// Writes the survival curves as CSV, with columns `metric` (`moves` or `merge_score`), `value` and `survival`.
+fn write_survival_curves(
+    path: &Path,
+    results: &[&GameResult],
+    stop_at_target: bool,
+) -> anyhow::Result<()> {
+    let mut csv = String::from("metric,value,survival\n");
+    for (metric, curve) in survival_curves(results, stop_at_target) {
+        for (value, survival) in curve {
+            csv.push_str(&format!("{metric},{value},{survival}\n"));
+        }
+    }
+    std::fs::write(path, csv).with_context(|| format!("Cannot write {}", path.display()))
+}
+
+/// Prints statistics on the time taken to select each action, over all games.
+fn print_latencies(results: &[&GameResult]) {
+    let mut times: Vec<f64> = results
+        .iter()
+        .flat_map(|result| result.move_times.iter().copied())
+        .collect();
+    if times.is_empty() {
+        return;
+    }
+    times.sort_by(f64::total_cmp);
+    let ms = |seconds: f64| seconds * 1000.0;
+    let overruns: usize = results.iter().map(|result| result.overruns).sum();
+    println!(
+        "Time per move (ms): mean {:.3}   p95 {:.3}   p99 {:.3}   max {:.3}   overruns: {overruns} ({:.2}%)",
+        ms(stats::mean(&times)),
+        ms(stats::quantile(&times, 0.95)),
+        ms(stats::quantile(&times, 0.99)),
+        ms(times[times.len() - 1]),
+        overruns as f64 / times.len() as f64 * 100.0
+    );
+}
+
+/// Prints the throughput of the searches over all games, as reported by the search (see `search::record_stats`).
+fn print_search_stats(results: &[&GameResult]) {
+    let mut totals = search::SearchTotals::default();
+    for result in results {
+        totals.add(&result.search);
+    }
+    if totals.num_searches == 0 {
+        println!("Search: no statistics (not reported by the strategy)");
+        return;
+    }
+    // time spent selecting actions, in seconds
+    let time: f64 = results
+        .iter()
+        .flat_map(|result| result.move_times.iter())
+        .sum();
+    let peak_memory = results
+        .iter()
+        .map(|result| result.peak_cache_memory)
+        .max()
+        .unwrap_or(0);
+    println!(
+        "Search: {:.0} nodes/s   {:.0} evals/s   cache hits {:.1}%   average depth {:.2}   peak cache memory {:.1} MiB",
+        totals.num_nodes as f64 / time,
+        totals.num_evals as f64 / time,
+        totals.num_cache_hits as f64 / totals.num_evals.max(1) as f64 * 100.0,
+        totals.total_depth as f64 / totals.num_searches as f64,
+        peak_memory as f64 / (1024.0 * 1024.0)
+    );
+}
+
+/// Width in characters of the longest bar of the histogram
+const HISTOGRAM_WIDTH: usize = 40;
+
+/// Number of games ending with each max tile, from the smallest to the largest max tile reached.
+fn max_tile_histogram(results: &[&GameResult]) -> Vec<(u8, usize)> {
+    let max_tiles: Vec<u8> = results
+        .iter()
+        .map(|result| result.board.board().max_tile())
+        .collect();
+    let (Some(&lowest), Some(&highest)) = (max_tiles.iter().min(), max_tiles.iter().max()) else {
+        return Vec::new();
+    };
+    (lowest..=highest)
+        .map(|tile| (tile, max_tiles.iter().filter(|&&t| t == tile).count()))
+        .collect()
+}
+
+/// Dashboard of the builds without the `tui` feature, where none can be shown and `play` is never given one
+#[cfg(not(feature = "tui"))]
+enum Dashboard {}
+
+#[cfg(not(feature = "tui"))]
+impl Dashboard {
+    fn update(&self, _seed: u64, _board: PlayableBoard, _num_moves: usize) {
+        match *self {}
+    }
+
+    fn finish(&self, _score: f32, _board: &PlayableBoard) {
+        match *self {}
+    }
+}
+
+/// Play a game with the given strategy and time limits, where random tiles are drawn from a generator seeded with `seed`.
+///
+/// With `verbose`, each action and the resulting board are printed. Each board is also shown on the dashboard if any,
+/// and written with the value of each action to the collector if any.
+fn play(
+    strategy: &Strategy,
+    seed: u64,
+    limits: Limits,
+    verbose: bool,
+    replays: Option<&Path>,
+    dashboard: Option<&Dashboard>,
+    collector: Option<&Collector>,
+) -> anyhow::Result<GameResult> {
+    // timestamp of when we started to play
+    let start = Instant::now();
+
+    // count of the number of move played
+    let mut num_moves = 0;
+    let mut merge_score = 0;
+    let mut move_times = Vec::new();
+    let mut overruns = 0;
+    // discard the statistics of previous games played by this thread
+    search::take_search_totals();
+    let mut search = search::SearchTotals::default();
+    let mut peak_cache_memory = 0;
+    let mut target_moves = None;
+    // random tiles and random decisions of the strategy are drawn from independent streams of the seed
+    let mut rng = StdRng::seed_from_u64(seed);
+    search::seed_strategy_rng(seed ^ search::STRATEGY_STREAM);
+    let mut board = PlayableBoard::init_with(&mut rng);
+    let mut replay = match replays {
+        Some(dir) => {
+            let mut writer = ReplayWriter::create(&dir.join(format!("game-{seed}.jsonl")))?;
+            writer.write(&Event::Start {
+                seed,
+                strategy: strategy.to_string(),
+                board: *board.board(),
+            })?;
+            Some(writer)
+        }
+        None => None,
+    };
+    let mut samples = Vec::new();
+
+    loop {
+        if verbose {
+            println!("{board}");
+        }
+        if let Some(dashboard) = dashboard {
+            dashboard.update(seed, board, num_moves);
+        }
+        if target_moves.is_none()
+            && limits
+                .target
+                .is_some_and(|target| board.board().max_tile() >= target)
+        {
+            target_moves = Some(num_moves);
+        }
+        let reached_target = limits.stop_at_target && target_moves.is_some();
+        let action = if reached_target {
+            None
+        } else {
+            let start_action_selection = Instant::now();
+            let action = match limits.per_move {
+                Some(budget) => strategy.select_action_within(board, budget),
+                None => strategy.select_action(board),
+            };
+            let move_time = start_action_selection.elapsed();
+            if let Some(action) = action {
+                // the seed tells apart the lines of the games played in parallel
+                log::info!(
+                    "[seed {seed}] move {}: {action:?} in {:.2}ms",
+                    num_moves + 1,
+                    move_time.as_secs_f64() * 1000.0
+                );
+            }
+            move_times.push(move_time.as_secs_f64());
+            search.add(&search::take_search_totals());
+            peak_cache_memory = peak_cache_memory.max(search::eval_cache_memory());
+            if limits.per_move.is_some_and(|budget| move_time > budget) {
+                overruns += 1;
+                log::info!(
+                    "[seed {seed}] overrun on move {}: {:.1}ms",
+                    num_moves + 1,
+                    move_time.as_secs_f64() * 1000.0
+                );
+            }
+            action
+        };
+        if interrupt::interrupted() {
+            return Err(Interrupted.into());
+        }
+        let timed_out = start.elapsed() > limits.game;
+        let Some(action) = action.filter(|_| !timed_out) else {
+            if let Some(mut replay) = replay {
+                replay.write(&Event::End {
+                    num_moves,
+                    merge_score,
+                    timed_out,
+                })?;
+                replay.finish()?;
+            }
+            if let Some(collector) = collector {
+                collector.add_game(&samples)?;
+            }
+            return Ok(GameResult {
+                seed,
+                score: num_moves as f32,
+                merge_score,
+                board,
+                timed_out,
+                move_times,
+                overruns,
+                search,
+                peak_cache_memory,
+                target_moves,
+            });
+        };
+
+        // the value of each action for the strategy, for the collector and the replay
+        let values = (collector.is_some() || replay.is_some()).then(|| {
+            let values = strategy.evaluate_all_actions(board);
+            // this search is not part of the decision: its statistics would be counted with the next move
+            search::take_search_totals();
+            values
+        });
+        if collector.is_some() {
+            samples.push(Sample {
+                seed,
+                board: *board.board(),
+                values: values.unwrap_or_default(),
+                action,
+            });
+        }
+        num_moves += 1;
+        let (played, action_score) = board.apply_scored(action).with_context(|| {
+            format!("Got inapplicable action {action:?} on board (seed {seed})\n{board}")
+        })?;
+        merge_score += action_score;
+        let next = played.with_random_tile_with(&mut rng);
+        if let Some(replay) = &mut replay {
+            let index = ALL_ACTIONS.iter().position(|&a| a == action);
+            let value = values.and_then(|values| values[index?]);
+            replay.write(&Event::Move {
+                action,
+                value: value.unwrap_or_else(|| played.evaluate()),
+                score: action_score,
+                spawn: Spawn::between(played.board(), next.board())
+                    .context("exactly one tile is placed after each action")?,
+            })?;
+        }
+        board = next;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::distill::LookupPolicy;
+
+    #[test]
+    fn test_play_collect() {
+        let dir = std::env::temp_dir().join(format!("ai-2048-bench-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("samples.bin");
+        let collector = Collector::create(&path).unwrap();
+        // a policy without preferences: the value of every action is 0, unlike the evaluation of its afterstate
+        let strategy = Strategy::Distilled(Box::leak(Box::new(LookupPolicy::new())));
+        let limits = Limits {
+            game: Duration::from_secs(60),
+            per_move: None,
+            target: None,
+            stop_at_target: false,
+        };
+        search::take_search_totals();
+        let result = play(
+            &strategy,
+            5,
+            limits,
+            false,
+            Some(&dir),
+            None,
+            Some(&collector),
+        )
+        .unwrap();
+        // one sample per action played
+        assert_eq!(collector.finish().unwrap(), result.score as u64);
+        let samples = crate::collect::read(&path).unwrap();
+        assert!(samples.iter().all(|sample| sample
+            .values
+            .iter()
+            .flatten()
+            .all(|&value| value == 0.0)));
+        // the replay stores the value of each action played for the strategy
+        let events = crate::replay::read(&dir.join("game-5.jsonl")).unwrap();
+        let values: Vec<f32> = events
+            .iter()
+            .filter_map(|event| match event {
+                Event::Move { value, .. } => Some(*value),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(values, vec![0.0; samples.len()]);
+        // valuing the actions is not counted as a search of the strategy
+        assert_eq!(result.search, search::SearchTotals::default());
+        assert_eq!(
+            search::take_search_totals(),
+            search::SearchTotals::default()
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_overruns() {
+        let limits = |game, per_move| Limits {
+            game,
+            per_move,
+            target: None,
+            stop_at_target: false,
+        };
+        let minute = Duration::from_secs(60);
+        let play = |limits| play(&Strategy::Random, 7, limits, false, None, None, None).unwrap();
+        let unlimited = play(limits(minute, None));
+        assert_eq!(unlimited.overruns, 0);
+        assert!(!unlimited.timed_out);
+
+        // the strategy ignores the budget: the same game is played, and every decision taking time overruns it
+        let within = play(limits(minute, Some(minute)));
+        assert_eq!(within.overruns, 0);
+        assert_eq!(within.score, unlimited.score);
+        let overrun = play(limits(minute, Some(Duration::ZERO)));
+        assert_eq!(overrun.score, unlimited.score);
+        assert_eq!(
+            overrun.overruns,
+            overrun
+                .move_times
+                .iter()
+                .filter(|&&time| time > 0.0)
+                .count()
+        );
+        assert!(overrun.overruns > 0);
+
+        // past the time of the game, the decision is dropped and the game ends there
+        let timed_out = play(limits(Duration::ZERO, None));
+        assert!(timed_out.timed_out);
+        assert_eq!(timed_out.score, 0.0);
+        assert_eq!(timed_out.move_times.len(), 1);
+    }
+}
@@ -0,0 +1,106 @@
+//! Blunder analysis of recorded games (`main analyze-replay`): each move of the game is re-examined by a much
+//! deeper search, and the moves whose action has a value significantly below the value of the best action for the
+//! deep search are reported as blunders, with the position and the gap. Looking at the positions of the largest
+//! blunders is the quickest way to find what a heuristic misjudges.
+
+use rayon::prelude::*;
+
+use crate::board::{Action, Board, PlayableBoard, ALL_ACTIONS};
+use crate::strategy::Strategy;
+
+/// A move re-examined by the deep search
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Review {
+    /// Number of the move in the game, from 1
+    pub number: usize,
+    pub board: Board,
+    pub played: Action,
+    /// Value of the action played for the deep search
+    pub played_value: f32,
+    /// Best action for the deep search, and its value
+    pub best: Action,
+    pub best_value: f32,
+}
+
+impl Review {
+    /// How much value was lost by playing the action, 0 if it is as good as the best one
+    pub fn gap(&self) -> f32 {
+        self.best_value - self.played_value
+    }
+
+    /// Gap relative to the magnitude of the best value (at least 1, for values close to 0)
+    pub fn relative_gap(&self) -> f32 {
+        self.gap() / self.best_value.abs().max(1.0)
+    }
+}
+
+/// Re-examines each position of the game (the board and the action played on it) with the strategy, in parallel.
+/// Positions on which the action played has no value for the strategy (e.g. it is not applicable) are left out.
+pub fn review(positions: &[(Board, Action)], strategy: &Strategy) -> Vec<Review> {
+    positions
+        .par_iter()
+        .enumerate()
+        .filter_map(|(i, &(board, played))| {
+            let values = strategy.evaluate_all_actions(PlayableBoard::from(board));
+            let value_of = |action: Action| {
+                let index = ALL_ACTIONS.iter().position(|&a| a == action)?;
+                values[index]
+            };
+            let (best, best_value) = ALL_ACTIONS
+                .into_iter()
+                .filter_map(|action| Some((action, value_of(action)?)))
+                .max_by(|(_, v1), (_, v2)| v1.total_cmp(v2))?;
+            Some(Review {
+                number: i + 1,
+                board,
+                played,
+                played_value: value_of(played)?,
+                best,
+                best_value,
+            })
+        })
+        .collect()
+}
+
+/// Reviews whose relative gap is above the threshold, from the largest gap to the smallest
+pub fn blunders(reviews: &[Review], threshold: f32) -> Vec<Review> {
+    let mut blunders: Vec<Review> = reviews
+        .iter()
+        .filter(|review| review.relative_gap() > threshold)
+        .copied()
+        .collect();
+    blunders.sort_by(|a, b| b.relative_gap().total_cmp(&a.relative_gap()));
+    blunders
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_blunders() {
+        let board = Board::from_values([[2, 4, 8, 16], [0; 4], [0; 4], [0, 0, 0, 2]]).unwrap();
+        let values = Strategy::Random.evaluate_all_actions(board.into());
+        let (best, _) = ALL_ACTIONS
+            .into_iter()
+            .zip(values)
+            .filter_map(|(action, value)| Some((action, value?)))
+            .max_by(|(_, v1), (_, v2)| v1.total_cmp(v2))
+            .unwrap();
+        let worst = ALL_ACTIONS
+            .into_iter()
+            .zip(values)
+            .filter_map(|(action, value)| Some((action, value?)))
+            .min_by(|(_, v1), (_, v2)| v1.total_cmp(v2))
+            .unwrap()
+            .0;
+        let reviews = review(&[(board, best), (board, worst)], &Strategy::Random);
+        assert_eq!(reviews.len(), 2);
+        assert_eq!(reviews[0].gap(), 0.0);
+        assert_eq!(reviews[1].number, 2);
+        assert!(reviews[1].gap() > 0.0);
+        let blunders = blunders(&reviews, 0.0);
+        assert_eq!(blunders.len(), 1);
+        assert_eq!(blunders[0].played, worst);
+    }
+}
@@ -2,28 +2,57 @@ use colored::*;
 use rand::Rng;
 use std::fmt::{Debug, Display, Formatter};
 
+#[cfg(all(feature = "simd", target_arch = "x86_64"))]
+pub mod simd;
+
 // A board on which the next thing to do is to play.
-#[derive(Clone, Copy, Hash, PartialEq, Eq)]
+#[derive(Clone, Copy, Hash, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 pub struct PlayableBoard(Board);
 
 impl PlayableBoard {
     /// Returns an initial board, with a single random tile.
     pub fn init() -> PlayableBoard {
+        Self::init_with(&mut rand::rng())
+    }
+
+    /// Returns an initial board, where the random tile is drawn from the given random number generator.
+    pub fn init_with(rng: &mut impl Rng) -> PlayableBoard {
         let mut board = Board::EMPTY;
-        board.add_random();
+        board.add_random_with(rng);
         PlayableBoard(board)
     }
 
     pub fn apply(&self, action: Action) -> Option<RandableBoard> {
-        match self.0.apply(action) {
-            Some(board) => Some(RandableBoard(board)),
-            None => None,
-        }
+        self.0.apply(action).map(RandableBoard)
+    }
+
+    /// Same as `apply`, but also returns the score of the action in the classic 2048 game (see `Board::apply_scored`).
+    pub fn apply_scored(&self, action: Action) -> Option<(RandableBoard, u32)> {
+        self.0
+            .apply_scored(action)
+            .map(|(board, score)| (RandableBoard(board), score))
     }
 
     pub fn has_at_least_tile(&self, i: u8) -> bool {
         self.0.cells.iter().flatten().any(|tile| *tile >= i)
     }
+
+    /// The underlying board
+    pub fn board(&self) -> &Board {
+        &self.0
+    }
+
+    /// Evaluates the board as a state, i.e. from the value of its best afterstate.
+    pub fn evaluate(&self) -> f32 {
+        crate::eval::eval_state(self)
+    }
+}
+
+impl From<Board> for PlayableBoard {
+    /// A board on which the player is to act, e.g. one loaded from a file.
+    fn from(board: Board) -> Self {
+        PlayableBoard(board)
+    }
 }
 
 impl Display for PlayableBoard {
@@ -38,11 +67,21 @@ pub struct RandableBoard(Board);
 
 impl RandableBoard {
     pub fn with_random_tile(&self) -> PlayableBoard {
+        self.with_random_tile_with(&mut rand::rng())
+    }
+
+    /// Same as `with_random_tile` but the tile is drawn from the given random number generator.
+    pub fn with_random_tile_with(&self, rng: &mut impl Rng) -> PlayableBoard {
         let mut board = self.0;
-        board.add_random();
+        board.add_random_with(rng);
         PlayableBoard(board)
     }
 
+    /// The underlying board
+    pub fn board(&self) -> &Board {
+        &self.0
+    }
+
     /// Given a board for which an action has already been applied, returns the list of possible successors as a result of placing a random tile (2 or 4) on an empty cell.
     ///
     /// ```rust
@@ -58,8 +97,9 @@ impl RandableBoard {
             .map(|(proba, board)| (proba, PlayableBoard(board)))
     }
 
+    /// Evaluates the board as an afterstate, before the random tile is placed.
     pub fn evaluate(&self) -> f32 {
-        crate::eval::eval(&self.0)
+        crate::eval::eval_afterstate(self)
     }
 }
 
@@ -72,79 +112,174 @@ impl Display for RandableBoard {
 /// Size of board
 pub const N: usize = 4;
 
+/// Exponent of the 2048 tile, which wins the game
+pub const WIN_TILE: u8 = 11;
+
 // A board is an NxN matrix where each entry represents a tile.
 //
 // A tile is encoded by an 8-bits unsigned int where:
 //
 //  - 0 represent the empty tile
 //  - n > 0 represents the tile `2^n`
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
 pub struct Board {
     pub cells: [[u8; N]; N],
 }
 
 impl Board {
     /// The completly empty board. This is not the initial board which can be built with the `PlayableBoard::init` method.
-    const EMPTY: Board = Board { cells: [[0; N]; N] };
+    pub const EMPTY: Board = Board { cells: [[0; N]; N] };
+
+    /// Builds a board from the values of its tiles (0 for empty cells, 2, 4, 8, ...), as written by other programs.
+    pub fn from_values(values: [[u32; N]; N]) -> anyhow::Result<Board> {
+        let mut board = Board::EMPTY;
+        for (row, values) in board.cells.iter_mut().zip(values) {
+            for (cell, value) in row.iter_mut().zip(values) {
+                anyhow::ensure!(
+                    value == 0 || (value >= 2 && value.is_power_of_two()),
+                    "A tile is 0 or a power of two, got {value}"
+                );
+                *cell = if value == 0 {
+                    0
+                } else {
+                    value.trailing_zeros() as u8
+                };
+            }
+        }
+        Ok(board)
+    }
+
+    /// Values of the tiles (0 for empty cells), the inverse of `from_values`.
+    pub fn values(&self) -> [[u32; N]; N] {
+        self.cells
+            .map(|row| row.map(|tile| if tile == 0 { 0 } else { 1 << tile }))
+    }
 
     /// Returns the board resuting from the action, or None if the action is not applicable.
     pub fn apply(&self, action: Action) -> Option<Board> {
-        let mut next = self.clone();
+        self.apply_scored(action).map(|(next, _)| next)
+    }
+
+    /// Same as `apply`, but also returns the score of the action in the classic 2048 game:
+    /// the sum of the values of the tiles created by merges (e.g. 8 when merging two 4s).
+    ///
+    /// With the feature `simd`, the four rows are pushed at once when the CPU allows it (see `simd`).
+    pub fn apply_scored(&self, action: Action) -> Option<(Board, u32)> {
+        #[cfg(all(feature = "simd", target_arch = "x86_64"))]
+        if let Some((next, score)) = simd::push(self, action) {
+            return (next != *self).then_some((next, score));
+        }
+        self.apply_scored_scalar(action)
+    }
+
+    /// Same as `apply_scored`, pushing one row at a time with `push_left`.
+    pub fn apply_scored_scalar(&self, action: Action) -> Option<(Board, u32)> {
+        let mut next = *self;
         // we only know how to push left, so this method:
         // - applies some symmetries to build a board where we can push left
         // - push left
         // - unapply the symmetries to get in the normal configuration
-        match action {
-            Action::Left => {
-                next.push_left();
-            }
+        let score = match action {
+            Action::Left => next.push_left(),
             Action::Up => {
                 next.transpose();
-                next.push_left();
+                let score = next.push_left();
                 next.transpose();
+                score
             }
             Action::Down => {
                 next.transpose();
                 next.swap_lr();
-                next.push_left();
+                let score = next.push_left();
                 next.swap_lr();
                 next.transpose();
+                score
             }
             Action::Right => {
                 next.swap_lr();
-                next.push_left();
+                let score = next.push_left();
                 next.swap_lr();
+                score
             }
-        }
+        };
         if *self != next {
             // the board has changed meaning the action is applicatble, return the resulting board
-            Some(next)
+            Some((next, score))
         } else {
             // Nothing changed, the action is not applicable
             None
         }
     }
 
+    /// Same as `apply`, but also returns how each tile moves, for animations: its cell before and after the action.
+    ///
+    /// Both tiles of a merge move to the cell of the merged tile.
+    pub fn apply_with_events(&self, action: Action) -> Option<(Board, Vec<TileMove>)> {
+        let next = self.apply(action)?;
+        // cell of the `i`-th position of the `line`, starting from the side the tiles are pushed to
+        let cell = |line: usize, i: usize| match action {
+            Action::Left => (line, i),
+            Action::Right => (line, N - 1 - i),
+            Action::Up => (i, line),
+            Action::Down => (N - 1 - i, line),
+        };
+        let mut moves: Vec<TileMove> = Vec::new();
+        for line in 0..N {
+            // same merges as `push_left`: each tile merges with the next one if they are equal and not yet merged
+            let mut target = 0;
+            let mut pending: Option<u8> = None;
+            for i in 0..N {
+                let from = cell(line, i);
+                let tile = self.cells[from.0][from.1];
+                if tile == 0 {
+                    continue;
+                }
+                let merged = pending == Some(tile);
+                if merged {
+                    // merges with the previous tile, already placed at `target - 1`
+                    if let Some(previous) = moves.last_mut() {
+                        previous.merged = true;
+                    }
+                    pending = None;
+                } else {
+                    target += 1;
+                    pending = Some(tile);
+                }
+                moves.push(TileMove {
+                    from,
+                    to: cell(line, target - 1),
+                    tile,
+                    merged,
+                });
+            }
+        }
+        Some((next, moves))
+    }
+
     /// Places a random tile (2 or 4) on an emtpy cell of the board
     pub fn add_random(&mut self) {
+        self.add_random_with(&mut rand::rng())
+    }
+
+    /// Places a random tile (2 or 4) on an empty cell of the board, using the given random number generator
+    pub fn add_random_with(&mut self, rng: &mut impl Rng) {
         // compute the nuber of empty cells
         let n = self.num_empty();
 
         // decide which empty of the cell to update in [0,n)
-        let picked = rand::rng().random_range(0..n);
+        let picked = rng.random_range(0..n);
 
         // get a mutable reference of cell
         let picked = self
             .cells
             .iter_mut()
-            .map(|row| row.iter_mut())
-            .flatten()
+            .flat_map(|row| row.iter_mut())
             .filter(|cell| **cell == 0)
             .nth(picked)
             .unwrap();
 
         // decide which value to put in the cell (2^1 = 2 with probability 0.9, 2^2 = 4 with probability 0.1)
-        let value = if rand::rng().random_bool(0.9) { 1 } else { 2 };
+        let value = if rng.random_bool(0.9) { 1 } else { 2 };
 
         // update the board by setting the value to the selected empty cell
         *picked = value;
@@ -159,6 +294,55 @@ impl Board {
             .count()
     }
 
+    /// Returns true if no action is applicable: the board is full and no two adjacent tiles are equal.
+    pub fn is_lost(&self) -> bool {
+        if self.num_empty() > 0 {
+            return false;
+        }
+        for i in 0..N {
+            for j in 0..(N - 1) {
+                if self.cells[i][j] == self.cells[i][j + 1]
+                    || self.cells[j][i] == self.cells[j + 1][i]
+                {
+                    return false;
+                }
+            }
+        }
+        true
+    }
+
+    /// Packs the board into 64 bits, with each row packed as in `pack_row` (the first row in the least significant bits).
+    ///
+    /// Tiles above `2^15` are clamped to `2^15`, so such boards cannot be distinguished from one another.
+    pub fn pack(&self) -> u64 {
+        self.cells
+            .iter()
+            .rev()
+            .fold(0, |packed, row| (packed << 16) | u64::from(pack_row(row)))
+    }
+
+    /// Inverse of `pack`
+    pub fn unpack(packed: u64) -> Board {
+        Board {
+            cells: std::array::from_fn(|i| unpack_row((packed >> (16 * i)) as u16)),
+        }
+    }
+
+    /// Exponent of the largest tile on the board (e.g. 10 with a 1024 tile), or 0 for an empty board
+    pub fn max_tile(&self) -> u8 {
+        self.cells.iter().flatten().copied().max().unwrap_or(0)
+    }
+
+    /// Sum of the values of all tiles on the board (e.g. `2 + 4 + 4 = 10`)
+    pub fn tile_sum(&self) -> u32 {
+        self.cells
+            .iter()
+            .flatten()
+            .filter(|&&cell| cell != 0)
+            .map(|&cell| 2u32.pow(cell as u32))
+            .sum()
+    }
+
     /// Given a board for which an action has already been applied, returns the list of possible successors as a result of placing a random tile (2 or 4) on an empty cell.
     ///
     /// ```rust
@@ -181,7 +365,7 @@ impl Board {
             [(1, 0.9), (2, 0.1)]
                 .into_iter()
                 .map(move |(new_value, proba)| {
-                    let mut next = self.clone();
+                    let mut next = *self;
                     next.cells[i][j] = new_value;
                     (proba / n, next)
                 })
@@ -214,17 +398,42 @@ impl Board {
 
     /// Build an equivalent board where the lines an columns have been transposed
     pub fn transposed(&self) -> Board {
-        let mut transposed = self.clone();
+        let mut transposed = *self;
         transposed.transpose();
         transposed
     }
 
-    /// Applies the action of playing *Left*
-    fn push_left(&mut self) {
+    /// The 8 boards obtained by rotations and reflections of this one, starting with the board itself.
+    pub fn symmetries(&self) -> [Board; 8] {
+        let mirror = |board: &Board| {
+            let mut mirrored = *board;
+            for row in &mut mirrored.cells {
+                row.reverse();
+            }
+            mirrored
+        };
+        let flip = |board: &Board| {
+            let mut flipped = *board;
+            flipped.cells.reverse();
+            flipped
+        };
+        let transposed = self.transposed();
+        [
+            *self,
+            mirror(self),
+            flip(self),
+            flip(&mirror(self)),
+            transposed,
+            mirror(&transposed),
+            flip(&transposed),
+            flip(&mirror(&transposed)),
+        ]
+    }
+
+    /// Applies the action of playing *Left*, and returns the score of the merges
+    fn push_left(&mut self) -> u32 {
         // apply the mush left method on each line
-        for row in &mut self.cells {
-            push_left(row);
-        }
+        self.cells.iter_mut().map(push_left).sum()
     }
 }
 
@@ -234,29 +443,7 @@ impl Display for Board {
         for row in &self.cells {
             write!(f, "{}", "║ ".bold())?;
             for &cell in row {
-                if cell != 0 {
-                    let value = 2u32.pow(cell as u32);
-                    let formatted = format!("{:^7}", value);
-                    let colored = match value {
-                        2 => formatted.black().on_truecolor(238, 228, 218), // #eee4da
-                        4 => formatted.black().on_truecolor(237, 224, 200), // #ede0c8
-                        8 => formatted.black().on_truecolor(242, 177, 121), // #f2b179
-                        16 => formatted.black().on_truecolor(245, 149, 99), // #f59563
-                        32 => formatted.black().on_truecolor(246, 124, 95), // #f67c5f
-                        64 => formatted.black().on_truecolor(246, 94, 59),  // #f65e3b
-                        128 => formatted.black().on_truecolor(237, 207, 114), // #edcf72
-                        256 => formatted.black().on_truecolor(237, 204, 97), // #edcc61
-                        512 => formatted.black().on_truecolor(237, 200, 80), // #edc850
-                        1024 => formatted.black().on_truecolor(237, 197, 63), // #edc53f
-                        2048 => formatted.black().on_truecolor(237, 194, 46), // 2048 -> #edc22e
-                        _ => formatted.bold().black().on_truecolor(237, 194, 46), // 4096+ -> #edc22e + bold
-                    };
-                    write!(f, "{} ", colored)?;
-                } else {
-                    let formatted = format!("   .   ");
-                    let colored = formatted.black().on_truecolor(205, 193, 180); // #cdc1b4
-                    write!(f, "{} ", colored)?;
-                }
+                write!(f, "{} ", styled_tile(cell))?;
             }
             writeln!(f, "{} ", "║".bold())?;
         }
@@ -265,8 +452,54 @@ impl Display for Board {
     }
 }
 
-/// The set of possible actions to apply on the board.
+/// A tile as drawn in the terminal: its value centered on 7 characters, on the background color of the tile.
+pub fn styled_tile(tile: u8) -> ColoredString {
+    let (r, g, b) = tile_color(tile);
+    if tile != 0 {
+        let formatted = format!("{:^7}", 2u32.pow(tile as u32)).black();
+        // 4096+ share the color of 2048, in bold
+        let formatted = if tile > 11 {
+            formatted.bold()
+        } else {
+            formatted
+        };
+        formatted.on_truecolor(r, g, b)
+    } else {
+        "   .   ".black().on_truecolor(r, g, b)
+    }
+}
+
+/// Background color of a tile given by its exponent (0 for an empty cell), as in the original game.
+pub fn tile_color(tile: u8) -> (u8, u8, u8) {
+    match tile {
+        0 => (205, 193, 180), // #cdc1b4
+        1 => (238, 228, 218), // 2 -> #eee4da
+        2 => (237, 224, 200), // 4 -> #ede0c8
+        3 => (242, 177, 121), // 8 -> #f2b179
+        4 => (245, 149, 99),  // 16 -> #f59563
+        5 => (246, 124, 95),  // 32 -> #f67c5f
+        6 => (246, 94, 59),   // 64 -> #f65e3b
+        7 => (237, 207, 114), // 128 -> #edcf72
+        8 => (237, 204, 97),  // 256 -> #edcc61
+        9 => (237, 200, 80),  // 512 -> #edc850
+        10 => (237, 197, 63), // 1024 -> #edc53f
+        _ => (237, 194, 46),  // 2048+ -> #edc22e
+    }
+}
+
+/// Move of a tile by an action, from its cell `(row, column)` before the action to its cell after it.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TileMove {
+    pub from: (usize, usize),
+    pub to: (usize, usize),
+    /// The tile before the action (as an exponent)
+    pub tile: u8,
+    /// Whether the tile merges with another one
+    pub merged: bool,
+}
+
+/// The set of possible actions to apply on the board.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 pub enum Action {
     Up,
     Down,
@@ -277,8 +510,23 @@ pub enum Action {
 /// An iterable list of all possible actions.
 pub const ALL_ACTIONS: [Action; 4] = [Action::Up, Action::Down, Action::Left, Action::Right];
 
-/// Applies the action of playing "left", on a single Row
-fn push_left(row: &mut [u8; N]) {
+/// Packs a row into 16 bits, with 4 bits per tile (the first tile in the least significant bits).
+///
+/// Tiles above `2^15` do not fit in 4 bits and are clamped to `2^15`.
+pub fn pack_row(row: &[u8; N]) -> u16 {
+    row.iter()
+        .rev()
+        .fold(0, |packed, &tile| (packed << 4) | u16::from(tile.min(15)))
+}
+
+/// Inverse of `pack_row`
+pub fn unpack_row(packed: u16) -> [u8; N] {
+    std::array::from_fn(|i| ((packed >> (4 * i)) & 0xF) as u8)
+}
+
+/// Applies the action of playing "left", on a single Row, and returns the sum of the values of the merged tiles
+pub fn push_left(row: &mut [u8; N]) -> u32 {
+    let mut score = 0;
     let mut write_index = 0; // Position to write next non-zero tile
     let mut read_index = 0; // Reading index
 
@@ -299,6 +547,7 @@ fn push_left(row: &mut [u8; N]) {
             }
             if read_index < N && row[read_index] == value {
                 row[write_index] = value + 1;
+                score += 2u32.pow(value as u32 + 1);
                 read_index += 1; // Skip merged cell
             } else {
                 row[write_index] = value;
@@ -311,6 +560,7 @@ fn push_left(row: &mut [u8; N]) {
     }
 
     row[write_index..].fill(0);
+    score
 }
 
 #[cfg(test)]
@@ -335,6 +585,31 @@ mod tests {
         check([1, 2, 0, 1], [1, 2, 1, 0]);
     }
 
+    #[test]
+    fn test_merge_score() {
+        let mut row = [1, 1, 2, 2];
+        assert_eq!(push_left(&mut row), 4 + 8);
+        let board = Board {
+            cells: [[1, 1, 0, 0], [3, 0, 3, 2], [0; N], [0; N]],
+        };
+        assert_eq!(board.apply_scored(Action::Left).unwrap().1, 4 + 16);
+        assert_eq!(board.apply_scored(Action::Down).unwrap().1, 0);
+    }
+
+    #[test]
+    fn test_pack_row() {
+        for row in [[0, 0, 0, 0], [1, 2, 3, 4], [15, 0, 7, 1]] {
+            assert_eq!(unpack_row(pack_row(&row)), row);
+        }
+        assert_eq!(pack_row(&[1, 0, 0, 0]), 1);
+        assert_eq!(unpack_row(pack_row(&[17, 0, 0, 0])), [15, 0, 0, 0]);
+
+        let board = Board {
+            cells: [[1, 2, 1, 0], [4, 1, 0, 0], [3, 0, 0, 0], [7, 0, 5, 2]],
+        };
+        assert_eq!(Board::unpack(board.pack()), board);
+    }
+
     #[test]
     fn test_actions() {
         let board = Board {
@@ -345,4 +620,47 @@ mod tests {
         };
         assert_eq!(board.apply(Action::Down), Some(target));
     }
+
+    #[test]
+    fn test_apply_with_events() {
+        let board = Board {
+            cells: [[1, 1, 1, 0], [0, 2, 0, 2], [0, 0, 0, 0], [3, 0, 0, 0]],
+        };
+        for action in ALL_ACTIONS {
+            let (next, moves) = board.apply_with_events(action).unwrap();
+            assert_eq!(Some(next), board.apply(action));
+            // replaying the moves gives the board after the action
+            let mut replayed = Board::EMPTY;
+            for m in &moves {
+                let cell = &mut replayed.cells[m.to.0][m.to.1];
+                *cell = if m.merged { m.tile + 1 } else { m.tile };
+            }
+            assert_eq!(replayed, next, "{action:?}");
+        }
+        let (_, moves) = board.apply_with_events(Action::Right).unwrap();
+        assert_eq!(moves.iter().filter(|m| m.merged).count(), 4);
+        assert!(moves.contains(&TileMove {
+            from: (0, 0),
+            to: (0, 2),
+            tile: 1,
+            merged: false
+        }));
+    }
+
+    #[test]
+    fn test_is_lost() {
+        let lost = Board {
+            cells: [[1, 2, 1, 2], [2, 1, 2, 1], [1, 2, 1, 2], [2, 1, 2, 1]],
+        };
+        assert!(lost.is_lost());
+        assert!(ALL_ACTIONS
+            .iter()
+            .all(|&action| lost.apply(action).is_none()));
+        let mut mergeable = lost;
+        mergeable.cells[3][3] = 2;
+        assert!(!mergeable.is_lost());
+        let mut with_empty = lost;
+        with_empty.cells[0][0] = 0;
+        assert!(!with_empty.is_lost());
+    }
 }
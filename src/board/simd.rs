@@ -0,0 +1,207 @@
+//! SSSE3 implementation of the actions, pushing the four rows of the board at once (feature `simd`, x86_64 only).
+//!
+//! The 16 cells of a board fit in a 128-bit register. Every action is reduced to pushing left by shuffling the cells
+//! (e.g. transposing the board for Up), then pushing left is:
+//! 1. moving the non-zero tiles of each row to its start, with a shuffle looked up from the non-zero cells of the row,
+//! 2. merging the equal adjacent tiles, from the left of the row so that a tile merges at most once,
+//! 3. moving the tiles to the start of each row again, to fill the holes left by the merges.
+//!
+//! The scalar implementation (`Board::apply_scored_scalar`) is kept for CPUs without SSSE3.
+
+use std::arch::x86_64::*;
+
+use super::{Action, Board, N};
+
+/// A shuffle of the 16 cells: cell `i` of the result is the cell `mask[i]` of the board, or empty when the high bit of
+/// `mask[i]` is set.
+type Mask = [u8; 16];
+
+const IDENTITY: Mask = {
+    let mut mask = [0; 16];
+    let mut i = 0;
+    while i < 16 {
+        mask[i] = i as u8;
+        i += 1;
+    }
+    mask
+};
+
+/// Reverses each row (`swap_lr`)
+const REVERSE: Mask = {
+    let mut mask = [0; 16];
+    let mut i = 0;
+    while i < 16 {
+        mask[i] = (i / N * N + N - 1 - i % N) as u8;
+        i += 1;
+    }
+    mask
+};
+
+const TRANSPOSE: Mask = {
+    let mut mask = [0; 16];
+    let mut i = 0;
+    while i < 16 {
+        mask[i] = (i % N * N + i / N) as u8;
+        i += 1;
+    }
+    mask
+};
+
+/// The shuffle `first` followed by the shuffle `then`
+const fn compose(first: Mask, then: Mask) -> Mask {
+    let mut mask = [0; 16];
+    let mut i = 0;
+    while i < 16 {
+        mask[i] = first[then[i] as usize];
+        i += 1;
+    }
+    mask
+}
+
+/// Shuffles of an action to a push left, and back, as in `Board::apply_scored_scalar`
+const fn shuffles(action: Action) -> (Mask, Mask) {
+    match action {
+        Action::Left => (IDENTITY, IDENTITY),
+        Action::Right => (REVERSE, REVERSE),
+        Action::Up => (TRANSPOSE, TRANSPOSE),
+        Action::Down => (compose(TRANSPOSE, REVERSE), compose(REVERSE, TRANSPOSE)),
+    }
+}
+
+/// Shuffle of a row moving its non-zero tiles to its start, for each set of non-zero cells (bit `i` set when cell
+/// `i` is not empty)
+const COMPACT: [[u8; N]; 16] = {
+    let mut table = [[0x80; N]; 16];
+    let mut pattern = 0;
+    while pattern < 16 {
+        let mut write = 0;
+        let mut read = 0;
+        while read < N {
+            if pattern >> read & 1 == 1 {
+                table[pattern][write] = read as u8;
+                write += 1;
+            }
+            read += 1;
+        }
+        pattern += 1;
+    }
+    table
+};
+
+/// Whether the CPU supports the instructions used here
+pub fn available() -> bool {
+    is_x86_feature_detected!("ssse3")
+}
+
+/// Board resulting from the action (the board itself if the action is not applicable) and the score of the action,
+/// or `None` if the CPU lacks SSSE3.
+pub fn push(board: &Board, action: Action) -> Option<(Board, u32)> {
+    if !available() {
+        return None;
+    }
+    // SAFETY: SSSE3 is available, and a board is 16 contiguous bytes
+    Some(unsafe { push_ssse3(board, action) })
+}
+
+#[target_feature(enable = "ssse3")]
+unsafe fn push_ssse3(board: &Board, action: Action) -> (Board, u32) {
+    let (to_left, from_left) = shuffles(action);
+    let cells = _mm_loadu_si128(board.cells.as_ptr() as *const __m128i);
+    let cells = shuffle(cells, &to_left);
+    let (cells, score) = merge(compact(cells));
+    let cells = shuffle(compact(cells), &from_left);
+    let mut next = Board::EMPTY;
+    _mm_storeu_si128(next.cells.as_mut_ptr() as *mut __m128i, cells);
+    (next, score)
+}
+
+#[target_feature(enable = "ssse3")]
+unsafe fn shuffle(cells: __m128i, mask: &Mask) -> __m128i {
+    _mm_shuffle_epi8(cells, _mm_loadu_si128(mask.as_ptr() as *const __m128i))
+}
+
+/// Moves the non-zero tiles of each row to its start.
+#[target_feature(enable = "ssse3")]
+unsafe fn compact(cells: __m128i) -> __m128i {
+    let empty = _mm_movemask_epi8(_mm_cmpeq_epi8(cells, _mm_setzero_si128())) as u32;
+    let row_mask = |row: u32| {
+        let pattern = !(empty >> (N as u32 * row)) & 0xF;
+        // the offset of the row keeps the high bit of the empty cells of the shuffle
+        (u32::from_le_bytes(COMPACT[pattern as usize]) + 0x0404_0404 * row) as i32
+    };
+    _mm_shuffle_epi8(
+        cells,
+        _mm_set_epi32(row_mask(3), row_mask(2), row_mask(1), row_mask(0)),
+    )
+}
+
+/// Merges each tile with the next one of its row if they are equal, from the start of the row. Returns the tiles,
+/// with holes where the merged tiles were, and the score of the merges.
+#[target_feature(enable = "ssse3")]
+unsafe fn merge(cells: __m128i) -> (__m128i, u32) {
+    // cells at position `i` of their row
+    let position = |i: u32| _mm_set1_epi32((0xFFu32 << (8 * i)) as i32);
+    let non_empty = _mm_xor_si128(
+        _mm_cmpeq_epi8(cells, _mm_setzero_si128()),
+        _mm_set1_epi8(-1),
+    );
+    // equal to the next cell (across rows for the last cell of a row, which is left out by the positions)
+    let equal = _mm_and_si128(_mm_cmpeq_epi8(cells, _mm_srli_si128(cells, 1)), non_empty);
+    // a cell merges with the next one if it was not merged with the previous one
+    let merged0 = _mm_and_si128(equal, position(0));
+    let merged1 = _mm_andnot_si128(
+        _mm_slli_si128(merged0, 1),
+        _mm_and_si128(equal, position(1)),
+    );
+    let merged2 = _mm_andnot_si128(
+        _mm_slli_si128(merged1, 1),
+        _mm_and_si128(equal, position(2)),
+    );
+    let merged = _mm_or_si128(merged0, _mm_or_si128(merged1, merged2));
+    // merged cells are -1, so subtracting them increments the merged tiles
+    let cells = _mm_andnot_si128(_mm_slli_si128(merged, 1), _mm_sub_epi8(cells, merged));
+
+    let mut merges = _mm_movemask_epi8(merged) as u32;
+    let mut score = 0;
+    if merges != 0 {
+        let mut tiles = [0u8; 16];
+        _mm_storeu_si128(tiles.as_mut_ptr() as *mut __m128i, cells);
+        while merges != 0 {
+            score += 1 << tiles[merges.trailing_zeros() as usize];
+            merges &= merges - 1;
+        }
+    }
+    (cells, score)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::ALL_ACTIONS;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    #[test]
+    fn test_simd() {
+        if !available() {
+            return;
+        }
+        let mut rng = StdRng::seed_from_u64(0);
+        for _ in 0..20_000 {
+            // few distinct tiles to have many merges, and up to the largest tile
+            let max = if rng.random_bool(0.5) { 3 } else { 17 };
+            let board = Board {
+                cells: std::array::from_fn(|_| std::array::from_fn(|_| rng.random_range(0..=max))),
+            };
+            for action in ALL_ACTIONS {
+                let (next, score) = push(&board, action).unwrap();
+                match board.apply_scored_scalar(action) {
+                    Some(expected) => {
+                        assert_eq!((next, score), expected, "{action:?} on {board:?}")
+                    }
+                    None => assert_eq!((next, score), (board, 0), "{action:?} on {board:?}"),
+                }
+            }
+        }
+    }
+}
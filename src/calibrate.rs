@@ -0,0 +1,155 @@
+use std::path::PathBuf;
+
+use ai_2048::{board, eval, game};
+use board::{Board, N};
+use clap::builder::PossibleValuesParser;
+use clap::Parser;
+use eval::{Evaluator, HEURISTICS, NUM_HEURISTICS};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rayon::prelude::*;
+
+/// Reports the distribution of the evaluation over several families of positions.
+///
+/// For each family, prints quantiles of the evaluation and the average magnitude of the contribution of each heuristic,
+/// which shows when a single term dominates the scale of the evaluation.
+#[derive(Parser, Debug)]
+#[command(version, about, long_about = None)]
+struct Args {
+    /// Weights of the evaluation (default weights if absent)
+    #[arg(short, long)]
+    weights: Option<PathBuf>,
+
+    /// Named preset of weights, instead of a weights file
+    #[arg(long, conflicts_with = "weights", value_parser = PossibleValuesParser::new(eval::presets::names()))]
+    eval_preset: Option<String>,
+
+    /// Comma-separated heuristics to switch off (a prefix such as `monotonicity` disables all `monotonicity_*` terms)
+    #[arg(long, value_delimiter = ',')]
+    disable: Vec<String>,
+
+    /// Number of positions in each family
+    #[arg(short, long, default_value = "2000")]
+    samples: usize,
+
+    /// Number of greedy games from which game positions are sampled
+    #[arg(short, long, default_value = "20")]
+    games: u64,
+
+    /// Seed of the random number generator
+    #[arg(long, default_value = "0")]
+    seed: u64,
+}
+
+/// Largest tile exponent placed on random positions (`2^11 = 2048`)
+const MAX_RANDOM_TILE: u8 = 11;
+
+fn main() -> anyhow::Result<()> {
+    let args: Args = Args::parse();
+    let mut weights = eval::load_weights(args.weights.as_deref(), args.eval_preset.as_deref())?;
+    for name in &args.disable {
+        weights.disable(name)?;
+    }
+    let evaluator = Evaluator::new(weights);
+    let mut rng = StdRng::seed_from_u64(args.seed);
+
+    let random: Vec<Board> = (0..args.samples).map(|_| random_board(&mut rng)).collect();
+
+    let played: Vec<Board> = (args.seed..args.seed + args.games)
+        .into_par_iter()
+        .flat_map(|seed| {
+            game::play_seeded(seed, |board| {
+                game::greedy_action(board, |after| evaluator.eval(after))
+            })
+        })
+        .collect();
+    let from_games: Vec<Board> = (0..args.samples.min(played.len()))
+        .map(|_| played[rng.random_range(0..played.len())])
+        .collect();
+
+    let lost: Vec<Board> = std::iter::repeat_with(|| random_full_board(&mut rng))
+        .filter(|board| board.is_lost())
+        .take(args.samples)
+        .collect();
+
+    report("Random positions", &random, &evaluator);
+    report("Positions from greedy games", &from_games, &evaluator);
+    report("Lost positions", &lost, &evaluator);
+
+    let lowest_not_lost = random
+        .iter()
+        .chain(&from_games)
+        .filter(|board| !board.is_lost())
+        .map(|board| evaluator.eval(board))
+        .fold(f32::INFINITY, f32::min);
+    let misordered = lost
+        .iter()
+        .filter(|board| evaluator.eval(board) >= lowest_not_lost)
+        .count();
+    println!("Lost positions evaluated above a position that is not lost: {misordered}");
+    Ok(())
+}
+
+/// A board with a random number of tiles, each with a random value up to `2^MAX_RANDOM_TILE`
+fn random_board(rng: &mut impl Rng) -> Board {
+    let num_tiles = rng.random_range(1..=N * N);
+    let mut cells = [[0; N]; N];
+    for cell in rand::seq::index::sample(rng, N * N, num_tiles) {
+        cells[cell / N][cell % N] = rng.random_range(1..=MAX_RANDOM_TILE);
+    }
+    Board { cells }
+}
+
+/// A board where all cells hold a random tile, which may or may not be lost
+fn random_full_board(rng: &mut impl Rng) -> Board {
+    Board {
+        cells: std::array::from_fn(|_| {
+            std::array::from_fn(|_| rng.random_range(1..=MAX_RANDOM_TILE))
+        }),
+    }
+}
+
+/// Prints quantiles of the evaluations and the average absolute contribution of each heuristic.
+fn report(title: &str, boards: &[Board], evaluator: &Evaluator) {
+    println!("=== {title} ({} positions)", boards.len());
+    if boards.is_empty() {
+        println!();
+        return;
+    }
+    let mut values: Vec<f32> = boards.iter().map(|board| evaluator.eval(board)).collect();
+    values.sort_by(f32::total_cmp);
+    let quantile = |q: f32| values[((values.len() - 1) as f32 * q).round() as usize];
+    println!(
+        "min: {:.0}   p5: {:.0}   median: {:.0}   p95: {:.0}   max: {:.0}",
+        values[0],
+        quantile(0.05),
+        quantile(0.5),
+        quantile(0.95),
+        values[values.len() - 1]
+    );
+
+    let mut contributions = [0.0f64; NUM_HEURISTICS];
+    for board in boards {
+        for (sum, term) in contributions.iter_mut().zip(evaluator.explain(board).terms) {
+            *sum += term.contribution.abs() as f64;
+        }
+    }
+    let total: f64 = contributions.iter().sum();
+    if total == 0.0 {
+        println!("No heuristic contributes (lost boards are evaluated from their tile sum only)\n");
+        return;
+    }
+    println!(
+        "{:<18} {:>16} {:>8}",
+        "heuristic", "avg |contrib.|", "share"
+    );
+    for (heuristic, sum) in HEURISTICS.iter().zip(contributions) {
+        println!(
+            "{:<18} {:>16.1} {:>7.1}%",
+            heuristic.name,
+            sum / boards.len() as f64,
+            sum / total * 100.0
+        );
+    }
+    println!();
+}
@@ -0,0 +1,140 @@
+//! Checkpoints of long benchmarks, so that an interrupted run can be resumed without replaying finished games.
+//!
+//! A checkpoint is a JSON-lines file: a header describing the run, followed by one record per completed game,
+//! flushed as soon as the game ends. A process killed while writing leaves at most a truncated last line,
+//! which is discarded when resuming.
+
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+
+use anyhow::{ensure, Context};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+/// Parameters of the run, which must be the same to resume it
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Header {
+    /// Seeds of all games of the run
+    pub seeds: Vec<u64>,
+    pub strategy: String,
+}
+
+/// Appends records to a checkpoint file.
+pub struct Checkpoint {
+    out: BufWriter<File>,
+}
+
+impl Checkpoint {
+    /// Creates a checkpoint with the given header, replacing any existing file.
+    pub fn create(path: &Path, header: &Header) -> anyhow::Result<Checkpoint> {
+        Self::rewrite::<()>(path, header, &[])
+    }
+
+    /// Reads the header and records of an existing checkpoint, and reopens it to append more records.
+    pub fn resume<T: Serialize + DeserializeOwned>(
+        path: &Path,
+    ) -> anyhow::Result<(Checkpoint, Header, Vec<T>)> {
+        let file = File::open(path).with_context(|| format!("Cannot open {}", path.display()))?;
+        let lines = BufReader::new(file)
+            .lines()
+            .collect::<Result<Vec<_>, _>>()?;
+        let (header, records) = lines
+            .split_first()
+            .with_context(|| format!("Empty checkpoint: {}", path.display()))?;
+        let header: Header = serde_json::from_str(header)
+            .with_context(|| format!("Invalid checkpoint header in {}", path.display()))?;
+        let mut parsed = Vec::with_capacity(records.len());
+        for (i, line) in records.iter().enumerate() {
+            match serde_json::from_str(line) {
+                Ok(record) => parsed.push(record),
+                // the process was interrupted while writing the last record
+                Err(_) if i == records.len() - 1 => break,
+                Err(e) => {
+                    return Err(e)
+                        .with_context(|| format!("Invalid record at {}:{}", path.display(), i + 2))
+                }
+            }
+        }
+        // rewrite the file so that a truncated record does not end up in the middle of it
+        let checkpoint = Self::rewrite(path, &header, &parsed)?;
+        Ok((checkpoint, header, parsed))
+    }
+
+    fn rewrite<T: Serialize>(
+        path: &Path,
+        header: &Header,
+        records: &[T],
+    ) -> anyhow::Result<Checkpoint> {
+        let file =
+            File::create(path).with_context(|| format!("Cannot create {}", path.display()))?;
+        let mut checkpoint = Checkpoint {
+            out: BufWriter::new(file),
+        };
+        checkpoint.write_line(header)?;
+        for record in records {
+            checkpoint.write_line(record)?;
+        }
+        checkpoint.out.flush()?;
+        Ok(checkpoint)
+    }
+
+    /// Appends a record and flushes it to the file.
+    pub fn append<T: Serialize>(&mut self, record: &T) -> anyhow::Result<()> {
+        self.write_line(record)?;
+        self.out.flush()?;
+        Ok(())
+    }
+
+    fn write_line<T: Serialize>(&mut self, value: &T) -> anyhow::Result<()> {
+        serde_json::to_writer(&mut self.out, value)?;
+        writeln!(self.out)?;
+        Ok(())
+    }
+}
+
+impl Header {
+    /// Fails if a run with these parameters cannot be continued from a checkpoint with the header `self`.
+    pub fn check_compatible(&self, other: &Header) -> anyhow::Result<()> {
+        ensure!(
+            self == other,
+            "Checkpoint of a different run:\n  checkpoint: {self:?}\n  requested:  {other:?}"
+        );
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resume() {
+        let path = std::env::temp_dir().join(format!("checkpoint-{}.jsonl", std::process::id()));
+        let header = Header {
+            seeds: vec![10, 11, 12, 13],
+            strategy: "greedy".to_string(),
+        };
+        let mut checkpoint = Checkpoint::create(&path, &header).unwrap();
+        checkpoint.append(&(10u64, 120u32)).unwrap();
+        checkpoint.append(&(12u64, 80u32)).unwrap();
+        drop(checkpoint);
+        // simulate a process killed in the middle of a record
+        let mut file = std::fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .unwrap();
+        write!(file, "[11,").unwrap();
+        drop(file);
+
+        let (mut checkpoint, read_header, records) =
+            Checkpoint::resume::<(u64, u32)>(&path).unwrap();
+        assert_eq!(read_header, header);
+        assert_eq!(records, vec![(10, 120), (12, 80)]);
+        checkpoint.append(&(11u64, 95u32)).unwrap();
+        drop(checkpoint);
+        let (_, _, records) = Checkpoint::resume::<(u64, u32)>(&path).unwrap();
+        assert_eq!(records.len(), 3);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
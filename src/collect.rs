@@ -0,0 +1,175 @@
+//! Training data collected while playing (`bench --collect`, `main selfplay --collect`): each board on which an
+//! action was selected, with the value of every action according to the player and the action selected, for
+//! imitation learning and the fitting of evaluation functions.
+//!
+//! The file is a compact binary format: the magic bytes `2048DATA`, the version of the format as a little-endian
+//! `u32`, then one record of `RECORD_SIZE` bytes per position, all little-endian:
+//!
+//! | bytes  | content                                                                              |
+//! |--------|--------------------------------------------------------------------------------------|
+//! | 0..8   | seed of the game (`u64`)                                                             |
+//! | 8..16  | board, packed as by `Board::pack` (`u64`)                                            |
+//! | 16..32 | value of Up, Down, Left and Right (`f32` each), NaN for the actions not applicable   |
+//! | 32     | action selected, as its index in Up, Down, Left, Right (`u8`)                        |
+//!
+//! The positions of a game are contiguous and in the order of the game.
+//!
+//! ```python
+//! import numpy as np
+//! record = np.dtype([("seed", "<u8"), ("board", "<u8"), ("values", "<f4", 4), ("action", "u1")])
+//! positions = np.fromfile("dataset.bin", dtype=record, offset=12)
+//! ```
+
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
+use std::sync::Mutex;
+
+use anyhow::{bail, ensure, Context};
+
+use crate::board::{Action, Board, ALL_ACTIONS};
+
+const MAGIC: &[u8; 8] = b"2048DATA";
+
+const FORMAT_VERSION: u32 = 1;
+
+/// Size of a record in bytes
+pub const RECORD_SIZE: usize = 33;
+
+/// A board on which an action was selected
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Sample {
+    pub seed: u64,
+    pub board: Board,
+    /// Value of each action (in the order of `ALL_ACTIONS`) for the player, `None` for the actions not applicable
+    pub values: [Option<f32>; 4],
+    pub action: Action,
+}
+
+impl Sample {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.extend(self.seed.to_le_bytes());
+        out.extend(self.board.pack().to_le_bytes());
+        for value in self.values {
+            out.extend(value.unwrap_or(f32::NAN).to_le_bytes());
+        }
+        let action = ALL_ACTIONS
+            .iter()
+            .position(|&action| action == self.action)
+            .expect("one of all actions");
+        out.push(action as u8);
+    }
+
+    fn decode(record: &[u8; RECORD_SIZE]) -> anyhow::Result<Sample> {
+        let u64_at = |i: usize| u64::from_le_bytes(record[i..i + 8].try_into().unwrap());
+        let values = std::array::from_fn(|i| {
+            let start = 16 + 4 * i;
+            Some(f32::from_le_bytes(
+                record[start..start + 4].try_into().unwrap(),
+            ))
+            .filter(|value| !value.is_nan())
+        });
+        let Some(&action) = ALL_ACTIONS.get(record[32] as usize) else {
+            bail!("Invalid action {}", record[32]);
+        };
+        Ok(Sample {
+            seed: u64_at(0),
+            board: Board::unpack(u64_at(8)),
+            values,
+            action,
+        })
+    }
+}
+
+/// Writes the samples of games played in parallel to a file.
+pub struct Collector {
+    out: Mutex<(BufWriter<File>, u64)>,
+}
+
+impl Collector {
+    pub fn create(path: &Path) -> anyhow::Result<Collector> {
+        let file =
+            File::create(path).with_context(|| format!("Cannot create {}", path.display()))?;
+        let mut out = BufWriter::new(file);
+        out.write_all(MAGIC)?;
+        out.write_all(&FORMAT_VERSION.to_le_bytes())?;
+        Ok(Collector {
+            out: Mutex::new((out, 0)),
+        })
+    }
+
+    /// Writes the samples of a game, after those of the games added before.
+    pub fn add_game(&self, samples: &[Sample]) -> anyhow::Result<()> {
+        let mut bytes = Vec::with_capacity(samples.len() * RECORD_SIZE);
+        for sample in samples {
+            sample.encode(&mut bytes);
+        }
+        let mut out = self.out.lock().unwrap();
+        out.0.write_all(&bytes)?;
+        out.1 += samples.len() as u64;
+        Ok(())
+    }
+
+    /// Flushes the file. Returns the number of samples written.
+    pub fn finish(self) -> anyhow::Result<u64> {
+        let (mut out, count) = self.out.into_inner().unwrap();
+        out.flush()?;
+        Ok(count)
+    }
+}
+
+/// Reads all samples of a file written by a `Collector`.
+pub fn read(path: &Path) -> anyhow::Result<Vec<Sample>> {
+    let file = File::open(path).with_context(|| format!("Cannot open {}", path.display()))?;
+    let mut input = BufReader::new(file);
+    let mut header = [0; 12];
+    input.read_exact(&mut header)?;
+    ensure!(
+        &header[..8] == MAGIC,
+        "{} is not a file of collected positions",
+        path.display()
+    );
+    let version = u32::from_le_bytes(header[8..].try_into().unwrap());
+    ensure!(
+        version == FORMAT_VERSION,
+        "Unsupported version of collected positions: {version}"
+    );
+    let mut bytes = Vec::new();
+    input.read_to_end(&mut bytes)?;
+    ensure!(
+        bytes.len() % RECORD_SIZE == 0,
+        "Truncated record at the end of {}",
+        path.display()
+    );
+    bytes
+        .chunks_exact(RECORD_SIZE)
+        .map(|record| Sample::decode(record.try_into().unwrap()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_collect() {
+        let board = Board::from_values([[2, 4, 0, 0], [0; 4], [0, 8, 0, 0], [0, 0, 0, 2]]).unwrap();
+        let sample = Sample {
+            seed: 42,
+            board,
+            values: [Some(1.5), None, Some(-2.0), Some(0.0)],
+            action: Action::Left,
+        };
+        let path = std::env::temp_dir().join(format!("collect-{}.bin", std::process::id()));
+        let collector = Collector::create(&path).unwrap();
+        collector.add_game(&[sample, sample]).unwrap();
+        collector.add_game(&[]).unwrap();
+        assert_eq!(collector.finish().unwrap(), 2);
+        let bytes = std::fs::read(&path).unwrap();
+        assert_eq!(bytes.len(), 12 + 2 * RECORD_SIZE);
+        // the action is the last byte of each record
+        assert_eq!(bytes[12 + RECORD_SIZE - 1], 2);
+        assert_eq!(read(&path).unwrap(), vec![sample, sample]);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
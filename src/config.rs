@@ -0,0 +1,252 @@
+//! Configuration files (`--config lab.toml`), holding the options of the command line that are always the same.
+//!
+//! Each key is the long name of an option (`num-games` or `num_games`), and its value is the value of the option:
+//! `true` for a flag, a number for an option given several times (`verbose = 2`), an array for an option taking
+//! several values. Top-level keys apply to every command having the option, and the keys of a section only to the
+//! command of the section (`[auto]`, `[bench]`, ...), overriding the top-level ones:
+//!
+//! ```toml
+//! strategy = "expectimax:depth=3"
+//! eval-preset = "corner-stacker"
+//!
+//! [auto]
+//! delay = 100
+//!
+//! [bench]
+//! num-games = 200
+//! time-per-move = 50
+//! ```
+//!
+//! The options of the file are inserted in the command line, except those given on the command line, which
+//! override them. `--config` itself is removed, so that it can be given anywhere, even before a subcommand.
+
+use std::ffi::OsString;
+use std::ops::Range;
+use std::path::PathBuf;
+
+use anyhow::{bail, Context};
+use clap::{Arg, ArgAction, Command};
+use toml_edit::{DocumentMut, Item, Value};
+
+/// Name of the option giving the configuration file
+const CONFIG: &str = "config";
+
+/// The command line `args` completed with the options of the configuration file it gives with `--config`, if any.
+///
+/// `root_section` is the section applying to `command` when no subcommand is given (e.g. `auto` for `main`, whose
+/// default is to play as `main auto`, or `bench` for the `bench` binary).
+pub fn with_config(
+    command: &Command,
+    root_section: &str,
+    args: Vec<OsString>,
+) -> anyhow::Result<Vec<OsString>> {
+    let Some((position, path)) = config_path(&args) else {
+        return Ok(args);
+    };
+    let mut args = args;
+    args.drain(position);
+    let text = std::fs::read_to_string(&path)
+        .with_context(|| format!("Cannot read the configuration {}", path.display()))?;
+    let config: DocumentMut = text
+        .parse()
+        .with_context(|| format!("Invalid configuration {}", path.display()))?;
+
+    let mut command = command.clone();
+    // propagate the global options to the subcommands
+    command.build();
+    // the first argument naming a subcommand selects it
+    let subcommand = args
+        .iter()
+        .enumerate()
+        .skip(1)
+        .find_map(|(i, arg)| Some((i, command.find_subcommand(arg.to_str()?)?.clone())));
+    let (target, section, insert_at) = match subcommand {
+        Some((i, subcommand)) => {
+            let name = subcommand.get_name().to_string();
+            (subcommand, name, i + 1)
+        }
+        None => (command.clone(), root_section.to_string(), 1),
+    };
+
+    let mut options: Vec<(String, &Value)> = Vec::new();
+    for (key, item) in config.iter() {
+        match item {
+            Item::Value(value) => {
+                let known = find_arg(&command, key).is_some()
+                    || command
+                        .get_subcommands()
+                        .any(|subcommand| find_arg(subcommand, key).is_some());
+                if !known {
+                    bail!("{}: unknown option `{key}`", path.display());
+                }
+                if find_arg(&target, key).is_some() {
+                    options.push((key.to_string(), value));
+                }
+            }
+            Item::Table(table) => {
+                // the file may be shared by both binaries, each ignoring the sections of the other one
+                let section_command = match command.find_subcommand(key) {
+                    Some(subcommand) => subcommand,
+                    None if key == root_section => &command,
+                    None => continue,
+                };
+                for (option, item) in table.iter() {
+                    let Some(value) = item.as_value() else {
+                        bail!("{}: `{key}.{option}` is not a value", path.display());
+                    };
+                    if find_arg(section_command, option).is_none() {
+                        bail!("{}: unknown option `{option}` in `[{key}]`", path.display());
+                    }
+                    if key != section {
+                        continue;
+                    }
+                    // the section overrides the top-level keys
+                    options.retain(|(name, _)| normalize(name) != normalize(option));
+                    options.push((option.to_string(), value));
+                }
+            }
+            _ => bail!("{}: unexpected `{key}`", path.display()),
+        }
+    }
+
+    let mut inserted = Vec::new();
+    for (key, value) in options {
+        let arg = find_arg(&target, &key).expect("checked above");
+        if given(arg, &args) {
+            continue;
+        }
+        inserted.extend(
+            to_args(arg, value).with_context(|| format!("{}: option `{key}`", path.display()))?,
+        );
+    }
+    args.splice(insert_at..insert_at, inserted);
+    Ok(args)
+}
+
+/// File given with `--config <FILE>` or `--config=<FILE>`, and the position of the option in the arguments
+fn config_path(args: &[OsString]) -> Option<(Range<usize>, PathBuf)> {
+    let flag = format!("--{CONFIG}");
+    let prefix = format!("--{CONFIG}=");
+    args.iter().enumerate().find_map(|(i, arg)| {
+        let arg = arg.to_str()?;
+        if arg == flag {
+            Some((i..i + 2, args.get(i + 1)?.into()))
+        } else {
+            Some((i..i + 1, arg.strip_prefix(&prefix)?.into()))
+        }
+    })
+}
+
+fn normalize(key: &str) -> String {
+    key.replace('_', "-")
+}
+
+/// Option of the command with the long name `key`
+fn find_arg<'a>(command: &'a Command, key: &str) -> Option<&'a Arg> {
+    let key = normalize(key);
+    command
+        .get_arguments()
+        .find(|arg| arg.get_long() == Some(key.as_str()) && arg.get_long() != Some(CONFIG))
+}
+
+/// Whether the option is given on the command line
+fn given(arg: &Arg, args: &[OsString]) -> bool {
+    let long = format!("--{}", arg.get_long().unwrap_or_default());
+    let long_with_value = format!("{long}=");
+    args.iter().filter_map(|arg| arg.to_str()).any(|token| {
+        token == long
+            || token.starts_with(&long_with_value)
+            || arg.get_short().is_some_and(|short| {
+                // `-s` or a group of short flags such as `-vq`
+                !token.starts_with("--") && token.starts_with('-') && token.contains(short)
+            })
+    })
+}
+
+/// Arguments of the command line giving the value to the option
+fn to_args(arg: &Arg, value: &Value) -> anyhow::Result<Vec<OsString>> {
+    let long = OsString::from(format!(
+        "--{}",
+        arg.get_long().expect("options have a long name")
+    ));
+    let text = |value: &Value| -> anyhow::Result<OsString> {
+        Ok(match value {
+            Value::String(s) => s.value().into(),
+            Value::Integer(i) => i.value().to_string().into(),
+            Value::Float(f) => f.value().to_string().into(),
+            _ => bail!("expected a string or a number"),
+        })
+    };
+    Ok(match (arg.get_action(), value) {
+        (ArgAction::SetTrue, Value::Boolean(flag)) => {
+            if *flag.value() {
+                vec![long]
+            } else {
+                vec![]
+            }
+        }
+        (ArgAction::Count, Value::Integer(count)) => {
+            vec![long; usize::try_from(*count.value()).context("negative count")?]
+        }
+        (ArgAction::SetTrue | ArgAction::Count, _) => bail!("expected a boolean or a count"),
+        (_, Value::Array(values)) => values
+            .iter()
+            .map(|value| Ok([long.clone(), text(value)?]))
+            .collect::<anyhow::Result<Vec<_>>>()?
+            .concat(),
+        (_, value) => vec![long, text(value)?],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_with_config() {
+        let command = Command::new("main")
+            .arg(Arg::new("config").long("config"))
+            .arg(Arg::new("strategy").long("strategy").short('s'))
+            .arg(
+                Arg::new("verbose")
+                    .long("verbose")
+                    .short('v')
+                    .action(ArgAction::Count)
+                    .global(true),
+            )
+            .subcommand(
+                Command::new("bench")
+                    .arg(Arg::new("strategy").long("strategy").short('s'))
+                    .arg(Arg::new("num-games").long("num-games").short('n')),
+            );
+        let path = std::env::temp_dir().join(format!("config-{}.toml", std::process::id()));
+        std::fs::write(
+            &path,
+            "strategy = \"greedy\"\nverbose = 2\n[bench]\nnum_games = 10\nstrategy = \"random\"\n",
+        )
+        .unwrap();
+        let complete = |args: &[&str]| {
+            let mut args: Vec<OsString> = args.iter().map(Into::into).collect();
+            args.extend(["--config".into(), path.clone().into()]);
+            let args = with_config(&command, "auto", args).unwrap();
+            args.iter()
+                .map(|arg| arg.to_str().unwrap().to_string())
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+        assert_eq!(
+            complete(&["main"]),
+            "main --strategy greedy --verbose --verbose"
+        );
+        // the section overrides the top-level keys, and the command line overrides the file
+        assert_eq!(
+            complete(&["main", "bench", "-n", "5"]),
+            "main bench --verbose --verbose --strategy random -n 5"
+        );
+
+        std::fs::write(&path, "[bench]\nunknown = 1\n").unwrap();
+        let args: Vec<OsString> = vec!["main".into(), "--config".into(), path.clone().into()];
+        assert!(with_config(&command, "auto", args).is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,273 @@
+//! Live dashboard of a running benchmark: the board of the game played by each worker, the distribution of the scores
+//! and how often each tile was reached, over the games finished so far.
+//!
+//! The state is updated by the workers and drawn in the terminal by another thread. The dashboard requires the `tui`
+//! feature (`cargo run --release --features tui --bin bench -- --dashboard`).
+
+use std::sync::Mutex;
+
+use crate::board::{PlayableBoard, N};
+
+/// Number of bars of the histogram of scores
+const NUM_BINS: usize = 10;
+
+/// Smallest tile (exponent) whose reach is reported
+const MIN_REPORTED_TILE: u8 = 7;
+
+/// Game currently played by a worker
+#[derive(Clone, Copy)]
+struct Current {
+    seed: u64,
+    board: PlayableBoard,
+    num_moves: usize,
+}
+
+#[derive(Default)]
+struct State {
+    /// Game of each worker, indexed by the rayon thread index
+    workers: Vec<Option<Current>>,
+    /// Score (#actions) of each finished game
+    scores: Vec<f32>,
+    /// Max tile of each finished game
+    max_tiles: Vec<u8>,
+}
+
+/// Progress of all games, shared between the workers and the thread drawing it.
+pub struct Dashboard {
+    num_games: usize,
+    state: Mutex<State>,
+}
+
+impl Dashboard {
+    pub fn new(num_workers: usize, num_games: usize) -> Dashboard {
+        Dashboard {
+            num_games,
+            state: Mutex::new(State {
+                workers: vec![None; num_workers],
+                ..State::default()
+            }),
+        }
+    }
+
+    /// Records the board reached in the game played by the calling worker.
+    pub fn update(&self, seed: u64, board: PlayableBoard, num_moves: usize) {
+        let worker = rayon::current_thread_index().unwrap_or(0);
+        let mut state = self.state.lock().unwrap();
+        if worker >= state.workers.len() {
+            state.workers.resize(worker + 1, None);
+        }
+        state.workers[worker] = Some(Current {
+            seed,
+            board,
+            num_moves,
+        });
+    }
+
+    /// Records a finished game, which is no longer shown as the game of the calling worker.
+    pub fn finish(&self, score: f32, board: &PlayableBoard) {
+        let mut state = self.state.lock().unwrap();
+        if let Some(worker) = rayon::current_thread_index() {
+            if let Some(current) = state.workers.get_mut(worker) {
+                *current = None;
+            }
+        }
+        state.scores.push(score);
+        state.max_tiles.push(board.board().max_tile());
+    }
+
+    /// Histogram of the scores of the finished games: lower bound of each bin and number of games in it
+    fn score_histogram(state: &State) -> Vec<(f32, u64)> {
+        let (Some(&lowest), Some(&highest)) = (
+            state.scores.iter().min_by(|a, b| a.total_cmp(b)),
+            state.scores.iter().max_by(|a, b| a.total_cmp(b)),
+        ) else {
+            return Vec::new();
+        };
+        let width = ((highest - lowest) / NUM_BINS as f32).max(1.0);
+        let mut counts = vec![0; NUM_BINS];
+        for score in &state.scores {
+            let bin = (((score - lowest) / width) as usize).min(NUM_BINS - 1);
+            counts[bin] += 1;
+        }
+        counts
+            .into_iter()
+            .enumerate()
+            .map(|(bin, count)| (lowest + bin as f32 * width, count))
+            .collect()
+    }
+
+    /// Percentage of the finished games reaching each tile (exponent), from `MIN_REPORTED_TILE` to the largest reached
+    fn tile_reach(state: &State) -> Vec<(u8, f64)> {
+        let Some(highest) = state.max_tiles.iter().copied().max() else {
+            return Vec::new();
+        };
+        (MIN_REPORTED_TILE..=highest.max(MIN_REPORTED_TILE))
+            .map(|tile| {
+                let count = state.max_tiles.iter().filter(|&&t| t >= tile).count();
+                (tile, count as f64 / state.max_tiles.len() as f64 * 100.0)
+            })
+            .collect()
+    }
+}
+
+/// Lines of text showing the tiles of the board
+fn board_lines(board: &PlayableBoard) -> Vec<String> {
+    board
+        .board()
+        .cells
+        .iter()
+        .map(|row| {
+            row.iter()
+                .map(|&tile| match tile {
+                    0 => format!("{:>5}", "."),
+                    _ => format!("{:>5}", 1u32 << tile),
+                })
+                .collect()
+        })
+        .collect()
+}
+
+#[cfg(feature = "tui")]
+mod tui {
+    use std::time::Duration;
+
+    use ratatui::crossterm::event::{self, Event, KeyCode};
+    use ratatui::layout::{Constraint, Layout, Rect};
+    use ratatui::style::Stylize;
+    use ratatui::widgets::{Bar, BarChart, BarGroup, Block, Gauge, Paragraph};
+    use ratatui::Frame;
+
+    use super::*;
+
+    /// Time between two refreshes of the dashboard
+    const REFRESH: Duration = Duration::from_millis(100);
+
+    /// Size of the box showing a board: 4 rows of 4 tiles, within borders
+    const BOARD_WIDTH: u16 = 5 * N as u16 + 2;
+    const BOARD_HEIGHT: u16 = N as u16 + 2;
+
+    impl Dashboard {
+        /// Draws the dashboard in the terminal until `done` returns true, or until the user presses `q`.
+        pub fn run(&self, done: impl Fn() -> bool) -> anyhow::Result<()> {
+            let mut terminal = ratatui::init();
+            let result = (|| {
+                loop {
+                    terminal.draw(|frame| self.draw(frame))?;
+                    if done() {
+                        break;
+                    }
+                    if event::poll(REFRESH)? {
+                        if let Event::Key(key) = event::read()? {
+                            if matches!(key.code, KeyCode::Char('q') | KeyCode::Esc) {
+                                break;
+                            }
+                        }
+                    }
+                }
+                Ok(())
+            })();
+            ratatui::restore();
+            result
+        }
+
+        fn draw(&self, frame: &mut Frame) {
+            let state = self.state.lock().unwrap();
+            let [top, main] =
+                Layout::vertical([Constraint::Length(3), Constraint::Fill(1)]).areas(frame.area());
+            let [boards, side] =
+                Layout::horizontal([Constraint::Fill(1), Constraint::Length(50)]).areas(main);
+            let [scores, tiles] =
+                Layout::vertical([Constraint::Fill(1), Constraint::Fill(1)]).areas(side);
+
+            let finished = state.scores.len();
+            let average = state.scores.iter().sum::<f32>() / finished.max(1) as f32;
+            frame.render_widget(
+                Gauge::default()
+                    .block(Block::bordered().title(" Games (q to hide) "))
+                    .ratio(finished as f64 / self.num_games.max(1) as f64)
+                    .label(format!(
+                        "{finished}/{}   average score (#actions): {average:.1}",
+                        self.num_games
+                    )),
+                top,
+            );
+            Self::draw_boards(frame, &state, boards);
+
+            let histogram = Dashboard::score_histogram(&state);
+            let bars: Vec<Bar> = histogram
+                .iter()
+                .map(|&(lower, count)| Bar::default().value(count).label(format!("{lower:.0}")))
+                .collect();
+            frame.render_widget(
+                BarChart::default()
+                    .block(Block::bordered().title(" Score distribution (#actions) "))
+                    .bar_width(4)
+                    .bar_gap(1)
+                    .data(BarGroup::default().bars(&bars)),
+                scores,
+            );
+
+            let reach: Vec<String> = Dashboard::tile_reach(&state)
+                .into_iter()
+                .map(|(tile, percent)| format!("{:>6}: {percent:>6.2}%", 1u32 << tile))
+                .collect();
+            frame.render_widget(
+                Paragraph::new(reach.join("\n")).block(Block::bordered().title(" Tile reached ")),
+                tiles,
+            );
+        }
+
+        /// Draws the board of each worker in a grid.
+        fn draw_boards(frame: &mut Frame, state: &State, area: Rect) {
+            let per_row = (area.width / BOARD_WIDTH).max(1) as usize;
+            for (i, current) in state.workers.iter().enumerate() {
+                let (row, col) = ((i / per_row) as u16, (i % per_row) as u16);
+                let cell = Rect::new(
+                    area.x + col * BOARD_WIDTH,
+                    area.y + row * BOARD_HEIGHT,
+                    BOARD_WIDTH,
+                    BOARD_HEIGHT,
+                );
+                if cell.bottom() > area.bottom() || cell.right() > area.right() {
+                    continue;
+                }
+                let (title, text) = match current {
+                    Some(current) => (
+                        format!(" #{i} seed {} ({}) ", current.seed, current.num_moves),
+                        board_lines(&current.board).join("\n"),
+                    ),
+                    None => (format!(" #{i} idle "), String::new()),
+                };
+                frame.render_widget(
+                    Paragraph::new(text).block(Block::bordered().title(title).dim()),
+                    cell,
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_statistics() {
+        let dashboard = Dashboard::new(1, 3);
+        let board = PlayableBoard::init();
+        for (score, tile) in [(100.0, 7), (150.0, 8), (200.0, 8)] {
+            let mut cells = board.board().cells;
+            cells[0][0] = tile;
+            dashboard.finish(score, &crate::board::Board { cells }.into());
+        }
+        let state = dashboard.state.lock().unwrap();
+        let histogram = Dashboard::score_histogram(&state);
+        assert_eq!(histogram.len(), NUM_BINS);
+        assert_eq!(histogram.iter().map(|&(_, count)| count).sum::<u64>(), 3);
+        assert_eq!(histogram[0], (100.0, 1));
+        assert_eq!(histogram[NUM_BINS - 1].1, 1);
+        let reach = Dashboard::tile_reach(&state);
+        assert_eq!(reach[0], (7, 100.0));
+        assert!((reach[1].1 - 200.0 / 3.0).abs() < 1e-9);
+    }
+}
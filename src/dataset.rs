@@ -0,0 +1,180 @@
+//! Datasets of positions for machine learning (`main dataset`): the replays of games (`bench --replays <DIR>`) are
+//! converted into a Parquet table with one row per move, much faster to load than JSON lines when there are millions
+//! of positions.
+//!
+//! The columns are the seed of the game and the index of the move, the board packed as by `Board::pack`, the
+//! features of the board (see `eval::features`), the action played, its value according to the search and the score
+//! reached before it, and the outcome of the game: its final score, its number of moves and its largest tile.
+//!
+//! ```python
+//! import pandas as pd
+//! positions = pd.read_parquet("positions.parquet")
+//! ```
+
+use std::path::Path;
+
+use anyhow::{bail, Context};
+
+use crate::eval::{self, FeatureVec, NUM_FEATURES};
+use crate::parquet::{Column, ParquetWriter};
+use crate::replay::Event;
+
+/// Number of positions of a row group, to bound the memory used by the conversion
+const ROW_GROUP_SIZE: usize = 1 << 18;
+
+/// Columns of the positions not written yet
+#[derive(Default)]
+struct Rows {
+    seed: Vec<u64>,
+    move_index: Vec<i32>,
+    board: Vec<u64>,
+    features: Vec<Vec<f32>>,
+    action: Vec<String>,
+    value: Vec<f32>,
+    score: Vec<i32>,
+    final_score: Vec<i32>,
+    final_num_moves: Vec<i32>,
+    final_max_tile: Vec<i32>,
+}
+
+/// Writes the positions of games to a Parquet file.
+pub struct DatasetWriter {
+    writer: ParquetWriter,
+    rows: Rows,
+}
+
+impl DatasetWriter {
+    pub fn create(path: &Path) -> anyhow::Result<DatasetWriter> {
+        Ok(DatasetWriter {
+            writer: ParquetWriter::create(path)?,
+            rows: Rows {
+                features: vec![Vec::new(); NUM_FEATURES],
+                ..Rows::default()
+            },
+        })
+    }
+
+    /// Adds a row for each move of the game. Returns the number of rows, 0 for a game without end (e.g. a game
+    /// saved before its end), whose outcome is unknown.
+    pub fn add_game(&mut self, events: &[Event]) -> anyhow::Result<usize> {
+        let Some(Event::Start { seed, board, .. }) = events.first() else {
+            bail!("The game does not start with a start event");
+        };
+        let Some(&Event::End {
+            num_moves,
+            merge_score,
+            ..
+        }) = events.last()
+        else {
+            return Ok(0);
+        };
+        let (seed, mut board) = (*seed, *board);
+        let mut positions = Vec::new();
+        let mut score = 0;
+        for event in &events[1..events.len() - 1] {
+            let Event::Move {
+                action,
+                value,
+                score: gain,
+                spawn,
+            } = *event
+            else {
+                bail!("Unexpected event in the middle of the game: {event:?}");
+            };
+            positions.push((board, action, value, score));
+            board = board
+                .apply(action)
+                .with_context(|| format!("Inapplicable action {action:?} (seed {seed})"))?;
+            board.cells[spawn.row][spawn.col] = spawn.tile;
+            score += gain;
+        }
+        let max_tile = 1i32 << board.max_tile();
+        for (i, (board, action, value, score)) in positions.iter().enumerate() {
+            let rows = &mut self.rows;
+            rows.seed.push(seed);
+            rows.move_index.push(i as i32);
+            rows.board.push(board.pack());
+            for (column, feature) in rows.features.iter_mut().zip(eval::features(board).0) {
+                column.push(feature);
+            }
+            rows.action.push(format!("{action:?}"));
+            rows.value.push(*value);
+            rows.score.push(*score as i32);
+            rows.final_score.push(merge_score as i32);
+            rows.final_num_moves.push(num_moves as i32);
+            rows.final_max_tile.push(max_tile);
+        }
+        if self.rows.seed.len() >= ROW_GROUP_SIZE {
+            self.write_rows()?;
+        }
+        Ok(positions.len())
+    }
+
+    fn write_rows(&mut self) -> anyhow::Result<()> {
+        let rows = std::mem::take(&mut self.rows);
+        let mut columns = vec![
+            ("seed", Column::UInt64(rows.seed)),
+            ("move", Column::Int32(rows.move_index)),
+            ("board", Column::UInt64(rows.board)),
+        ];
+        let names: Vec<String> = FeatureVec::names()
+            .map(|name| format!("feature_{name}"))
+            .collect();
+        for (name, values) in names.iter().zip(rows.features) {
+            columns.push((name.as_str(), Column::Float(values)));
+        }
+        columns.extend([
+            ("action", Column::Utf8(rows.action)),
+            ("value", Column::Float(rows.value)),
+            ("score", Column::Int32(rows.score)),
+            ("final_score", Column::Int32(rows.final_score)),
+            ("final_num_moves", Column::Int32(rows.final_num_moves)),
+            ("final_max_tile", Column::Int32(rows.final_max_tile)),
+        ]);
+        self.rows.features = vec![Vec::new(); NUM_FEATURES];
+        self.writer.write_row_group(&columns)
+    }
+
+    /// Writes the remaining positions and the end of the file. Returns the number of positions.
+    pub fn finish(mut self) -> anyhow::Result<usize> {
+        // an empty dataset still has a row group, for the schema
+        if !self.rows.seed.is_empty() || self.writer.num_row_groups() == 0 {
+            self.write_rows()?;
+        }
+        self.writer.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::savegame::GameInProgress;
+    use crate::search;
+
+    #[test]
+    fn test_dataset() {
+        let mut game = GameInProgress::new(5);
+        while let Some(action) = search::select_action_randomly(*game.board()) {
+            game.play(action);
+        }
+        let path = std::env::temp_dir().join(format!("positions-{}.parquet", std::process::id()));
+        let mut writer = DatasetWriter::create(&path).unwrap();
+        let events = game.to_replay("random");
+        assert_eq!(writer.add_game(&events).unwrap(), game.num_moves());
+        // the score before the last move, and the outcome
+        let Some(&Event::Move { score: gain, .. }) = events.iter().nth_back(1) else {
+            panic!("no last move");
+        };
+        assert_eq!(
+            writer.rows.score.last(),
+            Some(&((game.merge_score() - gain) as i32))
+        );
+        assert_eq!(writer.rows.final_num_moves[0], game.num_moves() as i32);
+        // a game without end is left out
+        let mut unfinished = events.clone();
+        unfinished.pop();
+        assert_eq!(writer.add_game(&unfinished).unwrap(), 0);
+        assert_eq!(writer.finish().unwrap(), game.num_moves());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,310 @@
+//! Distillation of a slow policy into a fast lookup policy (`main distill`): the actions selected by a deep search
+//! on many positions (as collected by `main expert` or `bench --collect`) are imitated by a linear softmax policy
+//! over tuple features, whose decision is a few table lookups.
+//!
+//! Each row, each column and each 2x2 square of the board is a tuple of 4 cells, and each tuple has a table giving,
+//! for each content of its cells, a preference for each action. The policy plays the applicable action with the
+//! highest sum of preferences over all tuples. It plays with the `distilled:file=policy.bin` strategy, to compare a
+//! "fast reflex" with the search it imitates under tight move clocks.
+//!
+//! The file format is the magic bytes `2048POLI`, the version of the format as a little-endian `u32`, the number of
+//! tuples (`u32`), the cells of each tuple (4 × `u32` each, by `row * N + col`), then the preferences (`f32`), tuple
+//! by tuple, content by content, in the order of `ALL_ACTIONS`. All numbers are little-endian.
+
+use std::fmt::{Debug, Formatter};
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use anyhow::{ensure, Context};
+use hashbrown::HashMap;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+
+use crate::board::{Action, Board, PlayableBoard, ALL_ACTIONS, N};
+use crate::collect::Sample;
+
+const MAGIC: &[u8; 8] = b"2048POLI";
+
+const FORMAT_VERSION: u32 = 1;
+
+/// Number of cells of a tuple
+const TUPLE_SIZE: usize = 4;
+
+/// Number of distinct contents of a cell in a tuple, tiles above `2^15` being clamped to `2^15`
+const NUM_VALUES: usize = 16;
+
+/// Number of entries of the table of a tuple (one per content of its cells)
+const TABLE_SIZE: usize = NUM_VALUES.pow(TUPLE_SIZE as u32);
+
+/// The rows, the columns and the 2x2 squares of the board
+fn default_tuples() -> Vec<[usize; TUPLE_SIZE]> {
+    let mut tuples = Vec::new();
+    for i in 0..N {
+        tuples.push(std::array::from_fn(|j| i * N + j));
+        tuples.push(std::array::from_fn(|j| j * N + i));
+    }
+    for row in 0..N - 1 {
+        for col in 0..N - 1 {
+            let cell = row * N + col;
+            tuples.push([cell, cell + 1, cell + N, cell + N + 1]);
+        }
+    }
+    tuples
+}
+
+/// A linear policy over tuple features, with a table of action preferences per tuple
+#[derive(Clone)]
+pub struct LookupPolicy {
+    tuples: Vec<[usize; TUPLE_SIZE]>,
+    /// Preferences of the actions, `ALL_ACTIONS.len()` per entry of the table of each tuple
+    preferences: Vec<f32>,
+    /// File the policy was loaded from, if any, to refer to it in the strategy
+    source: Option<PathBuf>,
+}
+
+impl Debug for LookupPolicy {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LookupPolicy")
+            .field("tuples", &self.tuples.len())
+            .field("source", &self.source)
+            .finish()
+    }
+}
+
+impl PartialEq for LookupPolicy {
+    /// Policies are shared once loaded (see `load_shared`), so two policies are the same only if they are the same
+    /// object.
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+impl Eq for LookupPolicy {}
+
+impl LookupPolicy {
+    /// A policy with no preference for any action
+    pub fn new() -> LookupPolicy {
+        let tuples = default_tuples();
+        LookupPolicy {
+            preferences: vec![0.0; tuples.len() * TABLE_SIZE * ALL_ACTIONS.len()],
+            tuples,
+            source: None,
+        }
+    }
+
+    pub fn source(&self) -> Option<&Path> {
+        self.source.as_deref()
+    }
+
+    /// Position in `preferences` of the preferences of each tuple for the board
+    fn offsets<'a>(&'a self, board: &'a Board) -> impl Iterator<Item = usize> + 'a {
+        self.tuples.iter().enumerate().map(move |(t, cells)| {
+            let index = cells.iter().fold(0, |index, &cell| {
+                let tile = board.cells[cell / N][cell % N].min(NUM_VALUES as u8 - 1);
+                index * NUM_VALUES + tile as usize
+            });
+            (t * TABLE_SIZE + index) * ALL_ACTIONS.len()
+        })
+    }
+
+    /// Preference of each action (in the order of `ALL_ACTIONS`) on the board, whether applicable or not
+    pub fn preferences(&self, board: &Board) -> [f32; 4] {
+        let mut preferences = [0.0; 4];
+        for offset in self.offsets(board) {
+            for (preference, weight) in preferences.iter_mut().zip(&self.preferences[offset..]) {
+                *preference += weight;
+            }
+        }
+        preferences
+    }
+
+    /// Applicable action of highest preference, or `None` if no action is applicable
+    pub fn select_action(&self, board: PlayableBoard) -> Option<Action> {
+        let preferences = self.preferences(board.board());
+        ALL_ACTIONS
+            .into_iter()
+            .zip(preferences)
+            .filter(|(action, _)| board.apply(*action).is_some())
+            .max_by(|(_, p1), (_, p2)| p1.total_cmp(p2))
+            .map(|(action, _)| action)
+    }
+
+    /// Moves the policy toward selecting the action of the sample, by a gradient step of the cross-entropy of the
+    /// softmax of the preferences of the applicable actions. Returns whether the policy already selected it.
+    pub fn learn(&mut self, sample: &Sample, alpha: f32) -> bool {
+        let board = PlayableBoard::from(sample.board);
+        let preferences = self.preferences(&sample.board);
+        let applicable = ALL_ACTIONS.map(|action| board.apply(action).is_some());
+        let max = (0..4)
+            .filter(|&a| applicable[a])
+            .map(|a| preferences[a])
+            .fold(f32::NEG_INFINITY, f32::max);
+        let exp: [f32; 4] = std::array::from_fn(|a| {
+            if applicable[a] {
+                (preferences[a] - max).exp()
+            } else {
+                0.0
+            }
+        });
+        let total: f32 = exp.iter().sum();
+        let selected = self.select_action(board) == Some(sample.action);
+        // each table moves by a fraction of the step, as all tables contribute to the preferences
+        let step = alpha / self.tuples.len() as f32;
+        let gradient: [f32; 4] = std::array::from_fn(|a| {
+            let target = if ALL_ACTIONS[a] == sample.action {
+                1.0
+            } else {
+                0.0
+            };
+            step * (target - exp[a] / total)
+        });
+        let offsets: Vec<usize> = self.offsets(&sample.board).collect();
+        for offset in offsets {
+            for (weight, gradient) in self.preferences[offset..offset + 4]
+                .iter_mut()
+                .zip(gradient)
+            {
+                *weight += gradient;
+            }
+        }
+        selected
+    }
+
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        let file =
+            File::create(path).with_context(|| format!("Cannot create {}", path.display()))?;
+        let mut out = BufWriter::new(file);
+        out.write_all(MAGIC)?;
+        out.write_all(&FORMAT_VERSION.to_le_bytes())?;
+        out.write_all(&(self.tuples.len() as u32).to_le_bytes())?;
+        for &cell in self.tuples.iter().flatten() {
+            out.write_all(&(cell as u32).to_le_bytes())?;
+        }
+        for preference in &self.preferences {
+            out.write_all(&preference.to_le_bytes())?;
+        }
+        out.flush()?;
+        Ok(())
+    }
+
+    pub fn load(path: &Path) -> anyhow::Result<LookupPolicy> {
+        let file = File::open(path).with_context(|| format!("Cannot open {}", path.display()))?;
+        let mut input = BufReader::new(file);
+        let mut read_u32 = || -> anyhow::Result<u32> {
+            let mut bytes = [0; 4];
+            input.read_exact(&mut bytes)?;
+            Ok(u32::from_le_bytes(bytes))
+        };
+        let magic = [read_u32()?, read_u32()?];
+        ensure!(
+            magic[0].to_le_bytes() == MAGIC[..4] && magic[1].to_le_bytes() == MAGIC[4..],
+            "{} is not a distilled policy",
+            path.display()
+        );
+        let version = read_u32()?;
+        ensure!(
+            version == FORMAT_VERSION,
+            "Unsupported version of distilled policy: {version}"
+        );
+        let num_tuples = read_u32()? as usize;
+        let mut tuples = Vec::with_capacity(num_tuples);
+        for _ in 0..num_tuples {
+            let mut tuple = [0; TUPLE_SIZE];
+            for cell in &mut tuple {
+                *cell = read_u32()? as usize;
+                ensure!(*cell < N * N, "Cell {cell} out of the board");
+            }
+            tuples.push(tuple);
+        }
+        let preferences = (0..num_tuples * TABLE_SIZE * ALL_ACTIONS.len())
+            .map(|_| read_u32().map(f32::from_bits))
+            .collect::<anyhow::Result<_>>()
+            .with_context(|| format!("Truncated distilled policy {}", path.display()))?;
+        Ok(LookupPolicy {
+            tuples,
+            preferences,
+            source: Some(path.to_path_buf()),
+        })
+    }
+}
+
+impl Default for LookupPolicy {
+    fn default() -> Self {
+        LookupPolicy::new()
+    }
+}
+
+/// Policies already loaded by `load_shared`, by file
+static LOADED: Mutex<Option<HashMap<PathBuf, &'static LookupPolicy>>> = Mutex::new(None);
+
+/// Loads the policy of the file once for the whole program, so that strategies can refer to it.
+pub fn load_shared(path: &Path) -> anyhow::Result<&'static LookupPolicy> {
+    let mut loaded = LOADED.lock().unwrap();
+    let loaded = loaded.get_or_insert_with(HashMap::new);
+    if let Some(&policy) = loaded.get(path) {
+        return Ok(policy);
+    }
+    let policy: &'static LookupPolicy = Box::leak(Box::new(LookupPolicy::load(path)?));
+    loaded.insert(path.to_path_buf(), policy);
+    Ok(policy)
+}
+
+/// Fraction of the samples on which the policy selects the action of the sample
+pub fn accuracy(policy: &LookupPolicy, samples: &[Sample]) -> f64 {
+    let agreeing = samples
+        .iter()
+        .filter(|sample| policy.select_action(sample.board.into()) == Some(sample.action))
+        .count();
+    agreeing as f64 / samples.len().max(1) as f64
+}
+
+/// Makes one pass over the samples in a random order, learning from each. Returns the fraction of the samples on
+/// which the policy already selected the action of the sample before learning from it.
+pub fn train_epoch(
+    policy: &mut LookupPolicy,
+    samples: &mut [Sample],
+    alpha: f32,
+    rng: &mut StdRng,
+) -> f64 {
+    samples.shuffle(rng);
+    let agreeing = samples
+        .iter()
+        .filter(|sample| policy.learn(sample, alpha))
+        .count();
+    agreeing as f64 / samples.len().max(1) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+
+    use super::*;
+
+    #[test]
+    fn test_distill() {
+        assert_eq!(default_tuples().len(), 2 * N + (N - 1) * (N - 1));
+        // always prefer Left when possible, Up otherwise
+        let board = Board::from_values([[0, 2, 0, 0], [0; 4], [0, 0, 4, 0], [0; 4]]).unwrap();
+        let sample = Sample {
+            seed: 0,
+            board,
+            values: [None; 4],
+            action: Action::Left,
+        };
+        let mut samples = vec![sample; 20];
+        let mut policy = LookupPolicy::new();
+        assert_eq!(accuracy(&policy, &samples), 0.0);
+        let mut rng = StdRng::seed_from_u64(0);
+        train_epoch(&mut policy, &mut samples, 1.0, &mut rng);
+        assert_eq!(accuracy(&policy, &samples), 1.0);
+
+        let path = std::env::temp_dir().join(format!("distill-{}.bin", std::process::id()));
+        policy.save(&path).unwrap();
+        let shared = load_shared(&path).unwrap();
+        assert_eq!(shared.preferences(&board), policy.preferences(&board));
+        assert!(std::ptr::eq(shared, load_shared(&path).unwrap()));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
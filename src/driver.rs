@@ -0,0 +1,277 @@
+//! Driver of the original 2048 web game (play2048.co, or any copy of gabrielecirulli/2048) by a strategy
+//! (`main drive`).
+//!
+//! A script run in the page of the game reads the board and the score from the DOM and sends them over a WebSocket;
+//! the driver answers the action selected by the strategy, and the script presses the corresponding arrow key.
+//!
+//! Each board read is checked against the rules of this crate: it must be one of the boards that the previous action
+//! and a random tile can lead to, the score must be the merge score and the game must be over exactly when the board
+//! is lost. A mismatch means that the rules differ from those of the real game.
+//!
+//! ```text
+//! cargo run --release --features server -- drive -s expectimax
+//! ```
+
+use std::io::Write;
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::time::Duration;
+
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::board::{Action, Board, PlayableBoard, RandableBoard, N};
+use crate::server::read_request;
+use crate::strategy::Strategy;
+use crate::websocket;
+
+/// State of the game read from the page
+#[derive(Deserialize)]
+struct Observation {
+    board: [[u32; N]; N],
+    score: u32,
+    /// Whether the page shows the game as over
+    over: bool,
+}
+
+/// Summary of a game driven in the page
+#[derive(Debug, Default)]
+pub struct Report {
+    pub num_moves: usize,
+    pub score: u32,
+    pub max_tile: u8,
+    /// Observations of the page that do not follow the rules of this crate
+    pub mismatches: Vec<String>,
+    /// Whether the game was played to its end, rather than the page being closed
+    pub over: bool,
+}
+
+/// Selects the actions of a game in the page, checking the observations against the rules
+struct Driver<'a> {
+    strategy: &'a Strategy,
+    time_per_move: Option<Duration>,
+    /// Last action played with the board after it, and the score expected after it
+    previous: Option<(Action, RandableBoard, u32)>,
+    report: Report,
+}
+
+impl Driver<'_> {
+    /// Checks the observation of the page, and selects the next action (`None` at the end of the game).
+    fn observe(&mut self, observation: &Observation) -> anyhow::Result<Option<Action>> {
+        let board = Board::from_values(observation.board)?;
+        if let Some((action, after, expected_score)) = self.previous.take() {
+            if !after.successors().any(|(_, next)| *next.board() == board) {
+                self.mismatch(format!(
+                    "after {action:?}, the page shows\n{board}\nwhich no random tile added to\n{after}\ncan lead to"
+                ));
+            }
+            if observation.score != expected_score {
+                self.mismatch(format!(
+                    "after {action:?}, the page shows a score of {}, instead of {expected_score}",
+                    observation.score
+                ));
+            }
+        }
+        if observation.over != board.is_lost() {
+            let shown = if observation.over { "over" } else { "not over" };
+            self.mismatch(format!("the page shows the game as {shown} on\n{board}"));
+        }
+        self.report.score = observation.score;
+        self.report.max_tile = board.max_tile();
+        if observation.over {
+            self.report.over = true;
+            return Ok(None);
+        }
+
+        let board = PlayableBoard::from(board);
+        let action = match self.time_per_move {
+            Some(budget) => self.strategy.select_action_within(board, budget),
+            None => self.strategy.select_action(board),
+        };
+        if let Some(action) = action {
+            // follow the game from the board of the page even if it differs from the expected one
+            let (after, gain) = board.apply_scored(action).expect("invalid action");
+            self.previous = Some((action, after, observation.score + gain));
+            self.report.num_moves += 1;
+        }
+        Ok(action)
+    }
+
+    fn mismatch(&mut self, message: String) {
+        eprintln!("Mismatch with the rules: {message}");
+        self.report.mismatches.push(message);
+    }
+}
+
+/// Plays the games of the pages connecting to the listener, one at a time, printing a report at the end of each.
+pub fn serve(
+    listener: TcpListener,
+    strategy: &Strategy,
+    time_per_move: Option<Duration>,
+) -> anyhow::Result<()> {
+    let addr = listener.local_addr()?;
+    for stream in listener.incoming() {
+        let mut stream = stream?;
+        let request = match read_request(&mut stream) {
+            Ok(request) => request,
+            Err(e) => {
+                eprintln!("Invalid request: {e:#}");
+                continue;
+            }
+        };
+        if request.path != "/driver" {
+            // the script, for pages that load it rather than pasting it
+            let script = script(addr);
+            write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Type: text/javascript\r\nContent-Length: {}\r\nAccess-Control-Allow-Origin: *\r\nConnection: close\r\n\r\n{script}",
+                script.len()
+            )?;
+            continue;
+        }
+        websocket::accept(&mut stream, &request)?;
+        eprintln!("Page connected, playing with {strategy}");
+        match drive(stream, strategy, time_per_move) {
+            Ok(report) => {
+                let end = if report.over {
+                    "Game over"
+                } else {
+                    "Page closed"
+                };
+                println!(
+                    "{end} after {} moves: score {}, max tile {}, {} mismatches with the rules",
+                    report.num_moves,
+                    report.score,
+                    1u32 << report.max_tile,
+                    report.mismatches.len()
+                );
+            }
+            Err(e) => eprintln!("The connection with the page failed: {e:#}"),
+        }
+    }
+    Ok(())
+}
+
+/// Plays the game of the page connected to the WebSocket, until its end or until the page closes the connection.
+fn drive(
+    mut stream: TcpStream,
+    strategy: &Strategy,
+    time_per_move: Option<Duration>,
+) -> anyhow::Result<Report> {
+    let mut driver = Driver {
+        strategy,
+        time_per_move,
+        previous: None,
+        report: Report::default(),
+    };
+    while let Some(message) = websocket::read_text(&mut stream)? {
+        let observation: Observation = serde_json::from_str(&message)?;
+        let action = driver.observe(&observation)?;
+        stream.write_all(&websocket::text_frame(
+            &json!({ "action": action }).to_string(),
+        ))?;
+        if action.is_none() {
+            break;
+        }
+    }
+    Ok(driver.report)
+}
+
+/// Script to run in the page of the game, connecting to the driver listening on `addr`
+pub fn script(addr: SocketAddr) -> String {
+    SCRIPT.replace("ADDR", &addr.to_string())
+}
+
+/// Script reading the DOM of gabrielecirulli/2048: the tiles are `.tile-<value>.tile-position-<column>-<row>`, with
+/// both tiles of a merge at the position of the merged one.
+const SCRIPT: &str = r#"(() => {
+  const socket = new WebSocket("ws://ADDR/driver");
+  const keys = {Up: ["ArrowUp", 38], Right: ["ArrowRight", 39], Down: ["ArrowDown", 40], Left: ["ArrowLeft", 37]};
+  const read = () => {
+    const board = [[0, 0, 0, 0], [0, 0, 0, 0], [0, 0, 0, 0], [0, 0, 0, 0]];
+    for (const tile of document.querySelectorAll(".tile-container .tile")) {
+      const classes = [...tile.classList];
+      const value = Number(classes.find((c) => /^tile-\d+$/.test(c)).slice(5));
+      const [x, y] = classes.find((c) => c.startsWith("tile-position-")).slice(14).split("-").map(Number);
+      board[y - 1][x - 1] = Math.max(board[y - 1][x - 1], value);
+    }
+    const score = parseInt(document.querySelector(".score-container").firstChild.textContent) || 0;
+    const over = document.querySelector(".game-message.game-over") !== null;
+    return {board, score, over};
+  };
+  let last = null;
+  let waited = 0;
+  const send = () => {
+    // continue after reaching 2048
+    if (document.querySelector(".game-message.game-won")) document.querySelector(".keep-playing-button").click();
+    const state = read();
+    const board = JSON.stringify(state.board);
+    // wait for the page to show the move, for at most 2s
+    if (board === last && !state.over && waited < 2000) {
+      waited += 50;
+      setTimeout(send, 50);
+      return;
+    }
+    last = board;
+    waited = 0;
+    socket.send(JSON.stringify(state));
+  };
+  socket.onopen = send;
+  socket.onmessage = (message) => {
+    const {action} = JSON.parse(message.data);
+    if (!action) return socket.close();
+    const [key, code] = keys[action];
+    const event = new KeyboardEvent("keydown", {key, code: key, bubbles: true});
+    Object.defineProperty(event, "keyCode", {get: () => code});
+    Object.defineProperty(event, "which", {get: () => code});
+    document.dispatchEvent(event);
+    setTimeout(send, 50);
+  };
+})();
+"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_observe() {
+        let strategy: Strategy = "random".parse().unwrap();
+        let mut driver = Driver {
+            strategy: &strategy,
+            time_per_move: None,
+            previous: None,
+            report: Report::default(),
+        };
+        let board = [[2, 2, 0, 0], [0, 0, 0, 0], [0, 0, 4, 0], [0, 0, 0, 0]];
+        let action = driver
+            .observe(&Observation {
+                board,
+                score: 12,
+                over: false,
+            })
+            .unwrap()
+            .unwrap();
+        let (after, gain) = PlayableBoard::from(Board::from_values(board).unwrap())
+            .apply_scored(action)
+            .unwrap();
+        let next = after.successors().next().unwrap().1;
+        let observation = Observation {
+            board: next.board().values(),
+            score: 12 + gain,
+            over: false,
+        };
+        assert!(driver.observe(&observation).unwrap().is_some());
+        assert!(driver.report.mismatches.is_empty());
+        assert_eq!(driver.report.num_moves, 2);
+
+        // a board that the action cannot lead to, with a wrong score
+        let observation = Observation {
+            board,
+            score: 0,
+            over: true,
+        };
+        assert_eq!(driver.observe(&observation).unwrap(), None);
+        assert_eq!(driver.report.mismatches.len(), 3);
+        assert!(driver.report.over);
+    }
+}
@@ -0,0 +1,144 @@
+//! Engine protocol over JSON lines, to let other programs (GUIs, graders, scripts in any language) use the AI.
+//!
+//! Each request is a line with the board as the values of its tiles row by row (0 for empty cells), an optional
+//! time budget in milliseconds and an optional strategy overriding the one of the engine:
+//!
+//! ```text
+//! {"board": [[2, 4, 0, 0], [0, 0, 0, 0], [0, 8, 0, 0], [0, 0, 0, 2]], "budget_ms": 50}
+//! ```
+//!
+//! and is answered by a line with the selected action (`null` if no action is applicable), the evaluation of the
+//! afterstate it leads to, and the time taken to select it:
+//!
+//! ```text
+//! {"action":"Down","time_ms":0.09,"value":1600599.25}
+//! ```
+//!
+//! An invalid request is answered with `{"error": "..."}`, and the engine waits for the next one.
+
+use std::io::{BufRead, Write};
+use std::time::{Duration, Instant};
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+
+use crate::board::{Action, Board, PlayableBoard, N};
+use crate::strategy::Strategy;
+
+#[derive(Deserialize)]
+pub(crate) struct Request {
+    pub board: [[u32; N]; N],
+    pub budget_ms: Option<u64>,
+    pub strategy: Option<String>,
+}
+
+#[derive(Serialize)]
+pub(crate) struct Response {
+    pub action: Option<Action>,
+    /// Evaluation of the afterstate reached by the action
+    pub value: Option<f32>,
+    pub time_ms: f64,
+}
+
+/// Answers the request of a line, with `strategy` unless the request gives another one.
+pub fn answer(line: &str, strategy: &Strategy) -> serde_json::Value {
+    match select(line, strategy) {
+        Ok(response) => serde_json::to_value(response).expect("serializable response"),
+        Err(e) => serde_json::json!({ "error": format!("{e:#}") }),
+    }
+}
+
+fn select(line: &str, strategy: &Strategy) -> anyhow::Result<Response> {
+    let request: Request = serde_json::from_str(line).context("Invalid request")?;
+    let board = PlayableBoard::from(Board::from_values(request.board)?);
+    let strategy = match &request.strategy {
+        Some(name) => name.parse()?,
+        None => *strategy,
+    };
+    best_move(
+        board,
+        request.budget_ms.map(Duration::from_millis),
+        &strategy,
+    )
+}
+
+/// Selects the action of the strategy on the board, within the budget if any.
+pub(crate) fn best_move(
+    board: PlayableBoard,
+    budget: Option<Duration>,
+    strategy: &Strategy,
+) -> anyhow::Result<Response> {
+    let start = Instant::now();
+    let action = match budget {
+        Some(budget) => strategy.select_action_within(board, budget),
+        None => strategy.select_action(board),
+    };
+    let time_ms = start.elapsed().as_secs_f64() * 1000.0;
+    let value = match action {
+        Some(action) => Some(
+            board
+                .apply(action)
+                .with_context(|| {
+                    format!("The strategy selected an inapplicable action: {action:?}")
+                })?
+                .evaluate(),
+        ),
+        None => None,
+    };
+    Ok(Response {
+        action,
+        value,
+        time_ms,
+    })
+}
+
+/// Answers each line of `input` on a line of `output`, until the end of the input.
+pub fn serve(
+    input: impl BufRead,
+    mut output: impl Write,
+    strategy: &Strategy,
+) -> anyhow::Result<()> {
+    for line in input.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        writeln!(output, "{}", answer(&line, strategy))?;
+        // the other program waits for the answer before sending the next request
+        output.flush()?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_serve() {
+        let input = concat!(
+            r#"{"board": [[2, 2, 0, 0], [0, 0, 0, 0], [0, 0, 0, 0], [0, 0, 0, 0]], "strategy": "random"}"#,
+            "\n\n",
+            r#"{"board": [[2, 4, 2, 4], [4, 2, 4, 2], [2, 4, 2, 4], [4, 2, 4, 2]]}"#,
+            "\n",
+            r#"{"board": [[3, 0, 0, 0], [0, 0, 0, 0], [0, 0, 0, 0], [0, 0, 0, 0]]}"#,
+            "\n",
+        );
+        let mut output = Vec::new();
+        serve(input.as_bytes(), &mut output, &Strategy::Random).unwrap();
+        let answers: Vec<serde_json::Value> = String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(answers.len(), 3);
+        assert!(answers[0]["action"].is_string());
+        assert!(answers[0]["value"].is_number());
+        // lost board: no action
+        assert!(answers[1]["action"].is_null());
+        assert!(answers[2]["error"]
+            .as_str()
+            .unwrap()
+            .contains("power of two"));
+    }
+}
@@ -1,37 +1,764 @@
+use std::cell::RefCell;
+use std::fmt::{Display, Formatter};
+use std::path::Path;
+use std::sync::{Arc, OnceLock};
+
+use anyhow::{bail, ensure, Context};
+
 use crate::board::*;
 
+pub mod cache;
+#[cfg(feature = "nn")]
+pub mod nn;
+pub mod ntuple;
+pub mod params;
+pub mod phased;
+pub mod presets;
+pub mod spec;
+pub mod symmetric;
+
 /// One line/column of the board
 type Row = [u8; N];
 
+/// An evaluation function on boards, usable at the leaves of a search.
+///
+/// All evaluation functions of this crate are defined on afterstates: `eval` receives the board obtained
+/// right after an action, before a random tile is placed.
+pub trait Evaluate: Send + Sync {
+    fn eval(&self, board: &Board) -> f32;
+
+    /// The linear evaluator behind this evaluation, if it is one, for the functions detailing a linear evaluation
+    /// (e.g. `explain`).
+    fn linear(&self) -> Option<&Evaluator> {
+        None
+    }
+
+    /// Evaluates the boards, writing the value of `boards[i]` to `values[i]`. Evaluators with a per-call overhead
+    /// (e.g. running a network) evaluate them at once.
+    ///
+    /// Panics if the slices have different lengths.
+    fn eval_batch_into(&self, boards: &[Board], values: &mut [f32]) {
+        assert_eq!(boards.len(), values.len(), "one value per board");
+        for (board, value) in boards.iter().zip(values) {
+            *value = self.eval(board);
+        }
+    }
+
+    /// Values of the boards, in the same order.
+    fn eval_batch(&self, boards: &[Board]) -> Vec<f32> {
+        let mut values = vec![0.0; boards.len()];
+        self.eval_batch_into(boards, &mut values);
+        values
+    }
+
+    /// Value of an afterstate (after the action of the player, before the random tile).
+    fn eval_afterstate(&self, board: &RandableBoard) -> f32 {
+        self.eval(board.board())
+    }
+
+    /// Value of a lost board (on which no action is applicable), below the value of any afterstate: `LOST` plus the
+    /// sum of the tiles by default.
+    ///
+    /// Afterstates are never lost (an action empties a cell or moves a tile to an empty one), so the search only
+    /// meets lost boards as playable leaves, which it evaluates with `eval_state`.
+    fn eval_lost(&self, board: &Board) -> f32 {
+        LOST + board.tile_sum() as f32
+    }
+
+    /// Value of a state (before the action of the player), as the value of its best afterstate.
+    /// If no action is applicable, this is the value of the lost board (see `eval_lost`).
+    fn eval_state(&self, board: &PlayableBoard) -> f32 {
+        ALL_ACTIONS
+            .into_iter()
+            .filter_map(|action| board.apply(action))
+            .map(|after| self.eval_afterstate(&after))
+            .max_by(f32::total_cmp)
+            .unwrap_or_else(|| self.eval_lost(board.board()))
+    }
+}
+
+impl Evaluate for Evaluator {
+    fn eval(&self, board: &Board) -> f32 {
+        Evaluator::eval(self, board)
+    }
+
+    fn linear(&self) -> Option<&Evaluator> {
+        Some(self)
+    }
+
+    fn eval_lost(&self, board: &Board) -> f32 {
+        Evaluator::eval_lost(self, board)
+    }
+}
+
+impl Evaluate for phased::PhasedEvaluator {
+    fn eval(&self, board: &Board) -> f32 {
+        phased::PhasedEvaluator::eval(self, board)
+    }
+
+    fn eval_lost(&self, board: &Board) -> f32 {
+        phased::PhasedEvaluator::eval_lost(self, board)
+    }
+}
+
+impl Evaluate for ntuple::NTupleNetwork {
+    fn eval(&self, board: &Board) -> f32 {
+        ntuple::NTupleNetwork::eval(self, board)
+    }
+}
+
+#[cfg(feature = "nn")]
+impl Evaluate for nn::NnEvaluator {
+    fn eval(&self, board: &Board) -> f32 {
+        nn::NnEvaluator::eval(self, board)
+    }
+
+    fn eval_batch_into(&self, boards: &[Board], values: &mut [f32]) {
+        values.copy_from_slice(&nn::NnEvaluator::eval_batch(self, boards));
+    }
+
+    fn eval_batch(&self, boards: &[Board]) -> Vec<f32> {
+        nn::NnEvaluator::eval_batch(self, boards)
+    }
+}
+
+/// Evaluates the board with the default weights.
+///
+/// Each row/column is evaluated with a single lookup in a table precomputed on the first call.
+/// Tiles above `2^15` are evaluated as if they were `2^15`.
 pub fn eval(board: &Board) -> f32 {
-    let mut sum = 0.0;
-    for row in board.cells.iter() {
-        sum += eval_row(row);
+    with_current(|evaluator| evaluator.eval(board))
+}
+
+/// Evaluates an afterstate with the default weights.
+pub fn eval_afterstate(board: &RandableBoard) -> f32 {
+    with_current(|evaluator| evaluator.eval_afterstate(board))
+}
+
+/// Evaluates the boards with the default weights, in the same order (see `Evaluate::eval_batch_into`).
+pub fn eval_batch(boards: &[Board]) -> Vec<f32> {
+    with_current(|evaluator| evaluator.eval_batch(boards))
+}
+
+/// Same as `eval_batch`, writing the value of `boards[i]` to `values[i]`.
+pub fn eval_batch_into(boards: &[Board], values: &mut [f32]) {
+    with_current(|evaluator| evaluator.eval_batch_into(boards, values))
+}
+
+/// Evaluates a state with the default weights, as the value of its best afterstate.
+pub fn eval_state(board: &PlayableBoard) -> f32 {
+    with_current(|evaluator| evaluator.eval_state(board))
+}
+
+/// Smallest and largest values that the default linear evaluation may return, on any board.
+///
+/// This and the other functions detailing a linear evaluation use the default weights if the evaluator of the
+/// current thread is not linear (e.g. an n-tuple network).
+pub fn bounds() -> (f32, f32) {
+    with_current_linear(|evaluator| evaluator.bounds())
+}
+
+/// Linear evaluation of the board with the default weights, mapped into `[0, 1]` according to `bounds()`.
+pub fn eval_normalized(board: &Board) -> f32 {
+    with_current_linear(|evaluator| evaluator.eval_normalized(board))
+}
+
+/// Details the linear evaluation of the board with the default weights.
+pub fn explain(board: &Board) -> EvalBreakdown {
+    with_current_linear(|evaluator| evaluator.explain(board))
+}
+
+/// Features of a board that are not heuristics of the registry, appended after the heuristics in `FeatureVec`.
+const EXTRA_FEATURES: [&str; 4] = ["max_tile", "max_tile_row", "max_tile_col", "tile_sum"];
+
+/// Number of features in a `FeatureVec`
+pub const NUM_FEATURES: usize = NUM_HEURISTICS + EXTRA_FEATURES.len();
+
+/// Numeric features of a board: the raw values of all heuristics (in the order of `HEURISTICS`),
+/// followed by the exponent of the max tile, its row and column, and the sum of all tiles.
+///
+/// For a board that is not lost, its evaluation is the dot product of the heuristic features with the weights,
+/// plus a constant. This makes it suitable for fitting weights by linear regression.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FeatureVec(pub [f32; NUM_FEATURES]);
+
+impl FeatureVec {
+    /// Names of the features, in order
+    pub fn names() -> impl Iterator<Item = &'static str> {
+        HEURISTICS
+            .iter()
+            .map(|heuristic| heuristic.name)
+            .chain(EXTRA_FEATURES)
     }
-    for col in board.transposed().cells.iter() {
-        sum += eval_row(col);
+
+    /// The raw values of the heuristics, in the order of `HEURISTICS`
+    pub fn heuristics(&self) -> &[f32] {
+        &self.0[..NUM_HEURISTICS]
+    }
+}
+
+/// Extracts the numeric features of the board.
+pub fn features(board: &Board) -> FeatureVec {
+    let mut features = [0.0; NUM_FEATURES];
+    for (feature, heuristic) in features.iter_mut().zip(&HEURISTICS) {
+        *feature = raw_value(heuristic, board);
     }
-    sum
+    // position of the max tile (the first one in reading order in case of ties)
+    let (max_cell, max_tile) = board.cells.iter().flatten().enumerate().fold(
+        (0, 0),
+        |(best_cell, best), (cell, &tile)| {
+            if tile > best {
+                (cell, tile)
+            } else {
+                (best_cell, best)
+            }
+        },
+    );
+    features[NUM_HEURISTICS] = max_tile as f32;
+    features[NUM_HEURISTICS + 1] = (max_cell / N) as f32;
+    features[NUM_HEURISTICS + 2] = (max_cell % N) as f32;
+    features[NUM_HEURISTICS + 3] = board.tile_sum() as f32;
+    FeatureVec(features)
 }
 
+/// The evaluator used by the free functions of this module
+static DEFAULT: OnceLock<Arc<dyn Evaluate>> = OnceLock::new();
+
+/// The linear evaluator with the default weights, for the functions detailing a linear evaluation when the current
+/// evaluator is not linear
+static DEFAULT_LINEAR: OnceLock<Evaluator> = OnceLock::new();
+
+/// The default evaluator, built with the default weights on the first call unless `set_default_evaluator` (or
+/// `set_default_weights`) was called.
+fn default_evaluator() -> &'static dyn Evaluate {
+    DEFAULT
+        .get_or_init(|| Arc::new(Evaluator::new(EvalWeights::default())))
+        .as_ref()
+}
+
+thread_local! {
+    /// Evaluator replacing the default one on the current thread, see `with_evaluator`
+    static OVERRIDE: RefCell<Option<Arc<dyn Evaluate>>> = const { RefCell::new(None) };
+}
+
+/// Calls `f` with the evaluator of the current thread: the one given to `with_evaluator`, or the default one.
+///
+/// Within `search::with_deadline`, stops the search instead once its deadline has passed.
+fn with_current<R>(f: impl FnOnce(&dyn Evaluate) -> R) -> R {
+    crate::search::check_deadline();
+    OVERRIDE.with_borrow(|evaluator| match evaluator {
+        Some(evaluator) => f(evaluator.as_ref()),
+        None => f(default_evaluator()),
+    })
+}
+
+/// Calls `f` with the evaluator of the current thread if it is linear, or else with the linear evaluator with the
+/// default weights.
+fn with_current_linear<R>(f: impl FnOnce(&Evaluator) -> R) -> R {
+    with_current(|evaluator| match evaluator.linear() {
+        Some(linear) => f(linear),
+        None => f(DEFAULT_LINEAR.get_or_init(|| Evaluator::new(EvalWeights::default()))),
+    })
+}
+
+/// Runs `f` with `evaluator` used by `eval` and the other free functions of this module on the current thread,
+/// instead of the default evaluator. This lets a single process compare several evaluations.
+///
+/// Evaluations memoized while `f` runs (e.g. in the cache of the search) are only valid for this evaluator.
+pub fn with_evaluator<R>(evaluator: Arc<dyn Evaluate>, f: impl FnOnce() -> R) -> R {
+    /// Restores the previous evaluator, even if `f` panics
+    struct Restore(Option<Arc<dyn Evaluate>>);
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            OVERRIDE.set(self.0.take());
+        }
+    }
+    let _restore = Restore(OVERRIDE.replace(Some(evaluator)));
+    f()
+}
+
+/// Weights of the evaluator of the current thread (see `with_evaluator`), or the default weights if it is not linear.
+pub fn weights() -> EvalWeights {
+    with_current_linear(|evaluator| *evaluator.weights())
+}
+
+/// Replaces the evaluator used by `eval` and the other free functions of this module, e.g. by an n-tuple network
+/// (see `spec::EvaluatorSpec`).
+///
+/// Fails if the default evaluator was already used (the evaluation cannot change in the middle of a game).
+pub fn set_default_evaluator(evaluator: Arc<dyn Evaluate>) -> anyhow::Result<()> {
+    ensure!(
+        DEFAULT.set(evaluator).is_ok(),
+        "The default evaluator was already used, it cannot be changed"
+    );
+    Ok(())
+}
+
+/// Replaces the weights used by `eval` and the other free functions of this module.
+///
+/// Fails if the default evaluator was already used (the weights cannot change in the middle of a game).
+pub fn set_default_weights(weights: EvalWeights) -> anyhow::Result<()> {
+    set_default_evaluator(Arc::new(Evaluator::new(weights)))
+}
+
+/// Weights from the given file (a checkpoint of `params` or a text weights file) or preset, or the default weights if
+/// neither is given.
+pub fn load_weights(path: Option<&Path>, preset: Option<&str>) -> anyhow::Result<EvalWeights> {
+    match (path, preset) {
+        (Some(_), Some(_)) => bail!("Weights cannot be given both as a file and as a preset"),
+        (Some(path), None) => Ok(params::load_linear(path)?.0),
+        (None, Some(preset)) => Ok(presets::find(preset)?.weights()),
+        (None, None) => Ok(EvalWeights::default()),
+    }
+}
+
+/// Value of each row/column of a board that is not lost, keeping evaluations positive.
 const NOT_LOST: f32 = 200_000f32;
-const MONOTONICITY_WEIGHT: f32 = 47.0;
-const EMPTY_WEIGHT: f32 = 270.0;
-const ADJACENT_WEIGHT: f32 = 700.0;
-const SUM_WEIGHT: f32 = 11.0;
 
-fn eval_row(row: &Row) -> f32 {
-    NOT_LOST
-        + monotonicity(row) * MONOTONICITY_WEIGHT
-        + empty(row) * EMPTY_WEIGHT
-        + adjacent(row) * ADJACENT_WEIGHT
-        + sum(row) * SUM_WEIGHT
+/// Value of a lost board (on which no action is applicable), far below the value of any board that is not lost.
+///
+/// The sum of the tiles is added to it, so that among lost boards, those where the game went further are preferred.
+/// An `Evaluator` whose weights allow boards that are not lost to go below `LOST` uses a lower value instead.
+pub const LOST: f32 = -1_000_000f32;
+
+/// Largest tile exponent that may appear on a board (`2^17`, the largest tile reachable on a 4x4 board)
+const MAX_TILE: u8 = 17;
+
+/// Upper bound on the sum of the tiles of a board
+const MAX_TILE_SUM: f32 = (N * N) as f32 * (1 << MAX_TILE) as f32;
+
+/// A term of the evaluation function.
+pub struct Heuristic {
+    /// Name of the heuristic, as used on the command line and in reports
+    pub name: &'static str,
+    /// How the raw value of the heuristic is computed
+    pub compute: Compute,
+    /// Weight of the heuristic in the default evaluation
+    pub default_weight: f32,
+}
+
+/// A heuristic computed on the whole board
+type BoardFn = fn(&Board) -> f32;
+
+/// How the raw value of a heuristic is computed on a board.
+///
+/// Row functions receive rows from left to right and columns from top to bottom.
+#[derive(Clone, Copy)]
+pub enum Compute {
+    /// Computed on each row and each column, and summed
+    Row(fn(&Row) -> f32),
+    /// Computed on each row only, and summed
+    Horizontal(fn(&Row) -> f32),
+    /// Computed on each column only, and summed
+    Vertical(fn(&Row) -> f32),
+    /// Computed on the whole board, with the smallest and largest values it may take on any board
+    Board(BoardFn, (f32, f32)),
+}
+
+/// The two axes along which a line of the board can be read.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Axis {
+    Rows,
+    Columns,
+}
+
+/// Number of heuristics in the registry
+pub const NUM_HEURISTICS: usize = 13;
+
+/// Registry of all heuristics that may take part in the evaluation.
+///
+/// Heuristics added after the default weights were tuned have a default weight of 0.
+pub const HEURISTICS: [Heuristic; NUM_HEURISTICS] = [
+    Heuristic {
+        name: "monotonicity_rows",
+        compute: Compute::Horizontal(monotonicity),
+        default_weight: 47.0,
+    },
+    Heuristic {
+        name: "monotonicity_cols",
+        compute: Compute::Vertical(monotonicity),
+        default_weight: 47.0,
+    },
+    Heuristic {
+        name: "empty",
+        compute: Compute::Row(empty),
+        default_weight: 270.0,
+    },
+    Heuristic {
+        name: "adjacent",
+        compute: Compute::Row(adjacent),
+        default_weight: 700.0,
+    },
+    Heuristic {
+        name: "sum",
+        compute: Compute::Row(sum),
+        default_weight: 11.0,
+    },
+    Heuristic {
+        name: "smoothness",
+        compute: Compute::Row(smoothness),
+        default_weight: 0.0,
+    },
+    Heuristic {
+        name: "merges",
+        compute: Compute::Board(merges, (0.0, (N * N / 2) as f32)),
+        default_weight: 0.0,
+    },
+    Heuristic {
+        name: "corner",
+        compute: Compute::Board(corner, (-(MAX_TILE as f32), MAX_TILE as f32)),
+        default_weight: 0.0,
+    },
+    Heuristic {
+        name: "trapped",
+        compute: Compute::Board(trapped, (-((N * N) as f32) * MAX_TILE as f32, 0.0)),
+        default_weight: 0.0,
+    },
+    Heuristic {
+        name: "monotonicity_left",
+        compute: Compute::Horizontal(decreasing),
+        default_weight: 0.0,
+    },
+    Heuristic {
+        name: "monotonicity_up",
+        compute: Compute::Vertical(decreasing),
+        default_weight: 0.0,
+    },
+    Heuristic {
+        name: "islands",
+        compute: Compute::Board(islands, (-((N * N) as f32), 0.0)),
+        default_weight: 0.0,
+    },
+    Heuristic {
+        name: "empty_log",
+        compute: Compute::Board(empty_log, (0.0, 2.8332133)), // ln(17)
+        default_weight: 0.0,
+    },
+];
+
+/// Weights of the heuristics, in the order of `HEURISTICS`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct EvalWeights(pub [f32; NUM_HEURISTICS]);
+
+impl Default for EvalWeights {
+    fn default() -> Self {
+        EvalWeights(HEURISTICS.map(|heuristic| heuristic.default_weight))
+    }
+}
+
+impl EvalWeights {
+    /// Weight of the heuristic with the given name, or `None` if there is no such heuristic.
+    pub fn get(&self, name: &str) -> Option<f32> {
+        heuristic_index(name).map(|i| self.0[i])
+    }
+
+    /// Sets the weight of the heuristic with the given name.
+    pub fn set(&mut self, name: &str, weight: f32) -> anyhow::Result<()> {
+        let i = heuristic_index(name).with_context(|| format!("Unknown heuristic: {name}"))?;
+        self.0[i] = weight;
+        Ok(())
+    }
+
+    /// Sets to 0 the weight of the heuristic with the given name, or if there is none, of all heuristics whose name
+    /// starts with `name_` (e.g. `monotonicity` disables `monotonicity_rows`, `monotonicity_cols`, ...).
+    pub fn disable(&mut self, name: &str) -> anyhow::Result<()> {
+        if self.set(name, 0.0).is_ok() {
+            return Ok(());
+        }
+        let prefix = format!("{name}_");
+        let mut found = false;
+        for (weight, heuristic) in self.0.iter_mut().zip(&HEURISTICS) {
+            if heuristic.name.starts_with(&prefix) {
+                *weight = 0.0;
+                found = true;
+            }
+        }
+        ensure!(found, "Unknown heuristic: {name}");
+        Ok(())
+    }
+
+    /// Names of the heuristics with a non-zero weight, which are the ones taking part in the evaluation
+    pub fn active(&self) -> impl Iterator<Item = &'static str> + '_ {
+        HEURISTICS
+            .iter()
+            .zip(self.0)
+            .filter(|(_, weight)| *weight != 0.0)
+            .map(|(heuristic, _)| heuristic.name)
+    }
+
+    /// Parses weights from lines of the form `name = weight`, as produced by `Display`.
+    ///
+    /// Empty lines and lines starting with `#` are ignored. Heuristics that are not mentioned keep their default weight.
+    pub fn parse(text: &str) -> anyhow::Result<EvalWeights> {
+        let mut weights = EvalWeights::default();
+        for line in text.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (name, weight) = line
+                .split_once('=')
+                .with_context(|| format!("Expected `name = weight` but got: {line}"))?;
+            let weight = weight
+                .trim()
+                .parse()
+                .with_context(|| format!("Invalid weight in: {line}"))?;
+            weights.set(name.trim(), weight)?;
+        }
+        Ok(weights)
+    }
+
+    /// Reads weights from a file in the format of `parse`.
+    pub fn load(path: &Path) -> anyhow::Result<EvalWeights> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Cannot read {}", path.display()))?;
+        Self::parse(&text).with_context(|| format!("Invalid weights file {}", path.display()))
+    }
+
+    /// Writes the weights to a file, one `name = weight` line per heuristic.
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        std::fs::write(path, self.to_string())
+            .with_context(|| format!("Cannot write {}", path.display()))
+    }
+
+    /// Weighted sum of all row heuristics on a single row (or column, depending on the axis)
+    fn eval_row(&self, row: &Row, axis: Axis) -> f32 {
+        let mut value = NOT_LOST;
+        for (heuristic, weight) in HEURISTICS.iter().zip(self.0) {
+            match (heuristic.compute, axis) {
+                (Compute::Row(f), _)
+                | (Compute::Horizontal(f), Axis::Rows)
+                | (Compute::Vertical(f), Axis::Columns) => value += f(row) * weight,
+                _ => {}
+            }
+        }
+        value
+    }
+}
+
+impl Display for EvalWeights {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for (heuristic, weight) in HEURISTICS.iter().zip(self.0) {
+            writeln!(f, "{} = {}", heuristic.name, weight)?;
+        }
+        Ok(())
+    }
+}
+
+/// Index of the heuristic with the given name in `HEURISTICS`
+pub fn heuristic_index(name: &str) -> Option<usize> {
+    HEURISTICS
+        .iter()
+        .position(|heuristic| heuristic.name == name)
+}
+
+/// An evaluation function with a given set of weights.
+///
+/// The evaluation of all possible rows and columns is precomputed at creation, so that evaluating
+/// a board only requires one table lookup per row and column, plus the board heuristics with a non-zero weight.
+pub struct Evaluator {
+    weights: EvalWeights,
+    /// `row_table[pack_row(row)]` is equal to `weights.eval_row(row, Axis::Rows)`
+    row_table: Vec<f32>,
+    /// `col_table[pack_row(col)]` is equal to `weights.eval_row(col, Axis::Columns)`
+    col_table: Vec<f32>,
+    /// Board heuristics with a non-zero weight, together with their weight
+    board_terms: Vec<(BoardFn, f32)>,
+    /// Value of lost boards, before adding their tile sum
+    lost: f32,
+    /// Smallest and largest possible evaluations, computed at creation
+    bounds: (f32, f32),
+}
+
+impl Evaluator {
+    pub fn new(weights: EvalWeights) -> Evaluator {
+        let table = |axis| {
+            (0..=u16::MAX)
+                .map(|packed| weights.eval_row(&unpack_row(packed), axis))
+                .collect()
+        };
+        let row_table = table(Axis::Rows);
+        let col_table = table(Axis::Columns);
+        let board_terms = HEURISTICS
+            .iter()
+            .zip(weights.0)
+            .filter_map(|(heuristic, weight)| match heuristic.compute {
+                Compute::Board(f, _) if weight != 0.0 => Some((f, weight)),
+                _ => None,
+            })
+            .collect();
+        let mut evaluator = Evaluator {
+            weights,
+            row_table,
+            col_table,
+            board_terms,
+            lost: LOST,
+            bounds: (0.0, 0.0),
+        };
+        // lost boards must stay below all other boards, whatever the scale of the heuristics
+        let (lower, _) = evaluator.not_lost_bounds();
+        evaluator.lost = LOST.min(lower - MAX_TILE_SUM - NOT_LOST);
+        evaluator.bounds = evaluator.bounds();
+        evaluator
+    }
+
+    pub fn weights(&self) -> &EvalWeights {
+        &self.weights
+    }
+
+    /// Evaluates the board as the sum of the evaluations of its rows and columns.
+    ///
+    /// The board is an afterstate, which is never lost: lost boards are evaluated by `eval_lost`.
+    pub fn eval(&self, board: &Board) -> f32 {
+        let mut sum = 0.0;
+        for row in board.cells.iter() {
+            sum += self.row_table[pack_row(row) as usize];
+        }
+        for col in board.transposed().cells.iter() {
+            sum += self.col_table[pack_row(col) as usize];
+        }
+        for (f, weight) in &self.board_terms {
+            sum += f(board) * weight;
+        }
+        sum
+    }
+
+    /// Value of a lost board: `LOST` plus the sum of the tiles, or lower if needed to stay below all other boards.
+    pub fn eval_lost(&self, board: &Board) -> f32 {
+        self.lost + board.tile_sum() as f32
+    }
+
+    /// Smallest and largest values that `eval` and `eval_lost` may return, on any board.
+    ///
+    /// The bounds are valid but not tight: each row, column and board heuristic is bounded independently.
+    /// Lost boards are evaluated in `[lost, lost + max tile sum]`, where `lost` is `LOST` or lower if needed
+    /// to stay below the lower bound of other boards.
+    pub fn bounds(&self) -> (f32, f32) {
+        let (lower, upper) = self.not_lost_bounds();
+        (lower.min(self.lost), upper.max(self.lost + MAX_TILE_SUM))
+    }
+
+    /// Smallest and largest possible evaluations of boards that are not lost.
+    fn not_lost_bounds(&self) -> (f32, f32) {
+        let table_bounds = |table: &[f32]| {
+            let min = table.iter().copied().fold(f32::INFINITY, f32::min);
+            let max = table.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+            (min * N as f32, max * N as f32)
+        };
+        let (rows_min, rows_max) = table_bounds(&self.row_table);
+        let (cols_min, cols_max) = table_bounds(&self.col_table);
+        let mut lower = rows_min + cols_min;
+        let mut upper = rows_max + cols_max;
+        for (heuristic, weight) in HEURISTICS.iter().zip(self.weights.0) {
+            if let Compute::Board(_, (min, max)) = heuristic.compute {
+                lower += (min * weight).min(max * weight);
+                upper += (min * weight).max(max * weight);
+            }
+        }
+        (lower, upper)
+    }
+
+    /// Evaluation of the board mapped into `[0, 1]` according to `bounds()`.
+    pub fn eval_normalized(&self, board: &Board) -> f32 {
+        let (lower, upper) = self.bounds;
+        (self.eval(board) - lower) / (upper - lower)
+    }
+
+    /// Details the contribution of each heuristic to the evaluation of the board.
+    ///
+    /// The total is computed without the row table, and may thus slightly differ from `eval`
+    /// for boards with tiles above `2^15`.
+    pub fn explain(&self, board: &Board) -> EvalBreakdown {
+        let lost = board.is_lost();
+        let terms = std::array::from_fn(|i| {
+            let raw = raw_value(&HEURISTICS[i], board);
+            let weight = self.weights.0[i];
+            TermBreakdown {
+                name: HEURISTICS[i].name,
+                raw,
+                weight,
+                contribution: if lost { 0.0 } else { raw * weight },
+            }
+        });
+        let base = if lost {
+            self.eval_lost(board)
+        } else {
+            NOT_LOST * (2 * N) as f32
+        };
+        EvalBreakdown { lost, base, terms }
+    }
+}
+
+/// Raw value of the heuristic on the board, summed over all rows and/or columns for row heuristics
+fn raw_value(heuristic: &Heuristic, board: &Board) -> f32 {
+    let rows = |f: fn(&Row) -> f32| board.cells.iter().map(f).sum::<f32>();
+    let cols = |f: fn(&Row) -> f32| board.transposed().cells.iter().map(f).sum::<f32>();
+    match heuristic.compute {
+        Compute::Row(f) => rows(f) + cols(f),
+        Compute::Horizontal(f) => rows(f),
+        Compute::Vertical(f) => cols(f),
+        Compute::Board(f, _) => f(board),
+    }
+}
+
+/// Contribution of each heuristic to the evaluation of a board, as returned by `explain`.
+#[derive(Clone, Debug)]
+pub struct EvalBreakdown {
+    /// Whether the board is lost, in which case the heuristics do not contribute
+    pub lost: bool,
+    /// Part of the evaluation that does not depend on the heuristics
+    /// (`NOT_LOST` for each row and column, or `LOST` plus the sum of tiles for lost boards)
+    pub base: f32,
+    /// One term per heuristic, in the order of `HEURISTICS`
+    pub terms: [TermBreakdown; NUM_HEURISTICS],
+}
+
+/// Contribution of a single heuristic to an evaluation.
+#[derive(Clone, Copy, Debug)]
+pub struct TermBreakdown {
+    pub name: &'static str,
+    /// Value of the heuristic, before weighting
+    pub raw: f32,
+    pub weight: f32,
+    /// Weighted value, added to the evaluation
+    pub contribution: f32,
+}
+
+impl EvalBreakdown {
+    /// The evaluation of the board: base value plus the contributions of all heuristics
+    pub fn total(&self) -> f32 {
+        self.base + self.terms.iter().map(|term| term.contribution).sum::<f32>()
+    }
+}
+
+impl Display for EvalBreakdown {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "{:<14} {:>12} {:>10} {:>14}",
+            "heuristic", "raw", "weight", "contribution"
+        )?;
+        for term in &self.terms {
+            writeln!(
+                f,
+                "{:<14} {:>12.1} {:>10.2} {:>14.1}",
+                term.name, term.raw, term.weight, term.contribution
+            )?;
+        }
+        let base = if self.lost { "base (lost)" } else { "base" };
+        writeln!(f, "{:<14} {:>38.1}", base, self.base)?;
+        writeln!(f, "{:<14} {:>38.1}", "total", self.total())
+    }
 }
 
 fn empty(row: &Row) -> f32 {
     row.iter().filter(|&&cell| cell == 0).count() as f32
 }
 
+/// Concave alternative to `empty`: `ln(1 + num_empty)`, so that each empty cell is worth more as the board fills up.
+fn empty_log(board: &Board) -> f32 {
+    (1.0 + board.num_empty() as f32).ln()
+}
+
 fn monotonicity(row: &Row) -> f32 {
     let mut left = 0;
     let mut right = 0;
@@ -49,6 +776,19 @@ fn monotonicity(row: &Row) -> f32 {
     -left.min(right) as f32
 }
 
+/// Penalizes tiles that are larger than the tile before them, so that rows are preferably decreasing
+/// from left to right (and columns from top to bottom).
+fn decreasing(row: &Row) -> f32 {
+    let mut increase = 0;
+    for i in 0..(N - 1) {
+        let (current, next) = (row[i], row[i + 1]);
+        if next > current {
+            increase += i32::from(next).pow(4) - i32::from(current).pow(4);
+        }
+    }
+    -increase as f32
+}
+
 fn adjacent(row: &Row) -> f32 {
     let mut adjacent_count = 0;
     let mut i = 0;
@@ -69,8 +809,442 @@ fn sum(row: &Row) -> f32 {
     -row.iter().map(|&v| POW_3_5_LOOKUP[v as usize]).sum::<f32>()
 }
 
+/// Penalizes the difference of exponents between each non-empty tile and the next non-empty tile of the row.
+fn smoothness(row: &Row) -> f32 {
+    let mut penalty = 0;
+    let mut previous: Option<u8> = None;
+    for &cell in row.iter().filter(|&&cell| cell != 0) {
+        if let Some(previous) = previous {
+            penalty += previous.abs_diff(cell) as i32;
+        }
+        previous = Some(cell);
+    }
+    -penalty as f32
+}
+
+/// Maximum difference of exponents between two neighbouring tiles for them to belong to the same island
+const ISLAND_GAP: u8 = 1;
+
+/// Penalizes fragmented boards, as the number of islands: groups of tiles connected through neighbours
+/// of similar values (e.g. 8 and 16, but not 8 and 32). Empty cells belong to no island.
+///
+/// A board made of many small islands has few merges in sight and tends to collapse in the late game.
+fn islands(board: &Board) -> f32 {
+    let mut visited = [[false; N]; N];
+    let mut num_islands = 0;
+    let mut stack = Vec::with_capacity(N * N);
+    for i in 0..N {
+        for j in 0..N {
+            if visited[i][j] || board.cells[i][j] == 0 {
+                continue;
+            }
+            num_islands += 1;
+            visited[i][j] = true;
+            stack.push((i, j));
+            while let Some((x, y)) = stack.pop() {
+                let tile = board.cells[x][y];
+                let neighbours = [
+                    (x.wrapping_sub(1), y),
+                    (x + 1, y),
+                    (x, y.wrapping_sub(1)),
+                    (x, y + 1),
+                ];
+                for (nx, ny) in neighbours {
+                    if nx < N
+                        && ny < N
+                        && !visited[nx][ny]
+                        && board.cells[nx][ny] != 0
+                        && board.cells[nx][ny].abs_diff(tile) <= ISLAND_GAP
+                    {
+                        visited[nx][ny] = true;
+                        stack.push((nx, ny));
+                    }
+                }
+            }
+        }
+    }
+    -(num_islands as f32)
+}
+
+/// Number of merges that a single action would immediately perform.
+///
+/// Merges are counted along rows (as when playing left or right) and along columns (up or down),
+/// and the best of the two axes is kept since a single action only merges along one axis.
+fn merges(board: &Board) -> f32 {
+    let horizontal: usize = board.cells.iter().map(row_merges).sum();
+    let vertical: usize = board.transposed().cells.iter().map(row_merges).sum();
+    horizontal.max(vertical) as f32
+}
+
+/// Number of merges performed when pushing the row, ignoring empty cells between tiles.
+fn row_merges(row: &Row) -> usize {
+    let mut count = 0;
+    let mut pending: Option<u8> = None;
+    for &cell in row.iter().filter(|&&cell| cell != 0) {
+        if pending == Some(cell) {
+            count += 1;
+            pending = None;
+        } else {
+            pending = Some(cell);
+        }
+    }
+    count
+}
+
+/// Exponent of the max tile if it sits in a corner, and its opposite otherwise.
+///
+/// Once the max tile has been forced out of its corner, the penalty grows with the value of that tile.
+fn corner(board: &Board) -> f32 {
+    let max = board.max_tile();
+    let m = N - 1;
+    let in_corner = [(0, 0), (0, m), (m, 0), (m, m)]
+        .iter()
+        .any(|&(i, j)| board.cells[i][j] == max);
+    if in_corner {
+        max as f32
+    } else {
+        -(max as f32)
+    }
+}
+
+/// Minimum difference of exponents with all its neighbours for a tile to be considered trapped (i.e. 4 times smaller)
+const TRAP_GAP: u8 = 2;
+
+/// Penalizes tiles whose neighbours are all much larger, as they cannot merge anytime soon and block their row and column.
+///
+/// Each trapped tile costs the difference of exponents with its smallest neighbour.
+/// A tile next to an empty cell is never trapped.
+fn trapped(board: &Board) -> f32 {
+    let mut penalty = 0;
+    for i in 0..N {
+        for j in 0..N {
+            let tile = board.cells[i][j];
+            if tile == 0 {
+                continue;
+            }
+            let neighbours = [
+                (i.wrapping_sub(1), j),
+                (i + 1, j),
+                (i, j.wrapping_sub(1)),
+                (i, j + 1),
+            ];
+            let smallest_neighbour = neighbours
+                .iter()
+                .filter(|&&(x, y)| x < N && y < N)
+                .map(|&(x, y)| board.cells[x][y])
+                .min()
+                .unwrap_or(0);
+            if smallest_neighbour >= tile + TRAP_GAP {
+                penalty += (smallest_neighbour - tile) as i32;
+            }
+        }
+    }
+    -penalty as f32
+}
+
 /// lookup table: `POW_3_5_LOOKUP[i]` is equal to `i^3.5` but faster to compute
 const POW_3_5_LOOKUP: [f32; 18] = [
     0.0, 1.0, 11.313708, 46.765373, 128.0, 279.50848, 529.0898, 907.4927, 1448.1547, 2187.0,
     3162.2776, 4414.4277, 5985.968, 7921.396, 10267.107, 13071.318, 16384.0, 20256.818,
 ];
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::*;
+
+    #[test]
+    fn test_row_table() {
+        let board = Board {
+            cells: [[1, 2, 1, 0], [4, 1, 0, 0], [3, 0, 0, 0], [7, 0, 5, 2]],
+        };
+        let weights = EvalWeights::default();
+        let rows = board.cells.iter().map(|row| (row, Axis::Rows));
+        let transposed = board.transposed();
+        let cols = transposed.cells.iter().map(|col| (col, Axis::Columns));
+        let direct: f32 = rows
+            .chain(cols)
+            .map(|(line, axis)| weights.eval_row(line, axis))
+            .sum();
+        assert_eq!(eval(&board), direct);
+    }
+
+    #[test]
+    fn test_weights_format() {
+        let mut weights = EvalWeights::default();
+        weights.set("smoothness", 12.5).unwrap();
+        assert_eq!(EvalWeights::parse(&weights.to_string()).unwrap(), weights);
+        assert_eq!(
+            EvalWeights::parse("# only one\n  smoothness=12.5 \n").unwrap(),
+            weights
+        );
+        assert!(EvalWeights::parse("unknown = 1").is_err());
+    }
+
+    #[test]
+    fn test_explain() {
+        let board = Board {
+            cells: [[1, 2, 1, 0], [4, 1, 0, 0], [3, 0, 0, 0], [7, 0, 5, 2]],
+        };
+        let mut weights = EvalWeights::default();
+        weights.set("merges", 100.0).unwrap();
+        let evaluator = Evaluator::new(weights);
+        let breakdown = evaluator.explain(&board);
+        assert!((breakdown.total() - evaluator.eval(&board)).abs() < 1.0);
+        let empty = heuristic_index("empty").unwrap();
+        assert_eq!(breakdown.terms[empty].name, "empty");
+        assert_eq!(breakdown.terms[empty].raw, 14.0); // 7 empty cells, in rows and columns
+    }
+
+    #[test]
+    fn test_features() {
+        let board = Board {
+            cells: [[1, 2, 1, 0], [4, 1, 0, 0], [3, 0, 0, 0], [7, 0, 5, 2]],
+        };
+        let features = features(&board);
+        assert_eq!(FeatureVec::names().count(), NUM_FEATURES);
+        assert_eq!(&features.0[NUM_HEURISTICS..], &[7.0, 3.0, 0.0, 198.0]);
+
+        let weights = EvalWeights::default();
+        let linear: f32 = features
+            .heuristics()
+            .iter()
+            .zip(weights.0)
+            .map(|(feature, weight)| feature * weight)
+            .sum();
+        let constant = NOT_LOST * (2 * N) as f32;
+        assert!((constant + linear - eval(&board)).abs() < 1.0);
+    }
+
+    #[test]
+    fn test_corner() {
+        let mut board = Board {
+            cells: [[1, 2, 1, 0], [4, 1, 0, 0], [3, 0, 0, 0], [7, 0, 5, 2]],
+        };
+        assert_eq!(corner(&board), 7.0);
+        board.cells[3] = [0, 7, 5, 2];
+        assert_eq!(corner(&board), -7.0);
+    }
+
+    #[test]
+    fn test_trapped() {
+        let mut board = Board {
+            cells: [[5, 6, 1, 0], [1, 5, 0, 0], [6, 0, 0, 0], [0, 0, 0, 0]],
+        };
+        // only the 2 at (1, 0) is trapped, by a 32 (diff 4)
+        assert_eq!(trapped(&board), -4.0);
+        board.cells[2][0] = 0;
+        assert_eq!(trapped(&board), 0.0);
+    }
+
+    #[test]
+    fn test_islands() {
+        let board = Board {
+            cells: [[5, 6, 1, 0], [1, 5, 0, 0], [6, 0, 0, 3], [0, 0, 0, 3]],
+        };
+        // {32, 64, 32}, {2 (top)}, {2 (left)}, {64 (bottom)}, {8, 8}
+        assert_eq!(islands(&board), -5.0);
+        assert_eq!(islands(&Board { cells: [[0; N]; N] }), 0.0);
+        let checkerboard = Board {
+            cells: std::array::from_fn(|i| std::array::from_fn(|j| 1 + 2 * ((i + j) % 2) as u8)),
+        };
+        assert_eq!(islands(&checkerboard), -16.0);
+    }
+
+    #[test]
+    fn test_empty_log() {
+        let mut board = Board { cells: [[1; N]; N] };
+        assert_eq!(empty_log(&board), 0.0);
+        board.cells[0][0] = 0;
+        let one = empty_log(&board);
+        board.cells[0][1] = 0;
+        let two = empty_log(&board);
+        assert!(one > 0.0 && two - one < one);
+        assert_eq!(empty_log(&Board { cells: [[0; N]; N] }), 17f32.ln());
+    }
+
+    #[test]
+    fn test_disable() {
+        let mut weights = EvalWeights::default();
+        weights.disable("monotonicity").unwrap();
+        weights.disable("adjacent").unwrap();
+        assert_eq!(weights.active().collect::<Vec<_>>(), ["empty", "sum"]);
+        assert!(weights.disable("unknown").is_err());
+    }
+
+    #[test]
+    fn test_eval_state() {
+        let board = Board {
+            cells: [[1, 2, 1, 0], [4, 1, 0, 0], [3, 0, 0, 0], [7, 0, 5, 2]],
+        };
+        let state = PlayableBoard::from(board);
+        let best = ALL_ACTIONS
+            .into_iter()
+            .filter_map(|action| state.apply(action))
+            .map(|after| eval_afterstate(&after))
+            .fold(f32::NEG_INFINITY, f32::max);
+        assert_eq!(eval_state(&state), best);
+
+        let lost = Board {
+            cells: [[1, 2, 1, 2], [2, 1, 2, 1], [1, 2, 1, 2], [2, 1, 2, 1]],
+        };
+        assert_eq!(
+            eval_state(&PlayableBoard::from(lost)),
+            Evaluator::new(EvalWeights::default()).eval_lost(&lost)
+        );
+    }
+
+    fn explain_default(board: &Board) -> EvalBreakdown {
+        Evaluator::new(EvalWeights::default()).explain(board)
+    }
+
+    #[test]
+    fn test_with_evaluator() {
+        let board = Board {
+            cells: [[1, 2, 1, 0], [4, 1, 0, 0], [3, 0, 0, 0], [7, 0, 5, 2]],
+        };
+        let default = eval(&board);
+        let baseline = Arc::new(Evaluator::new(presets::find("baseline").unwrap().weights()));
+        let expected = baseline.eval(&board);
+        assert_ne!(default, expected);
+        assert_eq!(with_evaluator(baseline.clone(), || eval(&board)), expected);
+        assert_eq!(eval(&board), default);
+        let baseline_bounds = baseline.bounds();
+        assert_eq!(with_evaluator(baseline, bounds), baseline_bounds);
+
+        // any evaluation function, e.g. an n-tuple network
+        let mut network = ntuple::NTupleNetwork::new(&ntuple::FOUR_TUPLES);
+        network.update(&board, 5.0);
+        let value = network.eval(&board);
+        let network: Arc<dyn Evaluate> = Arc::new(network);
+        with_evaluator(network.clone(), || {
+            assert_eq!(eval(&board), value);
+            assert_eq!(eval_batch(&[board, board]), vec![value; 2]);
+            // the details of a linear evaluation fall back to the default weights
+            assert_eq!(weights(), EvalWeights::default());
+            assert_eq!(explain(&board).total(), explain_default(&board).total());
+            // nested
+            with_evaluator(Arc::new(Evaluator::new(EvalWeights::default())), || {
+                assert_eq!(eval(&board), default)
+            });
+            assert_eq!(eval(&board), value);
+        });
+        // restored after a panic
+        let panicked = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            with_evaluator(network, || std::panic::resume_unwind(Box::new(())))
+        }));
+        assert!(panicked.is_err());
+        assert_eq!(eval(&board), default);
+    }
+
+    #[test]
+    fn test_bounds() {
+        let mut weights = EvalWeights::default();
+        weights.set("corner", 1000.0).unwrap();
+        weights.set("trapped", -5.0).unwrap();
+        let evaluator = Evaluator::new(weights);
+        let (lower, upper) = evaluator.bounds();
+        let boards = [
+            [[1, 2, 1, 0], [4, 1, 0, 0], [3, 0, 0, 0], [7, 0, 5, 2]],
+            [[0; N]; N],
+            [
+                [17, 16, 15, 14],
+                [10, 11, 12, 13],
+                [9, 8, 7, 6],
+                [2, 3, 4, 5],
+            ],
+            [[1, 2, 1, 2], [2, 1, 2, 1], [1, 2, 1, 2], [2, 1, 2, 1]],
+        ];
+        for cells in boards {
+            let board = Board { cells };
+            let value = evaluator.eval(&board);
+            assert!(lower <= value && value <= upper);
+            let normalized = evaluator.eval_normalized(&board);
+            assert!((0.0..=1.0).contains(&normalized));
+        }
+    }
+
+    #[test]
+    fn test_lost() {
+        let lost = Board {
+            cells: [[1, 2, 1, 2], [2, 1, 2, 1], [1, 2, 1, 2], [2, 1, 2, 1]],
+        };
+        let mut almost_lost = lost;
+        almost_lost.cells[0][0] = 0;
+        let lost_value = eval_state(&PlayableBoard::from(lost));
+        assert!(lost_value < 0.0);
+        assert!(lost_value < eval(&almost_lost));
+        assert!(lost_value < eval_state(&PlayableBoard::from(almost_lost)));
+        // the further the game went, the better
+        let mut further = lost;
+        further.cells[3][3] = 5;
+        assert!(further.is_lost());
+        assert!(eval_state(&PlayableBoard::from(further)) > lost_value);
+        // an evaluator with a larger scale keeps lost boards below all others
+        let mut weights = EvalWeights::default();
+        weights.set("corner", -1e6).unwrap();
+        let evaluator = Evaluator::new(weights);
+        assert!(evaluator.eval_lost(&lost) < LOST);
+        assert!(evaluator.eval_lost(&lost) < evaluator.not_lost_bounds().0);
+        assert_eq!(evaluator.explain(&lost).total(), evaluator.eval_lost(&lost));
+    }
+
+    #[test]
+    fn test_smoothness() {
+        assert_eq!(smoothness(&[0, 0, 0, 0]), 0.0);
+        assert_eq!(smoothness(&[3, 3, 0, 3]), 0.0);
+        assert_eq!(smoothness(&[1, 0, 4, 2]), -5.0);
+    }
+
+    #[test]
+    fn test_merges() {
+        assert_eq!(row_merges(&[1, 0, 1, 1]), 1);
+        assert_eq!(row_merges(&[2, 2, 2, 2]), 2);
+        let board = Board {
+            cells: [[1, 2, 3, 4], [1, 0, 3, 0], [0, 2, 0, 5], [1, 0, 3, 6]],
+        };
+        assert_eq!(merges(&board), 3.0);
+    }
+
+    /// Any board, with tiles up to `2^15`
+    fn any_board() -> impl Strategy<Value = Board> {
+        prop::array::uniform4(prop::array::uniform4(0u8..=15)).prop_map(|cells| Board { cells })
+    }
+
+    /// Full boards where neighbouring tiles have exponents of different parities, hence lost
+    fn lost_board() -> impl Strategy<Value = Board> {
+        prop::array::uniform4(prop::array::uniform4(0u8..=6)).prop_map(|halves| Board {
+            cells: std::array::from_fn(|i| {
+                std::array::from_fn(|j| 2 * halves[i][j] + 1 + ((i + j) % 2) as u8)
+            }),
+        })
+    }
+
+    /// Equality up to rounding errors, which depend on the order in which rows and columns are summed
+    fn assert_close(a: f32, b: f32) {
+        let scale = a.abs().max(b.abs()).max(NOT_LOST * (2 * N) as f32);
+        assert!((a - b).abs() <= 1e-6 * scale, "{a} != {b}");
+    }
+
+    proptest! {
+        #[test]
+        fn prop_reflection_invariant(board in any_board()) {
+            let [_, mirrored, flipped, ..] = board.symmetries();
+            assert_close(eval(&board), eval(&mirrored));
+            assert_close(eval(&board), eval(&flipped));
+        }
+
+        #[test]
+        fn prop_transpose_invariant(board in any_board()) {
+            assert_close(eval(&board), eval(&board.transposed()));
+        }
+
+        #[test]
+        fn prop_lost_below_not_lost(lost in lost_board(), board in any_board()) {
+            prop_assert!(lost.is_lost());
+            prop_assume!(!board.is_lost());
+            prop_assert!(eval_state(&PlayableBoard::from(lost)) < eval(&board));
+        }
+    }
+}
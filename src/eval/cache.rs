@@ -0,0 +1,172 @@
+//! Bounded cache of evaluations, keyed by packed boards.
+//!
+//! When the cache is full, the least recently used evaluation is discarded to make room for the new one.
+
+use hashbrown::HashMap;
+
+use crate::board::Board;
+
+/// Marks the absence of a previous/next entry in the recency list
+const NONE: usize = usize::MAX;
+
+/// An entry of the cache, which is part of a doubly linked list ordered from the most to the least recently used.
+struct Entry {
+    key: u64,
+    value: f32,
+    prev: usize,
+    next: usize,
+}
+
+/// A least-recently-used cache of board evaluations.
+///
+/// A cache with a capacity of 0 is disabled: it never stores anything.
+pub struct EvalCache {
+    capacity: usize,
+    /// position of the entry of each board (as given by `Board::pack`) in `entries`
+    index: HashMap<u64, usize>,
+    entries: Vec<Entry>,
+    /// most recently used entry
+    head: usize,
+    /// least recently used entry, first to be evicted
+    tail: usize,
+}
+
+impl EvalCache {
+    pub fn new(capacity: usize) -> EvalCache {
+        EvalCache {
+            capacity,
+            index: HashMap::with_capacity(capacity),
+            entries: Vec::with_capacity(capacity),
+            head: NONE,
+            tail: NONE,
+        }
+    }
+
+    /// Number of evaluations currently stored
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Approximate memory used by the stored evaluations, in bytes
+    pub fn memory(&self) -> usize {
+        // an entry of the index takes a control byte on top of the key and position
+        let per_entry = std::mem::size_of::<Entry>() + std::mem::size_of::<(u64, usize)>() + 1;
+        self.len() * per_entry
+    }
+
+    /// Returns the cached evaluation of the board, if any, marking it as the most recently used.
+    pub fn get(&mut self, board: &Board) -> Option<f32> {
+        let i = *self.index.get(&board.pack())?;
+        self.move_to_front(i);
+        Some(self.entries[i].value)
+    }
+
+    /// Stores the evaluation of the board, evicting the least recently used evaluation if the cache is full.
+    pub fn insert(&mut self, board: &Board, value: f32) {
+        if self.capacity == 0 {
+            return;
+        }
+        let key = board.pack();
+        let i = if let Some(&i) = self.index.get(&key) {
+            self.entries[i].value = value;
+            i
+        } else if self.entries.len() < self.capacity {
+            self.entries.push(Entry {
+                key,
+                value,
+                prev: NONE,
+                next: NONE,
+            });
+            let i = self.entries.len() - 1;
+            self.index.insert(key, i);
+            self.push_front(i);
+            i
+        } else {
+            // reuse the slot of the least recently used entry
+            let i = self.tail;
+            self.index.remove(&self.entries[i].key);
+            self.index.insert(key, i);
+            self.entries[i].key = key;
+            self.entries[i].value = value;
+            i
+        };
+        self.move_to_front(i);
+    }
+
+    /// Removes all evaluations from the cache.
+    pub fn clear(&mut self) {
+        self.index.clear();
+        self.entries.clear();
+        self.head = NONE;
+        self.tail = NONE;
+    }
+
+    fn move_to_front(&mut self, i: usize) {
+        if self.head == i {
+            return;
+        }
+        // unlink
+        let Entry { prev, next, .. } = self.entries[i];
+        if prev != NONE {
+            self.entries[prev].next = next;
+        }
+        if next != NONE {
+            self.entries[next].prev = prev;
+        } else {
+            self.tail = prev;
+        }
+        self.push_front(i);
+    }
+
+    /// Links the (unlinked) entry `i` at the head of the list
+    fn push_front(&mut self, i: usize) {
+        self.entries[i].prev = NONE;
+        self.entries[i].next = self.head;
+        if self.head != NONE {
+            self.entries[self.head].prev = i;
+        }
+        self.head = i;
+        if self.tail == NONE {
+            self.tail = i;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lru() {
+        let board = |tile: u8| Board {
+            cells: [[tile, 0, 0, 0], [0; 4], [0; 4], [0; 4]],
+        };
+        let mut cache = EvalCache::new(2);
+        cache.insert(&board(1), 1.0);
+        cache.insert(&board(2), 2.0);
+        assert_eq!(cache.get(&board(1)), Some(1.0));
+        // board 2 is the least recently used
+        cache.insert(&board(3), 3.0);
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.get(&board(2)), None);
+        assert_eq!(cache.get(&board(1)), Some(1.0));
+        assert_eq!(cache.get(&board(3)), Some(3.0));
+        // board 1 is now the least recently used
+        cache.insert(&board(3), 4.0);
+        cache.insert(&board(4), 5.0);
+        assert_eq!(cache.get(&board(1)), None);
+        assert_eq!(cache.get(&board(3)), Some(4.0));
+
+        let mut disabled = EvalCache::new(0);
+        disabled.insert(&board(1), 1.0);
+        assert_eq!(disabled.get(&board(1)), None);
+    }
+}
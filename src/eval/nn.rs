@@ -0,0 +1,160 @@
+//! Neural-network evaluation, available with the `nn` feature.
+//!
+//! The network is a multi-layer perceptron over the one-hot encoding of the board: each of the 16 cells is encoded
+//! by 16 inputs, one per possible tile (empty, 2, 4, ..., 2^15), for a total of 256 inputs and a single output.
+//! Hidden layers use a ReLU activation.
+//!
+//! Weights are read from a safetensors file with tensors `layers.{i}.weight` of shape `(out, in)`
+//! and `layers.{i}.bias` of shape `(out)`, for `i` from 0, following the convention of PyTorch's `nn.Linear`.
+//!
+//! ```rust
+//! let network = NnEvaluator::load(Path::new("model.safetensors"))?;
+//! let value = network.eval(&board);
+//! ```
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use anyhow::{ensure, Context};
+use candle_core::{DType, Device, Tensor};
+
+use crate::board::{Board, N};
+
+/// Number of values a cell may take in the encoding. Tiles above `2^15` are clamped to `2^15`.
+const NUM_VALUES: usize = 16;
+
+/// Number of inputs of the network
+pub const NUM_INPUTS: usize = N * N * NUM_VALUES;
+
+/// A multi-layer perceptron evaluating boards, run on the CPU.
+pub struct NnEvaluator {
+    /// Weights (transposed to `(in, out)`) and biases of each layer
+    layers: Vec<(Tensor, Tensor)>,
+    device: Device,
+}
+
+impl NnEvaluator {
+    /// Loads the network from a safetensors file, checking that the shapes of consecutive layers match.
+    pub fn load(path: &Path) -> anyhow::Result<NnEvaluator> {
+        let device = Device::Cpu;
+        let tensors = candle_core::safetensors::load(path, &device)
+            .with_context(|| format!("Cannot load network from {}", path.display()))?;
+        Self::from_tensors(tensors, device)
+    }
+
+    fn from_tensors(
+        mut tensors: HashMap<String, Tensor>,
+        device: Device,
+    ) -> anyhow::Result<NnEvaluator> {
+        let mut layers = Vec::new();
+        let mut num_inputs = NUM_INPUTS;
+        while let Some(weight) = tensors.remove(&format!("layers.{}.weight", layers.len())) {
+            let i = layers.len();
+            let bias = tensors
+                .remove(&format!("layers.{i}.bias"))
+                .with_context(|| format!("Missing bias of layer {i}"))?;
+            let (num_outputs, layer_inputs) = weight.dims2()?;
+            ensure!(
+                layer_inputs == num_inputs,
+                "Layer {i} expects {layer_inputs} inputs but gets {num_inputs}"
+            );
+            ensure!(
+                bias.dims() == [num_outputs],
+                "Bias of layer {i} has shape {:?} instead of [{num_outputs}]",
+                bias.dims()
+            );
+            layers.push((
+                weight.to_dtype(DType::F32)?.t()?.contiguous()?,
+                bias.to_dtype(DType::F32)?,
+            ));
+            num_inputs = num_outputs;
+        }
+        ensure!(
+            !layers.is_empty(),
+            "No layer found (expected tensors named `layers.0.weight`, ...)"
+        );
+        ensure!(
+            num_inputs == 1,
+            "The network has {num_inputs} outputs instead of 1"
+        );
+        Ok(NnEvaluator { layers, device })
+    }
+
+    /// Evaluates a single board.
+    pub fn eval(&self, board: &Board) -> f32 {
+        self.eval_batch(std::slice::from_ref(board))[0]
+    }
+
+    /// Evaluates several boards at once, which amortizes the overhead of running the network.
+    pub fn eval_batch(&self, boards: &[Board]) -> Vec<f32> {
+        self.forward(boards)
+            .expect("shapes are checked when loading the network")
+    }
+
+    fn forward(&self, boards: &[Board]) -> candle_core::Result<Vec<f32>> {
+        let mut x = encode(boards, &self.device)?;
+        for (i, (weight, bias)) in self.layers.iter().enumerate() {
+            x = x.matmul(weight)?.broadcast_add(bias)?;
+            if i + 1 < self.layers.len() {
+                x = x.relu()?;
+            }
+        }
+        x.flatten_all()?.to_vec1()
+    }
+}
+
+/// One-hot encoding of the boards, as a `(boards.len(), NUM_INPUTS)` tensor.
+fn encode(boards: &[Board], device: &Device) -> candle_core::Result<Tensor> {
+    let mut inputs = vec![0f32; boards.len() * NUM_INPUTS];
+    for (b, board) in boards.iter().enumerate() {
+        for (cell, &tile) in board.cells.iter().flatten().enumerate() {
+            let value = (tile as usize).min(NUM_VALUES - 1);
+            inputs[b * NUM_INPUTS + cell * NUM_VALUES + value] = 1.0;
+        }
+    }
+    Tensor::from_vec(inputs, (boards.len(), NUM_INPUTS), device)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_forward() {
+        let device = Device::Cpu;
+        // a hidden layer with two neurons: the first counts the empty cells, the second is always negative
+        let mut first = vec![0f32; 2 * NUM_INPUTS];
+        for cell in 0..(N * N) {
+            first[cell * NUM_VALUES] = 1.0;
+        }
+        let tensors = HashMap::from([
+            (
+                "layers.0.weight".to_string(),
+                Tensor::from_vec(first, (2, NUM_INPUTS), &device).unwrap(),
+            ),
+            (
+                "layers.0.bias".to_string(),
+                Tensor::new(&[0f32, -1.0], &device).unwrap(),
+            ),
+            (
+                "layers.1.weight".to_string(),
+                Tensor::new(&[[2f32, 5.0]], &device).unwrap(),
+            ),
+            (
+                "layers.1.bias".to_string(),
+                Tensor::new(&[0.5f32], &device).unwrap(),
+            ),
+        ]);
+        let path = std::env::temp_dir().join(format!("nn-{}.safetensors", std::process::id()));
+        candle_core::safetensors::save(&tensors, &path).unwrap();
+        let network = NnEvaluator::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let board = Board {
+            cells: [[1, 2, 1, 0], [4, 1, 0, 0], [3, 0, 0, 0], [7, 0, 5, 2]],
+        };
+        // 7 empty cells
+        assert_eq!(network.eval(&board), 2.0 * 7.0 + 0.5);
+        assert_eq!(network.eval_batch(&[board, board]), vec![14.5, 14.5]);
+    }
+}
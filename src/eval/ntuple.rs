@@ -0,0 +1,267 @@
+//! N-tuple network evaluation.
+//!
+//! An n-tuple network evaluates a board as a sum of weights, each looked up in a table indexed by the
+//! content of a small group of cells (a *tuple*). Each tuple is applied on the 8 symmetries (rotations
+//! and reflections) of the board, all sharing the same weights table, so that the evaluation is invariant
+//! to symmetries and each weight is trained 8 times more often.
+//!
+//! ```rust
+//! let network = NTupleNetwork::new(&SIX_TUPLES);
+//! let value = network.eval(&board);
+//! ```
+
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+use anyhow::{bail, ensure, Context};
+
+use crate::board::{Board, N};
+
+/// A tuple, given as the list of its cells where cell `(row, col)` is identified by `row * N + col`.
+pub type Pattern = &'static [usize];
+
+/// The four 6-tuples (two straight, two rectangular) introduced by Yeh et al. for 2048.
+pub const SIX_TUPLES: [Pattern; 4] = [
+    &[0, 1, 2, 3, 4, 5],
+    &[4, 5, 6, 7, 8, 9],
+    &[0, 1, 2, 4, 5, 6],
+    &[4, 5, 6, 8, 9, 10],
+];
+
+/// Straight lines and 2x2 squares (Szubert & Jaśkowski): much smaller tables, faster to train but weaker.
+pub const FOUR_TUPLES: [Pattern; 5] = [
+    &[0, 1, 2, 3],
+    &[4, 5, 6, 7],
+    &[0, 1, 4, 5],
+    &[1, 2, 5, 6],
+    &[5, 6, 9, 10],
+];
+
+/// Number of distinct values a cell may take in a tuple. Tiles above `2^15` are clamped to `2^15`.
+const NUM_VALUES: usize = 16;
+
+/// Magic bytes at the start of a weights file.
+pub(crate) const MAGIC: &[u8; 4] = b"NTUP";
+
+/// Version of the weights file format.
+const FORMAT_VERSION: u32 = 1;
+
+/// Number of symmetries of the square board.
+const NUM_SYMMETRIES: usize = 8;
+
+/// A single tuple together with its weights table.
+#[derive(Clone)]
+struct Tuple {
+    /// The cells of the tuple, as given by the user.
+    pattern: Vec<usize>,
+    /// The cells of the tuple in each of the 8 symmetries of the board.
+    expanded: [Vec<usize>; NUM_SYMMETRIES],
+    /// Weights indexed by the content of the cells, with `NUM_VALUES^len` entries.
+    weights: Vec<f32>,
+}
+
+impl Tuple {
+    fn new(pattern: &[usize]) -> Tuple {
+        assert!(
+            pattern.iter().all(|&cell| cell < N * N),
+            "cell out of the board"
+        );
+        let expanded = std::array::from_fn(|sym| {
+            pattern
+                .iter()
+                .map(|&cell| symmetric_cell(cell, sym))
+                .collect()
+        });
+        Tuple {
+            pattern: pattern.to_vec(),
+            expanded,
+            weights: vec![0.0; NUM_VALUES.pow(pattern.len() as u32)],
+        }
+    }
+
+    /// Index in the weights table of the given cells
+    fn index(cells: &[usize], board: &Board) -> usize {
+        cells.iter().fold(0, |index, &cell| {
+            let tile = board.cells[cell / N][cell % N].min(NUM_VALUES as u8 - 1);
+            index * NUM_VALUES + tile as usize
+        })
+    }
+}
+
+/// An n-tuple network, whose weights are stored in one flat table per tuple.
+#[derive(Clone)]
+pub struct NTupleNetwork {
+    tuples: Vec<Tuple>,
+}
+
+impl NTupleNetwork {
+    /// Creates a network with the given tuples, where all weights are initially 0.
+    pub fn new(patterns: &[Pattern]) -> NTupleNetwork {
+        NTupleNetwork {
+            tuples: patterns.iter().map(|pattern| Tuple::new(pattern)).collect(),
+        }
+    }
+
+    /// The tuples of the network, as given at creation.
+    pub fn patterns(&self) -> impl Iterator<Item = &[usize]> + '_ {
+        self.tuples.iter().map(|tuple| tuple.pattern.as_slice())
+    }
+
+    /// Number of weights summed in a single evaluation (one per tuple and per symmetry).
+    pub fn num_features(&self) -> usize {
+        self.tuples.len() * NUM_SYMMETRIES
+    }
+
+    /// Evaluates the board as the sum of the weights of all tuples, in all symmetries.
+    pub fn eval(&self, board: &Board) -> f32 {
+        let mut sum = 0.0;
+        for tuple in &self.tuples {
+            for cells in &tuple.expanded {
+                sum += tuple.weights[Tuple::index(cells, board)];
+            }
+        }
+        sum
+    }
+
+    /// Adds `delta` to every weight involved in the evaluation of the board.
+    ///
+    /// When moving the evaluation toward a target, `delta` is typically `alpha * error / num_features()`.
+    pub fn update(&mut self, board: &Board, delta: f32) {
+        for tuple in &mut self.tuples {
+            for cells in &tuple.expanded {
+                tuple.weights[Tuple::index(cells, board)] += delta;
+            }
+        }
+    }
+
+    /// Writes the tuples and their weights to a file, in a little-endian binary format.
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        let file =
+            File::create(path).with_context(|| format!("Cannot create {}", path.display()))?;
+        let mut out = BufWriter::new(file);
+        self.write_to(&mut out)?;
+        out.flush()?;
+        Ok(())
+    }
+
+    /// Writes the network in the format of `save`.
+    pub fn write_to(&self, out: &mut impl Write) -> anyhow::Result<()> {
+        out.write_all(MAGIC)?;
+        out.write_all(&FORMAT_VERSION.to_le_bytes())?;
+        out.write_all(&(self.tuples.len() as u32).to_le_bytes())?;
+        for tuple in &self.tuples {
+            out.write_all(&(tuple.pattern.len() as u32).to_le_bytes())?;
+            for &cell in &tuple.pattern {
+                out.write_all(&(cell as u32).to_le_bytes())?;
+            }
+            for weight in &tuple.weights {
+                out.write_all(&weight.to_le_bytes())?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads a network previously written with `save`.
+    pub fn load(path: &Path) -> anyhow::Result<NTupleNetwork> {
+        let file = File::open(path).with_context(|| format!("Cannot open {}", path.display()))?;
+        Self::read_from(&mut BufReader::new(file))
+            .with_context(|| format!("Invalid n-tuple weights file {}", path.display()))
+    }
+
+    /// Reads a network written by `write_to`.
+    pub fn read_from(input: &mut impl Read) -> anyhow::Result<NTupleNetwork> {
+        let mut read_u32 = || -> anyhow::Result<u32> {
+            let mut bytes = [0; 4];
+            input.read_exact(&mut bytes)?;
+            Ok(u32::from_le_bytes(bytes))
+        };
+        if read_u32()?.to_le_bytes() != *MAGIC {
+            bail!("Not an n-tuple network");
+        }
+        let version = read_u32()?;
+        ensure!(
+            version == FORMAT_VERSION,
+            "Unsupported weights file version: {version}"
+        );
+        let num_tuples = read_u32()?;
+        let mut tuples = Vec::with_capacity(num_tuples as usize);
+        for _ in 0..num_tuples {
+            let len = read_u32()?;
+            ensure!(len <= 8, "Tuple of length {len} is too large");
+            let pattern = (0..len)
+                .map(|_| read_u32().map(|cell| cell as usize))
+                .collect::<anyhow::Result<Vec<_>>>()?;
+            ensure!(
+                pattern.iter().all(|&cell| cell < N * N),
+                "Cell out of the board in tuple {pattern:?}"
+            );
+            let mut tuple = Tuple::new(&pattern);
+            for weight in &mut tuple.weights {
+                *weight = f32::from_bits(read_u32()?);
+            }
+            tuples.push(tuple);
+        }
+        Ok(NTupleNetwork { tuples })
+    }
+}
+
+/// Image of the cell by the `sym`-th symmetry of the board (`0` is the identity).
+fn symmetric_cell(cell: usize, sym: usize) -> usize {
+    let (r, c) = (cell / N, cell % N);
+    let m = N - 1;
+    let (r, c) = match sym {
+        0 => (r, c),
+        1 => (r, m - c),
+        2 => (m - r, c),
+        3 => (m - r, m - c),
+        4 => (c, r),
+        5 => (c, m - r),
+        6 => (m - c, r),
+        7 => (m - c, m - r),
+        _ => unreachable!(),
+    };
+    r * N + c
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_symmetric_eval() {
+        // all cells are distinct so that each feature is a distinct weight
+        let board = Board {
+            cells: [[0, 1, 2, 3], [4, 5, 6, 7], [8, 9, 10, 11], [12, 13, 14, 15]],
+        };
+        let mut network = NTupleNetwork::new(&FOUR_TUPLES);
+        network.update(&board, 1.0);
+        assert_eq!(network.eval(&board), network.num_features() as f32);
+
+        // all symmetries of the board share the same weights
+        let transposed = board.transposed();
+        let mut mirrored = board;
+        for row in &mut mirrored.cells {
+            row.reverse();
+        }
+        assert_eq!(network.eval(&transposed), network.eval(&board));
+        assert_eq!(network.eval(&mirrored), network.eval(&board));
+    }
+
+    #[test]
+    fn test_save_load() {
+        let board = Board {
+            cells: [[1, 2, 1, 0], [4, 1, 0, 0], [3, 0, 0, 0], [7, 0, 5, 2]],
+        };
+        let mut network = NTupleNetwork::new(&FOUR_TUPLES);
+        network.update(&board, 0.5);
+
+        let path = std::env::temp_dir().join(format!("ntuple-{}.weights", std::process::id()));
+        network.save(&path).unwrap();
+        let loaded = NTupleNetwork::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(loaded.patterns().eq(network.patterns()));
+        assert_eq!(loaded.eval(&board), network.eval(&board));
+    }
+}
@@ -0,0 +1,243 @@
+//! Checkpoints of learned evaluation parameters, shared by the trainers (`train`, `main selfplay`) and by everything
+//! loading weights for the search (`eval::load_weights`).
+//!
+//! A checkpoint starts with the magic bytes `2048PRMS` and the version of the format as a little-endian `u32`,
+//! followed by a JSON header (its length as a little-endian `u32`, then the UTF-8 text) giving the kind of
+//! parameters and the `Metadata` of the training, and finally the parameters themselves:
+//! - an n-tuple network, in the format of `NTupleNetwork::save`,
+//! - linear weights, as the length of the text (`u32`) followed by the text of `EvalWeights::parse`, so that the
+//!   weights stay attached to the names of their heuristics.
+//!
+//! ```rust
+//! let metadata = Metadata { trainer: "train".into(), training_steps: 1000, bench_score: Some(512.0) };
+//! params::save_ntuple(&path, &network, &metadata)?;
+//! let (params, metadata) = params::load(&path)?;
+//! ```
+//!
+//! `load` also accepts the files written before checkpoints existed (bare n-tuple networks and text weights), with
+//! default metadata.
+
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+use anyhow::{bail, ensure, Context};
+use serde::{Deserialize, Serialize};
+
+use super::ntuple::{self, NTupleNetwork};
+use super::EvalWeights;
+
+const MAGIC: &[u8; 8] = b"2048PRMS";
+
+const FORMAT_VERSION: u32 = 1;
+
+/// Learned parameters of an evaluation function
+#[derive(Clone)]
+pub enum Params {
+    NTuple(NTupleNetwork),
+    Linear(EvalWeights),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Kind {
+    NTuple,
+    Linear,
+}
+
+/// How the parameters were obtained
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Metadata {
+    /// Program that learned the parameters, empty if unknown
+    #[serde(default)]
+    pub trainer: String,
+    /// Number of training steps (e.g. games) done when the parameters were saved
+    #[serde(default)]
+    pub training_steps: u64,
+    /// Mean result of the last benchmark of the parameters before they were saved, if any: the score of the games,
+    /// or their number of actions for the trainers learning it
+    #[serde(default)]
+    pub bench_score: Option<f64>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Header {
+    kind: Kind,
+    #[serde(flatten)]
+    metadata: Metadata,
+}
+
+/// Writes the parameters with their metadata to a checkpoint file.
+pub fn save(path: &Path, params: &Params, metadata: &Metadata) -> anyhow::Result<()> {
+    match params {
+        Params::NTuple(network) => save_ntuple(path, network, metadata),
+        Params::Linear(weights) => save_linear(path, weights, metadata),
+    }
+}
+
+/// Same as `save`, without taking ownership of a network that may be large.
+pub fn save_ntuple(
+    path: &Path,
+    network: &NTupleNetwork,
+    metadata: &Metadata,
+) -> anyhow::Result<()> {
+    write(path, Kind::NTuple, metadata, |out| network.write_to(out))
+}
+
+pub fn save_linear(path: &Path, weights: &EvalWeights, metadata: &Metadata) -> anyhow::Result<()> {
+    write(path, Kind::Linear, metadata, |out| {
+        let text = weights.to_string();
+        out.write_all(&(text.len() as u32).to_le_bytes())?;
+        out.write_all(text.as_bytes())?;
+        Ok(())
+    })
+}
+
+fn write(
+    path: &Path,
+    kind: Kind,
+    metadata: &Metadata,
+    payload: impl FnOnce(&mut BufWriter<File>) -> anyhow::Result<()>,
+) -> anyhow::Result<()> {
+    let file = File::create(path).with_context(|| format!("Cannot create {}", path.display()))?;
+    let mut out = BufWriter::new(file);
+    let header = serde_json::to_vec(&Header {
+        kind,
+        metadata: metadata.clone(),
+    })?;
+    out.write_all(MAGIC)?;
+    out.write_all(&FORMAT_VERSION.to_le_bytes())?;
+    out.write_all(&(header.len() as u32).to_le_bytes())?;
+    out.write_all(&header)?;
+    payload(&mut out)?;
+    out.flush()?;
+    Ok(())
+}
+
+/// Reads a checkpoint, or a bare n-tuple network or text weights file (with default metadata).
+pub fn load(path: &Path) -> anyhow::Result<(Params, Metadata)> {
+    let file = File::open(path).with_context(|| format!("Cannot open {}", path.display()))?;
+    read(&mut BufReader::new(file))
+        .with_context(|| format!("Invalid weights file {}", path.display()))
+}
+
+fn read(input: &mut impl Read) -> anyhow::Result<(Params, Metadata)> {
+    let mut magic = [0; 8];
+    let len = read_prefix(input, &mut magic)?;
+    if magic[..len] != MAGIC[..] {
+        // a file written before checkpoints
+        let mut input = (&magic[..len]).chain(input);
+        if magic[..len.min(4)] == ntuple::MAGIC[..] {
+            let network = NTupleNetwork::read_from(&mut input)?;
+            return Ok((Params::NTuple(network), Metadata::default()));
+        }
+        let mut text = String::new();
+        input
+            .read_to_string(&mut text)
+            .context("Neither a checkpoint nor a text weights file")?;
+        return Ok((
+            Params::Linear(EvalWeights::parse(&text)?),
+            Metadata::default(),
+        ));
+    }
+    let version = read_u32(input)?;
+    ensure!(
+        version == FORMAT_VERSION,
+        "Unsupported checkpoint version: {version}"
+    );
+    let mut header = vec![0; read_u32(input)? as usize];
+    input.read_exact(&mut header)?;
+    let header: Header = serde_json::from_slice(&header).context("Invalid checkpoint header")?;
+    let params = match header.kind {
+        Kind::NTuple => Params::NTuple(NTupleNetwork::read_from(input)?),
+        Kind::Linear => {
+            let mut text = vec![0; read_u32(input)? as usize];
+            input.read_exact(&mut text)?;
+            Params::Linear(EvalWeights::parse(std::str::from_utf8(&text)?)?)
+        }
+    };
+    Ok((params, header.metadata))
+}
+
+/// Fills the buffer as much as the input allows, returning the number of bytes read.
+fn read_prefix(input: &mut impl Read, buffer: &mut [u8]) -> anyhow::Result<usize> {
+    let mut len = 0;
+    while len < buffer.len() {
+        match input.read(&mut buffer[len..])? {
+            0 => break,
+            n => len += n,
+        }
+    }
+    Ok(len)
+}
+
+fn read_u32(input: &mut impl Read) -> anyhow::Result<u32> {
+    let mut bytes = [0; 4];
+    input.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+/// Reads an n-tuple network from a checkpoint or a bare network file.
+pub fn load_ntuple(path: &Path) -> anyhow::Result<(NTupleNetwork, Metadata)> {
+    match load(path)? {
+        (Params::NTuple(network), metadata) => Ok((network, metadata)),
+        (Params::Linear(_), _) => bail!(
+            "{} holds linear weights, not an n-tuple network",
+            path.display()
+        ),
+    }
+}
+
+/// Reads linear weights from a checkpoint or a text weights file.
+pub fn load_linear(path: &Path) -> anyhow::Result<(EvalWeights, Metadata)> {
+    match load(path)? {
+        (Params::Linear(weights), metadata) => Ok((weights, metadata)),
+        (Params::NTuple(_), _) => bail!(
+            "{} holds an n-tuple network, which cannot be used as weights of the evaluation function",
+            path.display()
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::Board;
+    use crate::eval::ntuple::FOUR_TUPLES;
+
+    #[test]
+    fn test_params() {
+        let dir = std::env::temp_dir();
+        let metadata = Metadata {
+            trainer: "test".into(),
+            training_steps: 12,
+            bench_score: Some(345.5),
+        };
+
+        let mut network = NTupleNetwork::new(&FOUR_TUPLES);
+        let board = Board::from_values([[2, 4, 0, 0], [0; 4], [0, 8, 0, 0], [0, 0, 0, 2]]).unwrap();
+        network.update(&board, 1.5);
+        let path = dir.join(format!("params-ntuple-{}.bin", std::process::id()));
+        save_ntuple(&path, &network, &metadata).unwrap();
+        let (loaded, loaded_metadata) = load_ntuple(&path).unwrap();
+        assert_eq!(loaded.eval(&board), network.eval(&board));
+        assert_eq!(loaded_metadata, metadata);
+        assert!(load_linear(&path).is_err());
+        // a bare network is still accepted
+        network.save(&path).unwrap();
+        let (loaded, loaded_metadata) = load_ntuple(&path).unwrap();
+        assert_eq!(loaded.eval(&board), network.eval(&board));
+        assert_eq!(loaded_metadata, Metadata::default());
+        std::fs::remove_file(&path).unwrap();
+
+        let mut weights = EvalWeights::default();
+        weights.0[0] = 0.25;
+        let path = dir.join(format!("params-linear-{}.bin", std::process::id()));
+        save_linear(&path, &weights, &metadata).unwrap();
+        assert_eq!(load_linear(&path).unwrap(), (weights, metadata));
+        // as are text weights
+        weights.save(&path).unwrap();
+        assert_eq!(load_linear(&path).unwrap().0, weights);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
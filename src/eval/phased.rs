@@ -0,0 +1,187 @@
+//! Evaluation with weights depending on the phase of the game.
+//!
+//! The relative importance of heuristics changes along a game: empty cells are plentiful early on,
+//! while keeping large tiles ordered dominates once 1024+ tiles exist.
+//! A `PhasedEvaluator` holds one set of weights per phase, and linearly interpolates between them.
+//!
+//! ```rust
+//! let evaluator = PhasedEvaluator::new(
+//!     PhaseMeasure::MaxTile,
+//!     vec![(8.0, early_weights), (10.0, late_weights)],
+//! );
+//! // boards with a max tile of 512 (2^9) are evaluated with the average of the early and late weights
+//! let value = evaluator.eval(&board);
+//! ```
+
+use crate::board::Board;
+
+use super::{EvalWeights, Evaluator};
+
+/// How the progress of the game is measured to select the phase.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PhaseMeasure {
+    /// Exponent of the max tile (e.g. 10 with a 1024 tile)
+    MaxTile,
+    /// Base-2 logarithm of the sum of all tiles, which increases smoothly along the game
+    TileSum,
+}
+
+impl PhaseMeasure {
+    /// Progress of the game on the board
+    pub fn measure(&self, board: &Board) -> f32 {
+        match self {
+            PhaseMeasure::MaxTile => board.max_tile() as f32,
+            PhaseMeasure::TileSum => (board.tile_sum().max(1) as f32).log2(),
+        }
+    }
+}
+
+/// An evaluation function whose weights depend on the phase of the game.
+pub struct PhasedEvaluator {
+    measure: PhaseMeasure,
+    /// Anchor phases, sorted by increasing progress, with the evaluator for boards at exactly this progress
+    anchors: Vec<(f32, Evaluator)>,
+}
+
+impl PhasedEvaluator {
+    /// Creates an evaluator from a list of `(progress, weights)` anchors.
+    ///
+    /// Boards before the first anchor (resp. after the last) are evaluated with the weights of the first (resp. last)
+    /// anchor. In between two anchors, the evaluation is linearly interpolated according to the progress.
+    ///
+    /// Panics if no anchor is given.
+    pub fn new(measure: PhaseMeasure, mut anchors: Vec<(f32, EvalWeights)>) -> PhasedEvaluator {
+        assert!(!anchors.is_empty(), "at least one phase is needed");
+        anchors.sort_by(|(a, _), (b, _)| a.total_cmp(b));
+        PhasedEvaluator {
+            measure,
+            anchors: anchors
+                .into_iter()
+                .map(|(progress, weights)| (progress, Evaluator::new(weights)))
+                .collect(),
+        }
+    }
+
+    /// Progress of the game on the board, as used to select the phase
+    pub fn progress(&self, board: &Board) -> f32 {
+        self.measure.measure(board)
+    }
+
+    pub fn eval(&self, board: &Board) -> f32 {
+        let progress = self.progress(board);
+        let next = self
+            .anchors
+            .partition_point(|(anchor, _)| *anchor <= progress);
+        if next == 0 {
+            return self.anchors[0].1.eval(board);
+        }
+        if next == self.anchors.len() {
+            return self.anchors[next - 1].1.eval(board);
+        }
+        let (start, before) = &self.anchors[next - 1];
+        let (end, after) = &self.anchors[next];
+        // the evaluation is linear in the weights, so interpolating the evaluations
+        // is the same as evaluating with interpolated weights
+        let t = (progress - start) / (end - start);
+        (1.0 - t) * before.eval(board) + t * after.eval(board)
+    }
+
+    /// Value of a lost board, below all other boards for the weights of every phase
+    pub fn eval_lost(&self, board: &Board) -> f32 {
+        self.anchors
+            .iter()
+            .map(|(_, evaluator)| evaluator.eval_lost(board))
+            .fold(f32::INFINITY, f32::min)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::PlayableBoard;
+    use crate::eval::Evaluate;
+
+    #[test]
+    fn test_interpolation() {
+        let board = |max_tile: u8| Board {
+            cells: [[max_tile, 1, 0, 0], [2, 0, 0, 0], [0; 4], [0; 4]],
+        };
+        let early = EvalWeights::default();
+        let mut late = early;
+        late.set("empty", 0.0).unwrap();
+        let phased = PhasedEvaluator::new(PhaseMeasure::MaxTile, vec![(10.0, late), (8.0, early)]);
+
+        let (early, late) = (Evaluator::new(early), Evaluator::new(late));
+        assert_eq!(phased.eval(&board(3)), early.eval(&board(3)));
+        assert_eq!(phased.eval(&board(8)), early.eval(&board(8)));
+        assert_eq!(phased.eval(&board(11)), late.eval(&board(11)));
+        let middle = (early.eval(&board(9)) + late.eval(&board(9))) / 2.0;
+        assert!((phased.eval(&board(9)) - middle).abs() < 1.0);
+    }
+
+    #[test]
+    fn test_single_phase() {
+        let weights = EvalWeights::default();
+        let phased = PhasedEvaluator::new(PhaseMeasure::MaxTile, vec![(9.0, weights)]);
+        let evaluator = Evaluator::new(weights);
+        // before, at and after the only anchor, the evaluation is the one of its weights
+        for max_tile in [1, 9, 15] {
+            let board = Board {
+                cells: [[max_tile, 2, 1, 0], [1, 0, 0, 0], [0; 4], [0, 0, 0, 1]],
+            };
+            assert_eq!(phased.eval(&board), evaluator.eval(&board));
+            assert_eq!(phased.eval_lost(&board), evaluator.eval_lost(&board));
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "at least one phase")]
+    fn test_no_phase() {
+        PhasedEvaluator::new(PhaseMeasure::TileSum, vec![]);
+    }
+
+    #[test]
+    fn test_tile_sum() {
+        let measure = PhaseMeasure::TileSum;
+        assert_eq!(measure.measure(&Board { cells: [[0; 4]; 4] }), 0.0);
+        let board = Board {
+            cells: [[1, 1, 0, 0], [2, 0, 0, 0], [0; 4], [0, 0, 0, 3]],
+        };
+        assert_eq!(measure.measure(&board), 4.0);
+        assert_eq!(PhaseMeasure::MaxTile.measure(&board), 3.0);
+
+        // half way between the anchors of the sum of tiles
+        let early = EvalWeights::default();
+        let mut late = early;
+        late.set("empty", 0.0).unwrap();
+        let phased = PhasedEvaluator::new(measure, vec![(3.0, early), (5.0, late)]);
+        assert_eq!(phased.progress(&board), 4.0);
+        let (early, late) = (Evaluator::new(early), Evaluator::new(late));
+        let middle = (early.eval(&board) + late.eval(&board)) / 2.0;
+        assert!((phased.eval(&board) - middle).abs() <= middle.abs() * 1e-5);
+    }
+
+    #[test]
+    fn test_lost() {
+        let early = EvalWeights::default();
+        let mut late = early;
+        late.set("empty", 0.0).unwrap();
+        let phased = PhasedEvaluator::new(PhaseMeasure::MaxTile, vec![(8.0, early), (10.0, late)]);
+        let lost = Board {
+            cells: [[1, 2, 1, 2], [2, 9, 2, 1], [1, 2, 1, 2], [2, 1, 2, 1]],
+        };
+        assert!(lost.is_lost());
+        let value = phased.eval_lost(&lost);
+        for weights in [early, late] {
+            assert!(value <= Evaluator::new(weights).eval_lost(&lost));
+        }
+        // through the trait, a lost state gets this value, below the states that can still be played
+        let playable = Board {
+            cells: [[1, 2, 1, 2], [2, 9, 2, 1], [1, 2, 1, 2], [2, 1, 2, 0]],
+        };
+        let evaluate: &dyn Evaluate = &phased;
+        assert_eq!(evaluate.eval_lost(&lost), value);
+        assert_eq!(evaluate.eval_state(&PlayableBoard::from(lost)), value);
+        assert!(evaluate.eval_state(&PlayableBoard::from(playable)) > value);
+    }
+}
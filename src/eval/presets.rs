@@ -0,0 +1,111 @@
+//! Named sets of weights for well-known evaluation strategies.
+//!
+//! A heuristic that is not listed in a preset gets a weight of 0, so a preset defines both the set of heuristics
+//! taking part in the evaluation and their weights.
+//!
+//! ```rust
+//! let weights = presets::find("corner-stacker")?.weights();
+//! ```
+
+use anyhow::bail;
+
+use super::{EvalWeights, NUM_HEURISTICS};
+
+/// A named set of weights.
+pub struct Preset {
+    pub name: &'static str,
+    pub description: &'static str,
+    /// Weights of the heuristics that take part in the evaluation
+    terms: &'static [(&'static str, f32)],
+}
+
+impl Preset {
+    pub fn weights(&self) -> EvalWeights {
+        let mut weights = EvalWeights([0.0; NUM_HEURISTICS]);
+        for &(name, weight) in self.terms {
+            weights
+                .set(name, weight)
+                .expect("presets only refer to heuristics of the registry");
+        }
+        weights
+    }
+}
+
+/// All presets, the first one being the simplest.
+pub static PRESETS: [Preset; 4] = [
+    Preset {
+        name: "baseline",
+        description: "Number of empty cells only, the simplest heuristic that clearly beats random play",
+        terms: &[("empty", 1.0)],
+    },
+    Preset {
+        name: "corner-stacker",
+        description: "Keeps the tiles decreasing from the top-left corner, with the largest tile in it",
+        terms: &[
+            ("monotonicity_left", 47.0),
+            ("monotonicity_up", 47.0),
+            ("corner", 20_000.0),
+            ("empty", 270.0),
+            ("adjacent", 700.0),
+            ("sum", 11.0),
+        ],
+    },
+    Preset {
+        name: "nneissen",
+        description: "Monotonicity in any direction, empty cells, adjacent pairs and tile sum, as in nneonneo's \
+                      expectimax AI (the default weights)",
+        terms: &[
+            ("monotonicity_rows", 47.0),
+            ("monotonicity_cols", 47.0),
+            ("empty", 270.0),
+            ("adjacent", 700.0),
+            ("sum", 11.0),
+        ],
+    },
+    Preset {
+        name: "survival",
+        description: "Favours keeping the board open and unfragmented over building large tiles",
+        terms: &[
+            ("monotonicity_rows", 10.0),
+            ("monotonicity_cols", 10.0),
+            ("empty", 270.0),
+            ("empty_log", 2_000.0),
+            ("adjacent", 700.0),
+            ("merges", 500.0),
+            ("trapped", 200.0),
+            ("islands", 300.0),
+        ],
+    },
+];
+
+/// Names of all presets
+pub fn names() -> impl Iterator<Item = &'static str> {
+    PRESETS.iter().map(|preset| preset.name)
+}
+
+/// Returns the preset with the given name.
+pub fn find(name: &str) -> anyhow::Result<&'static Preset> {
+    match PRESETS.iter().find(|preset| preset.name == name) {
+        Some(preset) => Ok(preset),
+        None => bail!(
+            "Unknown preset: {name} (available: {})",
+            names().collect::<Vec<_>>().join(", ")
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_presets() {
+        for preset in &PRESETS {
+            // panics on unknown heuristic names
+            preset.weights();
+        }
+        assert_eq!(find("nneissen").unwrap().weights(), EvalWeights::default());
+        assert_eq!(find("baseline").unwrap().weights().get("sum"), Some(0.0));
+        assert!(find("unknown").is_err());
+    }
+}
@@ -0,0 +1,257 @@
+//! Registry of the evaluation functions usable at the leaves of the search, selectable by name from the command line.
+//!
+//! An evaluator is written as its name, optionally followed by parameters, in the same format as strategies:
+//! `linear`, `phased:early=survival,late=corner-stacker`, `ntuple:file=ntuple.weights`, `nn:file=net.safetensors`.
+//!
+//! ```rust
+//! let spec: EvaluatorSpec = "ntuple:file=ntuple.weights".parse()?;
+//! eval::set_default_evaluator(spec.load(weights)?)?;
+//! ```
+
+use std::fmt::{Display, Formatter};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
+
+use anyhow::{bail, Context};
+
+use super::phased::{PhaseMeasure, PhasedEvaluator};
+use super::{params, presets, EvalWeights, Evaluate, Evaluator};
+
+/// Weights of the early game of the phased evaluator: keeping the board open matters most
+const DEFAULT_EARLY: &str = "survival";
+
+/// Weights of the late game of the phased evaluator: keeping the large tiles ordered matters most
+const DEFAULT_LATE: &str = "corner-stacker";
+
+/// An evaluation function, as selected on the command line.
+#[derive(Clone, Debug, PartialEq)]
+pub enum EvaluatorSpec {
+    /// Linear combination of the heuristics, with the weights given separately (e.g. `--eval-preset`)
+    Linear,
+    /// Linear combination of the heuristics with the weights of `early` (a preset or a weights file) until the game
+    /// reaches 256, blended into those of `late` up to 1024 (see `phased`)
+    Phased {
+        early: String,
+        late: String,
+        measure: PhaseMeasure,
+    },
+    /// N-tuple network learned by `train` (see `ntuple`)
+    NTuple(PathBuf),
+    /// Neural network in a safetensors file, available with the `nn` feature (see `nn`)
+    Nn(PathBuf),
+}
+
+/// Names and descriptions of all evaluators, e.g. for help messages
+pub const EVALUATORS: [(&str, &str); 4] = [
+    (
+        "linear",
+        "linear combination of the heuristics, with the weights of `--eval-preset` or `--weights`",
+    ),
+    (
+        "phased",
+        "weights depending on the phase of the game, with parameters `early` and `late` (presets or weights \
+         files) and `measure` (`max-tile` or `tile-sum`)",
+    ),
+    (
+        "ntuple",
+        "n-tuple network learned by `train`, with parameter `file`",
+    ),
+    (
+        "nn",
+        "neural network (requires the `nn` feature), with parameter `file` (safetensors)",
+    ),
+];
+
+impl EvaluatorSpec {
+    /// Whether the evaluator uses the linear weights given separately (and `--disable`)
+    pub fn uses_weights(&self) -> bool {
+        *self == EvaluatorSpec::Linear
+    }
+
+    /// Builds the evaluator, with `weights` for the linear one.
+    pub fn load(&self, weights: EvalWeights) -> anyhow::Result<Arc<dyn Evaluate>> {
+        Ok(match self {
+            EvaluatorSpec::Linear => Arc::new(Evaluator::new(weights)),
+            EvaluatorSpec::Phased {
+                early,
+                late,
+                measure,
+            } => {
+                // the anchors where 256 and 1024 tiles appear
+                let (start, end) = match measure {
+                    PhaseMeasure::MaxTile => (8.0, 10.0),
+                    PhaseMeasure::TileSum => (9.0, 11.0),
+                };
+                Arc::new(PhasedEvaluator::new(
+                    *measure,
+                    vec![(start, phase_weights(early)?), (end, phase_weights(late)?)],
+                ))
+            }
+            EvaluatorSpec::NTuple(path) => Arc::new(params::load_ntuple(path)?.0),
+            #[cfg(feature = "nn")]
+            EvaluatorSpec::Nn(path) => Arc::new(super::nn::NnEvaluator::load(path)?),
+            #[cfg(not(feature = "nn"))]
+            EvaluatorSpec::Nn(_) => {
+                bail!("The neural network evaluator is not available in this build, rebuild with `--features nn`")
+            }
+        })
+    }
+}
+
+/// Weights of a phase: a preset, or else a weights file
+fn phase_weights(name: &str) -> anyhow::Result<EvalWeights> {
+    if presets::names().any(|preset| preset == name) {
+        super::load_weights(None, Some(name))
+    } else {
+        super::load_weights(Some(Path::new(name)), None)
+    }
+}
+
+impl FromStr for EvaluatorSpec {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<EvaluatorSpec> {
+        let (name, params) = s.split_once(':').unwrap_or((s, ""));
+        let mut early = DEFAULT_EARLY.to_string();
+        let mut late = DEFAULT_LATE.to_string();
+        let mut measure = PhaseMeasure::MaxTile;
+        let mut file = None;
+        for param in params.split(',').filter(|param| !param.is_empty()) {
+            let (key, value) = param
+                .split_once('=')
+                .with_context(|| format!("Expected `key=value` but got `{param}`"))?;
+            match (name, key) {
+                ("phased", "early") => early = value.to_string(),
+                ("phased", "late") => late = value.to_string(),
+                ("phased", "measure") => {
+                    measure = match value {
+                        "max-tile" => PhaseMeasure::MaxTile,
+                        "tile-sum" => PhaseMeasure::TileSum,
+                        _ => {
+                            bail!("Unknown phase measure: {value} (available: max-tile, tile-sum)")
+                        }
+                    }
+                }
+                ("ntuple" | "nn", "file") => file = Some(PathBuf::from(value)),
+                _ => bail!("Unknown parameter `{key}` for evaluator `{name}`"),
+            }
+        }
+        let file =
+            || file.with_context(|| format!("Missing parameter `file` of evaluator `{name}`"));
+        Ok(match name {
+            "linear" => EvaluatorSpec::Linear,
+            "phased" => EvaluatorSpec::Phased {
+                early,
+                late,
+                measure,
+            },
+            "ntuple" => EvaluatorSpec::NTuple(file()?),
+            "nn" => EvaluatorSpec::Nn(file()?),
+            _ => {
+                let names: Vec<_> = EVALUATORS.iter().map(|(name, _)| *name).collect();
+                bail!(
+                    "Unknown evaluator: {name} (available: {})",
+                    names.join(", ")
+                )
+            }
+        })
+    }
+}
+
+impl Display for EvaluatorSpec {
+    /// Writes the evaluator in the format accepted by `from_str`, with all its parameters.
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EvaluatorSpec::Linear => write!(f, "linear"),
+            EvaluatorSpec::Phased {
+                early,
+                late,
+                measure,
+            } => {
+                let measure = match measure {
+                    PhaseMeasure::MaxTile => "max-tile",
+                    PhaseMeasure::TileSum => "tile-sum",
+                };
+                write!(f, "phased:early={early},late={late},measure={measure}")
+            }
+            EvaluatorSpec::NTuple(path) => write!(f, "ntuple:file={}", path.display()),
+            EvaluatorSpec::Nn(path) => write!(f, "nn:file={}", path.display()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::Board;
+    use crate::eval::ntuple::{NTupleNetwork, FOUR_TUPLES};
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            "linear".parse::<EvaluatorSpec>().unwrap(),
+            EvaluatorSpec::Linear
+        );
+        assert_eq!(
+            "ntuple:file=a.weights".parse::<EvaluatorSpec>().unwrap(),
+            EvaluatorSpec::NTuple(PathBuf::from("a.weights"))
+        );
+        let phased: EvaluatorSpec = "phased:late=baseline,measure=tile-sum".parse().unwrap();
+        assert_eq!(
+            phased,
+            EvaluatorSpec::Phased {
+                early: DEFAULT_EARLY.to_string(),
+                late: "baseline".to_string(),
+                measure: PhaseMeasure::TileSum,
+            }
+        );
+        for spec in [phased, EvaluatorSpec::Nn(PathBuf::from("net.safetensors"))] {
+            assert_eq!(spec.to_string().parse::<EvaluatorSpec>().unwrap(), spec);
+        }
+        for invalid in [
+            "ntuple",
+            "nn:path=x",
+            "phased:measure=moves",
+            "phased:early",
+            "linear:file=x",
+            "mcts",
+        ] {
+            assert!(invalid.parse::<EvaluatorSpec>().is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn test_load() {
+        let board = Board::from_values([[2, 0, 0, 0], [4, 0, 0, 0], [0; 4], [8, 0, 0, 2]]).unwrap();
+        let weights = presets::find("baseline").unwrap().weights();
+        let linear = EvaluatorSpec::Linear.load(weights).unwrap();
+        assert_eq!(linear.eval(&board), Evaluator::new(weights).eval(&board));
+        assert!(linear.linear().is_some());
+
+        // before 256, the phased evaluator uses the early weights only
+        let phased: EvaluatorSpec = "phased:early=baseline,late=corner-stacker".parse().unwrap();
+        let phased = phased.load(EvalWeights::default()).unwrap();
+        assert_eq!(phased.eval(&board), linear.eval(&board));
+        assert!(phased.linear().is_none());
+        let missing: EvaluatorSpec = "phased:early=/nonexistent/weights".parse().unwrap();
+        assert!(missing.load(weights).is_err());
+
+        let path =
+            std::env::temp_dir().join(format!("ai-2048-spec-{}.weights", std::process::id()));
+        let mut network = NTupleNetwork::new(&FOUR_TUPLES);
+        network.update(&board, 3.0);
+        params::save_ntuple(&path, &network, &params::Metadata::default()).unwrap();
+        let loaded = EvaluatorSpec::NTuple(path.clone()).load(weights).unwrap();
+        assert_eq!(loaded.eval(&board), network.eval(&board));
+        // a linear checkpoint is not an n-tuple network
+        params::save_linear(&path, &weights, &params::Metadata::default()).unwrap();
+        assert!(EvaluatorSpec::NTuple(path.clone()).load(weights).is_err());
+        std::fs::remove_file(&path).unwrap();
+
+        #[cfg(not(feature = "nn"))]
+        assert!(EvaluatorSpec::Nn(PathBuf::from("net.safetensors"))
+            .load(weights)
+            .is_err());
+    }
+}
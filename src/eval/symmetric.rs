@@ -0,0 +1,144 @@
+//! Evaluation made invariant to the symmetries of the board.
+//!
+//! Rotating or reflecting a board does not change its value for the game, but hand-written heuristics
+//! (e.g. monotonicity towards a given corner) and learned evaluators are usually not invariant.
+//! A `SymmetricEvaluator` aggregates the evaluation of the 8 rotations/reflections of the board.
+//!
+//! ```rust
+//! let evaluator = SymmetricEvaluator::new(Evaluator::new(weights), Aggregation::Mean);
+//! assert_eq!(evaluator.eval(&board), evaluator.eval(&board.transposed()));
+//! ```
+
+use crate::board::Board;
+
+use super::Evaluate;
+
+/// How the evaluations of the symmetric boards are combined.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Aggregation {
+    /// Average over the symmetries, which reduces the variance of noisy evaluators
+    Mean,
+    /// Best evaluation over the symmetries, as if the preferred orientation was always chosen
+    Max,
+}
+
+/// Wraps an evaluator so that all symmetric boards get the same value.
+///
+/// Evaluating a board costs 8 evaluations of the inner evaluator.
+pub struct SymmetricEvaluator<E> {
+    inner: E,
+    aggregation: Aggregation,
+}
+
+impl<E: Evaluate> SymmetricEvaluator<E> {
+    pub fn new(inner: E, aggregation: Aggregation) -> SymmetricEvaluator<E> {
+        SymmetricEvaluator { inner, aggregation }
+    }
+
+    pub fn inner(&self) -> &E {
+        &self.inner
+    }
+
+    pub fn eval(&self, board: &Board) -> f32 {
+        let values = board.symmetries().map(|sym| self.inner.eval(&sym));
+        match self.aggregation {
+            Aggregation::Mean => values.iter().sum::<f32>() / values.len() as f32,
+            Aggregation::Max => values.into_iter().fold(f32::NEG_INFINITY, f32::max),
+        }
+    }
+}
+
+impl<E: Evaluate> Evaluate for SymmetricEvaluator<E> {
+    fn eval(&self, board: &Board) -> f32 {
+        SymmetricEvaluator::eval(self, board)
+    }
+
+    fn eval_lost(&self, board: &Board) -> f32 {
+        self.inner.eval_lost(board)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::PlayableBoard;
+    use crate::eval::{EvalWeights, Evaluator};
+
+    #[test]
+    fn test_invariance() {
+        let board = Board {
+            cells: [[9, 3, 1, 0], [4, 2, 0, 0], [1, 0, 0, 0], [1, 0, 0, 2]],
+        };
+        let symmetries = board.symmetries();
+        for i in 0..8 {
+            for j in 0..i {
+                assert_ne!(symmetries[i], symmetries[j], "symmetries {i} and {j}");
+            }
+        }
+
+        let evaluator = Evaluator::new(EvalWeights::default());
+        let max = symmetries
+            .iter()
+            .map(|sym| evaluator.eval(sym))
+            .fold(f32::NEG_INFINITY, f32::max);
+        for aggregation in [Aggregation::Mean, Aggregation::Max] {
+            let symmetric =
+                SymmetricEvaluator::new(Evaluator::new(EvalWeights::default()), aggregation);
+            let value = symmetric.eval(&board);
+            for sym in &symmetries {
+                assert!((symmetric.eval(sym) - value).abs() <= value.abs() * 1e-6);
+            }
+        }
+        let symmetric = SymmetricEvaluator::new(evaluator, Aggregation::Max);
+        assert_eq!(symmetric.eval(&board), max);
+    }
+
+    /// Value of the top-left tile: not invariant, and the 8 symmetries bring each corner there twice
+    struct TopLeft;
+
+    impl Evaluate for TopLeft {
+        fn eval(&self, board: &Board) -> f32 {
+            board.cells[0][0] as f32
+        }
+
+        fn eval_lost(&self, _board: &Board) -> f32 {
+            -1234.0
+        }
+    }
+
+    #[test]
+    fn test_aggregation() {
+        let board = Board {
+            cells: [[1, 0, 0, 3], [0, 0, 0, 0], [0, 0, 0, 0], [6, 0, 0, 10]],
+        };
+        let mean = SymmetricEvaluator::new(TopLeft, Aggregation::Mean);
+        let max = SymmetricEvaluator::new(TopLeft, Aggregation::Max);
+        assert_eq!(mean.eval(&board), 5.0);
+        assert_eq!(max.eval(&board), 10.0);
+        assert_eq!(mean.inner().eval(&board), 1.0);
+        // through the trait, as the search sees the evaluator
+        let (mean, max): (&dyn Evaluate, &dyn Evaluate) = (&mean, &max);
+        assert_eq!(mean.eval(&board), 5.0);
+        assert_eq!(max.eval(&board), 10.0);
+        assert_eq!(mean.eval_batch(&[board, board.transposed()]), [5.0, 5.0]);
+    }
+
+    #[test]
+    fn test_lost() {
+        // lost boards keep the value of the inner evaluator, without aggregation
+        let lost = Board {
+            cells: [[1, 2, 1, 2], [2, 1, 2, 1], [1, 2, 1, 2], [2, 1, 2, 1]],
+        };
+        for aggregation in [Aggregation::Mean, Aggregation::Max] {
+            let symmetric: &dyn Evaluate = &SymmetricEvaluator::new(TopLeft, aggregation);
+            assert_eq!(symmetric.eval_lost(&lost), -1234.0);
+            assert_eq!(symmetric.eval_state(&PlayableBoard::from(lost)), -1234.0);
+        }
+        let symmetric =
+            SymmetricEvaluator::new(Evaluator::new(EvalWeights::default()), Aggregation::Mean);
+        assert_eq!(
+            Evaluate::eval_lost(&symmetric, &lost),
+            symmetric.inner().eval_lost(&lost)
+        );
+    }
+}
@@ -0,0 +1,75 @@
+//! Datasets of expert iteration (`main expert`): a fast policy plays games to reach realistic positions, and each
+//! position is labelled by a much slower expert (typically a deep expectimax) with the value of every action and the
+//! action it selects. Evaluators trained on these labels approximate the deep search at the cost of a shallow one.
+//!
+//! The positions are labelled in parallel on the rayon pool, independently of the game they come from, so that a
+//! few long games keep all threads busy. The labels are written in the format of `collect`.
+
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use rayon::prelude::*;
+
+use crate::board::{Board, PlayableBoard};
+use crate::collect::Sample;
+use crate::strategy::Strategy;
+
+/// Boards reached by the policy in the game of the seed, one every `every` actions starting with the first one.
+pub fn positions(policy: &Strategy, seed: u64, every: usize) -> Vec<Board> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut board = PlayableBoard::init_with(&mut rng);
+    let mut positions = Vec::new();
+    for i in 0.. {
+        if i % every == 0 && !board.board().is_lost() {
+            positions.push(*board.board());
+        }
+        let Some(after) = policy
+            .select_action(board)
+            .and_then(|action| board.apply(action))
+        else {
+            break;
+        };
+        board = after.with_random_tile_with(&mut rng);
+    }
+    positions
+}
+
+/// Labels each position with the values of the actions according to the expert and the action it selects. Positions
+/// on which the expert selects no action are left out.
+pub fn label(positions: &[(u64, Board)], expert: &Strategy) -> Vec<Sample> {
+    positions
+        .par_iter()
+        .filter_map(|&(seed, board)| {
+            let playable = PlayableBoard::from(board);
+            let values = expert.evaluate_all_actions(playable);
+            Some(Sample {
+                seed,
+                board,
+                values,
+                action: expert.select_action(playable)?,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::ALL_ACTIONS;
+
+    #[test]
+    fn test_expert() {
+        let all = positions(&Strategy::Random, 3, 1);
+        let sparse = positions(&Strategy::Random, 3, 10);
+        // the first position is the initial board
+        let initial = PlayableBoard::init_with(&mut StdRng::seed_from_u64(3));
+        assert_eq!(all[0], *initial.board());
+        assert_eq!(sparse[0], all[0]);
+
+        let samples = label(&[(3, all[0]), (3, all[1])], &Strategy::Random);
+        assert_eq!(samples.len(), 2);
+        let action = samples[0].action;
+        let index = ALL_ACTIONS.iter().position(|&a| a == action).unwrap();
+        // the selected action is applicable, so it has a value
+        assert!(samples[0].values[index].is_some());
+    }
+}
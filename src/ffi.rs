@@ -0,0 +1,187 @@
+//! C API of the engine core, to embed the rules and the AI in programs written in other languages (declared in
+//! `include/ai_2048.h`, generated from this file by cbindgen, see `cbindgen.toml`).
+//!
+//! A board is passed as an array of 16 `uint8_t`, the exponents of its tiles row by row (0 for empty cells, 1 for a
+//! 2, 2 for a 4, ...), and an action as an integer: `AI_2048_UP` (0), `AI_2048_DOWN` (1), `AI_2048_LEFT` (2) and
+//! `AI_2048_RIGHT` (3).
+//! The functions never unwind into C: invalid arguments are reported by the returned value.
+
+use std::ffi::{c_char, CStr};
+use std::time::Duration;
+
+use crate::board::{Board, PlayableBoard, ALL_ACTIONS, N};
+use crate::strategy::Strategy;
+
+/// Number of the action moving the tiles up (the actions are numbered in the order of `ALL_ACTIONS`)
+pub const AI_2048_UP: i32 = 0;
+/// Number of the action moving the tiles down
+pub const AI_2048_DOWN: i32 = 1;
+/// Number of the action moving the tiles left
+pub const AI_2048_LEFT: i32 = 2;
+/// Number of the action moving the tiles right
+pub const AI_2048_RIGHT: i32 = 3;
+
+/// Largest exponent of a tile accepted from C (a tile of 131072, the largest one reachable on a 4x4 board)
+const MAX_EXPONENT: u8 = 17;
+
+/// Returned by `board_apply` and `select_action` for invalid arguments
+const INVALID: i32 = -2;
+
+/// Returned by `select_action` when the strategy panics (e.g. on a `todo!()`), which must not unwind into C
+const FAILED: i32 = -3;
+
+/// Board of the `N*N` exponents pointed to by `cells`, if they are valid.
+///
+/// # Safety
+/// `cells` must be null or point to `N*N` readable bytes.
+unsafe fn read_board(cells: *const u8) -> Option<Board> {
+    if cells.is_null() {
+        return None;
+    }
+    let cells = std::slice::from_raw_parts(cells, N * N);
+    if cells.iter().any(|&tile| tile > MAX_EXPONENT) {
+        return None;
+    }
+    Some(Board {
+        cells: std::array::from_fn(|row| std::array::from_fn(|col| cells[row * N + col])),
+    })
+}
+
+/// Applies the action to the board (without placing a random tile) and writes the resulting board to `out`.
+///
+/// Returns the score of the action in the classic 2048 game, -1 if the action does not move any tile (`out` is then
+/// left unchanged), or -2 if an argument is invalid.
+///
+/// # Safety
+/// `cells` must point to 16 readable bytes and `out` to 16 writable bytes (they may be the same array).
+#[no_mangle]
+pub unsafe extern "C" fn board_apply(cells: *const u8, action: i32, out: *mut u8) -> i32 {
+    // a negative action becomes a huge index
+    let (Some(board), Some(&action)) = (read_board(cells), ALL_ACTIONS.get(action as usize)) else {
+        return INVALID;
+    };
+    if out.is_null() {
+        return INVALID;
+    }
+    match board.apply_scored(action) {
+        Some((after, score)) => {
+            let out = std::slice::from_raw_parts_mut(out, N * N);
+            out.copy_from_slice(after.cells.as_flattened());
+            score as i32
+        }
+        None => -1,
+    }
+}
+
+/// Evaluation of the board by the heuristic of the engine, or NaN if the board is invalid.
+///
+/// # Safety
+/// `cells` must point to 16 readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn board_eval(cells: *const u8) -> f32 {
+    read_board(cells).map_or(f32::NAN, |board| crate::eval::eval(&board))
+}
+
+/// Action selected by the strategy (e.g. `"expectimax:depth=3"`, or `NULL` for the default one) on the board, within
+/// `budget_ms` milliseconds if it is not 0.
+///
+/// Returns the action, -1 if no action is applicable (the game is lost), -2 if an argument is invalid, or -3 if the
+/// strategy failed.
+///
+/// # Safety
+/// `cells` must point to 16 readable bytes and `strategy` must be null or a nul-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn select_action(
+    cells: *const u8,
+    strategy: *const c_char,
+    budget_ms: u64,
+) -> i32 {
+    let Some(board) = read_board(cells) else {
+        return INVALID;
+    };
+    let strategy = if strategy.is_null() {
+        Ok(Strategy::Default)
+    } else {
+        CStr::from_ptr(strategy)
+            .to_str()
+            .map_err(anyhow::Error::from)
+            .and_then(str::parse::<Strategy>)
+    };
+    let Ok(strategy) = strategy else {
+        return INVALID;
+    };
+    let board = PlayableBoard::from(board);
+    let action = std::panic::catch_unwind(|| {
+        if budget_ms == 0 {
+            strategy.select_action(board)
+        } else {
+            strategy.select_action_within(board, Duration::from_millis(budget_ms))
+        }
+    });
+    match action {
+        Ok(action) => action.map_or(-1, |action| action as i32),
+        Err(_) => FAILED,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_c_api() {
+        let mut cells = [0u8; N * N];
+        cells[0] = 1;
+        cells[1] = 1;
+        let mut out = [0u8; N * N];
+        unsafe {
+            // left: the two 2s merge into a 4
+            assert_eq!(
+                board_apply(cells.as_ptr(), AI_2048_LEFT, out.as_mut_ptr()),
+                4
+            );
+            assert_eq!(out[0], 2);
+            assert_eq!(
+                board_apply(out.as_ptr(), AI_2048_LEFT, out.as_mut_ptr()),
+                -1
+            );
+            assert_eq!(board_apply(cells.as_ptr(), 4, out.as_mut_ptr()), INVALID);
+            assert!(board_eval(cells.as_ptr()).is_finite());
+            let action = select_action(cells.as_ptr(), c"random".as_ptr(), 0);
+            assert!((0..4).contains(&action));
+            assert_eq!(select_action(cells.as_ptr(), c"nope".as_ptr(), 0), INVALID);
+            cells[2] = 40;
+            assert!(board_eval(cells.as_ptr()).is_nan());
+        }
+    }
+
+    #[test]
+    fn test_header() {
+        // the committed header is the one generated by cbindgen (see `cbindgen.toml`)
+        let dir = env!("CARGO_MANIFEST_DIR");
+        let config = cbindgen::Config::from_file(format!("{dir}/cbindgen.toml")).unwrap();
+        let mut generated = Vec::new();
+        cbindgen::Builder::new()
+            .with_config(config)
+            .with_src(format!("{dir}/src/ffi.rs"))
+            .generate()
+            .unwrap()
+            .write(&mut generated);
+        let generated = String::from_utf8(generated).unwrap();
+        assert!(
+            generated == include_str!("../include/ai_2048.h"),
+            "include/ai_2048.h is out of date, regenerate it with the command of cbindgen.toml"
+        );
+        for declaration in [
+            "board_apply(",
+            "board_eval(",
+            "select_action(",
+            "AI_2048_LEFT 2",
+        ] {
+            assert!(
+                generated.contains(declaration),
+                "{declaration} missing from the header"
+            );
+        }
+    }
+}
@@ -0,0 +1,266 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use ai_2048::{board, eval, game};
+use anyhow::{ensure, Context};
+use board::Board;
+use clap::builder::PossibleValuesParser;
+use clap::{Parser, Subcommand};
+use eval::{EvalWeights, Evaluator, NUM_HEURISTICS};
+use rayon::prelude::*;
+
+/// Fits the weights of the evaluation function by linear regression on labelled positions.
+///
+/// A positions file has one afterstate per line: the board packed in hexadecimal (as given by `Board::pack`)
+/// followed by its label, the number of actions that were played after it until the end of the game.
+#[derive(Parser, Debug)]
+#[command(version, about, long_about = None)]
+struct Args {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Plays seeded games with a greedy player and writes all afterstates encountered, with their labels
+    Label(LabelArgs),
+    /// Fits weights by ridge regression of the labels on the raw values of the heuristics
+    Regress(RegressArgs),
+}
+
+#[derive(clap::Args, Debug)]
+struct LabelArgs {
+    /// Number of games to play
+    #[arg(short, long, default_value = "100")]
+    games: u64,
+
+    /// Weights of the evaluation used by the greedy player (default weights if absent)
+    #[arg(short, long)]
+    weights: Option<PathBuf>,
+
+    /// Named preset of weights, instead of a weights file
+    #[arg(long, conflicts_with = "weights", value_parser = PossibleValuesParser::new(eval::presets::names()))]
+    eval_preset: Option<String>,
+
+    /// Seed of the first game (game `i` uses the seed `seed + i`)
+    #[arg(long, default_value = "0")]
+    seed: u64,
+
+    /// File where labelled positions are written
+    #[arg(short, long, default_value = "positions.txt")]
+    output: PathBuf,
+}
+
+#[derive(clap::Args, Debug)]
+struct RegressArgs {
+    /// File with labelled positions
+    #[arg(short, long, default_value = "positions.txt")]
+    input: PathBuf,
+
+    /// Comma-separated heuristics used as regressors (all heuristics by default). Others get a weight of 0
+    #[arg(long, value_delimiter = ',')]
+    heuristics: Vec<String>,
+
+    /// Ridge regularization strength, applied on standardized features (0 for ordinary least squares)
+    #[arg(short, long, default_value = "1.0")]
+    ridge: f64,
+
+    /// File where the fitted weights are written
+    #[arg(short, long, default_value = "fitted.weights")]
+    output: PathBuf,
+}
+
+fn main() -> anyhow::Result<()> {
+    let args: Args = Args::parse();
+    match &args.command {
+        Command::Label(label) => generate(label),
+        Command::Regress(regress) => fit(regress),
+    }
+}
+
+/// Plays the games and writes the labelled afterstates.
+fn generate(args: &LabelArgs) -> anyhow::Result<()> {
+    let weights = eval::load_weights(args.weights.as_deref(), args.eval_preset.as_deref())?;
+    let evaluator = Evaluator::new(weights);
+    let games: Vec<Vec<Board>> = (args.seed..args.seed + args.games)
+        .into_par_iter()
+        .map(|seed| {
+            game::play_seeded(seed, |board| {
+                game::greedy_action(board, |after| evaluator.eval(after))
+            })
+        })
+        .collect();
+
+    let file = File::create(&args.output)
+        .with_context(|| format!("Cannot create {}", args.output.display()))?;
+    let mut out = BufWriter::new(file);
+    let mut num_positions = 0;
+    for afterstates in &games {
+        for (i, afterstate) in afterstates.iter().enumerate() {
+            let label = afterstates.len() - 1 - i;
+            writeln!(out, "{:016x} {label}", afterstate.pack())?;
+            num_positions += 1;
+        }
+    }
+    out.flush()?;
+    println!(
+        "Wrote {num_positions} positions from {} games to {}",
+        games.len(),
+        args.output.display()
+    );
+    Ok(())
+}
+
+/// Reads a positions file, as written by `generate`.
+fn read_positions(path: &Path) -> anyhow::Result<Vec<(Board, f64)>> {
+    let file = File::open(path).with_context(|| format!("Cannot open {}", path.display()))?;
+    let mut positions = Vec::new();
+    for (line_number, line) in BufReader::new(file).lines().enumerate() {
+        let line = line?;
+        let parse = || -> anyhow::Result<(Board, f64)> {
+            let (board, label) = line
+                .split_once(' ')
+                .context("Expected `<packed board> <label>`")?;
+            let board = Board::unpack(u64::from_str_radix(board, 16)?);
+            Ok((board, label.trim().parse()?))
+        };
+        let position = parse().with_context(|| {
+            format!("Invalid position at {}:{}", path.display(), line_number + 1)
+        })?;
+        positions.push(position);
+    }
+    Ok(positions)
+}
+
+/// Fits the weights of the selected heuristics and writes them.
+fn fit(args: &RegressArgs) -> anyhow::Result<()> {
+    let selected: Vec<usize> = if args.heuristics.is_empty() {
+        (0..NUM_HEURISTICS).collect()
+    } else {
+        args.heuristics
+            .iter()
+            .map(|name| {
+                eval::heuristic_index(name).with_context(|| format!("Unknown heuristic: {name}"))
+            })
+            .collect::<anyhow::Result<_>>()?
+    };
+    let positions = read_positions(&args.input)?;
+    ensure!(
+        !positions.is_empty(),
+        "No position in {}",
+        args.input.display()
+    );
+
+    let rows: Vec<Vec<f64>> = positions
+        .par_iter()
+        .map(|(board, _)| {
+            let features = eval::features(board);
+            selected.iter().map(|&i| features.0[i] as f64).collect()
+        })
+        .collect();
+    let labels: Vec<f64> = positions.iter().map(|(_, label)| *label).collect();
+
+    let (coefficients, r2) = ridge_regression(&rows, &labels, args.ridge);
+    let mut weights = EvalWeights([0.0; NUM_HEURISTICS]);
+    for (&i, coefficient) in selected.iter().zip(coefficients) {
+        weights.0[i] = coefficient as f32;
+    }
+    weights.save(&args.output)?;
+    println!(
+        "Fitted on {} positions (R² = {r2:.3}), weights written to {}:\n{weights}",
+        positions.len(),
+        args.output.display()
+    );
+    Ok(())
+}
+
+/// Fits `y ≈ b + Σ w_j x_j` by ridge regression and returns the weights `w` (without the intercept `b`)
+/// together with the coefficient of determination R² on the given data.
+///
+/// Features are standardized before regularization so that `lambda` does not depend on their scales.
+/// Features that are constant over the data get a weight of 0.
+fn ridge_regression(xs: &[Vec<f64>], ys: &[f64], lambda: f64) -> (Vec<f64>, f64) {
+    let n = xs.len() as f64;
+    let k = xs[0].len();
+    let mean = |values: &mut dyn Iterator<Item = f64>| values.sum::<f64>() / n;
+    let means: Vec<f64> = (0..k).map(|j| mean(&mut xs.iter().map(|x| x[j]))).collect();
+    let stds: Vec<f64> = (0..k)
+        .map(|j| mean(&mut xs.iter().map(|x| (x[j] - means[j]).powi(2))).sqrt())
+        .collect();
+    let y_mean = mean(&mut ys.iter().copied());
+
+    // normal equations (ZᵀZ + λnI) β = Zᵀy on the standardized, centered features Z
+    let z = |x: &[f64], j: usize| {
+        if stds[j] > 0.0 {
+            (x[j] - means[j]) / stds[j]
+        } else {
+            0.0
+        }
+    };
+    let mut a = vec![vec![0.0; k]; k];
+    let mut b = vec![0.0; k];
+    for (x, y) in xs.iter().zip(ys) {
+        let zx: Vec<f64> = (0..k).map(|j| z(x, j)).collect();
+        for (i, row) in a.iter_mut().enumerate() {
+            b[i] += zx[i] * (y - y_mean);
+            for (entry, zj) in row.iter_mut().zip(&zx) {
+                *entry += zx[i] * zj;
+            }
+        }
+    }
+    for (i, row) in a.iter_mut().enumerate() {
+        // constant features are not constrained by the data: pin them to 0
+        row[i] += if stds[i] > 0.0 { lambda * n } else { 1.0 };
+    }
+    let beta = solve(a, b);
+    let weights: Vec<f64> = (0..k)
+        .map(|j| {
+            if stds[j] > 0.0 {
+                beta[j] / stds[j]
+            } else {
+                0.0
+            }
+        })
+        .collect();
+
+    let predict = |x: &[f64]| y_mean + (0..k).map(|j| beta[j] * z(x, j)).sum::<f64>();
+    let residual: f64 = xs
+        .iter()
+        .zip(ys)
+        .map(|(x, y)| (y - predict(x)).powi(2))
+        .sum();
+    let total: f64 = ys.iter().map(|y| (y - y_mean).powi(2)).sum();
+    let r2 = if total > 0.0 {
+        1.0 - residual / total
+    } else {
+        0.0
+    };
+    (weights, r2)
+}
+
+/// Solves the linear system `a x = b` by Gaussian elimination with partial pivoting.
+fn solve(mut a: Vec<Vec<f64>>, mut b: Vec<f64>) -> Vec<f64> {
+    let k = b.len();
+    for col in 0..k {
+        let pivot = (col..k)
+            .max_by(|&i, &j| a[i][col].abs().total_cmp(&a[j][col].abs()))
+            .unwrap();
+        a.swap(col, pivot);
+        b.swap(col, pivot);
+        for row in (col + 1)..k {
+            let factor = a[row][col] / a[col][col];
+            let pivot_row = a[col].clone();
+            for (entry, pivot_entry) in a[row].iter_mut().zip(pivot_row).skip(col) {
+                *entry -= factor * pivot_entry;
+            }
+            b[row] -= factor * b[col];
+        }
+    }
+    let mut x = vec![0.0; k];
+    for row in (0..k).rev() {
+        let rest: f64 = ((row + 1)..k).map(|j| a[row][j] * x[j]).sum();
+        x[row] = (b[row] - rest) / a[row][row];
+    }
+    x
+}
@@ -0,0 +1,40 @@
+//! Helpers to play full games with a given policy, as used by the tuning and training tools.
+
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+use crate::board::{Action, Board, PlayableBoard, ALL_ACTIONS};
+
+/// Returns the action leading to the afterstate with the best evaluation, or `None` if no action is applicable.
+pub fn greedy_action(board: PlayableBoard, eval: impl Fn(&Board) -> f32) -> Option<Action> {
+    ALL_ACTIONS
+        .into_iter()
+        .filter_map(|action| {
+            board
+                .apply(action)
+                .map(|after| (action, eval(after.board())))
+        })
+        .max_by(|(_, v1), (_, v2)| v1.total_cmp(v2))
+        .map(|(action, _)| action)
+}
+
+/// Plays a full game, where the random tiles are drawn from a generator seeded with `seed`,
+/// and returns the afterstates reached (one per action played).
+///
+/// Panics if the policy returns an inapplicable action.
+pub fn play_seeded(
+    seed: u64,
+    mut policy: impl FnMut(PlayableBoard) -> Option<Action>,
+) -> Vec<Board> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut board = PlayableBoard::init_with(&mut rng);
+    let mut afterstates = Vec::new();
+    while let Some(action) = policy(board) {
+        let after = board
+            .apply(action)
+            .unwrap_or_else(|| panic!("Got inapplicable action {action:?} on board\n{board}"));
+        afterstates.push(*after.board());
+        board = after.with_random_tile_with(&mut rng);
+    }
+    afterstates
+}
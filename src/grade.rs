@@ -0,0 +1,420 @@
+//! Autograder of the functions written during the lab: `select_action_greedily` and `select_action_expectimax` are
+//! run on a battery of positions with known correct answers, and a pass/fail report is printed for each exercise.
+//!
+//! The positions are the boards of random games drawn from `--seed`, so that all submissions are graded on the same
+//! ones, a few hand-made positions with a single applicable action, and reference positions with the expected values
+//! of their actions.
+//!
+//! ```text
+//! cargo run --release --bin grade
+//! ```
+//!
+//! The exit code is 0 when all checks pass and 1 otherwise, for scripts grading many submissions.
+
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+use ai_2048::board::{Action, Board, PlayableBoard, ALL_ACTIONS};
+use ai_2048::search;
+use clap::{Parser, ValueEnum};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+#[derive(Parser, Debug)]
+#[command(version, about, long_about = None)]
+struct Args {
+    /// Seed of the random games giving the positions
+    #[arg(long, default_value = "2048")]
+    seed: u64,
+
+    /// Number of positions of random games on which each function is checked
+    #[arg(short, long, default_value = "100")]
+    positions: usize,
+
+    /// Format of the report
+    #[arg(long, value_enum, default_value = "text")]
+    format: Format,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum Format {
+    Text,
+    Json,
+}
+
+/// Hand-made positions where a single action is applicable, with this action
+const SINGLE_ACTION: [([[u32; 4]; 4], Action); 2] = [
+    (
+        [[2, 4, 2, 4], [4, 2, 4, 2], [2, 4, 2, 4], [0, 0, 0, 0]],
+        Action::Down,
+    ),
+    (
+        [[0, 2, 4, 2], [0, 4, 2, 4], [0, 2, 4, 2], [0, 4, 2, 4]],
+        Action::Left,
+    ),
+];
+
+/// Value of each action, in the order of `ALL_ACTIONS`
+type ActionValues = [Option<f32>; 4];
+
+/// Positions with the expected value of each action (in the order of `ALL_ACTIONS`, `None` if not applicable) when
+/// looking two actions ahead: the average over the random tiles of the evaluation of the board reached, with the
+/// default weights. They were computed once with a reference implementation of expectimax, so that a search that is
+/// consistently wrong in `select_action_expectimax` and `evaluate_all_actions` does not pass.
+#[rustfmt::skip]
+const REFERENCE: [([[u32; 4]; 4], ActionValues); 8] = [
+    ([[0, 0, 2, 8], [0, 0, 0, 16], [0, 0, 8, 4], [0, 4, 2, 2]],
+     [Some(1585598.3), Some(1586275.0), Some(1586704.9), Some(1587442.6)]),
+    ([[0, 0, 0, 0], [0, 0, 2, 0], [8, 8, 2, 0], [4, 64, 4, 2]],
+     [Some(1513871.0), Some(1527328.6), Some(1518146.5), Some(1523022.4)]),
+    ([[2, 4, 2, 2], [2, 16, 16, 16], [0, 4, 64, 8], [0, 0, 8, 0]],
+     [Some(1441419.5), Some(1449701.9), Some(1475803.9), Some(1505072.6)]),
+    ([[8, 2, 4, 2], [64, 4, 8, 32], [8, 32, 4, 2], [0, 2, 2, 16]],
+     [None, Some(1446251.6), Some(1423119.8), Some(1437195.3)]),
+    ([[0, 0, 0, 0], [0, 0, 2, 0], [0, 0, 0, 2], [0, 0, 2, 4]],
+     [Some(1606396.1), Some(1606663.9), Some(1606816.0), Some(1607072.8)]),
+    ([[8, 16, 2, 8], [0, 4, 2, 8], [0, 2, 8, 4], [0, 0, 0, 4]],
+     [Some(1584735.9), Some(1584124.0), Some(1575373.5), None]),
+    ([[2, 4, 16, 16], [16, 8, 4, 4], [2, 4, 8, 0], [8, 16, 4, 2]],
+     [Some(1556662.6), Some(-23176976.0), Some(1545307.0), Some(1566495.4)]),
+    ([[2, 16, 0, 0], [4, 0, 0, 2], [2, 4, 16, 8], [32, 64, 8, 2]],
+     [Some(1486399.9), Some(1513339.5), Some(1518808.8), Some(1518743.4)]),
+];
+
+/// Largest number of random games played to find the positions
+const MAX_GAMES: usize = 200;
+
+/// Relative tolerance when comparing values computed by the graded functions with the expected ones
+const TOLERANCE: f32 = 1e-4;
+
+/// Result of a check of an exercise
+#[derive(serde::Serialize)]
+struct Check {
+    exercise: &'static str,
+    description: String,
+    /// Number of positions checked
+    positions: usize,
+    /// Number of positions where the answer is wrong
+    failures: usize,
+    /// First wrong answer, or the message of the panic that stopped the check
+    detail: Option<String>,
+    passed: bool,
+}
+
+/// Runs `check` on each position, stopping at the first panic (e.g. a function left as `todo!()`).
+///
+/// `check` returns a description of the error if the answer is wrong.
+fn check(
+    exercise: &'static str,
+    description: &str,
+    positions: &[PlayableBoard],
+    check: impl Fn(PlayableBoard) -> Option<String>,
+) -> Check {
+    let mut failures = 0;
+    let mut detail = None;
+    for &board in positions {
+        match catch_unwind(AssertUnwindSafe(|| check(board))) {
+            Ok(None) => {}
+            Ok(Some(error)) => {
+                failures += 1;
+                detail.get_or_insert_with(|| format!("{error} on\n{board}"));
+            }
+            Err(panic) => {
+                let message = panic
+                    .downcast_ref::<&str>()
+                    .map(|s| s.to_string())
+                    .or_else(|| panic.downcast_ref::<String>().cloned())
+                    .unwrap_or_default();
+                return Check {
+                    exercise,
+                    description: description.to_string(),
+                    positions: positions.len(),
+                    failures: positions.len(),
+                    detail: Some(format!("panicked: {message}")),
+                    passed: false,
+                };
+            }
+        }
+    }
+    Check {
+        exercise,
+        description: description.to_string(),
+        positions: positions.len(),
+        failures,
+        detail,
+        passed: failures == 0,
+    }
+}
+
+/// Positions of seeded random games
+struct Positions {
+    /// Boards from all stages of the games, on which an action is applicable
+    sampled: Vec<PlayableBoard>,
+    /// Lost boards ending the games
+    lost: Vec<PlayableBoard>,
+    /// Boards where some action surely loses the game and another one does not
+    dangerous: Vec<PlayableBoard>,
+}
+
+fn positions(seed: u64, count: usize) -> Positions {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut positions = Positions {
+        sampled: Vec::new(),
+        lost: Vec::new(),
+        dangerous: Vec::new(),
+    };
+    // dangerous positions are rare, more games are played to find enough of them
+    while positions.sampled.len() < count
+        || (positions.dangerous.len() < count / 4 && positions.lost.len() < MAX_GAMES)
+    {
+        let mut board = PlayableBoard::init_with(&mut rng);
+        loop {
+            let applicable: Vec<Action> = ALL_ACTIONS
+                .into_iter()
+                .filter(|&action| board.apply(action).is_some())
+                .collect();
+            if applicable.is_empty() {
+                positions.lost.push(board);
+                break;
+            }
+            // keep one board out of 4, for positions from all stages of the game
+            if rng.random_range(0..4) == 0 && positions.sampled.len() < count {
+                positions.sampled.push(board);
+            }
+            let doomed = doomed_actions(board).len();
+            if doomed > 0 && doomed < applicable.len() && positions.dangerous.len() < count {
+                positions.dangerous.push(board);
+            }
+            let action = applicable[rng.random_range(0..applicable.len())];
+            board = board.apply(action).unwrap().with_random_tile_with(&mut rng);
+        }
+    }
+    positions
+}
+
+/// Value of each action as an afterstate, `None` for the actions that are not applicable
+fn afterstate_values(board: PlayableBoard) -> [Option<f32>; 4] {
+    ALL_ACTIONS.map(|action| board.apply(action).map(|after| after.evaluate()))
+}
+
+/// Whether `value` is the best of `values`, within the tolerance
+fn is_best(value: f32, values: &[Option<f32>; 4]) -> bool {
+    let best = values.iter().flatten().copied().fold(f32::MIN, f32::max);
+    value >= best - TOLERANCE * best.abs().max(1.0)
+}
+
+/// Error if the values differ from the expected ones by more than the tolerance, or if they differ on which actions
+/// are applicable
+fn check_values(values: [Option<f32>; 4], expected: [Option<f32>; 4]) -> Option<String> {
+    let close = values
+        .iter()
+        .zip(&expected)
+        .all(|(value, expected)| match (value, expected) {
+            (Some(value), Some(expected)) => {
+                (value - expected).abs() <= TOLERANCE * expected.abs().max(1.0)
+            }
+            (value, expected) => value.is_none() && expected.is_none(),
+        });
+    (!close).then(|| {
+        format!("returned the values {values:?}, while the expected ones are {expected:?}")
+    })
+}
+
+/// Error if the selected action is not applicable, or if no action is selected while some are
+fn check_applicable(board: PlayableBoard, selected: Option<Action>) -> Option<String> {
+    match selected {
+        Some(action) if board.apply(action).is_none() => {
+            Some(format!("returned {action:?}, which is not applicable"))
+        }
+        None => Some("returned None while some actions are applicable".to_string()),
+        _ => None,
+    }
+}
+
+/// Error if the selected action is not the one of best value
+fn check_best(selected: Option<Action>, values: [Option<f32>; 4], what: &str) -> Option<String> {
+    let action = selected?;
+    let index = ALL_ACTIONS.iter().position(|&a| a == action)?;
+    let value = values[index]?;
+    (!is_best(value, &values)).then(|| {
+        format!("returned {action:?} of {what} {value:.1}, while the values of the actions are {values:?}")
+    })
+}
+
+/// Actions after which every random tile leads to a lost board
+fn doomed_actions(board: PlayableBoard) -> Vec<Action> {
+    ALL_ACTIONS
+        .into_iter()
+        .filter(|&action| {
+            board
+                .apply(action)
+                .is_some_and(|after| after.successors().all(|(_, next)| next.board().is_lost()))
+        })
+        .collect()
+}
+
+fn main() -> anyhow::Result<()> {
+    let args = Args::parse();
+    if args.format == Format::Json {
+        // boards in the report without the escape sequences of their colors
+        colored::control::set_override(false);
+    }
+    // panics of unfinished functions are reported in the checks, not on stderr
+    std::panic::set_hook(Box::new(|_| {}));
+
+    let Positions {
+        sampled: positions,
+        lost,
+        dangerous,
+    } = positions(args.seed, args.positions);
+    let single: Vec<(PlayableBoard, Action)> = SINGLE_ACTION
+        .iter()
+        .map(|&(values, action)| Ok((Board::from_values(values)?.into(), action)))
+        .collect::<anyhow::Result<_>>()?;
+    let single_boards: Vec<PlayableBoard> = single.iter().map(|&(board, _)| board).collect();
+    let single_action = |board: PlayableBoard| {
+        single
+            .iter()
+            .find(|&&(b, _)| b == board)
+            .map(|&(_, action)| action)
+            .expect("a hand-made position")
+    };
+    let reference: Vec<(PlayableBoard, ActionValues)> = REFERENCE
+        .iter()
+        .map(|&(values, expected)| Ok((Board::from_values(values)?.into(), expected)))
+        .collect::<anyhow::Result<_>>()?;
+    let reference_boards: Vec<PlayableBoard> = reference.iter().map(|&(board, _)| board).collect();
+    let reference_values = |board: PlayableBoard| {
+        reference
+            .iter()
+            .find(|&&(b, _)| b == board)
+            .map(|&(_, expected)| expected)
+            .expect("a reference position")
+    };
+    let greedy = search::select_action_greedily;
+    let expectimax = |depth| move |board| search::select_action_expectimax(board, depth);
+    let checks = vec![
+        check(
+            "greedy",
+            "returns an applicable action",
+            &positions,
+            |board| check_applicable(board, greedy(board)),
+        ),
+        check("greedy", "returns None on lost boards", &lost, |board| {
+            greedy(board).map(|action| format!("returned {action:?}"))
+        }),
+        check(
+            "greedy",
+            "plays the only applicable action",
+            &single_boards,
+            |board| {
+                let expected = single_action(board);
+                (greedy(board) != Some(expected)).then(|| format!("did not return {expected:?}"))
+            },
+        ),
+        check(
+            "greedy",
+            "selects the action of best afterstate value",
+            &positions,
+            |board| check_best(greedy(board), afterstate_values(board), "afterstate value"),
+        ),
+        check(
+            "expectimax",
+            "returns an applicable action (depth 2)",
+            &positions,
+            |board| check_applicable(board, expectimax(2)(board)),
+        ),
+        check(
+            "expectimax",
+            "returns None on lost boards (depth 2)",
+            &lost,
+            |board| expectimax(2)(board).map(|action| format!("returned {action:?}")),
+        ),
+        check(
+            "expectimax",
+            "plays the only applicable action (depth 2)",
+            &single_boards,
+            |board| {
+                let expected = single_action(board);
+                (expectimax(2)(board) != Some(expected))
+                    .then(|| format!("did not return {expected:?}"))
+            },
+        ),
+        check(
+            "expectimax",
+            "looking one action ahead, selects the action of best afterstate value",
+            &positions,
+            |board| {
+                check_best(
+                    expectimax(1)(board),
+                    afterstate_values(board),
+                    "afterstate value",
+                )
+            },
+        ),
+        check(
+            "expectimax",
+            "avoids the actions after which any new tile loses the game (depth 2)",
+            &dangerous,
+            |board| {
+                let selected = expectimax(2)(board)?;
+                doomed_actions(board)
+                    .contains(&selected)
+                    .then(|| format!("returned {selected:?}, after which the game is lost"))
+            },
+        ),
+        check(
+            "expectimax",
+            "computes the expected values of the actions of the reference positions (depth 2)",
+            &reference_boards,
+            |board| {
+                check_values(
+                    search::evaluate_all_actions(board, 2),
+                    reference_values(board),
+                )
+            },
+        ),
+        check(
+            "expectimax",
+            "selects the action of best expected value on the reference positions (depth 2)",
+            &reference_boards,
+            |board| {
+                check_best(
+                    expectimax(2)(board),
+                    reference_values(board),
+                    "expected value",
+                )
+            },
+        ),
+    ];
+
+    let passed = checks.iter().filter(|check| check.passed).count();
+    match args.format {
+        Format::Json => println!("{:#}", serde_json::to_value(&checks)?),
+        Format::Text => {
+            let mut exercise = "";
+            for check in &checks {
+                if check.exercise != exercise {
+                    exercise = check.exercise;
+                    println!("\n{exercise}");
+                }
+                let status = if check.passed { "PASS" } else { "FAIL" };
+                println!(
+                    "  [{status}] {} ({}/{} positions)",
+                    check.description,
+                    check.positions - check.failures,
+                    check.positions
+                );
+                if let Some(detail) = &check.detail {
+                    for line in detail.lines() {
+                        println!("         {line}");
+                    }
+                }
+            }
+            println!("\n{passed}/{} checks passed", checks.len());
+        }
+    }
+    if passed < checks.len() {
+        std::process::exit(1);
+    }
+    Ok(())
+}
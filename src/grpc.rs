@@ -0,0 +1,379 @@
+//! gRPC service of the engine, with the typed contract of `proto/ai2048.proto`, for course infrastructure in other
+//! languages (served by `main serve` under `/ai2048.Agent/`).
+//!
+//! The service is served on the port of the JSON endpoints, over HTTP/2 to the standard gRPC clients (`grpcurl`,
+//! `tonic`, `grpc-go`, `grpcio`...) and as gRPC-Web over HTTP/1.1 to the clients of the gRPC-Web tools in browsers.
+//! The messages, the service and a client (`proto::agent_client`) are generated from the proto file by `build.rs`.
+//!
+//! ```text
+//! grpcurl -plaintext -import-path proto -proto ai2048.proto \
+//!     -d '{"board": {"tiles": [2, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]}}' 127.0.0.1:8048 ai2048.Agent/GetMove
+//! ```
+//!
+//! As for the JSON endpoints, the strategies of the requests are limited by `server::remote_strategy`.
+
+use std::pin::Pin;
+use std::sync::Arc;
+
+use futures_util::{stream, Stream};
+use tokio::sync::{mpsc, Semaphore};
+use tonic::{Request, Response, Status};
+use tonic_web::GrpcWebLayer;
+use tower::Layer;
+
+use crate::board::{Action, Board, PlayableBoard, ALL_ACTIONS, N};
+use crate::engine;
+use crate::savegame::GameInProgress;
+use crate::server::{remote_budget, remote_strategy, run_engine, MAX_BODY};
+use crate::strategy::Strategy;
+
+/// Messages, service and client generated from `proto/ai2048.proto`
+pub mod proto {
+    tonic::include_proto!("ai2048");
+}
+
+/// Message of the calls during which the engine panicked (e.g. on a function left as `todo!()`)
+const ENGINE_FAILED: &str = "The engine failed, see the terminal of the server";
+
+/// Implementation of the service, with the strategy of the server unless a request gives another one
+struct Agent {
+    strategy: Strategy,
+    /// Searches allowed to run at the same time, shared with the JSON endpoints
+    engine: Arc<Semaphore>,
+}
+
+/// Routes of the service, for both gRPC and gRPC-Web.
+pub(crate) fn routes(strategy: Strategy, engine: Arc<Semaphore>) -> axum::Router {
+    let service = proto::agent_server::AgentServer::new(Agent { strategy, engine })
+        .max_decoding_message_size(MAX_BODY);
+    tonic::service::Routes::new(GrpcWebLayer::new().layer(service)).into_axum_router()
+}
+
+impl Agent {
+    /// Strategy given by a request, or that of the server
+    fn strategy(&self, name: Option<String>) -> Result<Strategy, Status> {
+        match name {
+            Some(name) => remote_strategy(&name).map_err(invalid),
+            None => Ok(self.strategy),
+        }
+    }
+
+    async fn run<R: Send + 'static>(
+        &self,
+        f: impl FnOnce() -> R + Send + 'static,
+    ) -> Result<R, Status> {
+        run_engine(&self.engine, f)
+            .await
+            .ok_or_else(|| Status::internal(ENGINE_FAILED))
+    }
+}
+
+fn invalid(e: anyhow::Error) -> Status {
+    Status::invalid_argument(format!("{e:#}"))
+}
+
+#[tonic::async_trait]
+impl proto::agent_server::Agent for Agent {
+    async fn get_move(
+        &self,
+        request: Request<proto::GetMoveRequest>,
+    ) -> Result<Response<proto::GetMoveResponse>, Status> {
+        let request = request.into_inner();
+        let board = board(request.board)?;
+        let budget = remote_budget(request.budget_ms);
+        let strategy = self.strategy(request.strategy)?;
+        let response = self
+            .run(move || engine::best_move(board, Some(budget), &strategy))
+            .await?
+            .map_err(invalid)?;
+        Ok(Response::new(proto::GetMoveResponse {
+            action: action_number(response.action),
+            value: response.value,
+            time_ms: response.time_ms,
+        }))
+    }
+
+    async fn evaluate_position(
+        &self,
+        request: Request<proto::EvaluatePositionRequest>,
+    ) -> Result<Response<proto::EvaluatePositionResponse>, Status> {
+        let request = request.into_inner();
+        let board = board(request.board)?;
+        let strategy = self.strategy(request.strategy)?;
+        let values = self
+            .run(move || strategy.evaluate_all_actions(board))
+            .await?;
+        let actions = ALL_ACTIONS
+            .into_iter()
+            .zip(values)
+            .filter_map(|(action, value)| {
+                Some(proto::ActionValue {
+                    action: action_number(Some(action)),
+                    value: value?,
+                })
+            })
+            .collect();
+        Ok(Response::new(proto::EvaluatePositionResponse {
+            value: board.evaluate(),
+            lost: board.board().is_lost(),
+            actions,
+        }))
+    }
+
+    type PlayGameStream = Pin<Box<dyn Stream<Item = Result<proto::GameState, Status>> + Send>>;
+
+    async fn play_game(
+        &self,
+        request: Request<proto::PlayGameRequest>,
+    ) -> Result<Response<Self::PlayGameStream>, Status> {
+        let request = request.into_inner();
+        let seed = request.seed.unwrap_or_else(rand::random);
+        let budget = remote_budget(request.budget_ms);
+        let strategy = self.strategy(request.strategy)?;
+        let (sender, receiver) = mpsc::channel(16);
+        let engine = self.engine.clone();
+        tokio::spawn(async move {
+            let states = sender.clone();
+            let played = run_engine(&engine, move || {
+                let mut game = GameInProgress::new(seed);
+                let mut action = None;
+                // the game is left when the client disconnects
+                while states.blocking_send(Ok(game_state(&game, action))).is_ok() {
+                    let Some(next) = strategy.select_action_within(*game.board(), budget) else {
+                        break;
+                    };
+                    if game.play(next).is_none() {
+                        return Err(Status::internal(
+                            "The strategy selected an inapplicable action",
+                        ));
+                    }
+                    action = Some(next);
+                }
+                Ok(())
+            })
+            .await;
+            let status = match played {
+                Some(Ok(())) => return,
+                Some(Err(status)) => status,
+                None => Status::internal(ENGINE_FAILED),
+            };
+            let _ = sender.send(Err(status)).await;
+        });
+        let states = stream::unfold(receiver, |mut receiver| async move {
+            Some((receiver.recv().await?, receiver))
+        });
+        Ok(Response::new(Box::pin(states)))
+    }
+}
+
+fn game_state(game: &GameInProgress, action: Option<Action>) -> proto::GameState {
+    let board = game.board().board();
+    proto::GameState {
+        board: Some(board_message(board)),
+        score: game.merge_score(),
+        num_moves: game.num_moves() as u32,
+        action: action_number(action),
+        lost: board.is_lost(),
+        seed: game.seed(),
+    }
+}
+
+/// Number of the action in the `Action` enum of the proto
+fn action_number(action: Option<Action>) -> i32 {
+    let action = match action {
+        None => proto::Action::None,
+        Some(Action::Up) => proto::Action::Up,
+        Some(Action::Down) => proto::Action::Down,
+        Some(Action::Left) => proto::Action::Left,
+        Some(Action::Right) => proto::Action::Right,
+    };
+    action as i32
+}
+
+fn board_message(board: &Board) -> proto::Board {
+    proto::Board {
+        tiles: board.values().into_iter().flatten().collect(),
+    }
+}
+
+/// Board of a request
+fn board(board: Option<proto::Board>) -> Result<PlayableBoard, Status> {
+    let tiles = board
+        .ok_or_else(|| Status::invalid_argument("Missing board"))?
+        .tiles;
+    if tiles.len() != N * N {
+        return Err(Status::invalid_argument(format!(
+            "Expected {} tiles, got {}",
+            N * N,
+            tiles.len()
+        )));
+    }
+    let mut values = [[0; N]; N];
+    for (i, tile) in tiles.into_iter().enumerate() {
+        values[i / N][i % N] = tile;
+    }
+    Ok(Board::from_values(values).map_err(invalid)?.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Write};
+    use std::net::{SocketAddr, TcpListener, TcpStream};
+
+    use tonic::Code;
+
+    use super::proto::agent_client::AgentClient;
+    use super::*;
+    use crate::server;
+
+    /// Starts a server with the random strategy, returning its address.
+    fn start() -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        std::thread::spawn(move || server::serve(listener, Strategy::Random));
+        addr
+    }
+
+    fn get_move(board: Option<proto::Board>, strategy: Option<&str>) -> proto::GetMoveRequest {
+        proto::GetMoveRequest {
+            board,
+            budget_ms: None,
+            strategy: strategy.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_grpc() {
+        let addr = start();
+        let board =
+            Board::from_values([[2, 2, 0, 4], [0, 0, 0, 0], [0, 0, 0, 0], [0, 0, 0, 0]]).unwrap();
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        runtime.block_on(async {
+            // a standard gRPC client, over HTTP/2
+            let channel = tonic::transport::Channel::from_shared(format!("http://{addr}"))
+                .unwrap()
+                .connect()
+                .await
+                .unwrap();
+            let mut client = AgentClient::new(channel);
+
+            let response = client
+                .get_move(get_move(Some(board_message(&board)), None))
+                .await
+                .unwrap()
+                .into_inner();
+            assert!((1..=4).contains(&response.action));
+            assert!(response.value.is_some());
+
+            let response = client
+                .evaluate_position(proto::EvaluatePositionRequest {
+                    board: Some(board_message(&board)),
+                    strategy: None,
+                })
+                .await
+                .unwrap()
+                .into_inner();
+            // all actions but Up are applicable
+            assert_eq!(response.actions.len(), 3);
+            assert!(!response.lost);
+
+            let mut states = client
+                .play_game(proto::PlayGameRequest {
+                    seed: Some(3),
+                    budget_ms: None,
+                    strategy: None,
+                })
+                .await
+                .unwrap()
+                .into_inner();
+            let first = states.message().await.unwrap().unwrap();
+            assert_eq!(
+                first.board,
+                Some(board_message(GameInProgress::new(3).board().board()))
+            );
+            assert_eq!(first.action, proto::Action::None as i32);
+            let mut last = first;
+            while let Some(state) = states.message().await.unwrap() {
+                assert_eq!(state.num_moves, last.num_moves + 1);
+                last = state;
+            }
+            assert!(last.lost);
+            assert_eq!(last.seed, 3);
+
+            // invalid arguments, including the strategies refused over the network
+            let mut tiles = board_message(&board);
+            tiles.tiles.pop();
+            for request in [
+                get_move(None, None),
+                get_move(Some(tiles), None),
+                get_move(Some(board_message(&board)), Some("expectimax:depth=99")),
+                get_move(
+                    Some(board_message(&board)),
+                    Some("distilled:file=/etc/passwd"),
+                ),
+            ] {
+                let status = client.get_move(request).await.unwrap_err();
+                assert_eq!(status.code(), Code::InvalidArgument);
+            }
+        });
+    }
+
+    /// Code of the `grpc-status` of a raw response
+    fn grpc_status(response: &[u8]) -> u32 {
+        let response = String::from_utf8_lossy(response).to_ascii_lowercase();
+        let (_, status) = response.split_once("grpc-status:").expect("grpc-status");
+        let status = status.trim_start();
+        let end = status.find(|c: char| !c.is_ascii_digit()).unwrap();
+        status[..end].parse().unwrap()
+    }
+
+    #[test]
+    fn test_grpc_web_framing() {
+        let addr = start();
+        let call = |method: &str, body: &[u8]| {
+            let mut stream = TcpStream::connect(addr).unwrap();
+            write!(
+                stream,
+                "POST /ai2048.Agent/{method} HTTP/1.1\r\nContent-Type: application/grpc-web+proto\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                body.len()
+            )
+            .unwrap();
+            stream.write_all(body).unwrap();
+            let mut response = Vec::new();
+            stream.read_to_end(&mut response).unwrap();
+            assert!(response.starts_with(b"HTTP/1.1 200"));
+            grpc_status(&response)
+        };
+        let frame = |flag: u8, message: &[u8]| {
+            let mut frame = vec![flag];
+            frame.extend((message.len() as u32).to_be_bytes());
+            frame.extend(message);
+            frame
+        };
+        let request = prost::Message::encode_to_vec(&get_move(
+            Some(proto::Board {
+                tiles: vec![2, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+            }),
+            None,
+        ));
+
+        assert_eq!(call("GetMove", &frame(0, &request)), Code::Ok as u32);
+        assert_eq!(
+            call("Resign", &frame(0, &request)),
+            Code::Unimplemented as u32
+        );
+        // frame shorter than its length
+        let mut truncated = frame(0, &request);
+        truncated.truncate(truncated.len() - 3);
+        assert_ne!(call("GetMove", &truncated), Code::Ok as u32);
+        // compressed without any compression negotiated
+        assert_ne!(call("GetMove", &frame(1, &request)), Code::Ok as u32);
+        // not a protobuf message
+        assert_ne!(call("GetMove", &frame(0, &[0xff; 8])), Code::Ok as u32);
+        // message over the limit
+        assert_eq!(
+            call("GetMove", &frame(0, &vec![0; MAX_BODY + 1])),
+            Code::OutOfRange as u32
+        );
+    }
+}
@@ -0,0 +1,534 @@
+//! Desktop window of the game (`main gui`): the board with the classic colors, played with the arrow keys or by the
+//! AI, with hints and a live panel of the evaluation and of the statistics of the game.
+//!
+//! The window is native, drawn by egui through eframe. The AI decides on a thread of its own, so that the window
+//! stays responsive during long searches: a search that panics (e.g. on a function left as `todo!()`) is reported in
+//! the window, and the game goes on. It does not depend on the colors of the terminal.
+
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::time::{Duration, Instant};
+
+use eframe::egui::{self, Align2, Color32, FontId, Key, Sense, TextEdit, Vec2};
+
+use crate::board::{Action, PlayableBoard, ALL_ACTIONS, N};
+use crate::eval;
+use crate::savegame::GameInProgress;
+use crate::strategy::Strategy;
+
+/// Side of a tile and gap between two tiles, in points
+const TILE: f32 = 96.0;
+const GAP: f32 = 12.0;
+
+/// Pauses after each move of the auto-play offered by the window, in milliseconds
+const DELAYS: [u64; 3] = [0, 100, 500];
+
+/// Keys playing each action
+const KEYS: [(Key, Action); 8] = [
+    (Key::ArrowUp, Action::Up),
+    (Key::ArrowDown, Action::Down),
+    (Key::ArrowLeft, Action::Left),
+    (Key::ArrowRight, Action::Right),
+    (Key::W, Action::Up),
+    (Key::S, Action::Down),
+    (Key::A, Action::Left),
+    (Key::D, Action::Right),
+];
+
+/// Opens the window, with `strategy` for the hints and the auto-play unless the window gives another one, and returns
+/// when it is closed.
+pub fn run(strategy: Strategy) -> anyhow::Result<()> {
+    let options = eframe::NativeOptions {
+        viewport: egui::ViewportBuilder::default().with_inner_size([960.0, 640.0]),
+        ..Default::default()
+    };
+    eframe::run_native(
+        "2048",
+        options,
+        Box::new(move |_| Ok(Box::new(Window::new(strategy, rand::random())))),
+    )
+    .map_err(|e| anyhow::anyhow!("Cannot open the window: {e}"))
+}
+
+/// Decision of the AI, made on a thread of its own
+struct Decision {
+    action: Option<Action>,
+    time: Duration,
+    /// Whether the action is to be played (auto-play) rather than shown (hint)
+    play: bool,
+}
+
+/// State of the window
+struct Window {
+    game: GameInProgress,
+    /// Seed of the next game as typed, random when empty
+    seed: String,
+    /// Strategy as typed, `strategy` when empty
+    strategy_name: String,
+    strategy: Strategy,
+    auto: bool,
+    /// Pause after each move of the auto-play, in milliseconds
+    delay: u64,
+    /// End of the pause after the last move of the auto-play
+    next_move: Instant,
+    /// Decision being made, during which the game is not played
+    pending: Option<Receiver<Decision>>,
+    /// Times of the decisions of the AI in this game
+    decisions: Vec<Duration>,
+    status: String,
+}
+
+impl eframe::App for Window {
+    fn ui(&mut self, ui: &mut egui::Ui, _frame: &mut eframe::Frame) {
+        self.show(ui);
+    }
+}
+
+impl Window {
+    fn new(strategy: Strategy, seed: u64) -> Window {
+        Window {
+            game: GameInProgress::new(seed),
+            seed: String::new(),
+            strategy_name: String::new(),
+            strategy,
+            auto: false,
+            delay: DELAYS[1],
+            next_move: Instant::now(),
+            pending: None,
+            decisions: Vec::new(),
+            status: "Play with the arrow keys".to_string(),
+        }
+    }
+
+    /// Starts a game with the seed typed, if it is valid.
+    fn new_game(&mut self) {
+        let seed = self.seed.trim();
+        let seed = if seed.is_empty() {
+            rand::random()
+        } else {
+            match seed.parse() {
+                Ok(seed) => seed,
+                Err(_) => {
+                    self.status = format!("Invalid seed: {seed}");
+                    return;
+                }
+            }
+        };
+        self.game = GameInProgress::new(seed);
+        self.auto = false;
+        // the decision of the previous game is dropped when it comes
+        self.pending = None;
+        self.decisions.clear();
+        self.status = "Play with the arrow keys".to_string();
+    }
+
+    fn lost(&self) -> bool {
+        self.game.board().board().is_lost()
+    }
+
+    fn play(&mut self, action: Action) {
+        if self.lost() {
+            return;
+        }
+        if self.game.play(action).is_none() {
+            // an action that moves no tile is not an error for the player
+            self.status = format!("{action:?} does not move any tile");
+            return;
+        }
+        self.status = format!("{action:?}");
+        if self.lost() {
+            self.auto = false;
+            self.status = format!("Game over: score {}", self.game.merge_score());
+        }
+    }
+
+    /// Asks the AI for its action on the board, to play it or only to show it.
+    fn decide(&mut self, play: bool) {
+        if self.pending.is_some() || self.lost() {
+            return;
+        }
+        let name = self.strategy_name.trim();
+        let strategy = if name.is_empty() {
+            Ok(self.strategy)
+        } else {
+            name.parse::<Strategy>()
+        };
+        let strategy = match strategy {
+            Ok(strategy) => strategy,
+            Err(e) => {
+                self.auto = false;
+                self.status = format!("Error: {e:#}");
+                return;
+            }
+        };
+        let board = *self.game.board();
+        let (sender, receiver) = mpsc::channel();
+        std::thread::spawn(move || {
+            let start = Instant::now();
+            let action = strategy.select_action(board);
+            let time = start.elapsed();
+            let _ = sender.send(Decision { action, time, play });
+        });
+        self.pending = Some(receiver);
+    }
+
+    /// Takes the decision of the AI if it is made, and starts the next one of the auto-play when it is time.
+    fn poll(&mut self) {
+        if let Some(pending) = &self.pending {
+            match pending.try_recv() {
+                Ok(decision) => {
+                    self.pending = None;
+                    self.decisions.push(decision.time);
+                    self.decided(decision);
+                }
+                Err(TryRecvError::Empty) => {}
+                Err(TryRecvError::Disconnected) => {
+                    self.pending = None;
+                    self.auto = false;
+                    self.status = "Error: the engine failed, see the terminal".to_string();
+                }
+            }
+        }
+        if self.auto && self.pending.is_none() && Instant::now() >= self.next_move {
+            self.decide(true);
+        }
+    }
+
+    fn decided(&mut self, decision: Decision) {
+        let time_ms = decision.time.as_secs_f64() * 1000.0;
+        match decision.action {
+            Some(action) if decision.play => {
+                self.play(action);
+                self.next_move = Instant::now() + Duration::from_millis(self.delay);
+            }
+            Some(action) => {
+                let value = self
+                    .game
+                    .board()
+                    .apply(action)
+                    .map_or(f32::NAN, |after| after.evaluate());
+                self.status =
+                    format!("Hint: {action:?} (afterstate value {value:.1}, {time_ms:.2} ms)");
+            }
+            None => self.auto = false,
+        }
+    }
+
+    /// Handles the input and draws the window.
+    fn show(&mut self, ui: &mut egui::Ui) {
+        self.poll();
+        // the letters are those typed in the seed or the strategy
+        if !ui.ctx().egui_wants_keyboard_input() && self.pending.is_none() {
+            for (key, action) in KEYS {
+                if ui.input(|input| input.key_pressed(key)) {
+                    self.auto = false;
+                    self.play(action);
+                }
+            }
+        }
+        egui::Panel::right("panel").show(ui, |ui| self.show_panel(ui));
+        egui::CentralPanel::default().show(ui, |ui| {
+            ui.heading("2048");
+            ui.horizontal(|ui| {
+                if ui.button("New game").clicked() {
+                    self.new_game();
+                }
+                ui.add(
+                    TextEdit::singleline(&mut self.seed)
+                        .hint_text("seed (random)")
+                        .desired_width(120.0),
+                );
+            });
+            ui.horizontal(|ui| {
+                if ui.button("Hint").clicked() {
+                    self.decide(false);
+                }
+                if ui.toggle_value(&mut self.auto, "Auto-play").clicked() && self.auto {
+                    self.status = "Auto-play (click again to stop)".to_string();
+                }
+                egui::ComboBox::from_id_salt("delay")
+                    .selected_text(delay_name(self.delay))
+                    .show_ui(ui, |ui| {
+                        for delay in DELAYS {
+                            ui.selectable_value(&mut self.delay, delay, delay_name(delay));
+                        }
+                    });
+                ui.add(
+                    TextEdit::singleline(&mut self.strategy_name)
+                        .hint_text(self.strategy.to_string())
+                        .desired_width(180.0),
+                );
+            });
+            ui.label(&self.status);
+            draw_board(ui, &self.game);
+        });
+        if self.auto || self.pending.is_some() {
+            ui.ctx().request_repaint_after(Duration::from_millis(10));
+        }
+    }
+
+    /// Statistics of the game, evaluation of the board and values of the actions
+    fn show_panel(&self, ui: &mut egui::Ui) {
+        let board = self.game.board();
+        ui.heading("Game");
+        let mean = match self.decisions.len() {
+            0 => "-".to_string(),
+            n => format!(
+                "{:.2} ms",
+                self.decisions.iter().sum::<Duration>().as_secs_f64() * 1000.0 / n as f64
+            ),
+        };
+        let max_tile = board.board().values().into_iter().flatten().max();
+        egui::Grid::new("game").show(ui, |ui| {
+            for (name, value) in [
+                ("seed", self.game.seed().to_string()),
+                ("moves", self.game.num_moves().to_string()),
+                ("score", self.game.merge_score().to_string()),
+                ("max tile", max_tile.unwrap_or(0).to_string()),
+                ("AI decisions", self.decisions.len().to_string()),
+                ("mean decision time", mean),
+            ] {
+                ui.label(name);
+                ui.label(value);
+                ui.end_row();
+            }
+        });
+
+        ui.heading("Evaluation");
+        let breakdown = eval::explain(board.board());
+        egui::Grid::new("evaluation").striped(true).show(ui, |ui| {
+            for header in ["heuristic", "raw", "weight", "contribution"] {
+                ui.strong(header);
+            }
+            ui.end_row();
+            for term in &breakdown.terms {
+                ui.label(term.name);
+                ui.label(format!("{:.1}", term.raw));
+                ui.label(format!("{:.1}", term.weight));
+                ui.label(format!("{:.1}", term.contribution));
+                ui.end_row();
+            }
+            ui.label("base");
+            ui.label("");
+            ui.label("");
+            ui.label(format!("{:.1}", breakdown.base));
+            ui.end_row();
+            ui.strong("total");
+            ui.label("");
+            ui.label("");
+            ui.strong(format!("{:.1}", breakdown.total()));
+            ui.end_row();
+        });
+
+        ui.heading("Actions");
+        egui::Grid::new("actions").striped(true).show(ui, |ui| {
+            for header in ["action", "score", "afterstate"] {
+                ui.strong(header);
+            }
+            ui.end_row();
+            for (action, score, value) in action_values(board) {
+                ui.label(format!("{action:?}"));
+                ui.label(score.to_string());
+                ui.label(format!("{value:.1}"));
+                ui.end_row();
+            }
+        });
+    }
+}
+
+/// Score and afterstate value of each applicable action
+fn action_values(board: &PlayableBoard) -> Vec<(Action, u32, f32)> {
+    ALL_ACTIONS
+        .into_iter()
+        .filter_map(|action| {
+            let (after, score) = board.apply_scored(action)?;
+            Some((action, score, after.evaluate()))
+        })
+        .collect()
+}
+
+fn delay_name(delay: u64) -> String {
+    match delay {
+        0 => "no delay".to_string(),
+        _ => format!("{delay} ms"),
+    }
+}
+
+fn draw_board(ui: &mut egui::Ui, game: &GameInProgress) {
+    let side = N as f32 * (TILE + GAP) + GAP;
+    let (rect, _) = ui.allocate_exact_size(Vec2::splat(side), Sense::hover());
+    let painter = ui.painter_at(rect);
+    painter.rect_filled(rect, 6.0, rgb(0xbbada0));
+    for (i, row) in game.board().board().values().into_iter().enumerate() {
+        for (j, value) in row.into_iter().enumerate() {
+            let min =
+                rect.min + Vec2::new(GAP + j as f32 * (TILE + GAP), GAP + i as f32 * (TILE + GAP));
+            let tile = egui::Rect::from_min_size(min, Vec2::splat(TILE));
+            let (background, color) = tile_colors(value);
+            painter.rect_filled(tile, 4.0, background);
+            if value != 0 {
+                painter.text(
+                    tile.center(),
+                    Align2::CENTER_CENTER,
+                    value.to_string(),
+                    FontId::proportional(font_size(value)),
+                    color,
+                );
+            }
+        }
+    }
+}
+
+/// Colors of the background and of the text of a tile, those of the original game
+fn tile_colors(value: u32) -> (Color32, Color32) {
+    let (dark, light) = (rgb(0x776e65), rgb(0xf9f6f2));
+    match value {
+        0 => (rgb(0xcdc1b4), dark),
+        2 => (rgb(0xeee4da), dark),
+        4 => (rgb(0xede0c8), dark),
+        8 => (rgb(0xf2b179), light),
+        16 => (rgb(0xf59563), light),
+        32 => (rgb(0xf67c5f), light),
+        64 => (rgb(0xf65e3b), light),
+        128 => (rgb(0xedcf72), light),
+        256 => (rgb(0xedcc61), light),
+        512 => (rgb(0xedc850), light),
+        1024 => (rgb(0xedc53f), light),
+        2048 => (rgb(0xedc22e), light),
+        _ => (rgb(0x3c3a32), light),
+    }
+}
+
+/// Size of the text of a tile, smaller for the tiles of more digits
+fn font_size(value: u32) -> f32 {
+    match value {
+        ..100 => 48.0,
+        100..1000 => 40.0,
+        1000..10000 => 32.0,
+        _ => 24.0,
+    }
+}
+
+fn rgb(rgb: u32) -> Color32 {
+    let [_, r, g, b] = rgb.to_be_bytes();
+    Color32::from_rgb(r, g, b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Runs a frame of the window without a display, with the keys pressed.
+    fn frame(ctx: &egui::Context, window: &mut Window, keys: &[Key]) -> egui::FullOutput {
+        let input = egui::RawInput {
+            events: keys
+                .iter()
+                .map(|&key| egui::Event::Key {
+                    key,
+                    physical_key: None,
+                    pressed: true,
+                    repeat: false,
+                    modifiers: Default::default(),
+                })
+                .collect(),
+            ..Default::default()
+        };
+        let mut output = ctx.run_ui(input, |ui| window.show(ui));
+        // no renderer takes the fonts
+        output.textures_delta.clear();
+        output
+    }
+
+    /// Runs frames until the decision of the AI is taken.
+    fn wait(ctx: &egui::Context, window: &mut Window) {
+        let start = Instant::now();
+        while window.pending.is_some() {
+            assert!(start.elapsed() < Duration::from_secs(10), "no decision");
+            std::thread::sleep(Duration::from_millis(1));
+            frame(ctx, window, &[]);
+        }
+    }
+
+    #[test]
+    fn test_gui() {
+        let ctx = egui::Context::default();
+        let mut window = Window::new(Strategy::Random, 3);
+        let output = frame(&ctx, &mut window, &[]);
+        assert!(!output.shapes.is_empty());
+
+        // the keys play the actions, those moving no tile being reported
+        let (key, action) = match window.game.board().apply(Action::Left) {
+            Some(_) => (Key::ArrowLeft, Action::Left),
+            None => (Key::D, Action::Right),
+        };
+        let mut expected = GameInProgress::new(3);
+        expected.play(action).unwrap();
+        frame(&ctx, &mut window, &[key]);
+        assert_eq!(window.game.board().board(), expected.board().board());
+        assert_eq!(window.status, format!("{action:?}"));
+        let blocked = ALL_ACTIONS
+            .into_iter()
+            .find(|&action| window.game.board().apply(action).is_none());
+        if let Some(blocked) = blocked {
+            window.play(blocked);
+            assert!(window.status.contains("does not move"));
+            assert_eq!(window.game.num_moves(), 1);
+        }
+
+        window.decide(false);
+        wait(&ctx, &mut window);
+        assert!(window.status.starts_with("Hint:"), "{}", window.status);
+        assert_eq!(window.game.num_moves(), 1);
+
+        // the auto-play goes on until the end of the game
+        window.auto = true;
+        window.delay = 0;
+        while window.auto {
+            wait(&ctx, &mut window);
+            frame(&ctx, &mut window, &[]);
+        }
+        assert!(window.lost());
+        assert!(window.status.starts_with("Game over"));
+        assert_eq!(window.decisions.len(), window.game.num_moves());
+
+        window.seed = "42".to_string();
+        window.new_game();
+        assert_eq!(window.game.seed(), 42);
+        assert!(window.decisions.is_empty());
+    }
+
+    #[test]
+    fn test_gui_errors() {
+        let ctx = egui::Context::default();
+        let mut window = Window::new(Strategy::Random, 3);
+        window.seed = "not a seed".to_string();
+        window.new_game();
+        assert!(window.status.starts_with("Invalid seed"));
+        assert_eq!(window.game.seed(), 3);
+
+        window.strategy_name = "mcts".to_string();
+        window.auto = true;
+        frame(&ctx, &mut window, &[]);
+        assert!(window.status.starts_with("Error"));
+        assert!(!window.auto && window.pending.is_none());
+
+        // a search that panics leaves the window usable
+        let (sender, receiver) = mpsc::channel::<Decision>();
+        drop(sender);
+        window.pending = Some(receiver);
+        window.auto = true;
+        frame(&ctx, &mut window, &[]);
+        assert!(window.status.contains("engine failed"));
+        assert!(!window.auto && window.pending.is_none());
+        frame(&ctx, &mut window, &[Key::ArrowUp, Key::ArrowDown]);
+        assert!(window.game.num_moves() > 0);
+    }
+
+    #[test]
+    fn test_tile_colors() {
+        assert_eq!(tile_colors(0).0, rgb(0xcdc1b4));
+        assert_eq!(tile_colors(4096), tile_colors(1 << 16));
+        assert_ne!(tile_colors(2048), tile_colors(4096));
+        assert_eq!(rgb(0x776e65), Color32::from_rgb(0x77, 0x6e, 0x65));
+        assert!(font_size(8) > font_size(128) && font_size(1024) > font_size(16384));
+    }
+}
@@ -0,0 +1,158 @@
+//! Interactive game, where the player picks the actions with the arrow keys (or WASD).
+//!
+//! The `h` key asks a strategy for a hint: the action it selects and the value it gives to each action, to compare
+//! one's intuition with the AI.
+//!
+//! The terminal is put in raw mode for the duration of the game, so that each key press is received immediately.
+
+use std::io::{stdout, Write};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use crossterm::cursor::MoveTo;
+use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use crossterm::queue;
+use crossterm::terminal::{self, Clear, ClearType};
+
+use crate::animation;
+use crate::board::{Action, PlayableBoard, ALL_ACTIONS};
+use crate::savegame::GameInProgress;
+use crate::strategy::Strategy;
+
+/// Duration of the animation of the tiles sliding after each move
+const SLIDE: Duration = Duration::from_millis(100);
+
+/// Plays the game in the terminal until it is lost or the player quits (`q` or `Esc`).
+///
+/// With `save`, the game is saved to this file after each move. Quitting with Ctrl-C saves the game, by default to
+/// `game-<seed>.json`. Hints are given by `strategy`.
+pub fn play(game: GameInProgress, save: Option<&Path>, strategy: &Strategy) -> anyhow::Result<()> {
+    terminal::enable_raw_mode()?;
+    let result = run(&mut stdout(), game, save, strategy);
+    // restore the terminal even if the game failed
+    terminal::disable_raw_mode()?;
+    if let Some(game) = result? {
+        let path = game.save_stopped(save)?;
+        println!(
+            "Game saved to {0}, continue it with `--load {0}`",
+            path.display()
+        );
+    }
+    Ok(())
+}
+
+/// Plays until the end of the game, returning the game if it is interrupted with Ctrl-C before its end.
+fn run(
+    out: &mut impl Write,
+    mut game: GameInProgress,
+    save: Option<&Path>,
+    strategy: &Strategy,
+) -> anyhow::Result<Option<GameInProgress>> {
+    let mut message = String::new();
+    loop {
+        let lost = game.board().board().is_lost();
+        if lost {
+            message = "GAME OVER!".to_string();
+        }
+        draw(out, &game, &game.board().to_string(), &message)?;
+        if lost {
+            return Ok(None);
+        }
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+        // in raw mode, Ctrl-C is a key rather than a signal
+        if key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL) {
+            return Ok(Some(game));
+        }
+        if matches!(key.code, KeyCode::Char('q') | KeyCode::Esc) {
+            return Ok(None);
+        }
+        if key.code == KeyCode::Char('h') {
+            message = hint(*game.board(), strategy);
+            continue;
+        }
+        let Some(action) = key_action(key.code) else {
+            continue;
+        };
+        let before = *game.board().board();
+        match game.play(action) {
+            Some(_) => {
+                message.clear();
+                // the tiles slide before the new tile appears
+                for frame in animation::frames(&before, action, animation::NUM_FRAMES) {
+                    draw(out, &game, &frame, "")?;
+                    std::thread::sleep(SLIDE / animation::NUM_FRAMES as u32);
+                }
+                if let Some(path) = save {
+                    game.save(path)?;
+                }
+            }
+            None => message = format!("{action:?} does not move any tile"),
+        }
+    }
+}
+
+/// Action selected by the strategy on the board, and its value for each action.
+fn hint(board: PlayableBoard, strategy: &Strategy) -> String {
+    let start = Instant::now();
+    // the strategy may not be implemented yet (`todo!()`), which must not end the game
+    let selection = std::panic::catch_unwind(|| {
+        (
+            strategy.select_action(board),
+            strategy.evaluate_all_actions(board),
+        )
+    });
+    let Ok((selected, values)) = selection else {
+        return format!("No hint: `{strategy}` failed");
+    };
+    let mut hint = format!(
+        "Hint of `{strategy}` ({:.1}ms): {}",
+        start.elapsed().as_secs_f64() * 1000.0,
+        selected.map_or("none".to_string(), |action| format!("{action:?}"))
+    );
+    for (action, value) in ALL_ACTIONS.into_iter().zip(values) {
+        let marker = if Some(action) == selected { "->" } else { "  " };
+        let value = value.map_or("not applicable".to_string(), |value| format!("{value:.1}"));
+        hint.push_str(&format!(
+            "\n {marker} {:<6} {value:>14}",
+            format!("{action:?}")
+        ));
+    }
+    hint
+}
+
+/// Action selected by a key, if any
+fn key_action(code: KeyCode) -> Option<Action> {
+    match code {
+        KeyCode::Up | KeyCode::Char('w') => Some(Action::Up),
+        KeyCode::Down | KeyCode::Char('s') => Some(Action::Down),
+        KeyCode::Left | KeyCode::Char('a') => Some(Action::Left),
+        KeyCode::Right | KeyCode::Char('d') => Some(Action::Right),
+        _ => None,
+    }
+}
+
+/// Redraws the whole screen: the scores, the board (as drawn by its `Display`) and a message below it.
+fn draw(
+    out: &mut impl Write,
+    game: &GameInProgress,
+    board: &str,
+    message: &str,
+) -> anyhow::Result<()> {
+    queue!(out, Clear(ClearType::All), MoveTo(0, 0))?;
+    // in raw mode, a line feed does not return to the first column
+    let text = format!(
+        "Moves: {}   Score: {}   (seed {})\n{}\n{message}\n\nArrow keys or WASD to play, h for a hint, q or Esc to quit, Ctrl-C to save and quit\n",
+        game.num_moves(),
+        game.merge_score(),
+        game.seed(),
+        board
+    );
+    write!(out, "{}", text.replace('\n', "\r\n"))?;
+    out.flush()?;
+    Ok(())
+}
@@ -0,0 +1,39 @@
+//! Handling of Ctrl-C, so that long runs can stop cleanly: a benchmark reports the games finished so far and a game
+//! in progress is saved.
+//!
+//! After `install`, the first Ctrl-C only sets a flag that the loops check with `interrupted`, and a second one
+//! terminates the process as usual, in case the program does not stop.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, OnceLock};
+
+use signal_hook::consts::SIGINT;
+
+/// Set by the first Ctrl-C
+static FLAG: OnceLock<Arc<AtomicBool>> = OnceLock::new();
+
+/// Error of a game stopped by Ctrl-C, to tell it apart from real failures
+#[derive(Debug)]
+pub struct Interrupted;
+
+impl std::fmt::Display for Interrupted {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Interrupted")
+    }
+}
+
+impl std::error::Error for Interrupted {}
+
+/// Catches Ctrl-C from now on.
+pub fn install() -> anyhow::Result<()> {
+    let flag = FLAG.get_or_init(Arc::default);
+    // registered first, so that it sees the flag before the second Ctrl-C sets it again
+    signal_hook::flag::register_conditional_shutdown(SIGINT, 130, flag.clone())?;
+    signal_hook::flag::register(SIGINT, flag.clone())?;
+    Ok(())
+}
+
+/// Whether Ctrl-C was pressed since `install`
+pub fn interrupted() -> bool {
+    FLAG.get().is_some_and(|flag| flag.load(Ordering::Relaxed))
+}
@@ -0,0 +1,63 @@
+//! Core of the game and of the AI, and the tools built on it, shared by all binaries and the micro-benchmarks of
+//! `benches/`.
+//!
+//! For `wasm32`, the modules using the terminal or threads are left out and `wasm` exposes the game to JavaScript.
+
+pub mod adversary;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod animation;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod benchmark;
+pub mod blunder;
+pub mod board;
+pub mod checkpoint;
+pub mod collect;
+pub mod config;
+#[cfg(all(feature = "tui", not(target_arch = "wasm32")))]
+pub mod dashboard;
+pub mod dataset;
+pub mod distill;
+#[cfg(all(feature = "server", not(target_arch = "wasm32")))]
+pub mod driver;
+pub mod engine;
+pub mod eval;
+pub mod expert;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod ffi;
+pub mod game;
+#[cfg(all(feature = "server", not(target_arch = "wasm32")))]
+pub mod grpc;
+#[cfg(all(feature = "gui", not(target_arch = "wasm32")))]
+pub mod gui;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod human;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod interrupt;
+pub mod logging;
+pub mod parquet;
+pub mod record;
+pub mod repl;
+pub mod replay;
+#[cfg(feature = "db")]
+pub mod results_db;
+pub mod rollout;
+pub mod savegame;
+pub mod search;
+pub mod selfplay;
+#[cfg(all(feature = "server", not(target_arch = "wasm32")))]
+pub mod server;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod spectate;
+pub mod stats;
+pub mod strategy;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod submission;
+pub mod svg;
+pub mod tabular;
+pub mod td;
+#[cfg(target_arch = "wasm32")]
+pub mod wasm;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod watch;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod websocket;
@@ -0,0 +1,47 @@
+//! Diagnostics of the binaries (decisions of each game, statistics of the searches, warnings), printed on stderr
+//! through the `log` macros so that they stay apart from the results on stdout.
+//!
+//! Binaries call `init` with the number of `-v` flags: warnings only by default, `-v` adds the detail of each move
+//! and `-vv` the statistics of each search.
+
+use log::{Level, LevelFilter, Log, Metadata, Record};
+
+struct StderrLogger;
+
+impl Log for StderrLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        // a single call per message, so that the lines of parallel games are not mixed
+        match record.level() {
+            Level::Error => eprintln!("error: {}", record.args()),
+            Level::Warn => eprintln!("warning: {}", record.args()),
+            Level::Info => eprintln!("{}", record.args()),
+            level => eprintln!("[{level} {}] {}", record.target(), record.args()),
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+/// Level of the messages printed with `verbose` flags
+pub fn level(verbose: u8) -> LevelFilter {
+    match verbose {
+        0 => LevelFilter::Warn,
+        1 => LevelFilter::Info,
+        2 => LevelFilter::Debug,
+        _ => LevelFilter::Trace,
+    }
+}
+
+/// Prints the messages up to the level of `verbose` flags on stderr.
+pub fn init(verbose: u8) {
+    // only the first call installs the logger, the later ones only change the level
+    let _ = log::set_logger(&StderrLogger);
+    log::set_max_level(level(verbose));
+}
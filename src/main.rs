@@ -1,52 +1,1432 @@
-#![allow(unused)]
-
-pub mod board;
-pub mod eval;
-pub mod search;
-
+use std::io::{IsTerminal, Write};
+use std::path::{Path, PathBuf};
 use std::{
     thread,
     time::{Duration, Instant},
 };
 
-use board::*;
+use ai_2048::adversary::Adversary;
+use ai_2048::board::*;
+use ai_2048::collect::Collector;
+use ai_2048::eval::params::Metadata;
+use ai_2048::eval::spec::EvaluatorSpec;
+use ai_2048::record::Record;
+use ai_2048::replay::{self, Event};
+use ai_2048::savegame::GameInProgress;
+use ai_2048::strategy::Strategy;
+use ai_2048::submission::{self, Submission};
+use ai_2048::{
+    animation, benchmark, blunder, collect, config, dataset, distill, engine, eval, expert, human,
+    interrupt, logging, repl, rollout, search, selfplay, spectate, svg, tabular, watch,
+};
+use anyhow::{ensure, Context};
+use clap::builder::PossibleValuesParser;
+use clap::{CommandFactory, Parser, Subcommand};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use rayon::prelude::*;
+
+/// Longest duration of the animation of the tiles sliding after an action
+const SLIDE: Duration = Duration::from_millis(150);
 
-fn main() {
-    let init = PlayableBoard::init();
+/// Plays 2048: lets the AI play a game (by default), plays it yourself, or runs the tools of the lab.
+#[derive(Parser, Debug)]
+#[command(version, about, long_about = None, args_conflicts_with_subcommands = true)]
+struct Args {
+    /// Without a command, the AI plays a game (as with `auto`)
+    #[command(subcommand)]
+    command: Option<Command>,
 
-    println!("Starting game!");
+    #[command(flatten)]
+    auto: AutoArgs,
 
-    play(init);
+    /// Prints the moves of the games (`-v`) and the statistics of each search (`-vv`) on stderr
+    #[arg(short, long, action = clap::ArgAction::Count, global = true)]
+    verbose: u8,
+
+    /// TOML file giving default values to the options (see `ai_2048::config`), overridden by the command line
+    #[arg(long, global = true)]
+    config: Option<PathBuf>,
 }
 
-pub fn play(init: PlayableBoard) {
-    let mut num_moves = 0;
-    let mut cur = init;
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Plays a game yourself, with the arrow keys or WASD
+    Play(PlayArgs),
+    /// Lets the AI play a game, showing each move
+    Auto(AutoArgs),
+    /// Lets the AI play against an adversary choosing the new tiles, instead of chance
+    Adversarial(AdversarialArgs),
+    /// Plays many games with a strategy and reports statistics over them
+    Bench(Box<benchmark::Args>),
+    /// Shows a recorded game move by move: a replay of `bench --replays <DIR>`, or a game saved with `--save`
+    Replay(ReplayArgs),
+    /// Prints the compact record of a replay or saved game, to share it or submit it (see `verify`)
+    Record(RecordArgs),
+    /// Checks a record by replaying its actions: they must be applicable and reach the claimed score
+    Verify(VerifyArgs),
+    /// Plays the standardized benchmark of the leaderboard and writes the submission file
+    Submit(SubmitArgs),
+    /// Renders a recorded game (replay or saved game) as SVG images, for reports and presentations
+    Export(ExportArgs),
+    /// Converts replays or saved games into a Parquet dataset of positions, for machine learning (see `dataset`)
+    Dataset(DatasetArgs),
+    /// Trains an evaluator on the outcomes of games played in batches, benchmarking it regularly (see `selfplay`)
+    Selfplay(SelfplayArgs),
+    /// Learns the exact value of each state of a 2x2 or 3x3 board, and compares the learned policy to expectimax
+    /// on the same boards (see `tabular`)
+    Tabular(TabularArgs),
+    /// Labels the positions reached by a fast policy with the action values of a slow expert, for training
+    /// evaluators approximating it (see `expert`)
+    Expert(ExpertArgs),
+    /// Distills the actions of labelled positions (e.g. of `expert`) into a fast lookup policy, played by the
+    /// `distilled:file=<policy>` strategy (see `distill`)
+    Distill(DistillArgs),
+    /// Shows the evaluation of a board and of each action, and the action selected by a strategy
+    Analyze(AnalyzeArgs),
+    /// Re-examines each move of a recorded game with a deep search, and reports the moves far below the best action
+    /// (see `blunder`)
+    AnalyzeReplay(AnalyzeReplayArgs),
+    /// Estimates the value of a position for a strategy (moves and score to come) from many seeded games, with
+    /// confidence intervals (see `rollout`)
+    Rollouts(RolloutsArgs),
+    /// Answers requests of other programs, one JSON line per board on the standard input (see `engine`)
+    Engine(EngineArgs),
+    /// Serves the game and the engine over HTTP, as JSON and as gRPC (see `proto/ai2048.proto`), for web frontends
+    /// and other programs (requires building with `--features server`)
+    Serve(ServeArgs),
+    /// Plays the original web game (play2048.co) in your browser, checking that its rules match those of this crate
+    /// (requires building with `--features server`)
+    Drive(DriveArgs),
+    /// Opens a window to play the game with the arrow keys or watch the AI play it, with hints and a live panel of the
+    /// evaluation (requires building with `--features gui`)
+    Gui(GuiArgs),
+}
+
+#[derive(clap::Args, Debug)]
+struct PlayArgs {
+    #[command(flatten)]
+    saves: SaveArgs,
+
+    /// Strategy giving the hints (`h` key)
+    #[arg(short, long, default_value = "default")]
+    strategy: Strategy,
+
+    #[command(flatten)]
+    eval: EvalArgs,
+}
+
+/// Options to save a game in progress and to continue it later
+#[derive(clap::Args, Debug)]
+struct SaveArgs {
+    /// Seed of the generator of random tiles (random if absent)
+    #[arg(long, conflicts_with = "load")]
+    seed: Option<u64>,
+
+    /// File where the game is saved after each move
+    #[arg(long)]
+    save: Option<PathBuf>,
+
+    /// Continues the game saved in this file (with `--save`)
+    #[arg(long)]
+    load: Option<PathBuf>,
+}
+
+impl SaveArgs {
+    /// The game loaded from the file of `--load`, or a new one
+    fn start(&self) -> anyhow::Result<GameInProgress> {
+        match &self.load {
+            Some(path) => GameInProgress::load(path),
+            None => Ok(GameInProgress::new(self.seed.unwrap_or_else(rand::random))),
+        }
+    }
+}
+
+/// Options of the evaluation function used by the strategies
+#[derive(clap::Args, Debug)]
+struct EvalArgs {
+    /// Named preset of weights for the evaluation function (default weights if absent)
+    #[arg(long, value_parser = PossibleValuesParser::new(eval::presets::names()))]
+    eval_preset: Option<String>,
+
+    /// File of weights for the evaluation function, instead of a named preset
+    #[arg(long, conflicts_with = "eval_preset")]
+    weights: Option<PathBuf>,
+
+    /// Evaluation function at the leaves of the search, with optional parameters (`linear` with the weights above,
+    /// `phased`, `ntuple:file=ntuple.weights`, `nn:file=net.safetensors`)
+    #[arg(long, default_value = "linear")]
+    evaluator: EvaluatorSpec,
+}
+
+impl EvalArgs {
+    /// Makes the evaluator given by the options the default evaluation
+    fn apply(&self) -> anyhow::Result<()> {
+        let weights = eval::load_weights(self.weights.as_deref(), self.eval_preset.as_deref())?;
+        if !self.evaluator.uses_weights() {
+            ensure!(
+                self.eval_preset.is_none() && self.weights.is_none(),
+                "The evaluator {} does not use the weights of the linear evaluation",
+                self.evaluator
+            );
+        } else if self.eval_preset.is_none() && self.weights.is_none() {
+            // the default evaluation already
+            return Ok(());
+        }
+        eval::set_default_evaluator(self.evaluator.load(weights)?)
+    }
+}
+
+#[derive(clap::Args, Debug)]
+struct AutoArgs {
+    /// Strategy selecting the actions (`default`, `random`, `greedy`, `expectimax:depth=4`)
+    #[arg(short, long, default_value = "default")]
+    strategy: Strategy,
+
+    /// Time budget in milliseconds for each decision, for the strategies able to stop early (expectimax)
+    #[arg(long)]
+    time_per_move: Option<u64>,
+
+    #[command(flatten)]
+    eval: EvalArgs,
+
+    #[command(flatten)]
+    saves: SaveArgs,
+
+    /// Pause in milliseconds after each move shown, to follow the game
+    #[arg(long, default_value = "300")]
+    delay: u64,
+
+    /// Plays at full speed, without pausing after the moves (same as `--delay 0`)
+    #[arg(long, conflicts_with = "delay")]
+    no_animation: bool,
+
+    /// Shows only one move out of `n`, and the end of the game
+    #[arg(long, default_value = "1", value_parser = clap::value_parser!(u64).range(1..))]
+    print_every: u64,
+
+    /// Plays at full speed without showing any board, only a summary of the game at its end
+    #[arg(short, long, conflicts_with_all = ["delay", "no_animation", "print_every"])]
+    quiet: bool,
+
+    /// Shows the game full screen, with the statistics of the search and the evaluation of the board beside it
+    /// (requires building with `--features tui`)
+    #[arg(long, conflicts_with_all = ["quiet", "print_every"])]
+    tui: bool,
+
+    /// Waits for Enter before playing each move shown, to follow the decisions one by one
+    #[arg(long, conflicts_with_all = ["quiet", "tui", "delay", "no_animation"])]
+    step: bool,
+
+    /// Prints the statistics of the search and the value of each action for each move shown
+    #[arg(long, conflicts_with_all = ["quiet", "tui"])]
+    explain: bool,
+
+    /// Broadcasts the game to spectators of the page served at this address, e.g. `0.0.0.0:8049` (requires building
+    /// with `--features server`)
+    #[arg(long, conflicts_with = "tui")]
+    spectate: Option<String>,
+}
+
+impl AutoArgs {
+    /// Pause after each move shown
+    fn delay(&self) -> Duration {
+        if self.no_animation {
+            Duration::ZERO
+        } else {
+            Duration::from_millis(self.delay)
+        }
+    }
+}
+
+#[derive(clap::Args, Debug)]
+struct AdversarialArgs {
+    /// Strategy selecting the actions
+    #[arg(short, long, default_value = "default")]
+    strategy: Strategy,
+
+    #[command(flatten)]
+    eval: EvalArgs,
+
+    /// Opponent placing the tiles
+    #[arg(short, long, value_enum, default_value = "worst")]
+    adversary: Adversary,
+
+    /// Seed of the random adversary
+    #[arg(long)]
+    seed: Option<u64>,
+
+    /// Pause in milliseconds after each move of the AI (not with a human adversary)
+    #[arg(long, default_value = "300")]
+    delay: u64,
+}
+
+#[derive(clap::Args, Debug)]
+struct ReplayArgs {
+    /// Replay file (`game-<seed>.jsonl`) or saved game
+    file: PathBuf,
+
+    /// Pause in milliseconds after each move shown
+    #[arg(long, default_value = "500")]
+    delay: u64,
+
+    /// Number of the first move shown, the previous ones being played without showing them
+    #[arg(long, default_value = "0")]
+    start: usize,
+}
+
+#[derive(clap::Args, Debug)]
+struct RecordArgs {
+    /// Replay file (`game-<seed>.jsonl`) or saved game
+    file: PathBuf,
+}
+
+#[derive(clap::Args, Debug)]
+struct DatasetArgs {
+    /// Replay files (`game-<seed>.jsonl`), saved games, or directories of replays (`bench --replays <DIR>`)
+    #[arg(required = true)]
+    files: Vec<PathBuf>,
+
+    /// Parquet file of the positions
+    #[arg(short, long, default_value = "positions.parquet")]
+    out: PathBuf,
+}
+
+#[derive(clap::Args, Debug)]
+struct SelfplayArgs {
+    /// Evaluator being trained
+    #[arg(short, long, value_enum, default_value = "four-tuples")]
+    learner: LearnerKind,
+
+    /// Strategy playing the training games, whose value is learned (the learner itself by default)
+    #[arg(short, long)]
+    player: Option<Strategy>,
+
+    /// Probability of a random action when the learner plays
+    #[arg(long, default_value = "0", conflicts_with = "player")]
+    epsilon: f32,
+
+    /// Value learned for an afterstate
+    #[arg(long, value_enum, default_value = "moves")]
+    outcome: selfplay::Outcome,
+
+    /// Number of iterations
+    #[arg(short, long, default_value = "100")]
+    iterations: u64,
+
+    /// Number of games played in each iteration
+    #[arg(short, long, default_value = "1000")]
+    games: u64,
+
+    /// Learning rate
+    #[arg(short, long, default_value = "0.1")]
+    alpha: f32,
+
+    /// Number of iterations between two benchmarks of the learner
+    #[arg(long, default_value = "10")]
+    bench_every: u64,
+
+    /// Number of games of each benchmark
+    #[arg(long, default_value = "100")]
+    bench_games: u64,
+
+    /// Seed of the first training game
+    #[arg(long, default_value = "0")]
+    seed: u64,
+
+    /// Checkpoint file where the learner is saved after each benchmark, with the number of training games and the
+    /// benchmark score (an n-tuple network, or weights of the evaluation for `linear`)
+    #[arg(short, long, default_value = "selfplay.weights")]
+    output: PathBuf,
+
+    /// File where each position of the training games is written, with the value of each action for the player and
+    /// the action selected (see `collect`)
+    #[arg(long)]
+    collect: Option<PathBuf>,
+}
+
+#[derive(clap::Args, Debug)]
+struct TabularArgs {
+    /// Size of the board
+    #[arg(long, default_value = "3", value_parser = clap::value_parser!(u8).range(2..=3))]
+    size: u8,
+
+    /// Number of training games
+    #[arg(short, long, default_value = "100000")]
+    episodes: u64,
+
+    /// Learning rate
+    #[arg(short, long, default_value = "0.1")]
+    alpha: f32,
+
+    /// Probability of a random action in training games
+    #[arg(long, default_value = "0.01")]
+    epsilon: f32,
+
+    /// Number of games played by each policy in the comparison
+    #[arg(short, long, default_value = "1000")]
+    games: u64,
+
+    /// Number of actions looked ahead by expectimax
+    #[arg(short, long, default_value = "2", value_parser = clap::value_parser!(u64).range(1..))]
+    depth: u64,
+
+    /// Seed of the training games
+    #[arg(long, default_value = "0")]
+    seed: u64,
+}
+
+#[derive(clap::Args, Debug)]
+struct ExpertArgs {
+    /// Fast strategy playing the games
+    #[arg(short, long, default_value = "greedy")]
+    policy: Strategy,
+
+    /// Slow strategy labelling the positions
+    #[arg(short, long, default_value = "expectimax:depth=4")]
+    expert: Strategy,
+
+    /// Number of games played by the policy
+    #[arg(short, long, default_value = "100")]
+    games: u64,
+
+    /// Labels one position every this number of actions of each game
+    #[arg(long, default_value = "1", value_parser = clap::value_parser!(u64).range(1..))]
+    every: u64,
+
+    /// Seed of the first game (game `i` uses the seed `seed + i`)
+    #[arg(long, default_value = "0")]
+    seed: u64,
+
+    /// File of the labelled positions (see `collect`)
+    #[arg(short, long, default_value = "expert.bin")]
+    output: PathBuf,
+
+    #[command(flatten)]
+    eval: EvalArgs,
+}
+
+#[derive(clap::Args, Debug)]
+struct DistillArgs {
+    /// Files of labelled positions (see `collect`)
+    #[arg(required = true)]
+    data: Vec<PathBuf>,
+
+    /// Number of passes over the positions
+    #[arg(short, long, default_value = "10")]
+    epochs: u64,
+
+    /// Learning rate, divided among the tuples of the policy
+    #[arg(short, long, default_value = "1.0")]
+    alpha: f32,
+
+    /// The positions of one game in this number (by seed) are held out of training, to measure the accuracy of
+    /// the policy on positions it did not learn from
+    #[arg(long, default_value = "10", value_parser = clap::value_parser!(u64).range(2..))]
+    holdout: u64,
+
+    /// Seed of the order of the positions in each pass
+    #[arg(long, default_value = "0")]
+    seed: u64,
+
+    /// File where the policy is written
+    #[arg(short, long, default_value = "policy.bin")]
+    output: PathBuf,
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+enum LearnerKind {
+    /// N-tuple network of straight 4-tuples and 2x2 squares
+    FourTuples,
+    /// N-tuple network of the four 6-tuples of Yeh et al. (requires ~270MB of memory)
+    SixTuples,
+    /// Linear function of the heuristics of the evaluation
+    Linear,
+}
+
+#[derive(clap::Args, Debug)]
+struct VerifyArgs {
+    /// Record (`2048:1:<seed>:<score>:<actions>`), file containing it, or submission file of `submit`
+    record: String,
+}
+
+#[derive(clap::Args, Debug)]
+struct SubmitArgs {
+    /// Strategy playing the games
+    #[arg(short, long, default_value = "default")]
+    strategy: Strategy,
+
+    #[command(flatten)]
+    eval: EvalArgs,
+
+    /// Name of the student or team, shown on the leaderboard
+    #[arg(long)]
+    name: String,
+
+    /// File where the submission is written
+    #[arg(short, long, default_value = "submission.json")]
+    out: PathBuf,
+}
+
+#[derive(clap::Args, Debug)]
+struct ExportArgs {
+    /// Replay file (`game-<seed>.jsonl`) or saved game
+    file: PathBuf,
+
+    /// Directory where the frames are written (`frame-0000.svg`, ...), or file of the animated SVG with `--animated`
+    #[arg(short, long)]
+    out: PathBuf,
+
+    /// Writes a single animated SVG playing the whole game instead of one image per move
+    #[arg(long)]
+    animated: bool,
+
+    /// Time in milliseconds during which each board is shown in the animated SVG
+    #[arg(long, default_value = "300", requires = "animated")]
+    delay: u64,
+}
+
+#[derive(clap::Args, Debug)]
+struct AnalyzeArgs {
+    /// Tiles of the board row by row, 0 for empty cells (e.g. `2,4,0,0/0,0,0,0/0,8,0,0/0,0,0,2`), analyzed
+    /// interactively if absent
+    board: Option<String>,
+
+    /// Studies the board interactively, with commands such as `best`, `tree 2`, `play up` and `undo`
+    #[arg(short, long)]
+    interactive: bool,
+
+    /// Strategy whose selected action is shown
+    #[arg(short, long, default_value = "default")]
+    strategy: Strategy,
+
+    #[command(flatten)]
+    eval: EvalArgs,
+}
+
+#[derive(clap::Args, Debug)]
+struct AnalyzeReplayArgs {
+    /// Replay file (`game-<seed>.jsonl`) or saved game
+    file: PathBuf,
+
+    /// Deep strategy re-examining the moves
+    #[arg(short, long, default_value = "expectimax:depth=4")]
+    strategy: Strategy,
+
+    /// A move is a blunder when the value of its action is below the value of the best action by more than this
+    /// fraction of the best value
+    #[arg(short, long, default_value = "0.01")]
+    threshold: f32,
+
+    /// Number of the largest blunders whose position is shown
+    #[arg(long, default_value = "5")]
+    top: usize,
+
+    #[command(flatten)]
+    eval: EvalArgs,
+}
+
+#[derive(clap::Args, Debug)]
+struct RolloutsArgs {
+    /// Tiles of the start position row by row, 0 for empty cells (a new game for each rollout if absent)
+    board: Option<String>,
+
+    /// Strategy playing the rollouts
+    #[arg(short, long, default_value = "default")]
+    strategy: Strategy,
+
+    /// Number of rollouts
+    #[arg(short, long, default_value = "1000")]
+    games: u64,
+
+    /// Seed of the first rollout (rollout `i` uses the seed `seed + i`)
+    #[arg(long, default_value = "0")]
+    seed: u64,
+
+    /// Confidence level of the intervals
+    #[arg(long, default_value = "0.95")]
+    level: f64,
+
+    /// Also estimates the value of each action by rollouts from its afterstate, compared with its evaluation
+    #[arg(long, requires = "board")]
+    per_action: bool,
+
+    #[command(flatten)]
+    eval: EvalArgs,
+}
+
+#[derive(clap::Args, Debug)]
+struct EngineArgs {
+    /// Strategy selecting the actions, unless a request gives another one
+    #[arg(short, long, default_value = "default")]
+    strategy: Strategy,
+
+    #[command(flatten)]
+    eval: EvalArgs,
+}
+
+#[derive(clap::Args, Debug)]
+struct ServeArgs {
+    /// Address on which the server listens
+    #[arg(long, default_value = "127.0.0.1:8048")]
+    addr: String,
+
+    /// Strategy selecting the best moves, unless a request gives another one
+    #[arg(short, long, default_value = "default")]
+    strategy: Strategy,
+
+    #[command(flatten)]
+    eval: EvalArgs,
+}
+
+#[derive(clap::Args, Debug)]
+struct DriveArgs {
+    /// Address on which the driver listens for the page of the game
+    #[arg(long, default_value = "127.0.0.1:8048")]
+    addr: String,
+
+    /// Strategy selecting the actions
+    #[arg(short, long, default_value = "default")]
+    strategy: Strategy,
+
+    /// Time budget in milliseconds for each decision, for strategies that can use one
+    #[arg(long)]
+    time_per_move: Option<u64>,
+
+    #[command(flatten)]
+    eval: EvalArgs,
+}
+
+#[derive(clap::Args, Debug)]
+struct GuiArgs {
+    /// Strategy of the hints and of the auto-play, unless the window gives another one
+    #[arg(short, long, default_value = "default")]
+    strategy: Strategy,
+
+    #[command(flatten)]
+    eval: EvalArgs,
+}
+
+fn main() -> anyhow::Result<()> {
+    let args = config::with_config(&Args::command(), "auto", std::env::args_os().collect())?;
+    let args = Args::parse_from(args);
+    logging::init(args.verbose);
+    match args.command {
+        Some(Command::Play(play)) => {
+            play.eval.apply()?;
+            human::play(
+                play.saves.start()?,
+                play.saves.save.as_deref(),
+                &play.strategy,
+            )
+        }
+        Some(Command::Auto(auto)) => auto_play(&auto),
+        Some(Command::Adversarial(args)) => {
+            args.eval.apply()?;
+            adversarial(&args)
+        }
+        Some(Command::Bench(bench)) => benchmark::run(*bench),
+        Some(Command::Replay(replay)) => show_replay(&replay),
+        Some(Command::AnalyzeReplay(args)) => {
+            args.eval.apply()?;
+            analyze_replay(&args)
+        }
+        Some(Command::Record(args)) => {
+            println!("{}", Record::of_events(&read_events(&args.file)?)?);
+            Ok(())
+        }
+        Some(Command::Verify(args)) => verify(&args),
+        Some(Command::Submit(args)) => {
+            args.eval.apply()?;
+            submit(&args)
+        }
+        Some(Command::Export(args)) => export(&args),
+        Some(Command::Dataset(args)) => dataset(&args),
+        Some(Command::Selfplay(args)) => train_by_selfplay(&args),
+        Some(Command::Tabular(args)) => tabular_learning(&args),
+        Some(Command::Distill(args)) => distill_policy(&args),
+        Some(Command::Expert(args)) => {
+            args.eval.apply()?;
+            label_by_expert(&args)
+        }
+        Some(Command::Analyze(args)) => {
+            args.eval.apply()?;
+            analyze(&args)
+        }
+        Some(Command::Rollouts(args)) => {
+            args.eval.apply()?;
+            estimate_by_rollouts(&args)
+        }
+        Some(Command::Engine(args)) => {
+            args.eval.apply()?;
+            engine::serve(std::io::stdin().lock(), std::io::stdout(), &args.strategy)
+        }
+        Some(Command::Serve(args)) => {
+            args.eval.apply()?;
+            serve(&args)
+        }
+        Some(Command::Drive(args)) => {
+            args.eval.apply()?;
+            drive(&args)
+        }
+        Some(Command::Gui(args)) => {
+            args.eval.apply()?;
+            gui(&args)
+        }
+        None => auto_play(&args.auto),
+    }
+}
+
+#[cfg(feature = "server")]
+fn serve(args: &ServeArgs) -> anyhow::Result<()> {
+    let listener = std::net::TcpListener::bind(&args.addr)
+        .with_context(|| format!("Cannot listen on {}", args.addr))?;
+    eprintln!("Listening on http://{}", listener.local_addr()?);
+    ai_2048::server::serve(listener, args.strategy)
+}
+
+#[cfg(not(feature = "server"))]
+fn serve(_args: &ServeArgs) -> anyhow::Result<()> {
+    anyhow::bail!("The server is not available in this build, rebuild with `--features server`")
+}
+
+#[cfg(feature = "server")]
+fn drive(args: &DriveArgs) -> anyhow::Result<()> {
+    let listener = std::net::TcpListener::bind(&args.addr)
+        .with_context(|| format!("Cannot listen on {}", args.addr))?;
+    let addr = listener.local_addr()?;
+    eprintln!("Open the game (e.g. https://play2048.co) and run this script in the console of the developer tools:\n");
+    println!("{}", ai_2048::driver::script(addr));
+    ai_2048::driver::serve(
+        listener,
+        &args.strategy,
+        args.time_per_move.map(Duration::from_millis),
+    )
+}
+
+#[cfg(not(feature = "server"))]
+fn drive(_args: &DriveArgs) -> anyhow::Result<()> {
+    anyhow::bail!("The driver is not available in this build, rebuild with `--features server`")
+}
+
+#[cfg(feature = "gui")]
+fn gui(args: &GuiArgs) -> anyhow::Result<()> {
+    ai_2048::gui::run(args.strategy)
+}
+
+#[cfg(not(feature = "gui"))]
+fn gui(_args: &GuiArgs) -> anyhow::Result<()> {
+    anyhow::bail!("The window is not available in this build, rebuild with `--features gui`")
+}
+
+fn auto_play(args: &AutoArgs) -> anyhow::Result<()> {
+    args.eval.apply()?;
+    let game = args.saves.start()?;
+
+    if args.tui {
+        return watch(game, args);
+    }
+    if !args.quiet {
+        println!("Starting game! (seed {})", game.seed());
+    }
+
+    play(game, args)
+}
+
+#[cfg(feature = "tui")]
+fn watch(game: GameInProgress, args: &AutoArgs) -> anyhow::Result<()> {
+    let stopped = ai_2048::watch::play(
+        game,
+        &args.strategy,
+        args.time_per_move.map(Duration::from_millis),
+        args.delay(),
+        args.saves.save.as_deref(),
+    )?;
+    if let Some(game) = stopped {
+        let path = game.save_stopped(args.saves.save.as_deref())?;
+        println!(
+            "Game saved to {0}, continue it with `--load {0}`",
+            path.display()
+        );
+    }
+    Ok(())
+}
+
+#[cfg(not(feature = "tui"))]
+fn watch(_game: GameInProgress, _args: &AutoArgs) -> anyhow::Result<()> {
+    anyhow::bail!(
+        "The full-screen view is not available in this build, rebuild with `--features tui`"
+    )
+}
+
+fn play(mut game: GameInProgress, args: &AutoArgs) -> anyhow::Result<()> {
+    let start = Instant::now();
+    let spectators = args.spectate.as_deref().map(spectate::bind).transpose()?;
+    let name = format!("{} #{}", args.strategy, game.seed());
+    if let Some(spectators) = &spectators {
+        spectators.update(&name, &game, None);
+    }
+    // on Ctrl-C, the game is saved to be continued later
+    interrupt::install()?;
     loop {
-        println!("{cur}");
-        // TO REMOVE eventually: slow down the program to make it easier to follow
-        thread::sleep(Duration::from_millis(300));
+        if interrupt::interrupted() {
+            let path = game.save_stopped(args.saves.save.as_deref())?;
+            println!(
+                "Interrupted after {} moves, game saved to {1} (continue it with `--load {1}`)",
+                game.num_moves(),
+                path.display()
+            );
+            return Ok(());
+        }
+        let cur = *game.board();
+        let num_moves = game.num_moves();
+        // only one move out of `print_every` is shown, and none when quiet
+        let shown = !args.quiet && (num_moves as u64).is_multiple_of(args.print_every);
+        if shown {
+            println!("{cur}");
+            // slow down the program to make it easier to follow
+            if !args.step {
+                thread::sleep(args.delay());
+            }
+        }
 
+        // only the statistics of the searches for this move are reported
+        search::take_search_totals();
         let start_action_selection = Instant::now();
-        let action = match search::select_action(cur) {
+        let action = match args.time_per_move {
+            Some(budget) => args
+                .strategy
+                .select_action_within(cur, Duration::from_millis(budget)),
+            None => args.strategy.select_action(cur),
+        };
+        let action = match action {
             Some(action) => action,
+            None if args.quiet => {
+                println!(
+                    "Game over after {num_moves} moves: score {}, max tile {}, in {:.2}s (seed {})",
+                    game.merge_score(),
+                    1u32 << cur.board().max_tile(),
+                    start.elapsed().as_secs_f64(),
+                    game.seed()
+                );
+                return Ok(());
+            }
             None => {
+                if !shown {
+                    println!("{cur}");
+                }
                 println!("GAME OVER!");
                 println!("Num moves: {num_moves}");
-                return;
+                return Ok(());
+            }
+        };
+        if shown {
+            // print the selected action, together with the time by the `select_action` function
+            println!(
+                "\n[{:.2}ms] Playing action {action:?}:",
+                start_action_selection.elapsed().as_secs_f64() * 1000.0
+            );
+            if args.explain {
+                explain(cur, action, &args.strategy);
             }
+            if args.step {
+                print!("Press Enter to play it, or q and Enter to quit: ");
+                std::io::stdout().flush()?;
+                let mut line = String::new();
+                if std::io::stdin().read_line(&mut line)? == 0 || line.trim() == "q" {
+                    return Ok(());
+                }
+            }
+            let played = cur.apply(action).expect("invalid action");
+            // the tiles slide from the board before the action to the board after it
+            if args.delay() > Duration::ZERO && std::io::stdout().is_terminal() {
+                print!("{cur}");
+                animation::play(
+                    &mut std::io::stdout(),
+                    cur.board(),
+                    action,
+                    args.delay().min(SLIDE),
+                )?;
+                println!();
+            } else {
+                println!("{played}");
+            }
+            println!("Adding random tile:");
+        }
+        game.play(action).expect("invalid action");
+        if let Some(spectators) = &spectators {
+            spectators.update(&name, &game, Some(action));
+        }
+        if let Some(path) = &args.saves.save {
+            game.save(path)?;
+        }
+    }
+}
+
+/// Prints the statistics of the searches made to select the action, and the value of each action.
+fn explain(board: PlayableBoard, selected: Action, strategy: &Strategy) {
+    for line in watch::search_lines(&search::take_search_totals()) {
+        println!("  {line}");
+    }
+    for (action, value) in ALL_ACTIONS
+        .into_iter()
+        .zip(strategy.evaluate_all_actions(board))
+    {
+        let marker = if action == selected { "->" } else { "  " };
+        let value = value.map_or("not applicable".to_string(), |value| format!("{value:.1}"));
+        println!("  {marker} {:<6} {value:>14}", format!("{action:?}"));
+    }
+}
+
+/// Plays a game where the adversary places every tile, including the first one.
+fn adversarial(args: &AdversarialArgs) -> anyhow::Result<()> {
+    let mut rng = StdRng::seed_from_u64(args.seed.unwrap_or_else(rand::random));
+    let place = |board: &Board, rng: &mut StdRng| -> anyhow::Result<PlayableBoard> {
+        let spawn = args.adversary.place(board, rng)?;
+        let mut next = *board;
+        next.cells[spawn.row][spawn.col] = spawn.tile;
+        Ok(PlayableBoard::from(next))
+    };
+    let mut board = place(&Board::EMPTY, &mut rng)?;
+    let mut num_moves = 0;
+    let mut merge_score = 0;
+    loop {
+        println!("{board}");
+        let Some(action) = args.strategy.select_action(board) else {
+            println!(
+                "GAME OVER against `{:?}` after {num_moves} moves, 2048 score: {merge_score}",
+                args.adversary
+            );
+            return Ok(());
         };
-        // print the selected action, together with the time by the `select_action` function
+        let (played, score) = board.apply_scored(action).expect("invalid action");
+        num_moves += 1;
+        merge_score += score;
+        println!("Move {num_moves}: {action:?}\n{played}");
+        if args.adversary != Adversary::Human {
+            thread::sleep(Duration::from_millis(args.delay));
+        }
+        board = place(played.board(), &mut rng)?;
+    }
+}
+
+/// Events of a replay file, or of a saved game (whose values are computed with the current evaluation function).
+fn read_events(path: &Path) -> anyhow::Result<Vec<Event>> {
+    match replay::read(path) {
+        Ok(events) => Ok(events),
+        Err(replay_error) => match GameInProgress::load(path) {
+            Ok(game) => Ok(game.to_replay("saved game")),
+            Err(save_error) => anyhow::bail!(
+                "{} is neither a replay ({replay_error:#}) nor a saved game ({save_error:#})",
+                path.display()
+            ),
+        },
+    }
+}
+
+/// Prints each board of a recorded game, with the action played and its value.
+fn show_replay(args: &ReplayArgs) -> anyhow::Result<()> {
+    let mut board = Board::EMPTY;
+    let mut num_moves = 0;
+    for event in read_events(&args.file)? {
+        match event {
+            Event::Start {
+                seed,
+                strategy,
+                board: start,
+            } => {
+                println!("Game of `{strategy}` on seed {seed}");
+                board = start;
+            }
+            Event::Move {
+                action,
+                value,
+                score,
+                spawn,
+            } => {
+                if num_moves >= args.start {
+                    println!("{board}");
+                    thread::sleep(Duration::from_millis(args.delay));
+                    println!(
+                        "Move {}: {action:?}   value: {value:.1}   score: +{score}",
+                        num_moves + 1
+                    );
+                }
+                num_moves += 1;
+                board = board
+                    .apply(action)
+                    .with_context(|| format!("Move {num_moves}: {action:?} is not applicable"))?;
+                board.cells[spawn.row][spawn.col] = spawn.tile;
+            }
+            Event::End {
+                num_moves,
+                merge_score,
+                timed_out,
+            } => {
+                println!("{board}");
+                let end = if timed_out {
+                    "Timeout"
+                } else {
+                    "End of the game"
+                };
+                println!("{end} after {num_moves} moves, 2048 score: {merge_score}");
+                return Ok(());
+            }
+        }
+    }
+    // game saved before its end
+    println!("{board}");
+    println!("Game in progress after {num_moves} moves");
+    Ok(())
+}
+
+fn analyze_replay(args: &AnalyzeReplayArgs) -> anyhow::Result<()> {
+    let positions = replay::positions(&read_events(&args.file)?)?;
+    let start = Instant::now();
+    let reviews = blunder::review(&positions, &args.strategy);
+    let blunders = blunder::blunders(&reviews, args.threshold);
+    println!(
+        "{} moves re-examined by `{}` in {:.1}s: {} blunders (gap above {:.1}% of the best value), {} other moves \
+         not the best",
+        reviews.len(),
+        args.strategy,
+        start.elapsed().as_secs_f32(),
+        blunders.len(),
+        100.0 * args.threshold,
+        reviews
+            .iter()
+            .filter(|review| review.played != review.best && review.gap() > 0.0)
+            .count()
+            - blunders.len()
+    );
+    if blunders.is_empty() {
+        return Ok(());
+    }
+    println!(
+        "\n{:>5} {:<6} {:>14} {:<6} {:>14} {:>12} {:>7}",
+        "move", "played", "value", "best", "value", "gap", "gap %"
+    );
+    let mut by_move = blunders.clone();
+    by_move.sort_by_key(|review| review.number);
+    for review in &by_move {
         println!(
-            "\n[{:.2}ms] Playing action {action:?}:",
-            start_action_selection.elapsed().as_secs_f64() * 1000.0
+            "{:>5} {:<6} {:>14.1} {:<6} {:>14.1} {:>12.1} {:>6.2}%",
+            review.number,
+            format!("{:?}", review.played),
+            review.played_value,
+            format!("{:?}", review.best),
+            review.best_value,
+            review.gap(),
+            100.0 * review.relative_gap()
         );
-        let played = cur.apply(action).expect("invalid action");
-        num_moves += 1;
-        println!("{played}");
+    }
+    for review in blunders.iter().take(args.top) {
+        println!(
+            "\nMove {}: {:?} instead of {:?} (gap {:.1}, {:.2}%)\n{}",
+            review.number,
+            review.played,
+            review.best,
+            review.gap(),
+            100.0 * review.relative_gap(),
+            review.board
+        );
+    }
+    Ok(())
+}
 
-        println!("Adding random tile:");
+fn verify(args: &VerifyArgs) -> anyhow::Result<()> {
+    let text = if Path::new(&args.record).is_file() {
+        std::fs::read_to_string(&args.record)
+            .with_context(|| format!("Cannot read {}", args.record))?
+    } else {
+        args.record.clone()
+    };
+    if text.trim_start().starts_with('{') {
+        let submission: Submission = serde_json::from_str(&text).context("Invalid submission")?;
+        let mean = submission.verify()?;
+        println!(
+            "Valid submission of {} ({}, commit {}): mean score {mean:.1} over {} games",
+            submission.name,
+            submission.strategy,
+            submission.git_commit.as_deref().unwrap_or("unknown"),
+            submission.records.len()
+        );
+        return Ok(());
+    }
+    let record: Record = text.parse()?;
+    let verified = record.verify()?;
+    println!("{}", verified.board);
+    let end = if verified.board.is_lost() {
+        "game over"
+    } else {
+        "game in progress"
+    };
+    println!(
+        "Valid record of seed {}: {} moves, score {}, max tile {} ({end})",
+        record.seed,
+        verified.num_moves,
+        verified.score,
+        1u32 << verified.board.max_tile()
+    );
+    Ok(())
+}
+
+fn submit(args: &SubmitArgs) -> anyhow::Result<()> {
+    println!(
+        "Playing {} games with {} ({}ms per move)...",
+        submission::SEEDS.count(),
+        args.strategy,
+        submission::TIME_PER_MOVE_MS
+    );
+    let submission = submission::run(&args.name, &args.strategy)?;
+    submission.save(&args.out)?;
+    println!(
+        "Mean score {:.1}, submission written to {} (check it with `verify {}`)",
+        submission.mean_score,
+        args.out.display(),
+        args.out.display()
+    );
+    if submission
+        .git_commit
+        .as_deref()
+        .is_none_or(|commit| commit.ends_with("-dirty"))
+    {
+        eprintln!(
+            "Warning: the code is not committed, the submission cannot be traced to a revision"
+        );
+    }
+    Ok(())
+}
+
+fn export(args: &ExportArgs) -> anyhow::Result<()> {
+    let frames = svg::frames(&read_events(&args.file)?)?;
+    if args.animated {
+        std::fs::write(&args.out, svg::render_animated(&frames, args.delay))
+            .with_context(|| format!("Cannot write {}", args.out.display()))?;
+        println!("{} boards written to {}", frames.len(), args.out.display());
+    } else {
+        let num_frames = svg::write_frames(&frames, &args.out)?;
+        println!("{num_frames} frames written to {}", args.out.display());
+    }
+    Ok(())
+}
+
+fn train_by_selfplay(args: &SelfplayArgs) -> anyhow::Result<()> {
+    use ai_2048::eval::ntuple::{NTupleNetwork, FOUR_TUPLES, SIX_TUPLES};
+    let config = selfplay::Config {
+        player: match args.player {
+            Some(strategy) => selfplay::Player::Strategy(strategy),
+            None => selfplay::Player::Learner {
+                epsilon: args.epsilon,
+            },
+        },
+        outcome: args.outcome,
+        iterations: args.iterations,
+        games_per_iteration: args.games,
+        alpha: args.alpha,
+        bench_every: args.bench_every,
+        bench_games: args.bench_games,
+        seed: args.seed,
+    };
+    match args.learner {
+        LearnerKind::FourTuples => selfplay_with(&config, NTupleNetwork::new(&FOUR_TUPLES), args),
+        LearnerKind::SixTuples => selfplay_with(&config, NTupleNetwork::new(&SIX_TUPLES), args),
+        LearnerKind::Linear => selfplay_with(&config, selfplay::LinearLearner::default(), args),
+    }
+}
+
+fn selfplay_with(
+    config: &selfplay::Config,
+    mut learner: impl selfplay::Learner,
+    args: &SelfplayArgs,
+) -> anyhow::Result<()> {
+    let collector = args.collect.as_deref().map(Collector::create).transpose()?;
+    let start = Instant::now();
+    selfplay::run(
+        config,
+        &mut learner,
+        collector.as_ref(),
+        |report, learner| {
+            let Some(bench) = report.bench else {
+                return Ok(());
+            };
+            let metadata = Metadata {
+                trainer: "selfplay".into(),
+                training_steps: report.num_games,
+                bench_score: Some(bench.mean_score),
+            };
+            learner.save(&args.output, &metadata)?;
+            println!(
+            "[{:>7.1}s] iteration {:>5}   games {:>8}   training score {:>8.0}   greedy score {:>8.0}   moves {:>6.0}   2048 rate {:>5.1}%",
+            start.elapsed().as_secs_f32(),
+            report.iteration,
+            report.num_games,
+            report.mean_training_score,
+            bench.mean_score,
+            bench.mean_moves,
+            100.0 * bench.win_rate
+        );
+            Ok(())
+        },
+    )?;
+    println!("Learner written to {}", args.output.display());
+    if let (Some(collector), Some(path)) = (collector, &args.collect) {
+        println!(
+            "{} positions written to {}",
+            collector.finish()?,
+            path.display()
+        );
+    }
+    Ok(())
+}
+
+fn label_by_expert(args: &ExpertArgs) -> anyhow::Result<()> {
+    let start = Instant::now();
+    let positions: Vec<(u64, Board)> = (args.seed..args.seed + args.games)
+        .into_par_iter()
+        .flat_map_iter(|seed| {
+            expert::positions(&args.policy, seed, args.every as usize)
+                .into_iter()
+                .map(move |board| (seed, board))
+        })
+        .collect();
+    println!(
+        "{} positions reached by `{}` in {:.1}s",
+        positions.len(),
+        args.policy,
+        start.elapsed().as_secs_f32()
+    );
+    let start = Instant::now();
+    let samples = expert::label(&positions, &args.expert);
+    println!(
+        "Labelled by `{}` in {:.1}s",
+        args.expert,
+        start.elapsed().as_secs_f32()
+    );
+    let collector = Collector::create(&args.output)?;
+    // the samples of a game are contiguous, as the positions
+    for game in samples.chunk_by(|a, b| a.seed == b.seed) {
+        collector.add_game(game)?;
+    }
+    println!(
+        "{} positions written to {}",
+        collector.finish()?,
+        args.output.display()
+    );
+    Ok(())
+}
+
+fn distill_policy(args: &DistillArgs) -> anyhow::Result<()> {
+    let (mut training, mut holdout) = (Vec::new(), Vec::new());
+    for path in &args.data {
+        let samples = collect::read(path)?;
+        println!("{} positions read from {}", samples.len(), path.display());
+        for sample in samples {
+            if sample.seed % args.holdout == 0 {
+                holdout.push(sample);
+            } else {
+                training.push(sample);
+            }
+        }
+    }
+    ensure!(!training.is_empty(), "No position to learn from");
+    println!(
+        "{} positions for training, {} held out",
+        training.len(),
+        holdout.len()
+    );
+    let mut policy = distill::LookupPolicy::new();
+    let mut rng = StdRng::seed_from_u64(args.seed);
+    let start = Instant::now();
+    for epoch in 1..=args.epochs {
+        let agreement = distill::train_epoch(&mut policy, &mut training, args.alpha, &mut rng);
+        println!(
+            "[{:>6.1}s] epoch {epoch:>4}   agreement during the pass {:>5.1}%   held-out accuracy {:>5.1}%",
+            start.elapsed().as_secs_f32(),
+            100.0 * agreement,
+            100.0 * distill::accuracy(&policy, &holdout)
+        );
+    }
+    policy.save(&args.output)?;
+    println!(
+        "Policy written to {}, play it with `-s distilled:file={}`",
+        args.output.display(),
+        args.output.display()
+    );
+    Ok(())
+}
+
+fn tabular_learning(args: &TabularArgs) -> anyhow::Result<()> {
+    let size = args.size as usize;
+    let mut learner = tabular::TabularLearner::new(size, args.alpha);
+    let mut rng = StdRng::seed_from_u64(args.seed);
+    let start = Instant::now();
+    let report_every = (args.episodes / 10).max(1);
+    let mut total = 0;
+    for episode in 1..=args.episodes {
+        total += learner.train_episode(&mut rng, args.epsilon) as u64;
+        if episode % report_every == 0 || episode == args.episodes {
+            println!(
+                "[{:>6.1}s] episode {episode:>8}   training score {:>8.1}   states {:>9}",
+                start.elapsed().as_secs_f32(),
+                total as f64 / report_every as f64,
+                learner.values.len()
+            );
+            total = 0;
+        }
+    }
+    let learned = tabular::evaluate(size, args.games, |board| {
+        learner.greedy(board).map(|(action, _, _)| action)
+    });
+    let searched = tabular::evaluate(size, args.games, |board| {
+        tabular::expectimax(board, args.depth as usize)
+    });
+    println!("\nOn {} games of the {size}x{size} board:", args.games);
+    for (name, results) in [
+        ("tabular".to_string(), learned),
+        (format!("expectimax (depth {})", args.depth), searched),
+    ] {
+        println!(
+            "  {name:<22} mean score {:>8.1}   mean max tile {:>7.1}",
+            results.mean_score, results.mean_max_tile
+        );
+    }
+    Ok(())
+}
+
+fn dataset(args: &DatasetArgs) -> anyhow::Result<()> {
+    let mut files = Vec::new();
+    for path in &args.files {
+        if path.is_dir() {
+            let mut replays: Vec<PathBuf> = std::fs::read_dir(path)
+                .with_context(|| format!("Cannot read {}", path.display()))?
+                .map(|entry| Ok(entry?.path()))
+                .collect::<anyhow::Result<Vec<_>>>()?
+                .into_iter()
+                .filter(|path| path.extension().is_some_and(|ext| ext == "jsonl"))
+                .collect();
+            replays.sort();
+            files.extend(replays);
+        } else {
+            files.push(path.clone());
+        }
+    }
+    let mut writer = dataset::DatasetWriter::create(&args.out)?;
+    let mut unfinished = 0;
+    for file in &files {
+        let positions = writer
+            .add_game(&read_events(file)?)
+            .with_context(|| format!("Invalid game in {}", file.display()))?;
+        if positions == 0 {
+            unfinished += 1;
+        }
+    }
+    let num_positions = writer.finish()?;
+    println!(
+        "{num_positions} positions of {} games written to {}",
+        files.len() - unfinished,
+        args.out.display()
+    );
+    if unfinished > 0 {
+        eprintln!(
+            "Warning: {unfinished} games without end were left out, their outcome being unknown"
+        );
+    }
+    Ok(())
+}
+
+fn estimate_by_rollouts(args: &RolloutsArgs) -> anyhow::Result<()> {
+    ensure!(args.games > 0, "At least one rollout is needed");
+    ensure!(
+        args.level > 0.0 && args.level < 1.0,
+        "The confidence level must be between 0 and 1"
+    );
+    let board = args.board.as_deref().map(repl::parse_board).transpose()?;
+    let start = Instant::now();
+    let games = match board {
+        Some(board) => {
+            println!("{board}");
+            rollout::rollouts(
+                rollout::Start::Board(board.into()),
+                &args.strategy,
+                args.seed,
+                args.games,
+            )
+        }
+        None => (args.seed..args.seed + args.games)
+            .into_par_iter()
+            .map(|seed| {
+                let board = PlayableBoard::init_with(&mut StdRng::seed_from_u64(seed));
+                // the start position is drawn with the seed, the rollout uses the next one
+                rollout::rollout(rollout::Start::Board(board), &args.strategy, seed + 1)
+            })
+            .collect(),
+    };
+    let estimate = rollout::Estimate::of(&games, args.level).context("No rollouts")?;
+    println!(
+        "{} rollouts of `{}` in {:.1}s\n",
+        args.games,
+        args.strategy,
+        start.elapsed().as_secs_f32()
+    );
+    print!("{estimate}");
+    if let (Some(board), true) = (board, args.per_action) {
+        println!(
+            "\n{:<6} {:>6} {:>14} {:>26}",
+            "action", "score", "afterstate", "score to come (CI)"
+        );
+        let board = PlayableBoard::from(board);
+        for (action, score, estimate) in
+            rollout::action_values(board, &args.strategy, args.seed, args.games, args.level)
+        {
+            let after = board.apply(action).expect("an applicable action");
+            println!(
+                "{:<6} {score:>6} {:>14.1} {:>8.1} [{:.1}, {:.1}]",
+                format!("{action:?}"),
+                after.evaluate(),
+                estimate.score.mean,
+                estimate.score_ci.0,
+                estimate.score_ci.1
+            );
+        }
+    }
+    Ok(())
+}
 
-        cur = played.with_random_tile();
+fn analyze(args: &AnalyzeArgs) -> anyhow::Result<()> {
+    let board = args.board.as_deref().map(repl::parse_board).transpose()?;
+    let board = match board {
+        Some(board) if !args.interactive => PlayableBoard::from(board),
+        _ => {
+            return repl::run(
+                std::io::stdin().lock(),
+                std::io::stdout(),
+                board,
+                args.strategy,
+            )
+        }
+    };
+    println!("{board}");
+    println!("Value of the board: {:.1}\n", board.evaluate());
+    println!("{:<6} {:>6} {:>14}", "action", "score", "afterstate");
+    for action in ALL_ACTIONS {
+        match board.apply_scored(action) {
+            Some((after, score)) => println!(
+                "{:<6} {score:>6} {:>14.1}",
+                format!("{action:?}"),
+                after.evaluate()
+            ),
+            None => println!("{:<6} {:>21}", format!("{action:?}"), "not applicable"),
+        }
     }
+    let start = Instant::now();
+    let selected = args.strategy.select_action(board);
+    println!(
+        "\n`{}` selects {selected:?} in {:.2}ms\n",
+        args.strategy,
+        start.elapsed().as_secs_f64() * 1000.0
+    );
+    println!("Evaluation of the board by heuristic:");
+    print!("{}", eval::explain(board.board()));
+    Ok(())
 }
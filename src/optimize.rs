@@ -0,0 +1,544 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Instant;
+
+use ai_2048::{eval, game};
+use anyhow::{ensure, Context};
+use clap::builder::PossibleValuesParser;
+use clap::{Parser, Subcommand};
+use eval::{EvalWeights, Evaluator};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rayon::prelude::*;
+
+/// Tunes the weights of the evaluation function.
+///
+/// Each candidate is evaluated by the average number of actions of a greedy player (that picks the action
+/// with the best evaluation) over a fixed set of seeded games, so that all candidates face the same games.
+#[derive(Parser, Debug)]
+#[command(version, about, long_about = None)]
+struct Args {
+    #[command(subcommand)]
+    command: Command,
+
+    /// Number of games played to evaluate each candidate
+    #[arg(short, long, default_value = "64", global = true)]
+    games: u64,
+
+    /// File with the initial weights (default weights if absent)
+    #[arg(long, global = true)]
+    init: Option<PathBuf>,
+
+    /// Named preset of initial weights, instead of a weights file
+    #[arg(long, global = true, conflicts_with = "init", value_parser = PossibleValuesParser::new(eval::presets::names()))]
+    eval_preset: Option<String>,
+
+    /// Seed of the first game (game `i` uses the seed `seed + i`), also used to seed the optimizer
+    #[arg(long, default_value = "0", global = true)]
+    seed: u64,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Improves the weights with a (1+1) evolution strategy
+    Es(EsArgs),
+    /// Evolves a population of weights by mutation and selection, keeping track of the lineage of the best
+    Population(PopulationArgs),
+    /// Improves the weights with the cross-entropy method: samples candidates from a Gaussian, and refits the
+    /// Gaussian on the best of them
+    Cem(CemArgs),
+    /// Evaluates a grid or a random sample of weight combinations and writes a CSV of weights vs average score
+    Sweep(SweepArgs),
+}
+
+#[derive(clap::Args, Debug)]
+struct EsArgs {
+    /// Number of candidates to evaluate
+    #[arg(short, long, default_value = "200")]
+    iterations: u64,
+
+    /// Comma-separated heuristics to tune (by default, all heuristics with a non-zero initial weight)
+    #[arg(long, value_delimiter = ',')]
+    tune: Vec<String>,
+
+    /// Initial mutation strength: each tuned weight is multiplied by `exp(sigma * N(0,1))`
+    #[arg(long, default_value = "0.3")]
+    sigma: f32,
+
+    /// File where the best weights are written
+    #[arg(short, long, default_value = "best.weights")]
+    output: PathBuf,
+}
+
+#[derive(clap::Args, Debug)]
+struct PopulationArgs {
+    /// Number of generations
+    #[arg(short = 'n', long, default_value = "20")]
+    generations: u64,
+
+    /// Number of candidates in each generation
+    #[arg(short, long, default_value = "16")]
+    population: usize,
+
+    /// Number of the best candidates of a generation that survive to the next one, where the other candidates are
+    /// mutations of them
+    #[arg(long, default_value = "4")]
+    survivors: usize,
+
+    /// Comma-separated heuristics to tune (by default, all heuristics with a non-zero initial weight)
+    #[arg(long, value_delimiter = ',')]
+    tune: Vec<String>,
+
+    /// Mutation strength: each tuned weight of a child is multiplied by `exp(sigma * N(0,1))`
+    #[arg(long, default_value = "0.2")]
+    sigma: f32,
+
+    /// File where the best weights are written
+    #[arg(short, long, default_value = "best.weights")]
+    output: PathBuf,
+}
+
+#[derive(clap::Args, Debug)]
+struct CemArgs {
+    /// Number of iterations
+    #[arg(short = 'n', long, default_value = "20")]
+    iterations: u64,
+
+    /// Number of candidates sampled in each iteration
+    #[arg(long, default_value = "32")]
+    samples: usize,
+
+    /// Fraction of the candidates of each iteration (the best ones) on which the Gaussian is refitted
+    #[arg(long, default_value = "0.2")]
+    elite: f32,
+
+    /// Comma-separated heuristics to tune (by default, all heuristics with a non-zero initial weight)
+    #[arg(long, value_delimiter = ',')]
+    tune: Vec<String>,
+
+    /// Initial standard deviation of each tuned weight, relative to its initial value
+    #[arg(long, default_value = "0.5")]
+    sigma: f32,
+
+    /// Smallest standard deviation of each tuned weight, relative to its initial value, so that the search does not
+    /// collapse on the elite of the first iterations
+    #[arg(long, default_value = "0.01")]
+    min_sigma: f32,
+
+    /// File where the best weights are written
+    #[arg(short, long, default_value = "best.weights")]
+    output: PathBuf,
+}
+
+#[derive(clap::Args, Debug)]
+struct SweepArgs {
+    /// Range of a weight, as `name=min:max` or `name=min:max:steps` (5 steps by default). Can be repeated.
+    /// Other weights keep their initial value.
+    #[arg(short, long, required = true)]
+    vary: Vec<WeightRange>,
+
+    /// Evaluate this many uniformly sampled combinations instead of the full grid
+    #[arg(long)]
+    samples: Option<u64>,
+
+    /// CSV file where results are written
+    #[arg(short, long, default_value = "sweep.csv")]
+    output: PathBuf,
+}
+
+/// Range of values taken by one weight in a sweep.
+#[derive(Clone, Debug)]
+struct WeightRange {
+    /// Index of the heuristic in `eval::HEURISTICS`
+    index: usize,
+    min: f32,
+    max: f32,
+    /// Number of values of the range in a grid, evenly spaced from `min` to `max`
+    steps: usize,
+}
+
+impl WeightRange {
+    /// The `i`-th value of the grid
+    fn grid_value(&self, i: usize) -> f32 {
+        if self.steps == 1 {
+            self.min
+        } else {
+            self.min + (self.max - self.min) * i as f32 / (self.steps - 1) as f32
+        }
+    }
+}
+
+impl FromStr for WeightRange {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        let (name, range) = s
+            .split_once('=')
+            .context("Expected `name=min:max[:steps]`")?;
+        let index =
+            eval::heuristic_index(name).with_context(|| format!("Unknown heuristic: {name}"))?;
+        let parts: Vec<&str> = range.split(':').collect();
+        ensure!(
+            parts.len() == 2 || parts.len() == 3,
+            "Expected `name=min:max[:steps]` but got {s}"
+        );
+        let steps = match parts.get(2) {
+            Some(steps) => steps.parse()?,
+            None => 5,
+        };
+        ensure!(steps > 0, "A range needs at least one step");
+        let (min, max): (f32, f32) = (parts[0].parse()?, parts[1].parse()?);
+        ensure!(
+            min <= max,
+            "Invalid range of {name}: its min ({min}) is above its max ({max})"
+        );
+        Ok(WeightRange {
+            index,
+            min,
+            max,
+            steps,
+        })
+    }
+}
+
+fn main() -> anyhow::Result<()> {
+    let args: Args = Args::parse();
+
+    // configure the global thread pool of rayon to have as many threads as we have *physical* CPUs
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(num_cpus::get_physical())
+        .build_global()
+        .unwrap();
+
+    let init = eval::load_weights(args.init.as_deref(), args.eval_preset.as_deref())?;
+    match &args.command {
+        Command::Es(es) => evolution_strategy(&args, es, init),
+        Command::Population(population) => evolve_population(&args, population, init),
+        Command::Cem(cem) => cross_entropy(&args, cem, init),
+        Command::Sweep(sweep) => weight_sweep(&args, sweep, init),
+    }
+}
+
+/// Runs the (1+1) evolution strategy from the initial weights.
+fn evolution_strategy(args: &Args, es: &EsArgs, mut best: EvalWeights) -> anyhow::Result<()> {
+    let tuned = tuned_weights(&best, &es.tune)?;
+    let start = Instant::now();
+    let mut rng = StdRng::seed_from_u64(args.seed);
+    let mut sigma = es.sigma;
+    let mut best_score = average_score(&best, args.games, args.seed);
+    println!("Initial weights: average score (#actions) {best_score:.1}\n{best}");
+    best.save(&es.output)?;
+
+    for iteration in 1..=es.iterations {
+        let mut candidate = best;
+        for &i in &tuned {
+            candidate.0[i] *= (sigma * gaussian(&mut rng)).exp();
+        }
+        let score = average_score(&candidate, args.games, args.seed);
+        let success = score > best_score;
+        if success {
+            best = candidate;
+            best_score = score;
+            best.save(&es.output)?;
+        }
+        // 1/5th success rule: increase the step size on success, decrease it on failure,
+        // such that it is stable when one in five mutations is successful
+        sigma *= if success { 1.5 } else { 1.5f32.powf(-0.25) };
+
+        println!(
+            "[{:>7.1}s] iteration {iteration:>5}   candidate: {score:>8.1}   best: {best_score:>8.1}   sigma: {sigma:.3}{}",
+            start.elapsed().as_secs_f32(),
+            if success { "   (improved)" } else { "" }
+        );
+    }
+    println!(
+        "\nBest weights (average score {best_score:.1}), written to {}:\n{best}",
+        es.output.display()
+    );
+    Ok(())
+}
+
+/// Indices of the weights to tune: those of the given heuristics, or all non-zero weights if none is given. The
+/// steps of the tuners being relative to the initial weights, the tuned weights must not be 0.
+fn tuned_weights(init: &EvalWeights, names: &[String]) -> anyhow::Result<Vec<usize>> {
+    if names.is_empty() {
+        return Ok((0..init.0.len()).filter(|&i| init.0[i] != 0.0).collect());
+    }
+    let mut tuned = Vec::new();
+    for name in names {
+        let Some(i) = eval::heuristic_index(name) else {
+            anyhow::bail!("Unknown heuristic: {name}");
+        };
+        ensure!(
+            init.0[i] != 0.0,
+            "Cannot tune {name}: its initial weight is 0 and the steps of the tuners are relative to it"
+        );
+        tuned.push(i);
+    }
+    Ok(tuned)
+}
+
+/// A candidate of the population
+#[derive(Clone, Copy, Debug)]
+struct Individual {
+    /// Index of the individual in the history of all individuals
+    id: usize,
+    /// Individual it is a mutation of, `None` for the initial weights
+    parent: Option<usize>,
+    /// Generation in which it was born
+    generation: u64,
+    weights: EvalWeights,
+    score: f32,
+}
+
+/// Evolves the population from the initial weights: in each generation, the best candidates survive unchanged
+/// (their score on the same games stays the same) and the others are replaced by mutations of survivors picked at
+/// random.
+fn evolve_population(
+    args: &Args,
+    population: &PopulationArgs,
+    init: EvalWeights,
+) -> anyhow::Result<()> {
+    ensure!(
+        0 < population.survivors && population.survivors < population.population,
+        "There must be at least one survivor and fewer survivors than candidates"
+    );
+    let tuned = tuned_weights(&init, &population.tune)?;
+    let start = Instant::now();
+    let mut rng = StdRng::seed_from_u64(args.seed);
+    // every individual ever evaluated, indexed by id, to trace the lineage of the best one
+    let mut history = vec![Individual {
+        id: 0,
+        parent: None,
+        generation: 0,
+        weights: init,
+        score: average_score(&init, args.games, args.seed),
+    }];
+    let mut survivors = history.clone();
+    println!(
+        "Initial weights: average score (#actions) {:.1}\n{init}",
+        history[0].score
+    );
+
+    for generation in 1..=population.generations {
+        let children: Vec<(usize, EvalWeights)> = (survivors.len()..population.population)
+            .map(|_| {
+                let parent = survivors[rng.random_range(0..survivors.len())];
+                let mut weights = parent.weights;
+                for &i in &tuned {
+                    weights.0[i] *= (population.sigma * gaussian(&mut rng)).exp();
+                }
+                (parent.id, weights)
+            })
+            .collect();
+        let scores: Vec<f32> = children
+            .par_iter()
+            .map(|(_, weights)| average_score(weights, args.games, args.seed))
+            .collect();
+        let mut candidates = survivors.clone();
+        for ((parent, weights), score) in children.into_iter().zip(scores) {
+            let child = Individual {
+                id: history.len(),
+                parent: Some(parent),
+                generation,
+                weights,
+                score,
+            };
+            history.push(child);
+            candidates.push(child);
+        }
+        // stable sort: on ties, older individuals stay first
+        candidates.sort_by(|a, b| b.score.total_cmp(&a.score));
+        let best = candidates[0];
+        best.weights.save(&population.output)?;
+        let mean = candidates.iter().map(|c| c.score).sum::<f32>() / candidates.len() as f32;
+        println!(
+            "[{:>7.1}s] generation {generation:>4}   best: {:>8.1} (#{}, born in generation {})   mean: {mean:>8.1}",
+            start.elapsed().as_secs_f32(),
+            best.score,
+            best.id,
+            best.generation
+        );
+        candidates.truncate(population.survivors);
+        survivors = candidates;
+    }
+
+    let best = survivors[0];
+    println!("\nLineage of the best weights:");
+    let mut ancestor = Some(best.id);
+    while let Some(id) = ancestor {
+        let individual = history[id];
+        println!(
+            "  #{id:<5} generation {:>4}   average score {:>8.1}",
+            individual.generation, individual.score
+        );
+        ancestor = individual.parent;
+    }
+    println!(
+        "\nBest weights (average score {:.1}), written to {}:\n{}",
+        best.score,
+        population.output.display(),
+        best.weights
+    );
+    Ok(())
+}
+
+/// Runs the cross-entropy method from the initial weights: the tuned weights are sampled from independent
+/// Gaussians centered on the initial weights, and after each iteration each Gaussian is refitted (mean and standard
+/// deviation) on the elite candidates. The best candidate ever evaluated is kept.
+fn cross_entropy(args: &Args, cem: &CemArgs, init: EvalWeights) -> anyhow::Result<()> {
+    let num_elite = (cem.elite * cem.samples as f32).round() as usize;
+    ensure!(
+        num_elite >= 2 && num_elite <= cem.samples,
+        "The elite must have at least 2 candidates among the samples"
+    );
+    let tuned = tuned_weights(&init, &cem.tune)?;
+    let scale: Vec<f32> = tuned.iter().map(|&i| init.0[i].abs()).collect();
+    let mut mean: Vec<f32> = tuned.iter().map(|&i| init.0[i]).collect();
+    let mut std: Vec<f32> = scale.iter().map(|scale| cem.sigma * scale).collect();
+
+    let start = Instant::now();
+    let mut rng = StdRng::seed_from_u64(args.seed);
+    let mut best = init;
+    let mut best_score = average_score(&best, args.games, args.seed);
+    println!("Initial weights: average score (#actions) {best_score:.1}\n{best}");
+    best.save(&cem.output)?;
+
+    for iteration in 1..=cem.iterations {
+        let candidates: Vec<EvalWeights> = (0..cem.samples)
+            .map(|_| {
+                let mut weights = init;
+                for (k, &i) in tuned.iter().enumerate() {
+                    weights.0[i] = mean[k] + std[k] * gaussian(&mut rng);
+                }
+                weights
+            })
+            .collect();
+        let mut scored: Vec<(f32, EvalWeights)> = candidates
+            .par_iter()
+            .map(|weights| (average_score(weights, args.games, args.seed), *weights))
+            .collect();
+        scored.sort_by(|(s1, _), (s2, _)| s2.total_cmp(s1));
+        let elite = &scored[..num_elite];
+        for (k, &i) in tuned.iter().enumerate() {
+            let values: Vec<f32> = elite.iter().map(|(_, weights)| weights.0[i]).collect();
+            mean[k] = values.iter().sum::<f32>() / num_elite as f32;
+            let variance =
+                values.iter().map(|v| (v - mean[k]).powi(2)).sum::<f32>() / num_elite as f32;
+            std[k] = variance.sqrt().max(cem.min_sigma * scale[k]);
+        }
+        let improved = elite[0].0 > best_score;
+        if improved {
+            (best_score, best) = elite[0];
+            best.save(&cem.output)?;
+        }
+        let elite_mean = elite.iter().map(|(score, _)| score).sum::<f32>() / num_elite as f32;
+        // spread of the Gaussian relative to the initial weights, averaged over the tuned weights
+        let spread = std
+            .iter()
+            .zip(&scale)
+            .map(|(std, scale)| std / scale)
+            .sum::<f32>()
+            / tuned.len().max(1) as f32;
+        println!(
+            "[{:>7.1}s] iteration {iteration:>4}   elite mean: {elite_mean:>8.1}   best: {best_score:>8.1}   relative std: {spread:.3}{}",
+            start.elapsed().as_secs_f32(),
+            if improved { "   (improved)" } else { "" }
+        );
+    }
+    println!(
+        "\nBest weights (average score {best_score:.1}), written to {}:\n{best}",
+        cem.output.display()
+    );
+    Ok(())
+}
+
+/// Evaluates all weights of the grid (or a random sample of the ranges) and writes one CSV line per candidate.
+fn weight_sweep(args: &Args, sweep: &SweepArgs, init: EvalWeights) -> anyhow::Result<()> {
+    let candidates: Vec<EvalWeights> = match sweep.samples {
+        Some(samples) => {
+            let mut rng = StdRng::seed_from_u64(args.seed);
+            (0..samples)
+                .map(|_| {
+                    let mut weights = init;
+                    for range in &sweep.vary {
+                        weights.0[range.index] = rng.random_range(range.min..=range.max);
+                    }
+                    weights
+                })
+                .collect()
+        }
+        None => {
+            let num_candidates: usize = sweep.vary.iter().map(|range| range.steps).product();
+            (0..num_candidates)
+                .map(|mut i| {
+                    // decompose `i` in the mixed radix given by the number of steps of each range
+                    let mut weights = init;
+                    for range in &sweep.vary {
+                        weights.0[range.index] = range.grid_value(i % range.steps);
+                        i /= range.steps;
+                    }
+                    weights
+                })
+                .collect()
+        }
+    };
+
+    let file = File::create(&sweep.output)
+        .with_context(|| format!("Cannot create {}", sweep.output.display()))?;
+    let mut csv = BufWriter::new(file);
+    let header: Vec<&str> = eval::HEURISTICS.iter().map(|h| h.name).collect();
+    writeln!(csv, "{},average_score", header.join(","))?;
+
+    let start = Instant::now();
+    let mut best: Option<(f32, EvalWeights)> = None;
+    for (i, candidate) in candidates.iter().enumerate() {
+        let score = average_score(candidate, args.games, args.seed);
+        let weights: Vec<String> = candidate.0.iter().map(|w| w.to_string()).collect();
+        writeln!(csv, "{},{score}", weights.join(","))?;
+        // flush after each candidate so that partial results survive an interrupted sweep
+        csv.flush()?;
+        println!(
+            "[{:>7.1}s] candidate {:>5}/{}   weights: [{}]   average score: {score:>8.1}",
+            start.elapsed().as_secs_f32(),
+            i + 1,
+            candidates.len(),
+            weights.join(", ")
+        );
+        if best.is_none_or(|(best_score, _)| score > best_score) {
+            best = Some((score, *candidate));
+        }
+    }
+    if let Some((score, weights)) = best {
+        println!("\nBest weights (average score {score:.1}):\n{weights}");
+    }
+    println!("Results written to {}", sweep.output.display());
+    Ok(())
+}
+
+/// Average number of actions of the greedy player over `num_games` games with seeds `seed..seed + num_games`
+fn average_score(weights: &EvalWeights, num_games: u64, seed: u64) -> f32 {
+    let evaluator = Evaluator::new(*weights);
+    let total: usize = (seed..seed + num_games)
+        .into_par_iter()
+        .map(|game_seed| play_greedy(&evaluator, game_seed))
+        .sum();
+    total as f32 / num_games as f32
+}
+
+/// Plays a full game where the action with the best evaluation is always selected, and returns the number of actions.
+fn play_greedy(evaluator: &Evaluator, seed: u64) -> usize {
+    game::play_seeded(seed, |board| {
+        game::greedy_action(board, |after| evaluator.eval(after))
+    })
+    .len()
+}
+
+/// Samples the standard normal distribution (Box-Muller transform)
+fn gaussian(rng: &mut impl Rng) -> f32 {
+    let u1: f32 = 1.0 - rng.random::<f32>(); // in (0, 1], to avoid ln(0)
+    let u2: f32 = rng.random();
+    (-2.0 * u1.ln()).sqrt() * (2.0 * std::f32::consts::PI * u2).cos()
+}
@@ -0,0 +1,377 @@
+//! Minimal writer of Parquet files, for the datasets of `dataset`: flat tables of required columns, written by row
+//! groups with a single uncompressed page per column and the plain encoding.
+//!
+//! This is the simplest layout of the format, read by all Parquet readers (pandas, polars, Arrow, DuckDB, Spark).
+//! The metadata are encoded with the compact protocol of Thrift, by hand as the rest of the file.
+
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use anyhow::{ensure, Context};
+
+/// Magic number at the start and at the end of a Parquet file
+const MAGIC: &[u8] = b"PAR1";
+
+/// Values of a column
+#[derive(Clone, Debug, PartialEq)]
+pub enum Column {
+    Int32(Vec<i32>),
+    Int64(Vec<i64>),
+    UInt64(Vec<u64>),
+    Float(Vec<f32>),
+    Utf8(Vec<String>),
+}
+
+impl Column {
+    pub fn len(&self) -> usize {
+        match self {
+            Column::Int32(values) => values.len(),
+            Column::Int64(values) => values.len(),
+            Column::UInt64(values) => values.len(),
+            Column::Float(values) => values.len(),
+            Column::Utf8(values) => values.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Physical type of the column in Parquet, and its converted type if any
+    fn types(&self) -> (i32, Option<i32>) {
+        match self {
+            Column::Int32(_) => (1, None),
+            Column::Int64(_) => (2, None),
+            // UINT_64
+            Column::UInt64(_) => (2, Some(14)),
+            Column::Float(_) => (4, None),
+            // UTF8
+            Column::Utf8(_) => (6, Some(0)),
+        }
+    }
+
+    /// Values in the plain encoding: little-endian numbers, and strings prefixed by their length
+    fn plain(&self) -> Vec<u8> {
+        let mut data = Vec::new();
+        match self {
+            Column::Int32(values) => values.iter().for_each(|v| data.extend(v.to_le_bytes())),
+            Column::Int64(values) => values.iter().for_each(|v| data.extend(v.to_le_bytes())),
+            Column::UInt64(values) => values.iter().for_each(|v| data.extend(v.to_le_bytes())),
+            Column::Float(values) => values.iter().for_each(|v| data.extend(v.to_le_bytes())),
+            Column::Utf8(values) => {
+                for value in values {
+                    data.extend((value.len() as u32).to_le_bytes());
+                    data.extend(value.as_bytes());
+                }
+            }
+        }
+        data
+    }
+}
+
+/// Location of a column chunk in the file
+struct ChunkMeta {
+    offset: u64,
+    size: u64,
+    num_values: usize,
+}
+
+struct RowGroupMeta {
+    chunks: Vec<ChunkMeta>,
+    num_rows: usize,
+}
+
+/// Writes a table to a Parquet file, one row group at a time.
+pub struct ParquetWriter {
+    out: BufWriter<File>,
+    /// Number of bytes written so far
+    offset: u64,
+    /// Name and types of each column, set by the first row group
+    schema: Vec<(String, (i32, Option<i32>))>,
+    row_groups: Vec<RowGroupMeta>,
+}
+
+impl ParquetWriter {
+    pub fn create(path: &Path) -> anyhow::Result<ParquetWriter> {
+        let file =
+            File::create(path).with_context(|| format!("Cannot create {}", path.display()))?;
+        let mut out = BufWriter::new(file);
+        out.write_all(MAGIC)?;
+        Ok(ParquetWriter {
+            out,
+            offset: MAGIC.len() as u64,
+            schema: Vec::new(),
+            row_groups: Vec::new(),
+        })
+    }
+
+    /// Writes a row group, made of the named columns. All row groups must have the same columns, in the same order.
+    pub fn write_row_group(&mut self, columns: &[(&str, Column)]) -> anyhow::Result<()> {
+        let schema: Vec<(String, (i32, Option<i32>))> = columns
+            .iter()
+            .map(|(name, column)| (name.to_string(), column.types()))
+            .collect();
+        if self.schema.is_empty() {
+            self.schema = schema;
+        } else {
+            ensure!(
+                schema == self.schema,
+                "The columns differ from those of the first row group"
+            );
+        }
+        let num_rows = columns.first().map_or(0, |(_, column)| column.len());
+        ensure!(
+            columns.iter().all(|(_, column)| column.len() == num_rows),
+            "The columns of a row group must have the same length"
+        );
+        let mut chunks = Vec::new();
+        for (_, column) in columns {
+            let data = column.plain();
+            let mut header = Thrift::new();
+            // DATA_PAGE
+            header.i32(1, 0);
+            header.i32(2, data.len() as i32);
+            header.i32(3, data.len() as i32);
+            header.begin_struct(5);
+            header.i32(1, num_rows as i32);
+            // PLAIN values, and RLE for the levels (absent for required columns)
+            header.i32(2, 0);
+            header.i32(3, 3);
+            header.i32(4, 3);
+            header.end_struct();
+            header.stop();
+            self.out.write_all(&header.buf)?;
+            self.out.write_all(&data)?;
+            let size = (header.buf.len() + data.len()) as u64;
+            chunks.push(ChunkMeta {
+                offset: self.offset,
+                size,
+                num_values: num_rows,
+            });
+            self.offset += size;
+        }
+        self.row_groups.push(RowGroupMeta { chunks, num_rows });
+        Ok(())
+    }
+
+    pub fn num_row_groups(&self) -> usize {
+        self.row_groups.len()
+    }
+
+    /// Writes the metadata at the end of the file. Returns the number of rows.
+    pub fn finish(mut self) -> anyhow::Result<usize> {
+        let num_rows: usize = self.row_groups.iter().map(|group| group.num_rows).sum();
+        let mut meta = Thrift::new();
+        meta.i32(1, 1);
+        // the root of the schema, with a leaf per column
+        meta.list(2, STRUCT, self.schema.len() + 1);
+        meta.begin_element();
+        meta.i32(3, 0);
+        meta.binary(4, b"schema");
+        meta.i32(5, self.schema.len() as i32);
+        meta.end_struct();
+        for (name, (physical, converted)) in &self.schema {
+            meta.begin_element();
+            meta.i32(1, *physical);
+            // REQUIRED
+            meta.i32(3, 0);
+            meta.binary(4, name.as_bytes());
+            if let Some(converted) = converted {
+                meta.i32(6, *converted);
+            }
+            meta.end_struct();
+        }
+        meta.i64(3, num_rows as i64);
+        meta.list(4, STRUCT, self.row_groups.len());
+        for group in &self.row_groups {
+            meta.begin_element();
+            meta.list(1, STRUCT, group.chunks.len());
+            for (chunk, (name, (physical, _))) in group.chunks.iter().zip(&self.schema) {
+                meta.begin_element();
+                meta.i64(2, chunk.offset as i64);
+                meta.begin_struct(3);
+                meta.i32(1, *physical);
+                meta.list(2, I32, 1);
+                meta.element_i32(0);
+                meta.list(3, BINARY, 1);
+                meta.element_binary(name.as_bytes());
+                // UNCOMPRESSED
+                meta.i32(4, 0);
+                meta.i64(5, chunk.num_values as i64);
+                meta.i64(6, chunk.size as i64);
+                meta.i64(7, chunk.size as i64);
+                meta.i64(9, chunk.offset as i64);
+                meta.end_struct();
+                meta.end_struct();
+            }
+            let size: u64 = group.chunks.iter().map(|chunk| chunk.size).sum();
+            meta.i64(2, size as i64);
+            meta.i64(3, group.num_rows as i64);
+            meta.end_struct();
+        }
+        meta.binary(
+            6,
+            format!("ai-2048 version {}", env!("CARGO_PKG_VERSION")).as_bytes(),
+        );
+        meta.stop();
+        self.out.write_all(&meta.buf)?;
+        self.out.write_all(&(meta.buf.len() as u32).to_le_bytes())?;
+        self.out.write_all(MAGIC)?;
+        self.out.flush()?;
+        Ok(num_rows)
+    }
+}
+
+/// Types of the compact protocol of Thrift
+const I32: u8 = 5;
+const I64: u8 = 6;
+const BINARY: u8 = 8;
+const LIST: u8 = 9;
+const STRUCT: u8 = 12;
+
+/// Encoder of a Thrift struct in the compact protocol
+struct Thrift {
+    buf: Vec<u8>,
+    /// Id of the last field written in each struct being written, from the outermost one
+    last_ids: Vec<i16>,
+}
+
+impl Thrift {
+    fn new() -> Thrift {
+        Thrift {
+            buf: Vec::new(),
+            last_ids: vec![0],
+        }
+    }
+
+    fn varint(&mut self, mut value: u64) {
+        while value >= 0x80 {
+            self.buf.push(value as u8 | 0x80);
+            value >>= 7;
+        }
+        self.buf.push(value as u8);
+    }
+
+    fn zigzag(&mut self, value: i64) {
+        self.varint(((value << 1) ^ (value >> 63)) as u64);
+    }
+
+    fn field(&mut self, id: i16, kind: u8) {
+        let last = self.last_ids.last_mut().expect("a struct being written");
+        let delta = id - *last;
+        *last = id;
+        if (1..=15).contains(&delta) {
+            self.buf.push((delta as u8) << 4 | kind);
+        } else {
+            self.buf.push(kind);
+            self.zigzag(id.into());
+        }
+    }
+
+    fn i32(&mut self, id: i16, value: i32) {
+        self.field(id, I32);
+        self.zigzag(value.into());
+    }
+
+    fn i64(&mut self, id: i16, value: i64) {
+        self.field(id, I64);
+        self.zigzag(value);
+    }
+
+    fn binary(&mut self, id: i16, value: &[u8]) {
+        self.field(id, BINARY);
+        self.element_binary(value);
+    }
+
+    fn list(&mut self, id: i16, element: u8, len: usize) {
+        self.field(id, LIST);
+        if len < 15 {
+            self.buf.push((len as u8) << 4 | element);
+        } else {
+            self.buf.push(0xf0 | element);
+            self.varint(len as u64);
+        }
+    }
+
+    fn element_i32(&mut self, value: i32) {
+        self.zigzag(value.into());
+    }
+
+    fn element_binary(&mut self, value: &[u8]) {
+        self.varint(value.len() as u64);
+        self.buf.extend(value);
+    }
+
+    fn begin_struct(&mut self, id: i16) {
+        self.field(id, STRUCT);
+        self.last_ids.push(0);
+    }
+
+    /// Starts a struct element of a list
+    fn begin_element(&mut self) {
+        self.last_ids.push(0);
+    }
+
+    fn end_struct(&mut self) {
+        self.stop();
+        self.last_ids.pop();
+    }
+
+    /// Ends the outermost struct
+    fn stop(&mut self) {
+        self.buf.push(0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parquet() {
+        let path = std::env::temp_dir().join(format!("table-{}.parquet", std::process::id()));
+        let mut writer = ParquetWriter::create(&path).unwrap();
+        let group = |start: i32| {
+            vec![
+                ("id", Column::Int32(vec![start, start + 1])),
+                (
+                    "name",
+                    Column::Utf8(vec!["a".to_string(), "bc".to_string()]),
+                ),
+            ]
+        };
+        writer.write_row_group(&group(0)).unwrap();
+        writer.write_row_group(&group(2)).unwrap();
+        assert!(writer
+            .write_row_group(&[("id", Column::Float(vec![1.0]))])
+            .is_err());
+        assert_eq!(writer.finish().unwrap(), 4);
+
+        let bytes = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(&bytes[..4], MAGIC);
+        assert_eq!(&bytes[bytes.len() - 4..], MAGIC);
+        let footer_len =
+            u32::from_le_bytes(bytes[bytes.len() - 8..bytes.len() - 4].try_into().unwrap())
+                as usize;
+        let footer = &bytes[bytes.len() - 8 - footer_len..bytes.len() - 8];
+        // the metadata start with the version, then the list of the 3 elements of the schema
+        assert_eq!(&footer[..3], &[0x15, 0x02, 0x19]);
+        assert_eq!(footer[3], 0x3c);
+        // the first page holds the plain values right after its header
+        let values: Vec<u8> = [0i32, 1].iter().flat_map(|v| v.to_le_bytes()).collect();
+        let first_page = &bytes[4..];
+        let header_len = first_page
+            .windows(values.len())
+            .position(|window| window == values)
+            .unwrap();
+        // type DATA_PAGE, 8 bytes of values, twice, and the header of the data page with 2 values
+        assert_eq!(
+            &first_page[..8],
+            &[0x15, 0x00, 0x15, 0x10, 0x15, 0x10, 0x2c, 0x15]
+        );
+        assert_eq!(first_page[8], 0x04);
+        assert!(header_len < 20);
+    }
+}
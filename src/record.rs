@@ -0,0 +1,168 @@
+//! Compact records of games, to share them or to submit them to a leaderboard.
+//!
+//! A record is a single line holding the seed of the random tiles, the claimed 2048 score and the actions as
+//! letters (`U`, `D`, `L`, `R`):
+//!
+//! ```text
+//! 2048:1:42:1312:LURDLLUR...
+//! ```
+//!
+//! The tiles only depend on the seed, so anyone can verify a record by replaying its actions and comparing the score
+//! reached with the claimed one.
+
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+use anyhow::{bail, ensure, Context};
+
+use crate::board::{Action, Board};
+use crate::replay::Event;
+use crate::savegame::GameInProgress;
+
+/// Prefix and version of the format
+const MAGIC: &str = "2048";
+const VERSION: u32 = 1;
+
+/// A game as a seed, a list of actions and the score they are claimed to reach
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Record {
+    pub seed: u64,
+    pub claimed_score: u32,
+    pub actions: Vec<Action>,
+}
+
+/// Outcome of a record replayed by `Record::verify`
+pub struct Verified {
+    pub num_moves: usize,
+    pub score: u32,
+    pub board: Board,
+}
+
+impl Record {
+    /// Record of the actions played so far in the game.
+    pub fn of(game: &GameInProgress) -> Record {
+        Record {
+            seed: game.seed(),
+            claimed_score: game.merge_score(),
+            actions: game.actions().to_vec(),
+        }
+    }
+
+    /// Record of a replay (see `replay`), or of a saved game converted with `GameInProgress::to_replay`.
+    pub fn of_events(events: &[Event]) -> anyhow::Result<Record> {
+        let Some(Event::Start { seed, .. }) = events.first() else {
+            bail!("The events do not start with the start of a game");
+        };
+        let mut record = Record {
+            seed: *seed,
+            claimed_score: 0,
+            actions: Vec::new(),
+        };
+        for event in events {
+            if let Event::Move { action, score, .. } = event {
+                record.actions.push(*action);
+                record.claimed_score += score;
+            }
+        }
+        Ok(record)
+    }
+
+    /// Replays the actions on the tiles of the seed, checking that each of them is applicable and that they reach
+    /// the claimed score.
+    pub fn verify(&self) -> anyhow::Result<Verified> {
+        let mut game = GameInProgress::new(self.seed);
+        for (i, &action) in self.actions.iter().enumerate() {
+            game.play(action)
+                .with_context(|| format!("Move {}: {action:?} is not applicable", i + 1))?;
+        }
+        ensure!(
+            game.merge_score() == self.claimed_score,
+            "The record claims a score of {}, but its actions reach {}",
+            self.claimed_score,
+            game.merge_score()
+        );
+        Ok(Verified {
+            num_moves: game.num_moves(),
+            score: game.merge_score(),
+            board: *game.board().board(),
+        })
+    }
+}
+
+fn letter(action: Action) -> char {
+    match action {
+        Action::Up => 'U',
+        Action::Down => 'D',
+        Action::Left => 'L',
+        Action::Right => 'R',
+    }
+}
+
+impl Display for Record {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let actions: String = self.actions.iter().map(|&action| letter(action)).collect();
+        write!(
+            f,
+            "{MAGIC}:{VERSION}:{}:{}:{actions}",
+            self.seed, self.claimed_score
+        )
+    }
+}
+
+impl FromStr for Record {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Record> {
+        let fields: Vec<&str> = s.trim().split(':').collect();
+        let [MAGIC, version, seed, score, actions] = fields[..] else {
+            bail!("A record has the form `{MAGIC}:{VERSION}:<seed>:<score>:<actions>`");
+        };
+        ensure!(
+            version == VERSION.to_string(),
+            "Unsupported version of record: {version}"
+        );
+        let actions = actions
+            .chars()
+            .map(|c| match c.to_ascii_uppercase() {
+                'U' => Ok(Action::Up),
+                'D' => Ok(Action::Down),
+                'L' => Ok(Action::Left),
+                'R' => Ok(Action::Right),
+                _ => bail!("Invalid action `{c}`, expected U, D, L or R"),
+            })
+            .collect::<anyhow::Result<_>>()?;
+        Ok(Record {
+            seed: seed.parse().context("Invalid seed")?,
+            claimed_score: score.parse().context("Invalid score")?,
+            actions,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::ALL_ACTIONS;
+
+    #[test]
+    fn test_record() {
+        let mut game = GameInProgress::new(11);
+        for action in ALL_ACTIONS.iter().cycle().take(30) {
+            game.play(*action);
+        }
+        let record = Record::of(&game);
+        let parsed: Record = record.to_string().parse().unwrap();
+        assert_eq!(parsed, record);
+        assert_eq!(Record::of_events(&game.to_replay("test")).unwrap(), record);
+        let verified = parsed.verify().unwrap();
+        assert_eq!(verified.score, game.merge_score());
+        assert_eq!(verified.board, *game.board().board());
+
+        let inflated = Record {
+            claimed_score: record.claimed_score + 4,
+            ..record
+        };
+        assert!(inflated.verify().is_err());
+        assert!("2048:2:11:0:".parse::<Record>().is_err());
+    }
+}
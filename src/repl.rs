@@ -0,0 +1,307 @@
+//! Interactive analysis of positions (`main analyze` without a board, or with `-i`): enter a board, then study it
+//! with commands, playing actions and taking them back to explore the game.
+//!
+//! ```text
+//! > 2 4 0 0 / 0 0 0 0 / 0 8 0 0 / 0 0 0 2
+//! > best
+//! > tree 2
+//! > play left
+//! > undo
+//! ```
+//!
+//! A command that panics (e.g. a search function left as `todo!()`) is reported, and the session goes on.
+
+use std::io::{BufRead, Write};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::time::Instant;
+
+use anyhow::{bail, ensure, Context};
+
+use crate::board::{Action, Board, PlayableBoard, ALL_ACTIONS, N};
+use crate::eval;
+use crate::search;
+use crate::strategy::Strategy;
+
+const HELP: &str = "\
+Commands:
+  <16 tiles>             sets the board, given row by row (0 for empty cells), e.g. 2 4 0 0 / 0 0 0 0 / ...
+  show                   prints the board
+  eval                   evaluation of the board, by heuristic
+  best                   action selected by the strategy, and the score and afterstate value of each action
+  tree [depth]           expected value of each action looking `depth` actions ahead (default 2), with the best and
+                         worst random tiles after it
+  play <action> [r c v]  plays the action (up, down, left, right or u, d, l, r), then places the tile of value `v`
+                         at row `r` and column `c` (counted from 0), or a random tile
+  strategy <strategy>    changes the strategy (e.g. `expectimax:depth=3`)
+  undo                   goes back to the previous board
+  help                   prints this help
+  quit                   ends the session";
+
+/// Number of random tiles shown after each action by `tree`, at both ends of the values
+const TREE_SPAWNS: usize = 2;
+
+struct Session {
+    /// Boards entered or reached, the current one last
+    history: Vec<Board>,
+    strategy: Strategy,
+}
+
+/// Runs the commands of `input` until its end or `quit`, printing their results on `output`.
+pub fn run(
+    input: impl BufRead,
+    mut output: impl Write,
+    board: Option<Board>,
+    strategy: Strategy,
+) -> anyhow::Result<()> {
+    let mut session = Session {
+        history: board.into_iter().collect(),
+        strategy,
+    };
+    writeln!(output, "Type `help` for the list of commands.")?;
+    if let Some(board) = board {
+        writeln!(output, "{board}")?;
+    }
+    let mut lines = input.lines();
+    loop {
+        write!(output, "> ")?;
+        output.flush()?;
+        let Some(line) = lines.next() else {
+            break;
+        };
+        let line = line?;
+        let line = line.trim();
+        if line == "quit" || line == "exit" {
+            break;
+        }
+        if line.is_empty() {
+            continue;
+        }
+        match catch_unwind(AssertUnwindSafe(|| session.execute(line))) {
+            Ok(Ok(text)) => write!(output, "{text}")?,
+            Ok(Err(e)) => writeln!(output, "Error: {e:#}")?,
+            Err(panic) => {
+                let message = panic
+                    .downcast_ref::<&str>()
+                    .map(|s| s.to_string())
+                    .or_else(|| panic.downcast_ref::<String>().cloned())
+                    .unwrap_or_default();
+                writeln!(output, "The command panicked: {message}")?;
+            }
+        }
+    }
+    Ok(())
+}
+
+impl Session {
+    fn board(&self) -> anyhow::Result<PlayableBoard> {
+        self.history
+            .last()
+            .map(|&board| board.into())
+            .context("No board yet, enter its 16 tiles")
+    }
+
+    /// Executes the command, returning the text to print.
+    fn execute(&mut self, line: &str) -> anyhow::Result<String> {
+        let (command, rest) = line.split_once(' ').unwrap_or((line, ""));
+        let rest = rest.trim();
+        let mut text = String::new();
+        match command {
+            "help" => text = format!("{HELP}\n"),
+            "show" => text = self.board()?.to_string(),
+            "eval" => {
+                let board = self.board()?;
+                text = format!(
+                    "Value of the board: {:.1}\n\n{}",
+                    board.evaluate(),
+                    eval::explain(board.board())
+                );
+            }
+            "best" => {
+                let board = self.board()?;
+                text.push_str(&format!(
+                    "{:<6} {:>6} {:>14}\n",
+                    "action", "score", "afterstate"
+                ));
+                for action in ALL_ACTIONS {
+                    text.push_str(&match board.apply_scored(action) {
+                        Some((after, score)) => format!(
+                            "{:<6} {score:>6} {:>14.1}\n",
+                            format!("{action:?}"),
+                            after.evaluate()
+                        ),
+                        None => format!("{:<6} {:>21}\n", format!("{action:?}"), "not applicable"),
+                    });
+                }
+                let start = Instant::now();
+                let selected = self.strategy.select_action(board);
+                text.push_str(&format!(
+                    "`{}` selects {selected:?} in {:.2}ms\n",
+                    self.strategy,
+                    start.elapsed().as_secs_f64() * 1000.0
+                ));
+            }
+            "tree" => {
+                let depth = match rest {
+                    "" => 2,
+                    depth => depth.parse().context("Invalid depth")?,
+                };
+                ensure!(depth >= 1, "The depth must be at least 1");
+                text = tree(self.board()?, depth);
+            }
+            "play" => {
+                let mut args = rest.split_whitespace();
+                let action = parse_action(args.next().context("Missing action")?)?;
+                let spawn: Vec<&str> = args.collect();
+                let board = self.board()?;
+                let after = board
+                    .apply(action)
+                    .with_context(|| format!("{action:?} does not move any tile"))?;
+                let next = match spawn.as_slice() {
+                    [] => *after.with_random_tile().board(),
+                    [row, col, value] => {
+                        let (row, col): (usize, usize) = (row.parse()?, col.parse()?);
+                        ensure!(row < N && col < N, "The cell must be on the board");
+                        ensure!(
+                            after.board().cells[row][col] == 0,
+                            "The cell ({row}, {col}) is not empty"
+                        );
+                        let tile = match *value {
+                            "2" => 1,
+                            "4" => 2,
+                            _ => bail!("The random tile is a 2 or a 4"),
+                        };
+                        let mut next = *after.board();
+                        next.cells[row][col] = tile;
+                        next
+                    }
+                    _ => bail!("Expected `play <action>` or `play <action> <row> <col> <value>`"),
+                };
+                self.history.push(next);
+                text = next.to_string();
+            }
+            "strategy" => {
+                self.strategy = rest.parse()?;
+                text = format!("Strategy: {}\n", self.strategy);
+            }
+            "undo" => {
+                ensure!(self.history.len() > 1, "Nothing to undo");
+                self.history.pop();
+                text = self.board()?.to_string();
+            }
+            _ if line.starts_with(|c: char| c.is_ascii_digit()) => {
+                let board = parse_board(line)?;
+                self.history.push(board);
+                text = board.to_string();
+            }
+            _ => bail!("Unknown command `{command}`, type `help` for the list of commands"),
+        }
+        Ok(text)
+    }
+}
+
+/// Expected value of each action looking `depth` actions ahead, and below each action the random tiles leading to
+/// the best and the worst boards, valued looking `depth - 1` actions ahead.
+fn tree(board: PlayableBoard, depth: usize) -> String {
+    let mut text = String::new();
+    for (action, value) in ALL_ACTIONS
+        .into_iter()
+        .zip(search::evaluate_all_actions(board, depth))
+    {
+        let (Some(value), Some(after)) = (value, board.apply(action)) else {
+            text.push_str(&format!("{action:?}: not applicable\n"));
+            continue;
+        };
+        text.push_str(&format!("{action:?}: {value:.1}\n"));
+        if depth == 1 {
+            continue;
+        }
+        // value of the best action after each random tile, `None` if the board is lost
+        let mut spawns: Vec<(Option<f32>, String, f32)> = after
+            .successors()
+            .map(|(probability, next)| {
+                let (row, col) = (0..N * N)
+                    .map(|cell| (cell / N, cell % N))
+                    .find(|&(row, col)| {
+                        after.board().cells[row][col] == 0 && next.board().cells[row][col] != 0
+                    })
+                    .expect("a new tile");
+                let value = search::evaluate_all_actions(next, depth - 1)
+                    .into_iter()
+                    .flatten()
+                    .reduce(f32::max);
+                let tile = 1u32 << next.board().cells[row][col];
+                (value, format!("{tile} at ({row}, {col})"), probability)
+            })
+            .collect();
+        spawns.sort_by(|a, b| b.0.unwrap_or(f32::MIN).total_cmp(&a.0.unwrap_or(f32::MIN)));
+        let shown: Vec<&(Option<f32>, String, f32)> = if spawns.len() > 2 * TREE_SPAWNS {
+            spawns[..TREE_SPAWNS]
+                .iter()
+                .chain(&spawns[spawns.len() - TREE_SPAWNS..])
+                .collect()
+        } else {
+            spawns.iter().collect()
+        };
+        for (i, (value, spawn, probability)) in shown.into_iter().enumerate() {
+            if i == TREE_SPAWNS && spawns.len() > 2 * TREE_SPAWNS {
+                text.push_str(&format!(
+                    "  ... {} other tiles\n",
+                    spawns.len() - 2 * TREE_SPAWNS
+                ));
+            }
+            let value = value.map_or("lost".to_string(), |value| format!("{value:.1}"));
+            text.push_str(&format!("  {spawn:<14} p={probability:.3}  {value}\n"));
+        }
+    }
+    text
+}
+
+fn parse_action(s: &str) -> anyhow::Result<Action> {
+    Ok(match s.to_ascii_lowercase().as_str() {
+        "up" | "u" => Action::Up,
+        "down" | "d" => Action::Down,
+        "left" | "l" => Action::Left,
+        "right" | "r" => Action::Right,
+        _ => bail!("Unknown action `{s}` (up, down, left or right)"),
+    })
+}
+
+/// Parses a board given by its tiles row by row (`0` for empty cells), separated by any non-digit characters.
+pub fn parse_board(s: &str) -> anyhow::Result<Board> {
+    let values = s
+        .split(|c: char| !c.is_ascii_digit())
+        .filter(|value| !value.is_empty())
+        .map(|value| value.parse::<u32>())
+        .collect::<Result<Vec<_>, _>>()?;
+    ensure!(
+        values.len() == N * N,
+        "A board has {} tiles, got {}",
+        N * N,
+        values.len()
+    );
+    Board::from_values(std::array::from_fn(|row| {
+        std::array::from_fn(|col| values[row * N + col])
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_repl() {
+        colored::control::set_override(false);
+        let input = "best\n2 2 0 0 / 0 0 0 0 / 0 0 0 0 / 0 0 0 0\nplay left 0 3 4\nfly\nplay up\nundo\nundo\nundo\nquit\nshow\n";
+        let mut output = Vec::new();
+        run(input.as_bytes(), &mut output, None, Strategy::Random).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.contains("Error: No board yet"));
+        // the merged tiles and the placed 4
+        assert!(output.contains("4       .       .       4"));
+        assert!(output.contains("Error: Unknown command `fly`"));
+        assert!(output.contains("Error: Up does not move any tile"));
+        assert!(output.contains("Error: Nothing to undo"));
+        // nothing is executed after `quit`
+        assert_eq!(output.matches("> ").count(), 9);
+    }
+}
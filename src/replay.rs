@@ -0,0 +1,188 @@
+//! Replays of games as JSON lines, one event per line.
+//!
+//! A replay starts with a `start` event holding the seed and the initial board, followed by one `move` event
+//! per action played (the action, the evaluation of the afterstate and where the random tile appeared),
+//! and ends with an `end` event.
+//!
+//! ```text
+//! {"type":"start","seed":3,"strategy":"default","board":{"cells":[[0,0,0,0],[0,1,0,0],[0,0,0,0],[0,0,0,0]]}}
+//! {"type":"move","action":"Left","value":1601023.5,"score":0,"spawn":{"row":2,"col":3,"tile":1}}
+//! ...
+//! {"type":"end","num_moves":95,"merge_score":720,"timed_out":false}
+//! ```
+
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+
+use crate::board::{Action, Board, N};
+
+/// A random tile placed on the board
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Spawn {
+    pub row: usize,
+    pub col: usize,
+    /// Exponent of the tile (1 for a 2, 2 for a 4)
+    pub tile: u8,
+}
+
+impl Spawn {
+    /// The tile placed on `before` to obtain `after`, if they differ by exactly one new tile.
+    pub fn between(before: &Board, after: &Board) -> Option<Spawn> {
+        let mut spawns = (0..N * N).filter_map(|cell| {
+            let (row, col) = (cell / N, cell % N);
+            (before.cells[row][col] == 0 && after.cells[row][col] != 0).then(|| Spawn {
+                row,
+                col,
+                tile: after.cells[row][col],
+            })
+        });
+        let spawn = spawns.next();
+        spawn.filter(|_| spawns.next().is_none())
+    }
+}
+
+/// An event of a game
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Event {
+    /// Beginning of the game, where random tiles are drawn from a generator seeded with `seed`
+    Start {
+        seed: u64,
+        strategy: String,
+        board: Board,
+    },
+    /// An action of the player, followed by the placement of a random tile
+    Move {
+        action: Action,
+        /// Value of the action for the player: its value for the strategy when known (e.g. its expected value for
+        /// expectimax in `bench`, see `Strategy::evaluate_all_actions`), or else the evaluation of the afterstate
+        value: f32,
+        /// Score of the action in the classic 2048 game
+        score: u32,
+        spawn: Spawn,
+    },
+    /// End of the game
+    End {
+        num_moves: usize,
+        merge_score: u32,
+        timed_out: bool,
+    },
+}
+
+/// Writes the events of a game to a file, one JSON object per line.
+pub struct ReplayWriter {
+    out: BufWriter<File>,
+}
+
+impl ReplayWriter {
+    pub fn create(path: &Path) -> anyhow::Result<ReplayWriter> {
+        let file =
+            File::create(path).with_context(|| format!("Cannot create {}", path.display()))?;
+        Ok(ReplayWriter {
+            out: BufWriter::new(file),
+        })
+    }
+
+    pub fn write(&mut self, event: &Event) -> anyhow::Result<()> {
+        serde_json::to_writer(&mut self.out, event)?;
+        writeln!(self.out)?;
+        Ok(())
+    }
+
+    /// Flushes the events to the file, which is otherwise done when the writer is dropped (ignoring errors).
+    pub fn finish(mut self) -> anyhow::Result<()> {
+        self.out.flush()?;
+        Ok(())
+    }
+}
+
+/// Reads all events of a replay file.
+pub fn read(path: &Path) -> anyhow::Result<Vec<Event>> {
+    let file = File::open(path).with_context(|| format!("Cannot open {}", path.display()))?;
+    BufReader::new(file)
+        .lines()
+        .enumerate()
+        .map(|(line_number, line)| {
+            let event = serde_json::from_str(&line?);
+            event
+                .with_context(|| format!("Invalid event at {}:{}", path.display(), line_number + 1))
+        })
+        .collect()
+}
+
+/// Board before each move of the events, with the action played on it.
+pub fn positions(events: &[Event]) -> anyhow::Result<Vec<(Board, Action)>> {
+    let mut board = Board::EMPTY;
+    let mut positions = Vec::new();
+    for event in events {
+        match *event {
+            Event::Start { board: start, .. } => board = start,
+            Event::Move { action, spawn, .. } => {
+                positions.push((board, action));
+                board = board.apply(action).with_context(|| {
+                    format!("Move {}: {action:?} is not applicable", positions.len())
+                })?;
+                board.cells[spawn.row][spawn.col] = spawn.tile;
+            }
+            Event::End { .. } => break,
+        }
+    }
+    Ok(positions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_roundtrip() {
+        let before = Board {
+            cells: [[1, 0, 0, 0], [0; N], [0; N], [0; N]],
+        };
+        let mut after = before;
+        after.cells[2][3] = 2;
+        let spawn = Spawn::between(&before, &after).unwrap();
+        assert_eq!(
+            spawn,
+            Spawn {
+                row: 2,
+                col: 3,
+                tile: 2
+            }
+        );
+        assert_eq!(Spawn::between(&before, &before), None);
+
+        let events = [
+            Event::Start {
+                seed: 3,
+                strategy: "random".to_string(),
+                board: before,
+            },
+            Event::Move {
+                action: Action::Right,
+                value: 1.5,
+                score: 4,
+                spawn,
+            },
+            Event::End {
+                num_moves: 1,
+                merge_score: 4,
+                timed_out: false,
+            },
+        ];
+        let path = std::env::temp_dir().join(format!("replay-{}.jsonl", std::process::id()));
+        let mut writer = ReplayWriter::create(&path).unwrap();
+        for event in &events {
+            writer.write(event).unwrap();
+        }
+        writer.finish().unwrap();
+        let read_events = read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(read_events, events);
+        assert_eq!(positions(&events).unwrap(), vec![(before, Action::Right)]);
+    }
+}
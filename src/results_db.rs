@@ -0,0 +1,230 @@
+//! Store of benchmark results in a SQLite database, to follow the performance of an agent from run to run.
+//!
+//! Each run is recorded with its configuration, the commit of the code and when it started, along with every game
+//! it played. Requires the `db` feature.
+
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::Context;
+use rusqlite::{params, Connection};
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS runs (
+    id INTEGER PRIMARY KEY,
+    started_at INTEGER NOT NULL,
+    git_commit TEXT,
+    strategy TEXT NOT NULL,
+    config TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS games (
+    run_id INTEGER NOT NULL REFERENCES runs(id),
+    seed INTEGER NOT NULL,
+    score REAL NOT NULL,
+    merge_score INTEGER NOT NULL,
+    max_tile INTEGER NOT NULL,
+    timed_out INTEGER NOT NULL,
+    mean_move_time REAL NOT NULL
+);
+CREATE INDEX IF NOT EXISTS games_run ON games(run_id);
+";
+
+/// A benchmark run
+pub struct Run<'a> {
+    /// Start of the run, in seconds since the Unix epoch
+    pub started_at: i64,
+    pub git_commit: Option<String>,
+    pub strategy: &'a str,
+    /// Parameters of the run, as JSON
+    pub config: &'a str,
+}
+
+/// A game of a run
+pub struct Game {
+    pub seed: u64,
+    /// Number of actions played
+    pub score: f32,
+    pub merge_score: u32,
+    /// Exponent of the largest tile at the end of the game
+    pub max_tile: u8,
+    pub timed_out: bool,
+    /// Mean time to select an action, in seconds
+    pub mean_move_time: f64,
+}
+
+/// Statistics over the games of a past run
+#[derive(Debug, PartialEq)]
+pub struct RunSummary {
+    pub id: i64,
+    /// Start of the run, as `YYYY-MM-DD HH:MM:SS` (UTC)
+    pub started_at: String,
+    pub git_commit: Option<String>,
+    pub strategy: String,
+    pub num_games: i64,
+    pub mean_score: f64,
+    pub mean_merge_score: f64,
+    /// Fraction of the games reaching the 2048 tile
+    pub win_rate: f64,
+}
+
+pub struct ResultsDb {
+    conn: Connection,
+}
+
+impl ResultsDb {
+    /// Opens the database, creating it and its tables if needed.
+    pub fn open(path: &Path) -> anyhow::Result<ResultsDb> {
+        let conn = Connection::open(path)
+            .with_context(|| format!("Cannot open the database {}", path.display()))?;
+        conn.execute_batch(SCHEMA)?;
+        Ok(ResultsDb { conn })
+    }
+
+    /// Records a run and all its games, returning the id of the run.
+    pub fn insert(&mut self, run: &Run, games: &[Game]) -> anyhow::Result<i64> {
+        let tx = self.conn.transaction()?;
+        tx.execute(
+            "INSERT INTO runs (started_at, git_commit, strategy, config) VALUES (?1, ?2, ?3, ?4)",
+            params![run.started_at, run.git_commit, run.strategy, run.config],
+        )?;
+        let run_id = tx.last_insert_rowid();
+        {
+            let mut insert = tx.prepare(
+                "INSERT INTO games (run_id, seed, score, merge_score, max_tile, timed_out, mean_move_time)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            )?;
+            for game in games {
+                // SQLite integers are signed: seeds are stored with the same bits
+                insert.execute(params![
+                    run_id,
+                    game.seed as i64,
+                    game.score,
+                    game.merge_score,
+                    game.max_tile,
+                    game.timed_out,
+                    game.mean_move_time
+                ])?;
+            }
+        }
+        tx.commit()?;
+        Ok(run_id)
+    }
+
+    /// Summaries of the last runs, from the oldest to the most recent.
+    pub fn history(&self, last: usize) -> anyhow::Result<Vec<RunSummary>> {
+        let mut query = self.conn.prepare(
+            "SELECT runs.id, datetime(runs.started_at, 'unixepoch'), runs.git_commit, runs.strategy,
+                    COUNT(games.seed), AVG(games.score), AVG(games.merge_score),
+                    AVG(games.max_tile >= 11)
+             FROM runs LEFT JOIN games ON games.run_id = runs.id
+             GROUP BY runs.id
+             ORDER BY runs.id DESC
+             LIMIT ?1",
+        )?;
+        let mut runs = query
+            .query_map([last as i64], |row| {
+                Ok(RunSummary {
+                    id: row.get(0)?,
+                    started_at: row.get(1)?,
+                    git_commit: row.get(2)?,
+                    strategy: row.get(3)?,
+                    num_games: row.get(4)?,
+                    mean_score: row.get::<_, Option<f64>>(5)?.unwrap_or(f64::NAN),
+                    mean_merge_score: row.get::<_, Option<f64>>(6)?.unwrap_or(f64::NAN),
+                    win_rate: row.get::<_, Option<f64>>(7)?.unwrap_or(f64::NAN),
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        runs.reverse();
+        Ok(runs)
+    }
+
+    /// All games of all runs, with the strategy and configuration of their run. The strategy, seeds and number of
+    /// threads are removed from the configuration, so that runs of the same experiment on different seeds share it.
+    pub fn games(&self) -> anyhow::Result<Vec<(String, String, Game)>> {
+        let mut query = self.conn.prepare(
+            "SELECT runs.strategy, json_remove(runs.config, '$.strategy', '$.seeds', '$.threads'),
+                    games.seed, games.score, games.merge_score, games.max_tile, games.timed_out,
+                    games.mean_move_time
+             FROM games JOIN runs ON games.run_id = runs.id
+             ORDER BY runs.id",
+        )?;
+        let games = query
+            .query_map([], |row| {
+                let game = Game {
+                    seed: row.get::<_, i64>(2)? as u64,
+                    score: row.get(3)?,
+                    merge_score: row.get(4)?,
+                    max_tile: row.get(5)?,
+                    timed_out: row.get(6)?,
+                    mean_move_time: row.get(7)?,
+                };
+                Ok((row.get(0)?, row.get(1)?, game))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(games)
+    }
+}
+
+/// Seconds since the Unix epoch
+pub fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs() as i64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_history() {
+        let mut db = ResultsDb::open(Path::new(":memory:")).unwrap();
+        let game = |seed, score, max_tile| Game {
+            seed,
+            score,
+            merge_score: 1000,
+            max_tile,
+            timed_out: false,
+            mean_move_time: 0.001,
+        };
+        let run = |strategy| Run {
+            started_at: 0,
+            git_commit: Some("abc1234".to_string()),
+            strategy,
+            config: r#"{"seeds":"1..3","strategy":"random","threads":4,"timeout":600}"#,
+        };
+        db.insert(&run("random"), &[game(1, 100.0, 8), game(2, 200.0, 9)])
+            .unwrap();
+        db.insert(
+            &run("expectimax:depth=3"),
+            &[game(1, 900.0, 11), game(u64::MAX, 700.0, 10)],
+        )
+        .unwrap();
+        db.insert(&run("greedy"), &[]).unwrap();
+
+        let history = db.history(2).unwrap();
+        assert_eq!(history.len(), 2);
+        assert_eq!(
+            history[0],
+            RunSummary {
+                id: 2,
+                started_at: "1970-01-01 00:00:00".to_string(),
+                git_commit: Some("abc1234".to_string()),
+                strategy: "expectimax:depth=3".to_string(),
+                num_games: 2,
+                mean_score: 800.0,
+                mean_merge_score: 1000.0,
+                win_rate: 0.5,
+            }
+        );
+        assert_eq!(history[1].num_games, 0);
+        assert!(history[1].mean_score.is_nan());
+
+        let games = db.games().unwrap();
+        assert_eq!(games.len(), 4);
+        assert_eq!(games[0].1, r#"{"timeout":600}"#);
+        assert_eq!(games[3].0, "expectimax:depth=3");
+        assert_eq!(games[3].2.seed, u64::MAX);
+    }
+}
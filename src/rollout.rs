@@ -0,0 +1,190 @@
+//! Monte Carlo evaluation of a strategy (`main rollouts`): many seeded games are played by the strategy from a
+//! start position, and the distribution of what they achieve from there (number of moves, score, largest tile)
+//! estimates the value of the position for this strategy, with confidence intervals.
+//!
+//! Starting the rollouts from the afterstate of each action gives the empirical value of each action, to compare
+//! with the evaluation of the afterstates: an evaluation function ranking the actions as their empirical values is
+//! a good guide for the search.
+
+use std::fmt::{Display, Formatter};
+
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use rayon::prelude::*;
+
+use crate::board::{Action, PlayableBoard, RandableBoard, ALL_ACTIONS};
+use crate::stats::{self, Summary};
+use crate::strategy::Strategy;
+
+/// What a game achieved from the start position
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Rollout {
+    pub seed: u64,
+    pub moves: usize,
+    pub score: u32,
+    /// Exponent of the largest tile at the end of the game
+    pub max_tile: u8,
+}
+
+/// Position the rollouts start from
+#[derive(Clone, Copy)]
+pub enum Start {
+    /// A board on which the strategy plays first
+    Board(PlayableBoard),
+    /// An afterstate, on which a random tile is added first
+    Afterstate(RandableBoard),
+}
+
+/// Plays a game with the strategy from the start position, the random tiles being drawn from a generator seeded
+/// with `seed`.
+pub fn rollout(start: Start, strategy: &Strategy, seed: u64) -> Rollout {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut board = match start {
+        Start::Board(board) => board,
+        Start::Afterstate(after) => after.with_random_tile_with(&mut rng),
+    };
+    let (mut moves, mut score) = (0, 0);
+    while let Some((after, gain)) = strategy
+        .select_action(board)
+        .and_then(|action| board.apply_scored(action))
+    {
+        moves += 1;
+        score += gain;
+        board = after.with_random_tile_with(&mut rng);
+    }
+    Rollout {
+        seed,
+        moves,
+        score,
+        max_tile: board.board().max_tile(),
+    }
+}
+
+/// Plays the rollouts of the seeds `seed..seed + num_games`, in parallel.
+pub fn rollouts(start: Start, strategy: &Strategy, seed: u64, num_games: u64) -> Vec<Rollout> {
+    (seed..seed + num_games)
+        .into_par_iter()
+        .map(|seed| rollout(start, strategy, seed))
+        .collect()
+}
+
+/// Estimate of the value of a position from its rollouts
+#[derive(Clone, Debug, PartialEq)]
+pub struct Estimate {
+    pub moves: Summary,
+    pub score: Summary,
+    /// Bootstrap confidence intervals of the mean number of moves and of the mean score
+    pub moves_ci: (f64, f64),
+    pub score_ci: (f64, f64),
+    /// Confidence level of the intervals
+    pub level: f64,
+    /// Number of rollouts ending with each largest tile (by exponent), in increasing order of tiles
+    pub max_tiles: Vec<(u8, usize)>,
+}
+
+impl Estimate {
+    /// Estimates the value from the rollouts, with intervals at the given confidence level (e.g. 0.95). Returns
+    /// `None` if there are no rollouts.
+    pub fn of(rollouts: &[Rollout], level: f64) -> Option<Estimate> {
+        let moves: Vec<f64> = rollouts.iter().map(|r| r.moves as f64).collect();
+        let scores: Vec<f64> = rollouts.iter().map(|r| r.score as f64).collect();
+        let mut max_tiles: Vec<(u8, usize)> = Vec::new();
+        let mut tiles: Vec<u8> = rollouts.iter().map(|r| r.max_tile).collect();
+        tiles.sort_unstable();
+        for tile in tiles {
+            match max_tiles.last_mut() {
+                Some((last, count)) if *last == tile => *count += 1,
+                _ => max_tiles.push((tile, 1)),
+            }
+        }
+        Some(Estimate {
+            moves: Summary::of(&moves)?,
+            score: Summary::of(&scores)?,
+            moves_ci: stats::bootstrap_ci(&moves, stats::mean, level),
+            score_ci: stats::bootstrap_ci(&scores, stats::mean, level),
+            level,
+            max_tiles,
+        })
+    }
+}
+
+impl Display for Estimate {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let level = 100.0 * self.level;
+        writeln!(
+            f,
+            "Moves (mean in [{:.1}, {:.1}] at {level}%):",
+            self.moves_ci.0, self.moves_ci.1
+        )?;
+        write!(f, "{}", self.moves)?;
+        writeln!(
+            f,
+            "Score (mean in [{:.1}, {:.1}] at {level}%):",
+            self.score_ci.0, self.score_ci.1
+        )?;
+        write!(f, "{}", self.score)?;
+        writeln!(f, "Largest tile:")?;
+        for (tile, count) in &self.max_tiles {
+            writeln!(
+                f,
+                "  {:>6}: {:>5.1}%",
+                1u32 << tile,
+                100.0 * *count as f64 / self.moves.count as f64
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Empirical value of each applicable action: the estimate of the rollouts from its afterstate, and the score of
+/// the action itself.
+pub fn action_values(
+    board: PlayableBoard,
+    strategy: &Strategy,
+    seed: u64,
+    num_games: u64,
+    level: f64,
+) -> Vec<(Action, u32, Estimate)> {
+    ALL_ACTIONS
+        .into_iter()
+        .filter_map(|action| {
+            let (after, score) = board.apply_scored(action)?;
+            let rollouts = rollouts(Start::Afterstate(after), strategy, seed, num_games);
+            Some((action, score, Estimate::of(&rollouts, level)?))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::Board;
+
+    #[test]
+    fn test_rollouts() {
+        let start = Start::Board(PlayableBoard::init_with(&mut StdRng::seed_from_u64(1)));
+        let games = rollouts(start, &Strategy::Random, 10, 50);
+        assert_eq!(games[3].seed, 13);
+        let estimate = Estimate::of(&games, 0.95).unwrap();
+        assert_eq!(estimate.moves.count, 50);
+        assert!(
+            estimate.moves_ci.0 <= estimate.moves.mean
+                && estimate.moves.mean <= estimate.moves_ci.1
+        );
+        assert_eq!(
+            estimate
+                .max_tiles
+                .iter()
+                .map(|(_, count)| count)
+                .sum::<usize>(),
+            50
+        );
+
+        // a lost board has no move to come
+        let lost =
+            Board::from_values([[2, 4, 2, 4], [4, 2, 4, 2], [2, 4, 2, 4], [4, 2, 4, 2]]).unwrap();
+        let games = rollouts(Start::Board(lost.into()), &Strategy::Random, 0, 5);
+        assert!(games.iter().all(|game| game.moves == 0 && game.score == 0));
+        assert!(action_values(lost.into(), &Strategy::Random, 0, 5, 0.95).is_empty());
+    }
+}
@@ -0,0 +1,195 @@
+//! Games in progress, which can be saved to a file and continued later.
+//!
+//! The random tiles are drawn from a generator seeded at the start of the game, so a saved game only needs the seed
+//! and the actions played: loading it replays the actions, which restores the state of the generator along with the
+//! board. The board and score are saved as well, to check the replay and to be readable.
+//!
+//! ```text
+//! {"seed":42,"actions":["Left","Up","Left"],"board":{"cells":[[2,1,0,0],[0,0,0,0],[0,1,0,0],[0,0,0,0]]},"merge_score":4}
+//! ```
+
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::{ensure, Context};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use serde::{Deserialize, Serialize};
+
+use crate::board::{Action, Board, PlayableBoard};
+use crate::replay::{Event, Spawn};
+
+/// Content of a save file
+#[derive(Serialize, Deserialize)]
+struct Saved {
+    seed: u64,
+    actions: Vec<Action>,
+    board: Board,
+    merge_score: u32,
+}
+
+/// A game in progress, whose random tiles are drawn from a generator seeded with `seed`.
+pub struct GameInProgress {
+    seed: u64,
+    rng: StdRng,
+    board: PlayableBoard,
+    actions: Vec<Action>,
+    merge_score: u32,
+}
+
+impl GameInProgress {
+    /// Starts a new game.
+    pub fn new(seed: u64) -> GameInProgress {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let board = PlayableBoard::init_with(&mut rng);
+        GameInProgress {
+            seed,
+            rng,
+            board,
+            actions: Vec::new(),
+            merge_score: 0,
+        }
+    }
+
+    /// Board on which the next action is played
+    pub fn board(&self) -> &PlayableBoard {
+        &self.board
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn num_moves(&self) -> usize {
+        self.actions.len()
+    }
+
+    /// Actions played since the start of the game
+    pub fn actions(&self) -> &[Action] {
+        &self.actions
+    }
+
+    /// Score of the classic 2048 game: sum of the values of all tiles created by merges
+    pub fn merge_score(&self) -> u32 {
+        self.merge_score
+    }
+
+    /// Plays the action and places a random tile, returning the score of the action,
+    /// or `None` (leaving the game unchanged) if the action is not applicable.
+    pub fn play(&mut self, action: Action) -> Option<u32> {
+        let (after, score) = self.board.apply_scored(action)?;
+        self.board = after.with_random_tile_with(&mut self.rng);
+        self.actions.push(action);
+        self.merge_score += score;
+        Some(score)
+    }
+
+    /// Events of the game as in a replay, where the value of each move is the evaluation of its afterstate by the
+    /// current evaluation function.
+    pub fn to_replay(&self, strategy: &str) -> Vec<Event> {
+        let mut game = GameInProgress::new(self.seed);
+        let mut events = vec![Event::Start {
+            seed: self.seed,
+            strategy: strategy.to_string(),
+            board: *game.board.board(),
+        }];
+        for &action in &self.actions {
+            let after = game.board.apply(action).expect("replayed action");
+            let score = game.play(action).expect("replayed action");
+            events.push(Event::Move {
+                action,
+                value: after.evaluate(),
+                score,
+                spawn: Spawn::between(after.board(), game.board.board()).expect("single spawn"),
+            });
+        }
+        // a game saved before its end has no end event
+        if game.board.board().is_lost() {
+            events.push(Event::End {
+                num_moves: game.num_moves(),
+                merge_score: game.merge_score,
+                timed_out: false,
+            });
+        }
+        events
+    }
+
+    /// Saves the game to the file, replacing it only once the game is completely written.
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        let saved = Saved {
+            seed: self.seed,
+            actions: self.actions.clone(),
+            board: *self.board.board(),
+            merge_score: self.merge_score,
+        };
+        let partial = path.with_extension("partial");
+        let file = File::create(&partial)
+            .with_context(|| format!("Cannot create {}", partial.display()))?;
+        let mut out = BufWriter::new(file);
+        serde_json::to_writer(&mut out, &saved)?;
+        writeln!(out)?;
+        out.flush()?;
+        drop(out);
+        std::fs::rename(&partial, path)
+            .with_context(|| format!("Cannot write {}", path.display()))?;
+        Ok(())
+    }
+
+    /// Saves a game stopped before its end to `path`, or by default to `game-<seed>.json` in the current directory,
+    /// returning the file written.
+    pub fn save_stopped(&self, path: Option<&Path>) -> anyhow::Result<PathBuf> {
+        let path = path.map_or_else(
+            || PathBuf::from(format!("game-{}.json", self.seed)),
+            Path::to_path_buf,
+        );
+        self.save(&path)?;
+        Ok(path)
+    }
+
+    /// Loads a game saved with `save`, in the same state.
+    pub fn load(path: &Path) -> anyhow::Result<GameInProgress> {
+        let file = File::open(path).with_context(|| format!("Cannot open {}", path.display()))?;
+        let saved: Saved = serde_json::from_reader(BufReader::new(file))
+            .with_context(|| format!("Invalid saved game: {}", path.display()))?;
+        let mut game = GameInProgress::new(saved.seed);
+        for (i, &action) in saved.actions.iter().enumerate() {
+            game.play(action)
+                .with_context(|| format!("Move {}: {action:?} is not applicable", i + 1))?;
+        }
+        ensure!(
+            *game.board.board() == saved.board && game.merge_score == saved.merge_score,
+            "The saved board and score are not the ones reached by the actions of {}",
+            path.display()
+        );
+        Ok(game)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::ALL_ACTIONS;
+
+    #[test]
+    fn test_save_load() {
+        let mut game = GameInProgress::new(7);
+        for action in ALL_ACTIONS.iter().cycle().take(20) {
+            game.play(*action);
+        }
+        let path = std::env::temp_dir().join(format!("savegame-{}.json", std::process::id()));
+        game.save(&path).unwrap();
+        let mut loaded = GameInProgress::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.board().board(), game.board().board());
+        assert_eq!(loaded.num_moves(), game.num_moves());
+        assert_eq!(loaded.merge_score(), game.merge_score());
+        let events = loaded.to_replay("human");
+        assert_eq!(events.len(), 1 + loaded.num_moves());
+        // the generator of random tiles continues from the same state
+        for action in ALL_ACTIONS.iter().cycle().take(20) {
+            assert_eq!(loaded.play(*action), game.play(*action));
+            assert_eq!(loaded.board().board(), game.board().board());
+        }
+    }
+}
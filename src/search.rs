@@ -1,6 +1,20 @@
+use rand::rngs::StdRng;
 use rand::Rng; // import trait to make the `random_range` method available (Rng = Random number generator)
+use rand::SeedableRng;
+
+use std::cell::RefCell;
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Instant;
+
+#[cfg(target_arch = "wasm32")]
+use web_time::Instant;
 
 use crate::board::*;
+use crate::eval::cache::EvalCache;
+use tt::TranspositionTable;
+
+pub mod arena;
+pub mod tt;
 
 pub fn select_action(board: PlayableBoard) -> Option<Action> {
     select_action_randomly(board)
@@ -8,59 +22,542 @@ pub fn select_action(board: PlayableBoard) -> Option<Action> {
 }
 
 pub fn select_action_randomly(board: PlayableBoard) -> Option<Action> {
-    // iterate through all actions and keep the applicable ones
-    let mut applicable_actions: Vec<Action> = Vec::new();
+    // iterate through all actions and keep the applicable ones, in a fixed-size array rather than a `Vec`:
+    // this is called millions of times per benchmark, and there are at most 4 applicable actions
+    let mut applicable_actions = [Action::Up; 4];
+    let mut num_actions = 0;
     for action in ALL_ACTIONS {
         if let Some(_succ) = board.apply(action) {
             // action is applicable
-            applicable_actions.push(action);
+            applicable_actions[num_actions] = action;
+            num_actions += 1;
         } else {
             // action is not aplicable, ignore
         }
     }
 
     // if there is no available actions, return `None` immediately
-    let num_actions = applicable_actions.len();
     if num_actions == 0 {
         // no available action
         return None;
     }
 
     // otherwise, randomly pick an action among the applicable ones
-    let randomly_selected_action_index = rand::rng().random_range(0..num_actions);
+    let randomly_selected_action_index = with_strategy_rng(|rng| rng.random_range(0..num_actions));
     let randomly_selected_action = applicable_actions[randomly_selected_action_index];
     Some(randomly_selected_action)
 }
 
+#[allow(unused)]
 pub fn select_action_greedily(board: PlayableBoard) -> Option<Action> {
     // DO NOT COPY PAST from select_action_randomly
     // You can use for inspiration on how to use the API, but the selection process is fairly different
+    // As for the random selection, avoid allocating (e.g. a `Vec`) in functions called for every action
     todo!()
 }
 
+#[allow(unused)]
 pub fn select_action_expectimax(board: PlayableBoard, max_actions: usize) -> Option<Action> {
     let mut stats = Stats::default();
+    // once done, pass the statistics of the search to `record_stats` for bench to report nodes/sec and evals/sec
     todo!()
 }
 
+/// Expected value of each action (in the order of `ALL_ACTIONS`) when looking `max_actions` actions ahead, as
+/// computed by `evaluate_randable` on its afterstate, or `None` for the actions that are not applicable.
+///
+/// The statistics of the search are added to the totals of the thread, for bench to report nodes/sec and evals/sec.
+pub fn evaluate_all_actions(board: PlayableBoard, max_actions: usize) -> [Option<f32>; 4] {
+    let mut stats = Stats::default();
+    // the root
+    stats.num_nodes += 1;
+    let values = ALL_ACTIONS.map(|action| {
+        board
+            .apply(action)
+            .map(|after| evaluate_randable(after, max_actions.saturating_sub(1), &mut stats))
+    });
+    record_stats(&stats, max_actions);
+    values
+}
+
+#[allow(unused)]
 fn evaluate_randable(board: RandableBoard, remaining_actions: usize, stats: &mut Stats) -> f32 {
+    // the recursion is called for every node: boards are `Copy`, and iterating over `successors()` allocates nothing
+    // far from the leaves (see `PARALLEL_CHANCE_DEPTH`), `map_in_parallel` evaluates the successors on several threads
+    // `with_transposition_table` avoids searching again a board reached by another sequence of actions and tiles
+    // at the last layer, `evaluate_leaves` evaluates the afterstates of all successors of a chance node at once
     todo!()
 }
 
+#[allow(unused)]
 fn evaluate_playable(board: PlayableBoard, remaining_actions: usize, stats: &mut Stats) -> f32 {
+    // a board on which no action is applicable is lost: `board.evaluate()` gives it the value of lost boards, far
+    // below any other (see `eval::LOST`), which is how the search learns to avoid losing
     todo!()
 }
 
+/// Smallest number of actions still to look ahead for the successors of a chance node to be worth evaluating in
+/// parallel with `map_in_parallel`. Closer to the leaves, a successor is too cheap to evaluate for the overhead of
+/// sending it to another thread.
+#[allow(dead_code)]
+const PARALLEL_CHANCE_DEPTH: usize = 3;
+
+/// Smallest number of successors of a chance node for them to be worth evaluating in parallel
+#[allow(dead_code)]
+const PARALLEL_CHANCE_SUCCESSORS: usize = 8;
+
+/// Calls `f` on each item on the rayon pool, each call with its own statistics which are added to `stats` afterward
+/// and with the deadline of the current thread (see `with_deadline`), and returns the results in the order of the
+/// items. Summed in this order, the results do not depend on the
+/// scheduling of the threads.
+///
+/// This is a helper for `evaluate_randable`, e.g. to evaluate the successors of a chance node far from the leaves.
+/// Sending an item to another thread has an overhead that only large subtrees amortize. On wasm, where there is a
+/// single thread, the items are evaluated one after the other.
+#[allow(dead_code)]
+fn map_in_parallel<T: Sync, R: Send>(
+    items: &[T],
+    stats: &mut Stats,
+    f: impl Fn(&T, &mut Stats) -> R + Sync,
+) -> Vec<R> {
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        use rayon::prelude::*;
+        // the deadline of the search carries to the other threads
+        let deadline = DEADLINE.get();
+        let results: Vec<(R, Stats)> = items
+            .par_iter()
+            .map(|item| {
+                let _restore = RestoreDeadline(DEADLINE.replace(deadline));
+                let mut stats = Stats::default();
+                (f(item, &mut stats), stats)
+            })
+            .collect();
+        results
+            .into_iter()
+            .map(|(result, branch)| {
+                stats.add(&branch);
+                result
+            })
+            .collect()
+    }
+    #[cfg(target_arch = "wasm32")]
+    items
+        .iter()
+        .map(|item| {
+            let mut branch = Stats::default();
+            let result = f(item, &mut branch);
+            stats.add(&branch);
+            result
+        })
+        .collect()
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+thread_local! {
+    /// Instant at which the search of the current thread must stop, see `with_deadline`
+    static DEADLINE: std::cell::Cell<Option<Instant>> = const { std::cell::Cell::new(None) };
+    /// Number of calls to `check_deadline` since the clock was last read
+    static DEADLINE_CHECKS: std::cell::Cell<u32> = const { std::cell::Cell::new(0) };
+}
+
+/// Number of calls to `check_deadline` between two readings of the clock, which is slower than an evaluation
+#[cfg(not(target_arch = "wasm32"))]
+const DEADLINE_CHECK_PERIOD: u32 = 64;
+
+/// Payload of the unwinding of a search stopped by `check_deadline`
+#[cfg(not(target_arch = "wasm32"))]
+struct DeadlineExceeded;
+
+/// Sets the deadline of the current thread back to what it was, even if the search was stopped
+#[cfg(not(target_arch = "wasm32"))]
+struct RestoreDeadline(Option<Instant>);
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Drop for RestoreDeadline {
+    fn drop(&mut self) {
+        DEADLINE.set(self.0);
+    }
+}
+
+/// Runs the search `f` on the current thread, stopping it as soon as possible after `deadline`: returns what `f`
+/// returns if it completes in time, `None` if it was stopped.
+///
+/// The deadline is checked by the evaluations (see `check_deadline`), so any search is stopped while evaluating its
+/// leaves, on the current thread and on the threads of `map_in_parallel`. Nothing written to the caches by a stopped
+/// search is invalid, since values are only stored once computed. On wasm, where a search cannot be stopped, `f`
+/// always completes.
+pub fn with_deadline<R>(deadline: Instant, f: impl FnOnce() -> R) -> Option<R> {
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        use std::panic::{catch_unwind, resume_unwind, AssertUnwindSafe};
+        // a nested deadline cannot extend the one of the enclosing search
+        let previous = DEADLINE.get();
+        let _restore = RestoreDeadline(previous);
+        DEADLINE.set(Some(
+            previous.map_or(deadline, |previous| previous.min(deadline)),
+        ));
+        match catch_unwind(AssertUnwindSafe(f)) {
+            Ok(result) => Some(result),
+            Err(payload) if payload.is::<DeadlineExceeded>() => None,
+            Err(payload) => resume_unwind(payload),
+        }
+    }
+    #[cfg(target_arch = "wasm32")]
+    {
+        let _ = deadline;
+        Some(f())
+    }
+}
+
+/// Stops the search of the current thread (by unwinding up to `with_deadline`) if its deadline has passed.
+///
+/// Called by the evaluation functions of `eval` before each evaluation: the clock is only read once every
+/// `DEADLINE_CHECK_PERIOD` calls, and not at all outside of `with_deadline`.
+pub(crate) fn check_deadline() {
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Some(deadline) = DEADLINE.get() {
+        let checks = DEADLINE_CHECKS.get() + 1;
+        DEADLINE_CHECKS.set(checks % DEADLINE_CHECK_PERIOD);
+        if checks >= DEADLINE_CHECK_PERIOD && Instant::now() >= deadline {
+            // not a panic: the panic hook is not called
+            std::panic::resume_unwind(Box::new(DeadlineExceeded));
+        }
+    }
+}
+
+thread_local! {
+    /// Generator of the random decisions of the strategies, separate from the one placing the random tiles
+    static STRATEGY_RNG: RefCell<StdRng> = RefCell::new(StdRng::from_os_rng());
+}
+
+/// Calls `f` with the random number generator to use for any random decision of a strategy.
+///
+/// The random tiles are drawn from another generator, so that two strategies played on the same seed see the same
+/// tiles even if one of them makes random decisions.
+pub fn with_strategy_rng<R>(f: impl FnOnce(&mut StdRng) -> R) -> R {
+    STRATEGY_RNG.with_borrow_mut(f)
+}
+
+/// Mixed into the seed of a game to seed the random decisions of the strategy, independently of the random tiles
+pub const STRATEGY_STREAM: u64 = 0x9e37_79b9_7f4a_7c15;
+
+/// Seeds the generator of the random decisions of the strategies on the current thread, e.g. to replay a game.
+pub fn seed_strategy_rng(seed: u64) {
+    STRATEGY_RNG.set(StdRng::seed_from_u64(seed));
+}
+
+/// Maximum number of evaluations memoized by each thread (0 disables the cache)
+const EVAL_CACHE_CAPACITY: usize = 1 << 16;
+
+/// Number of entries of the transposition table of each thread (16 MiB)
+const TT_ENTRIES: usize = 1 << 20;
+
+thread_local! {
+    /// Values of the boards searched by the current thread
+    static TRANSPOSITION_TABLE: RefCell<TranspositionTable> =
+        RefCell::new(TranspositionTable::new(TT_ENTRIES));
+}
+
+/// Calls `f` with the transposition table of the current thread, e.g. to look up the value of a board before searching
+/// it and to store it afterward. `TranspositionTable::new_search` should be called at the start of each search.
+pub fn with_transposition_table<R>(f: impl FnOnce(&mut TranspositionTable) -> R) -> R {
+    TRANSPOSITION_TABLE.with_borrow_mut(f)
+}
+
+thread_local! {
+    /// Evaluations memoized across all searches of the current thread
+    static EVAL_CACHE: RefCell<EvalCache> = RefCell::new(EvalCache::new(EVAL_CACHE_CAPACITY));
+}
+
+/// Largest number of leaves evaluated at once by `evaluate_leaves`: the afterstates of all successors of a chance node
+const LEAF_BATCH: usize = 4 * 2 * N * N;
+
+/// Evaluates afterstates at the leaves of the search (e.g. the afterstates of all actions on the successors of a
+/// chance node at the last layer), writing the value of `boards[i]` to `values[i]`, and records the evaluations in
+/// `stats`.
+///
+/// Identical afterstates are often reached from sibling branches, so evaluations are memoized in a per-thread cache.
+/// The afterstates missing from the cache are evaluated in batches with `eval::eval_batch_into`, so that evaluators
+/// with a per-call overhead amortize it over many leaves. Nothing is allocated.
+#[allow(dead_code)]
+fn evaluate_leaves(boards: &[RandableBoard], values: &mut [f32], stats: &mut Stats) {
+    assert_eq!(boards.len(), values.len(), "one value per board");
+    stats.num_evals += boards.len();
+    EVAL_CACHE.with_borrow_mut(|cache| {
+        for (boards, values) in boards.chunks(LEAF_BATCH).zip(values.chunks_mut(LEAF_BATCH)) {
+            // position in `boards` of each board missing from the cache
+            let mut missing = [0; LEAF_BATCH];
+            let mut missing_boards = [Board::EMPTY; LEAF_BATCH];
+            let mut num_missing = 0;
+            for (i, (board, value)) in boards.iter().zip(values.iter_mut()).enumerate() {
+                match cache.get(board.board()) {
+                    Some(cached) => {
+                        stats.num_cache_hits += 1;
+                        *value = cached;
+                    }
+                    None => {
+                        missing[num_missing] = i;
+                        missing_boards[num_missing] = *board.board();
+                        num_missing += 1;
+                    }
+                }
+            }
+            let mut missing_values = [0.0; LEAF_BATCH];
+            crate::eval::eval_batch_into(
+                &missing_boards[..num_missing],
+                &mut missing_values[..num_missing],
+            );
+            for k in 0..num_missing {
+                values[missing[k]] = missing_values[k];
+                cache.insert(&missing_boards[k], missing_values[k]);
+            }
+        }
+    })
+}
+
 /// A small structure to accumulated statistics accros deeply nested calls
 #[derive(Default)]
 struct Stats {
+    /// number of boards (playable or randable) expanded by the search
+    pub num_nodes: usize,
     /// number of time the evaluation method is called on
     pub num_evals: usize,
+    /// number of evaluations that were found in the evaluation cache
+    pub num_cache_hits: usize,
+}
+
+impl Stats {
+    /// Adds the statistics of a part of the search made separately, e.g. on another thread
+    fn add(&mut self, other: &Stats) {
+        self.num_nodes += other.num_nodes;
+        self.num_evals += other.num_evals;
+        self.num_cache_hits += other.num_cache_hits;
+    }
+}
+
+/// Statistics accumulated over all searches of a thread, see `take_search_totals`
+#[derive(Clone, Copy, Debug, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct SearchTotals {
+    pub num_searches: usize,
+    pub num_nodes: usize,
+    pub num_evals: usize,
+    pub num_cache_hits: usize,
+    /// sum over all searches of the number of actions looked ahead
+    pub total_depth: usize,
+}
+
+impl SearchTotals {
+    pub fn add(&mut self, other: &SearchTotals) {
+        self.num_searches += other.num_searches;
+        self.num_nodes += other.num_nodes;
+        self.num_evals += other.num_evals;
+        self.num_cache_hits += other.num_cache_hits;
+        self.total_depth += other.total_depth;
+    }
+}
+
+thread_local! {
+    /// Statistics of the searches of the current thread since the last call to `take_search_totals`
+    static SEARCH_TOTALS: std::cell::Cell<SearchTotals> = std::cell::Cell::default();
+}
+
+/// Adds the statistics of a search looking `depth` actions ahead to the totals of the current thread.
+fn record_stats(stats: &Stats, depth: usize) {
+    log::debug!(
+        "Search at depth {depth}: {} nodes, {} evals, {} cache hits",
+        stats.num_nodes,
+        stats.num_evals,
+        stats.num_cache_hits
+    );
+    SEARCH_TOTALS.with(|totals| {
+        let mut sum = totals.get();
+        sum.add(&SearchTotals {
+            num_searches: 1,
+            num_nodes: stats.num_nodes,
+            num_evals: stats.num_evals,
+            num_cache_hits: stats.num_cache_hits,
+            total_depth: depth,
+        });
+        totals.set(sum);
+    });
+}
+
+/// Returns the statistics of the searches made by the current thread since the last call, and resets them.
+pub fn take_search_totals() -> SearchTotals {
+    SEARCH_TOTALS.take()
+}
+
+/// Forgets all evaluations memoized by the current thread, e.g. before searching with another evaluation function.
+///
+/// The values of the transposition table depend on the evaluation function too, and are forgotten as well.
+pub fn clear_eval_cache() {
+    EVAL_CACHE.with_borrow_mut(|cache| cache.clear());
+    TRANSPOSITION_TABLE.with_borrow_mut(|table| table.clear());
+}
+
+/// Approximate memory used by the evaluation cache of the current thread, in bytes
+pub fn eval_cache_memory() -> usize {
+    EVAL_CACHE.with_borrow(|cache| cache.memory())
 }
 
 impl std::fmt::Display for Stats {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Num nodes: {}", self.num_nodes)?;
         writeln!(f, "Num evals: {}", self.num_evals)?;
+        let hit_rate = if self.num_evals > 0 {
+            self.num_cache_hits as f32 / self.num_evals as f32 * 100.0
+        } else {
+            0.0
+        };
+        writeln!(f, "Cache hits: {} ({hit_rate:.1}%)", self.num_cache_hits)?;
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_map_in_parallel() {
+        let board = Board::from_values([[2, 0, 0, 0], [4, 0, 0, 0], [0; 4], [8, 0, 0, 0]]).unwrap();
+        let after = PlayableBoard::from(board).apply(Action::Right).unwrap();
+        let successors: Vec<(f32, PlayableBoard)> = after.successors().collect();
+        // the value of a successor is the sum of its tiles
+        let mut stats = Stats::default();
+        let values = map_in_parallel(&successors, &mut stats, |(_, next), stats| {
+            stats.num_evals += 1;
+            next.board().tile_sum() as f32
+        });
+        // in the order of the items, whatever the scheduling
+        let expected: Vec<f32> = successors
+            .iter()
+            .map(|(_, next)| next.board().tile_sum() as f32)
+            .collect();
+        assert_eq!(values, expected);
+        assert_eq!(stats.num_evals, 2 * 13);
+        // the statistics of all threads are added to the previous ones
+        map_in_parallel(&successors[..3], &mut stats, |_, stats| {
+            stats.num_nodes += 1
+        });
+        assert_eq!((stats.num_evals, stats.num_nodes), (2 * 13, 3));
+        assert!(map_in_parallel(&[] as &[u8], &mut stats, |_, _| 0).is_empty());
+    }
+
+    #[test]
+    fn test_with_deadline() {
+        use std::time::Duration;
+        let board = Board::from_values([[2, 0, 0, 0], [4, 0, 0, 0], [0; 4], [8, 0, 0, 2]]).unwrap();
+        let far = Instant::now() + Duration::from_secs(3600);
+        assert_eq!(with_deadline(far, || 3), Some(3));
+        // a search that would never end is stopped shortly after its deadline
+        let start = Instant::now();
+        let endless = || loop {
+            std::hint::black_box(crate::eval::eval(&board));
+        };
+        assert_eq!(
+            with_deadline(start + Duration::from_millis(20), endless),
+            None
+        );
+        assert!(start.elapsed() < Duration::from_secs(2));
+        // on all the threads of the search
+        let start = Instant::now();
+        let items = [board; 8];
+        let parallel = || {
+            map_in_parallel(&items, &mut Stats::default(), |board, _| loop {
+                std::hint::black_box(crate::eval::eval(board));
+            })
+        };
+        assert!(with_deadline(start + Duration::from_millis(20), parallel).is_none());
+        assert!(start.elapsed() < Duration::from_secs(2));
+        // afterward, evaluations are no longer stopped, on any thread
+        let values = map_in_parallel(&items, &mut Stats::default(), |board, _| {
+            (0..1000).map(|_| crate::eval::eval(board)).sum::<f32>()
+        });
+        assert_eq!(values.len(), items.len());
+        // a nested deadline cannot extend the enclosing one
+        let start = Instant::now();
+        let nested = with_deadline(start + Duration::from_millis(20), || {
+            with_deadline(far, endless)
+        });
+        assert_eq!(nested, Some(None));
+        assert!(start.elapsed() < Duration::from_secs(2));
+        // other panics go through
+        let panicked = std::panic::catch_unwind(|| {
+            with_deadline(far, || std::panic::resume_unwind(Box::new(7)))
+        });
+        assert_eq!(panicked.unwrap_err().downcast_ref::<i32>(), Some(&7));
+    }
+
+    #[test]
+    fn test_evaluate_all_actions() {
+        // no action is applicable on a lost board: nothing is searched below the root
+        let lost =
+            Board::from_values([[2, 4, 2, 4], [4, 2, 4, 2], [2, 4, 2, 4], [4, 2, 4, 2]]).unwrap();
+        take_search_totals();
+        assert_eq!(
+            evaluate_all_actions(PlayableBoard::from(lost), 3),
+            [None; 4]
+        );
+        assert_eq!(
+            evaluate_all_actions(PlayableBoard::from(lost), 0),
+            [None; 4]
+        );
+        let totals = take_search_totals();
+        assert_eq!(totals.num_searches, 2);
+        assert_eq!(totals.total_depth, 3);
+        assert_eq!(totals.num_nodes, 2);
+        assert_eq!(totals.num_evals, 0);
+        // the totals were reset
+        assert_eq!(take_search_totals(), SearchTotals::default());
+    }
+
+    #[test]
+    fn test_lost_leaves() {
+        let board =
+            Board::from_values([[2, 4, 2, 4], [4, 2, 4, 2], [2, 4, 2, 8], [0, 8, 16, 32]]).unwrap();
+        let board = PlayableBoard::from(board);
+        // after Left, any random tile loses the game, although the afterstate does not look worse than after Down
+        let left = board.apply(Action::Left).unwrap();
+        let down = board.apply(Action::Down).unwrap();
+        assert!(left.successors().all(|(_, next)| next.board().is_lost()));
+        assert!(left.evaluate() > crate::eval::LOST / 2.0);
+        assert!(down.evaluate() > crate::eval::LOST / 2.0);
+        // looking two actions ahead, the playable leaves are evaluated with `evaluate`, which gives lost ones the
+        // value of lost boards
+        let value = |after: RandableBoard| {
+            after
+                .successors()
+                .map(|(probability, next)| probability * next.evaluate())
+                .sum::<f32>()
+        };
+        assert!(value(left) < crate::eval::LOST / 2.0);
+        assert!(value(down) > crate::eval::LOST / 2.0);
+        let lost = left.successors().next().unwrap().1;
+        assert!(lost.evaluate() <= crate::eval::LOST + lost.board().tile_sum() as f32);
+    }
+
+    #[test]
+    fn test_evaluate_leaves() {
+        clear_eval_cache();
+        let board = Board::from_values([[2, 0, 0, 0], [4, 0, 0, 0], [0; 4], [8, 0, 0, 2]]).unwrap();
+        let leaves: Vec<RandableBoard> = PlayableBoard::from(board)
+            .apply(Action::Right)
+            .unwrap()
+            .successors()
+            .flat_map(|(_, next)| ALL_ACTIONS.into_iter().filter_map(move |a| next.apply(a)))
+            .collect();
+        let mut stats = Stats::default();
+        let expected: Vec<f32> = leaves.iter().map(|leaf| leaf.evaluate()).collect();
+        let mut values = vec![0.0; leaves.len()];
+        evaluate_leaves(&leaves, &mut values, &mut stats);
+        assert_eq!(values, expected);
+        // the second time, all leaves are found in the cache
+        let hits = stats.num_cache_hits;
+        evaluate_leaves(&leaves, &mut values, &mut stats);
+        assert_eq!(values, expected);
+        assert_eq!(stats.num_evals, 2 * leaves.len());
+        assert_eq!(stats.num_cache_hits, hits + leaves.len());
+    }
+}
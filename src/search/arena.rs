@@ -0,0 +1,218 @@
+//! Arena of search-tree nodes, for searches keeping an explicit tree (e.g. MCTS, or expectimax reusing its tree from
+//! one move to the next).
+//!
+//! Nodes live in a single growing vector and refer to each other by `NodeId` instead of `Box` or a `Vec` per node,
+//! so that building a tree of millions of nodes makes no allocation once the arena has grown, and dropping it frees
+//! nothing node by node. The children of a node are allocated together (`alloc_children`), and referred to by a
+//! single `Children` range.
+//!
+//! Between two moves, `reset` empties the arena while keeping its memory. The ids handed out before a reset carry
+//! the generation of the arena when they were created, so that using one after the reset fails loudly instead of
+//! silently reading a node of the new tree.
+//!
+//! ```rust
+//! let mut arena = Arena::new();
+//! let root = arena.alloc(Node { visits: 0, children: Children::NONE });
+//! let children = arena.alloc_children(ALL_ACTIONS.map(|_| Node { visits: 0, children: Children::NONE }));
+//! arena[root].children = children;
+//! for child in children.ids() { arena[child].visits += 1; }
+//! arena.reset();
+//! ```
+
+use std::ops::{Index, IndexMut};
+
+/// Handle of a node of an `Arena`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct NodeId {
+    index: u32,
+    generation: u32,
+}
+
+/// Handles of nodes allocated together, typically the children of a node
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Children {
+    start: u32,
+    len: u32,
+    generation: u32,
+}
+
+impl Children {
+    /// No children, e.g. for a node not expanded yet
+    pub const NONE: Children = Children {
+        start: 0,
+        len: 0,
+        generation: 0,
+    };
+
+    pub fn len(&self) -> usize {
+        self.len as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Handle of each node of the range, in the order of their allocation
+    pub fn ids(&self) -> impl Iterator<Item = NodeId> {
+        let generation = self.generation;
+        (self.start..self.start + self.len).map(move |index| NodeId { index, generation })
+    }
+}
+
+/// Nodes of a tree, linked by their `NodeId`
+pub struct Arena<T> {
+    nodes: Vec<T>,
+    /// Incremented by each reset, to detect the ids of a previous tree
+    generation: u32,
+}
+
+impl<T> Arena<T> {
+    pub fn new() -> Arena<T> {
+        Arena::with_capacity(0)
+    }
+
+    /// An arena with room for `capacity` nodes before it needs to grow
+    pub fn with_capacity(capacity: usize) -> Arena<T> {
+        Arena {
+            nodes: Vec::with_capacity(capacity),
+            generation: 0,
+        }
+    }
+
+    /// Number of nodes allocated since the last reset
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Approximate memory reserved by the arena, in bytes
+    pub fn memory(&self) -> usize {
+        self.nodes.capacity() * std::mem::size_of::<T>()
+    }
+
+    pub fn alloc(&mut self, node: T) -> NodeId {
+        let index = self.next_index();
+        self.nodes.push(node);
+        NodeId {
+            index,
+            generation: self.generation,
+        }
+    }
+
+    /// Allocates the nodes next to each other.
+    pub fn alloc_children(&mut self, nodes: impl IntoIterator<Item = T>) -> Children {
+        let start = self.next_index();
+        self.nodes.extend(nodes);
+        Children {
+            start,
+            len: self.next_index() - start,
+            generation: self.generation,
+        }
+    }
+
+    /// The nodes of the range, in the order of their allocation
+    pub fn children(&self, children: Children) -> &[T] {
+        if children.is_empty() {
+            return &[];
+        }
+        self.check(children.generation);
+        &self.nodes[children.start as usize..(children.start + children.len) as usize]
+    }
+
+    pub fn children_mut(&mut self, children: Children) -> &mut [T] {
+        if children.is_empty() {
+            return &mut [];
+        }
+        self.check(children.generation);
+        &mut self.nodes[children.start as usize..(children.start + children.len) as usize]
+    }
+
+    /// Forgets all nodes, keeping the memory of the arena for the next tree. The ids of the forgotten nodes must not
+    /// be used anymore.
+    pub fn reset(&mut self) {
+        self.nodes.clear();
+        self.generation = self.generation.wrapping_add(1);
+    }
+
+    fn next_index(&self) -> u32 {
+        u32::try_from(self.nodes.len()).expect("too many nodes in the arena")
+    }
+
+    fn check(&self, generation: u32) {
+        assert_eq!(
+            generation, self.generation,
+            "node of a tree forgotten by `Arena::reset`"
+        );
+    }
+}
+
+impl<T> Default for Arena<T> {
+    fn default() -> Self {
+        Arena::new()
+    }
+}
+
+impl<T> Index<NodeId> for Arena<T> {
+    type Output = T;
+
+    fn index(&self, id: NodeId) -> &T {
+        self.check(id.generation);
+        &self.nodes[id.index as usize]
+    }
+}
+
+impl<T> IndexMut<NodeId> for Arena<T> {
+    fn index_mut(&mut self, id: NodeId) -> &mut T {
+        self.check(id.generation);
+        &mut self.nodes[id.index as usize]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Node {
+        visits: u32,
+        children: Children,
+    }
+
+    #[test]
+    fn test_arena() {
+        let leaf = || Node {
+            visits: 0,
+            children: Children::NONE,
+        };
+        let mut arena = Arena::new();
+        let root = arena.alloc(leaf());
+        let children = arena.alloc_children((0..4).map(|_| leaf()));
+        arena[root].children = children;
+        for child in children.ids() {
+            arena[child].visits += 1;
+            arena[root].visits += 1;
+        }
+        assert_eq!(arena.len(), 5);
+        assert_eq!(arena[root].visits, 4);
+        assert!(arena
+            .children(children)
+            .iter()
+            .all(|child| child.visits == 1));
+        // the children are not expanded
+        let first = children.ids().next().unwrap();
+        assert!(arena.children(arena[first].children).is_empty());
+
+        let memory = arena.memory();
+        arena.reset();
+        assert!(arena.is_empty());
+        // the memory is kept for the next tree
+        assert_eq!(arena.memory(), memory);
+        let new_root = arena.alloc(leaf());
+        assert_eq!(arena[new_root].visits, 0);
+        // an id of the previous tree is detected
+        let stale = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| arena[root].visits));
+        assert!(stale.is_err());
+    }
+}
@@ -0,0 +1,135 @@
+//! Transposition table of the search: values of boards already searched, with the number of actions they were
+//! searched ahead, so that a board reached again by another sequence of actions and random tiles is not searched
+//! twice.
+//!
+//! The table is a fixed array of a power-of-two number of entries, allocated once: a board can only be stored in
+//! the entry given by its hash. When two boards compete for an entry, the one searched deeper is kept (its value
+//! cost more to compute and is valid for more searches), except that entries of previous searches are always
+//! replaced, so that the table does not fill up with deep entries of old positions.
+
+use crate::board::Board;
+
+/// An entry of the table: 16 bytes, with no heap allocation
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+struct Entry {
+    /// Board as packed by `Board::pack`
+    key: u64,
+    value: f32,
+    /// Number of actions searched ahead, plus one (0 for an empty entry)
+    depth: u8,
+    /// Search in which the entry was stored, modulo 256
+    generation: u8,
+}
+
+/// Values of boards, indexed by their packed representation
+pub struct TranspositionTable {
+    entries: Box<[Entry]>,
+    /// `entries.len() - 1`, to compute the entry of a hash with a mask
+    mask: usize,
+    generation: u8,
+}
+
+impl TranspositionTable {
+    /// A table of `capacity` entries, rounded down to a power of two (at least one entry).
+    pub fn new(capacity: usize) -> TranspositionTable {
+        let len = if capacity <= 1 {
+            1
+        } else {
+            1 << capacity.ilog2()
+        };
+        TranspositionTable {
+            entries: vec![Entry::default(); len].into_boxed_slice(),
+            mask: len - 1,
+            generation: 0,
+        }
+    }
+
+    /// Number of entries of the table
+    pub fn capacity(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Memory used by the table, in bytes
+    pub fn memory(&self) -> usize {
+        self.entries.len() * std::mem::size_of::<Entry>()
+    }
+
+    fn slot(&self, key: u64) -> usize {
+        // Fibonacci hashing: the high bits of the product depend on all bits of the key
+        (key.wrapping_mul(0x9e37_79b9_7f4a_7c15) >> 32) as usize & self.mask
+    }
+
+    /// Value of the board if it was stored after being searched at least `depth` actions ahead.
+    pub fn get(&self, board: &Board, depth: usize) -> Option<f32> {
+        let key = board.pack();
+        let entry = &self.entries[self.slot(key)];
+        (entry.depth > 0 && entry.key == key && entry.depth as usize > depth).then_some(entry.value)
+    }
+
+    /// Stores the value of the board searched `depth` actions ahead, unless its entry holds another board of the
+    /// current search searched deeper.
+    ///
+    /// Tiles above `2^15` are clamped when packing boards, so boards differing only by such tiles share their values.
+    pub fn insert(&mut self, board: &Board, depth: usize, value: f32) {
+        let key = board.pack();
+        let generation = self.generation;
+        let slot = self.slot(key);
+        let entry = &mut self.entries[slot];
+        let depth = depth.min(u8::MAX as usize - 1) as u8 + 1;
+        let replace = entry.depth == 0
+            || entry.key == key
+            || entry.generation != generation
+            || depth >= entry.depth;
+        if replace {
+            *entry = Entry {
+                key,
+                value,
+                depth,
+                generation,
+            };
+        }
+    }
+
+    /// Marks the start of a new search: the entries stored so far stay readable, but are replaced first.
+    pub fn new_search(&mut self) {
+        self.generation = self.generation.wrapping_add(1);
+    }
+
+    /// Forgets all entries, e.g. when the evaluation function changes.
+    pub fn clear(&mut self) {
+        self.entries.fill(Entry::default());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_replace_by_depth() {
+        assert_eq!(std::mem::size_of::<Entry>(), 16);
+        assert_eq!(TranspositionTable::new(1000).capacity(), 512);
+        // a single entry, for which all boards compete
+        let mut table = TranspositionTable::new(1);
+        let board = |tile: u8| Board {
+            cells: [[tile, 0, 0, 0], [0; 4], [0; 4], [0; 4]],
+        };
+        table.insert(&board(1), 3, 1.0);
+        assert_eq!(table.get(&board(1), 3), Some(1.0));
+        assert_eq!(table.get(&board(1), 2), Some(1.0));
+        // a value searched less deep does not answer a deeper search
+        assert_eq!(table.get(&board(1), 4), None);
+        assert_eq!(table.get(&board(2), 0), None);
+        // a shallower board does not replace a deeper one of the same search
+        table.insert(&board(2), 1, 2.0);
+        assert_eq!(table.get(&board(1), 3), Some(1.0));
+        table.insert(&board(2), 3, 2.0);
+        assert_eq!(table.get(&board(2), 3), Some(2.0));
+        // but it replaces one of a previous search
+        table.new_search();
+        table.insert(&board(1), 0, 3.0);
+        assert_eq!(table.get(&board(1), 0), Some(3.0));
+        table.clear();
+        assert_eq!(table.get(&board(1), 0), None);
+    }
+}
@@ -0,0 +1,375 @@
+//! Self-play training loop (`main selfplay`): games are played in batches, each afterstate reached is paired with
+//! the outcome that followed it (the number of actions or the score still to come), and a learnable evaluator is
+//! moved toward these outcomes. Every few batches, the greedy policy of the learner is benchmarked on fixed seeds.
+//!
+//! ```rust
+//! let mut learner = NTupleNetwork::new(&FOUR_TUPLES);
+//! selfplay::run(&Config::default(), &mut learner, None, |report, _| {
+//!     println!("{report:?}");
+//!     Ok(())
+//! })?;
+//! ```
+//!
+//! Unlike the temporal-difference learning of `td` (the `train` binary), the targets are the actual outcomes of the
+//! games (Monte Carlo), so that the games may be played by any strategy, the learner then estimating the value of
+//! that strategy.
+
+use std::path::Path;
+
+use rand::rngs::StdRng;
+use rand::seq::IndexedRandom;
+use rand::{Rng, SeedableRng};
+use rayon::prelude::*;
+
+use crate::board::{Action, Board, PlayableBoard, ALL_ACTIONS};
+use crate::collect::{Collector, Sample};
+use crate::eval::ntuple::NTupleNetwork;
+use crate::eval::params::{self, Metadata};
+use crate::eval::{self, EvalWeights, NUM_HEURISTICS};
+use crate::game;
+use crate::strategy::Strategy;
+
+/// An evaluator of afterstates whose parameters are learned from examples.
+pub trait Learner: Sync {
+    fn value(&self, board: &Board) -> f32;
+
+    /// Moves the value of the board toward the target, by a fraction `alpha` of the error.
+    fn learn(&mut self, board: &Board, target: f32, alpha: f32);
+
+    /// Writes the learned parameters to a checkpoint file (see `eval::params`).
+    fn save(&self, path: &Path, metadata: &Metadata) -> anyhow::Result<()>;
+}
+
+impl Learner for NTupleNetwork {
+    fn value(&self, board: &Board) -> f32 {
+        self.eval(board)
+    }
+
+    fn learn(&mut self, board: &Board, target: f32, alpha: f32) {
+        let error = target - self.eval(board);
+        self.update(board, alpha * error / self.num_features() as f32);
+    }
+
+    fn save(&self, path: &Path, metadata: &Metadata) -> anyhow::Result<()> {
+        params::save_ntuple(path, self, metadata)
+    }
+}
+
+/// Linear function of the raw values of the heuristics (see `eval::features`), plus a constant.
+///
+/// The values of the heuristics have very different scales, so the updates are normalized by the squared norm of
+/// the features (normalized least mean squares).
+#[derive(Clone, Debug, Default)]
+pub struct LinearLearner {
+    pub weights: [f32; NUM_HEURISTICS],
+    pub bias: f32,
+}
+
+impl LinearLearner {
+    /// The weights as weights of the evaluation function, the constant being irrelevant to compare afterstates.
+    pub fn eval_weights(&self) -> EvalWeights {
+        EvalWeights(self.weights)
+    }
+}
+
+impl Learner for LinearLearner {
+    fn value(&self, board: &Board) -> f32 {
+        let features = eval::features(board);
+        self.bias
+            + features
+                .heuristics()
+                .iter()
+                .zip(self.weights)
+                .map(|(x, w)| x * w)
+                .sum::<f32>()
+    }
+
+    fn learn(&mut self, board: &Board, target: f32, alpha: f32) {
+        let features = eval::features(board);
+        let x = features.heuristics();
+        let step =
+            alpha * (target - self.value(board)) / (1.0 + x.iter().map(|x| x * x).sum::<f32>());
+        for (w, x) in self.weights.iter_mut().zip(x) {
+            *w += step * x;
+        }
+        self.bias += step;
+    }
+
+    fn save(&self, path: &Path, metadata: &Metadata) -> anyhow::Result<()> {
+        params::save_linear(path, &self.eval_weights(), metadata)
+    }
+}
+
+/// Who plays the training games
+#[derive(Clone, Copy, Debug)]
+pub enum Player {
+    /// The learner itself, selecting the afterstate of highest value, or a random action with probability
+    /// `epsilon`
+    Learner { epsilon: f32 },
+    /// A fixed strategy, whose value is learned
+    Strategy(Strategy),
+}
+
+/// What is learned as the value of an afterstate
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum Outcome {
+    /// Number of actions played after the afterstate until the end of the game
+    Moves,
+    /// Score made after the afterstate until the end of the game
+    Score,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Config {
+    pub player: Player,
+    pub outcome: Outcome,
+    pub iterations: u64,
+    /// Number of games played in each iteration, before learning from them
+    pub games_per_iteration: u64,
+    /// Learning rate
+    pub alpha: f32,
+    /// Number of iterations between two benchmarks of the learner
+    pub bench_every: u64,
+    /// Number of games of each benchmark, always with the same seeds
+    pub bench_games: u64,
+    /// Seed of the first training game (game `i` uses the seed `seed + i`)
+    pub seed: u64,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            player: Player::Learner { epsilon: 0.0 },
+            outcome: Outcome::Moves,
+            iterations: 100,
+            games_per_iteration: 100,
+            alpha: 0.1,
+            bench_every: 10,
+            bench_games: 100,
+            seed: 0,
+        }
+    }
+}
+
+/// Results of the greedy policy of the learner
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Bench {
+    pub mean_score: f64,
+    pub mean_moves: f64,
+    /// Fraction of the games reaching the 2048 tile
+    pub win_rate: f64,
+}
+
+/// Progress of the training after an iteration
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Report {
+    pub iteration: u64,
+    /// Number of training games played so far
+    pub num_games: u64,
+    /// Number of (afterstate, outcome) pairs learned from in this iteration
+    pub num_pairs: usize,
+    pub mean_training_score: f64,
+    /// Benchmark of the learner after this iteration, if it was its turn
+    pub bench: Option<Bench>,
+}
+
+/// A finished game: its afterstates with the outcome that followed each of them, and its score
+struct Game {
+    pairs: Vec<(Board, f32)>,
+    score: u32,
+    /// Boards on which an action was selected, when collecting them
+    samples: Vec<Sample>,
+}
+
+/// Runs the training, calling `on_iteration` with the report and the learner after each iteration (e.g. to save
+/// the learner). Stops at the first error of `on_iteration`.
+///
+/// The positions of the training games are written to the collector if any, with the value of each action for the
+/// player: the value of its afterstate for the learner, the values of `Strategy::evaluate_all_actions` for a
+/// strategy.
+pub fn run<L: Learner>(
+    config: &Config,
+    learner: &mut L,
+    collector: Option<&Collector>,
+    mut on_iteration: impl FnMut(&Report, &L) -> anyhow::Result<()>,
+) -> anyhow::Result<()> {
+    for iteration in 1..=config.iterations {
+        let first = config.seed + (iteration - 1) * config.games_per_iteration;
+        let games: Vec<Game> = (first..first + config.games_per_iteration)
+            .into_par_iter()
+            .map(|seed| {
+                play(
+                    &*learner,
+                    config.player,
+                    config.outcome,
+                    seed,
+                    collector.is_some(),
+                )
+            })
+            .collect();
+        let mut num_pairs = 0;
+        for game in &games {
+            if let Some(collector) = collector {
+                collector.add_game(&game.samples)?;
+            }
+            for (board, target) in &game.pairs {
+                learner.learn(board, *target, config.alpha);
+            }
+            num_pairs += game.pairs.len();
+        }
+        let bench = (iteration % config.bench_every == 0 || iteration == config.iterations)
+            .then(|| benchmark(&*learner, config.bench_games));
+        let report = Report {
+            iteration,
+            num_games: iteration * config.games_per_iteration,
+            num_pairs,
+            mean_training_score: games.iter().map(|game| game.score as f64).sum::<f64>()
+                / games.len().max(1) as f64,
+            bench,
+        };
+        on_iteration(&report, learner)?;
+    }
+    Ok(())
+}
+
+/// Plays a game with the player, and pairs each afterstate with its outcome. With `collect`, also returns the
+/// samples of the boards on which an action was selected.
+fn play(
+    learner: &impl Learner,
+    player: Player,
+    outcome: Outcome,
+    seed: u64,
+    collect: bool,
+) -> Game {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut board = PlayableBoard::init_with(&mut rng);
+    // afterstates and the score of the actions leading to them
+    let mut trajectory = Vec::new();
+    let mut samples = Vec::new();
+    loop {
+        let action = match player {
+            Player::Learner { epsilon } if rng.random::<f32>() < epsilon => {
+                let applicable: Vec<_> = ALL_ACTIONS
+                    .into_iter()
+                    .filter(|&action| board.apply(action).is_some())
+                    .collect();
+                applicable.choose(&mut rng).copied()
+            }
+            Player::Learner { .. } => greedy(learner, board),
+            Player::Strategy(strategy) => strategy.select_action(board),
+        };
+        let Some((action, (after, score))) =
+            action.and_then(|action| Some((action, board.apply_scored(action)?)))
+        else {
+            break;
+        };
+        if collect {
+            let values = match player {
+                Player::Learner { .. } => ALL_ACTIONS.map(|action| {
+                    board
+                        .apply(action)
+                        .map(|after| learner.value(after.board()))
+                }),
+                Player::Strategy(strategy) => strategy.evaluate_all_actions(board),
+            };
+            samples.push(Sample {
+                seed,
+                board: *board.board(),
+                values,
+                action,
+            });
+        }
+        trajectory.push((*after.board(), score));
+        board = after.with_random_tile_with(&mut rng);
+    }
+    let mut pairs = Vec::with_capacity(trajectory.len());
+    let mut to_come = 0.0;
+    let mut total = 0;
+    for (after, score) in trajectory.into_iter().rev() {
+        pairs.push((after, to_come));
+        to_come += match outcome {
+            Outcome::Moves => 1.0,
+            Outcome::Score => score as f32,
+        };
+        total += score;
+    }
+    Game {
+        pairs,
+        score: total,
+        samples,
+    }
+}
+
+/// Action leading to the afterstate of highest value for the learner
+fn greedy(learner: &impl Learner, board: PlayableBoard) -> Option<Action> {
+    game::greedy_action(board, |after| learner.value(after))
+}
+
+/// Plays greedily with the learner on the seeds `0..num_games`, in parallel.
+pub fn benchmark(learner: &impl Learner, num_games: u64) -> Bench {
+    let games: Vec<(u32, usize, bool)> = (0..num_games)
+        .into_par_iter()
+        .map(|seed| {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let mut board = PlayableBoard::init_with(&mut rng);
+            let (mut score, mut moves) = (0, 0);
+            while let Some((after, gain)) =
+                greedy(learner, board).and_then(|action| board.apply_scored(action))
+            {
+                score += gain;
+                moves += 1;
+                board = after.with_random_tile_with(&mut rng);
+            }
+            (score, moves, board.board().max_tile() >= 11)
+        })
+        .collect();
+    let n = games.len().max(1) as f64;
+    Bench {
+        mean_score: games.iter().map(|game| game.0 as f64).sum::<f64>() / n,
+        mean_moves: games.iter().map(|game| game.1 as f64).sum::<f64>() / n,
+        win_rate: games.iter().filter(|game| game.2).count() as f64 / n,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::eval::ntuple::FOUR_TUPLES;
+
+    #[test]
+    fn test_selfplay() {
+        // the outcomes are the actions still to come, down to 0 after the last action
+        let game = play(
+            &LinearLearner::default(),
+            Player::Strategy(Strategy::Random),
+            Outcome::Moves,
+            7,
+            true,
+        );
+        let targets: Vec<f32> = game.pairs.iter().map(|(_, target)| *target).collect();
+        assert_eq!(targets[0], 0.0);
+        assert_eq!(*targets.last().unwrap(), (targets.len() - 1) as f32);
+        // one sample per action played
+        assert_eq!(game.samples.len(), game.pairs.len());
+
+        let config = Config {
+            iterations: 4,
+            games_per_iteration: 20,
+            bench_every: 2,
+            bench_games: 10,
+            ..Config::default()
+        };
+        let mut learner = NTupleNetwork::new(&FOUR_TUPLES);
+        let mut reports = Vec::new();
+        run(&config, &mut learner, None, |report, _| {
+            reports.push(*report);
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(reports.len(), 4);
+        assert_eq!(reports[3].num_games, 80);
+        assert!(reports[0].bench.is_none() && reports[1].bench.is_some());
+        // the learner learned that a fresh board has moves to come
+        assert!(learner.value(PlayableBoard::init().board()) > 0.0);
+    }
+}
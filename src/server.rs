@@ -0,0 +1,598 @@
+//! HTTP server exposing the game and the engine, for web frontends and remote tournaments (`main serve`).
+//!
+//! Built on axum: the bodies of the requests are limited to `MAX_BODY` bytes, each request is answered within
+//! `REQUEST_TIMEOUT`, and the engine runs on blocking threads, one search per core at a time. All bodies are JSON.
+//!
+//! - `POST /games` with an optional `{"seed": 42}` starts a game and returns its state, including its `id`.
+//! - `GET /games/<id>` returns the state of a game.
+//! - `POST /games/<id>/moves` with `{"action": "Left"}` plays the action and returns the new state.
+//! - `GET /games/<id>/events` streams the state of the game as server-sent events, once at the start and after
+//!   each move, until the game is lost.
+//! - `POST /best-move` answers a request of the engine protocol (see `engine`) for an arbitrary board.
+//! - `POST /analysis` with `{"board": [[2, 4, 0, 0], ...]}` returns the evaluation of the board, the contribution of
+//!   each heuristic to it, and the score and afterstate value of each applicable action.
+//! - `POST /ai2048.Agent/<method>` calls the gRPC service of `proto/ai2048.proto`, over HTTP/2 or as gRPC-Web
+//!   (see `grpc`).
+//!
+//! The strategies given by the requests come from the network: only those reading no file and searching a bounded
+//! depth are accepted (see `remote_strategy`), and each of their decisions gets at most `MAX_REMOTE_BUDGET`.
+//!
+//! The state of a game is
+//!
+//! ```text
+//! {"board":[[2,0,0,0],[0,0,0,0],[0,0,0,0],[0,0,0,0]],"id":1,"lost":false,"num_moves":0,"score":0,"seed":42}
+//! ```
+
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::io::{BufRead, BufReader, Read};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use anyhow::{bail, ensure, Context};
+use axum::body::Bytes;
+use axum::extract::{DefaultBodyLimit, Path, State};
+use axum::http::StatusCode;
+use axum::response::sse::{Event, Sse};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use futures_util::stream;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tokio::sync::{watch, Semaphore};
+use tower_http::cors::CorsLayer;
+use tower_http::timeout::TimeoutLayer;
+
+use crate::board::{Action, Board, PlayableBoard, ALL_ACTIONS, N};
+use crate::engine;
+use crate::eval;
+use crate::grpc;
+use crate::savegame::GameInProgress;
+use crate::strategy::Strategy;
+
+/// Largest body of a request, in bytes (a board takes less than 100)
+pub(crate) const MAX_BODY: usize = 64 * 1024;
+
+/// Time to receive a request and answer it, the event streams being unlimited once started
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Deepest expectimax search that a request can ask for
+pub const MAX_REMOTE_DEPTH: usize = 4;
+
+/// Longest time budget of a decision asked for by a request, and the budget of the requests giving none
+pub const MAX_REMOTE_BUDGET: Duration = Duration::from_secs(10);
+
+/// Games in progress, by id
+struct Games {
+    games: Mutex<HashMap<u64, GameInProgress>>,
+    /// Number of moves played in all games, watched by the event streams
+    moves: watch::Sender<u64>,
+}
+
+struct Server {
+    games: Games,
+    /// Strategy of `/best-move`, unless the request gives another one
+    strategy: Strategy,
+    /// Searches allowed to run at the same time, one per core
+    engine: Arc<Semaphore>,
+}
+
+/// Status and body of a response
+type Reply = (StatusCode, Json<Value>);
+
+/// Error answering a request, as `{"error": "..."}`
+struct Error {
+    status: StatusCode,
+    error: anyhow::Error,
+}
+
+impl<E: Into<anyhow::Error>> From<E> for Error {
+    /// The errors are those of invalid requests, unless stated otherwise
+    fn from(error: E) -> Error {
+        Error {
+            status: StatusCode::BAD_REQUEST,
+            error: error.into(),
+        }
+    }
+}
+
+impl IntoResponse for Error {
+    fn into_response(self) -> Response {
+        let error = format!("{:#}", self.error);
+        (self.status, Json(json!({ "error": error }))).into_response()
+    }
+}
+
+#[derive(Deserialize)]
+struct NewGame {
+    seed: Option<u64>,
+}
+
+#[derive(Deserialize)]
+struct Move {
+    action: Action,
+}
+
+#[derive(Deserialize)]
+struct Position {
+    board: [[u32; N]; N],
+}
+
+/// Serves the requests received on the listener, forever.
+pub fn serve(listener: TcpListener, strategy: Strategy) -> anyhow::Result<()> {
+    listener.set_nonblocking(true)?;
+    tokio::runtime::Runtime::new()?.block_on(async {
+        let listener = tokio::net::TcpListener::from_std(listener)?;
+        axum::serve(listener, router(strategy)).await?;
+        Ok(())
+    })
+}
+
+fn router(strategy: Strategy) -> Router {
+    let engine = Arc::new(Semaphore::new(num_cpus::get()));
+    let server = Server {
+        games: Games {
+            games: Mutex::new(HashMap::new()),
+            moves: watch::Sender::new(0),
+        },
+        strategy,
+        engine: engine.clone(),
+    };
+    let router = Router::new()
+        .route("/games", post(new_game))
+        .route("/games/{id}", get(game))
+        .route("/games/{id}/moves", post(play))
+        .route("/games/{id}/events", get(events))
+        .route("/best-move", post(best_move))
+        .route("/analysis", post(analysis));
+    router
+        .with_state(Arc::new(server))
+        .merge(grpc::routes(strategy, engine))
+        .fallback(|| async {
+            (
+                StatusCode::NOT_FOUND,
+                Json(json!({ "error": "Unknown endpoint" })),
+            )
+        })
+        .layer(DefaultBodyLimit::max(MAX_BODY))
+        .layer(TimeoutLayer::with_status_code(
+            StatusCode::REQUEST_TIMEOUT,
+            REQUEST_TIMEOUT,
+        ))
+        .layer(CorsLayer::permissive())
+}
+
+/// Strategy given by a request: any strategy that reads no file and looks at most `MAX_REMOTE_DEPTH` actions ahead.
+pub(crate) fn remote_strategy(name: &str) -> anyhow::Result<Strategy> {
+    let strategy = Strategy::parse_without_files(name)?;
+    match strategy {
+        Strategy::Default | Strategy::Random | Strategy::Greedy => {}
+        Strategy::Expectimax { depth } => ensure!(
+            depth <= MAX_REMOTE_DEPTH,
+            "The depth of the strategy is limited to {MAX_REMOTE_DEPTH} on this server"
+        ),
+        Strategy::Distilled(_) => bail!("The strategy `{name}` is not available on this server"),
+    }
+    Ok(strategy)
+}
+
+/// Time budget of a decision asked for by a request, at most `MAX_REMOTE_BUDGET`.
+pub(crate) fn remote_budget(budget_ms: Option<u64>) -> Duration {
+    budget_ms.map_or(MAX_REMOTE_BUDGET, |budget| {
+        Duration::from_millis(budget).min(MAX_REMOTE_BUDGET)
+    })
+}
+
+/// Runs the engine on a blocking thread once one of the `engine` permits is free, or returns `None` if it panics
+/// (e.g. on a function left as `todo!()`).
+pub(crate) async fn run_engine<R: Send + 'static>(
+    engine: &Arc<Semaphore>,
+    f: impl FnOnce() -> R + Send + 'static,
+) -> Option<R> {
+    let permit = engine
+        .clone()
+        .acquire_owned()
+        .await
+        .expect("the permits of the engine are never closed");
+    // the permit is kept until the end of the search, even if the request times out before it
+    tokio::task::spawn_blocking(move || {
+        let _permit = permit;
+        f()
+    })
+    .await
+    .ok()
+}
+
+impl Server {
+    async fn run_engine<R: Send + 'static>(
+        &self,
+        f: impl FnOnce() -> R + Send + 'static,
+    ) -> Result<R, Error> {
+        run_engine(&self.engine, f).await.ok_or_else(|| Error {
+            status: StatusCode::INTERNAL_SERVER_ERROR,
+            error: anyhow::anyhow!("The engine failed, see the terminal of the server"),
+        })
+    }
+
+    /// Applies `f` to the game of the id, or returns a 404 if there is no such game.
+    fn with_game(
+        &self,
+        id: &str,
+        f: impl FnOnce(u64, &mut GameInProgress) -> anyhow::Result<Value>,
+    ) -> Result<Reply, Error> {
+        let mut games = self.games.games.lock().unwrap();
+        match id
+            .parse()
+            .ok()
+            .and_then(|id| Some((id, games.get_mut(&id)?)))
+        {
+            Some((id, game)) => Ok((StatusCode::OK, Json(f(id, game)?))),
+            None => Ok(no_game(id)),
+        }
+    }
+}
+
+fn no_game(id: &str) -> Reply {
+    (
+        StatusCode::NOT_FOUND,
+        Json(json!({ "error": format!("No game {id}") })),
+    )
+}
+
+/// Body of a request, which must be UTF-8
+fn text(body: &Bytes) -> anyhow::Result<&str> {
+    std::str::from_utf8(body).context("The body is not UTF-8")
+}
+
+async fn new_game(State(server): State<Arc<Server>>, body: Bytes) -> Result<Reply, Error> {
+    let body = text(&body)?;
+    let request: NewGame = if body.trim().is_empty() {
+        NewGame { seed: None }
+    } else {
+        serde_json::from_str(body).context("Invalid game")?
+    };
+    let game = GameInProgress::new(request.seed.unwrap_or_else(rand::random));
+    let mut games = server.games.games.lock().unwrap();
+    let id = games.len() as u64 + 1;
+    let response = state(id, &game);
+    games.insert(id, game);
+    Ok((StatusCode::CREATED, Json(response)))
+}
+
+async fn game(State(server): State<Arc<Server>>, Path(id): Path<String>) -> Result<Reply, Error> {
+    server.with_game(&id, |id, game| Ok(state(id, game)))
+}
+
+async fn play(
+    State(server): State<Arc<Server>>,
+    Path(id): Path<String>,
+    body: Bytes,
+) -> Result<Reply, Error> {
+    let request: Move = serde_json::from_str(text(&body)?).context("Invalid move")?;
+    let response = server.with_game(&id, |id, game| {
+        if game.play(request.action).is_none() {
+            bail!("{:?} does not move any tile", request.action);
+        }
+        Ok(state(id, game))
+    })?;
+    server.games.moves.send_modify(|moves| *moves += 1);
+    Ok(response)
+}
+
+/// Sends the state of the game each time it changes, until it is lost or the client disconnects.
+async fn events(State(server): State<Arc<Server>>, Path(id): Path<String>) -> Response {
+    let Some(id) = id
+        .parse()
+        .ok()
+        .filter(|id| server.games.games.lock().unwrap().contains_key(id))
+    else {
+        return no_game(&id).into_response();
+    };
+    // subscribed before reading the game, to miss no move
+    let moves = server.games.moves.subscribe();
+    let events = stream::unfold(
+        (server, moves, None, false),
+        move |(server, mut moves, sent, lost)| async move {
+            if lost {
+                return None;
+            }
+            loop {
+                let (num_moves, lost, event) = {
+                    let games = server.games.games.lock().unwrap();
+                    let game = &games[&id];
+                    let lost = game.board().board().is_lost();
+                    (game.num_moves(), lost, state(id, game))
+                };
+                if sent != Some(num_moves) {
+                    let event = Event::default().data(event.to_string());
+                    return Some((
+                        Ok::<_, Infallible>(event),
+                        (server, moves, Some(num_moves), lost),
+                    ));
+                }
+                moves.changed().await.ok()?;
+            }
+        },
+    );
+    Sse::new(events).into_response()
+}
+
+async fn best_move(State(server): State<Arc<Server>>, body: Bytes) -> Result<Reply, Error> {
+    let request: engine::Request = serde_json::from_slice(&body).context("Invalid request")?;
+    let board = PlayableBoard::from(Board::from_values(request.board)?);
+    let strategy = match &request.strategy {
+        Some(name) => remote_strategy(name)?,
+        None => server.strategy,
+    };
+    let budget = remote_budget(request.budget_ms);
+    let response = server
+        .run_engine(move || engine::best_move(board, Some(budget), &strategy))
+        .await??;
+    Ok((StatusCode::OK, Json(serde_json::to_value(response)?)))
+}
+
+/// Evaluation of a board and of its actions
+async fn analysis(body: Bytes) -> Result<Reply, Error> {
+    let position: Position = serde_json::from_slice(&body).context("Invalid position")?;
+    let board = Board::from_values(position.board)?;
+    let breakdown = eval::explain(&board);
+    let terms: Vec<Value> = breakdown
+        .terms
+        .iter()
+        .map(|term| {
+            json!({
+                "name": term.name,
+                "raw": term.raw,
+                "weight": term.weight,
+                "contribution": term.contribution,
+            })
+        })
+        .collect();
+    let playable = PlayableBoard::from(board);
+    let actions: Vec<Value> = ALL_ACTIONS
+        .into_iter()
+        .filter_map(|action| {
+            let (after, score) = playable.apply_scored(action)?;
+            Some(json!({ "action": action, "score": score, "value": after.evaluate() }))
+        })
+        .collect();
+    Ok((
+        StatusCode::OK,
+        Json(json!({
+            "value": breakdown.total(),
+            "lost": breakdown.lost,
+            "base": breakdown.base,
+            "terms": terms,
+            "actions": actions,
+        })),
+    ))
+}
+
+/// State of a game, as sent to the clients
+fn state(id: u64, game: &GameInProgress) -> Value {
+    json!({
+        "id": id,
+        "seed": game.seed(),
+        "board": game.board().board().values(),
+        "num_moves": game.num_moves(),
+        "score": game.merge_score(),
+        "lost": game.board().board().is_lost(),
+    })
+}
+
+/// Largest request line and headers of the servers built on the standard library, in bytes
+const MAX_HEAD: u64 = 16 * 1024;
+
+/// A request read by the servers built on the standard library (`driver`, `spectate`), which only answer `GET`s
+pub(crate) struct Request {
+    pub path: String,
+    /// Headers, by lowercase name
+    pub headers: HashMap<String, String>,
+}
+
+/// Reads the request line and the headers (at most `MAX_HEAD` bytes), within `REQUEST_TIMEOUT`.
+pub(crate) fn read_request(stream: &mut TcpStream) -> anyhow::Result<Request> {
+    stream.set_read_timeout(Some(REQUEST_TIMEOUT))?;
+    let request = read_head(stream);
+    // the connection may become a WebSocket, waiting for messages for as long as it lasts
+    stream.set_read_timeout(None)?;
+    request
+}
+
+fn read_head(stream: &mut TcpStream) -> anyhow::Result<Request> {
+    let mut reader = BufReader::new(stream.take(MAX_HEAD));
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    let mut parts = line.split_whitespace();
+    let (Some(_method), Some(path)) = (parts.next(), parts.next()) else {
+        bail!("Invalid request line: {}", line.trim());
+    };
+    let path = path.to_string();
+    let mut headers = HashMap::new();
+    loop {
+        line.clear();
+        ensure!(reader.read_line(&mut line)? > 0, "Truncated request");
+        let header = line.trim();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            headers.insert(name.trim().to_ascii_lowercase(), value.trim().to_string());
+        }
+    }
+    Ok(Request { path, headers })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{BufRead, Write};
+
+    use super::*;
+
+    /// Starts a server with the random strategy, returning its address.
+    fn start() -> std::net::SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        std::thread::spawn(move || serve(listener, Strategy::Random));
+        addr
+    }
+
+    /// Sends the raw bytes of a request and returns the status and body of the response.
+    fn send_raw(addr: std::net::SocketAddr, request: &[u8]) -> (u16, String) {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        stream.write_all(request).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        let status = response[9..12].parse().unwrap();
+        let (_, body) = response.split_once("\r\n\r\n").unwrap();
+        (status, body.to_string())
+    }
+
+    /// Sends a request and returns the status and body of the response.
+    fn send(addr: std::net::SocketAddr, method: &str, path: &str, body: &str) -> (u16, Value) {
+        let request = format!(
+            "{method} {path} HTTP/1.1\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+            body.len()
+        );
+        let (status, body) = send_raw(addr, request.as_bytes());
+        (status, serde_json::from_str(&body).unwrap())
+    }
+
+    #[test]
+    fn test_server() {
+        let addr = start();
+        let (status, game) = send(addr, "POST", "/games", r#"{"seed": 3}"#);
+        assert_eq!(status, 201);
+        assert_eq!(game["num_moves"], 0);
+        let path = format!("/games/{}", game["id"]);
+
+        // the event stream sends the state at the start and after each move
+        let mut events = TcpStream::connect(addr).unwrap();
+        write!(events, "GET {path}/events HTTP/1.1\r\n\r\n").unwrap();
+        let mut events = BufReader::new(events);
+        let mut next_event = || loop {
+            let mut line = String::new();
+            events.read_line(&mut line).unwrap();
+            if let Some(event) = line.strip_prefix("data:") {
+                return serde_json::from_str::<Value>(event.trim()).unwrap();
+            }
+        };
+        assert_eq!(next_event(), game);
+
+        let action = GameInProgress::new(3)
+            .board()
+            .board()
+            .apply(Action::Left)
+            .map_or("Right", |_| "Left");
+        let (status, game) = send(
+            addr,
+            "POST",
+            &format!("{path}/moves"),
+            &format!(r#"{{"action": "{action}"}}"#),
+        );
+        assert_eq!(status, 200);
+        assert_eq!(game["num_moves"], 1);
+        assert_eq!(next_event(), game);
+        assert_eq!(send(addr, "GET", &path, "").1, game);
+        assert_eq!(send(addr, "GET", "/games/99", "").0, 404);
+        assert_eq!(send(addr, "GET", "/games/99/events", "").0, 404);
+        assert_eq!(send(addr, "GET", "/unknown", "").0, 404);
+
+        let board = r#"{"board": [[2, 2, 0, 0], [0, 0, 0, 0], [0, 0, 0, 0], [0, 0, 0, 0]]}"#;
+        let (status, answer) = send(addr, "POST", "/best-move", board);
+        assert_eq!(status, 200);
+        assert!(answer["action"].is_string());
+
+        let (status, analysis) = send(addr, "POST", "/analysis", board);
+        assert_eq!(status, 200);
+        assert_eq!(analysis["lost"], false);
+        // all actions but Up move the tiles
+        assert_eq!(analysis["actions"].as_array().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_invalid_requests() {
+        let addr = start();
+        // a body over the limit is refused once the limit is reached
+        let mut request = format!(
+            "POST /best-move HTTP/1.1\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+            MAX_BODY + 1
+        )
+        .into_bytes();
+        request.resize(request.len() + MAX_BODY + 1, b' ');
+        assert_eq!(send_raw(addr, &request).0, 413);
+        let (status, _) = send_raw(addr, b"GARBAGE\r\n\r\n");
+        assert_eq!(status, 400);
+        let (status, _) = send_raw(
+            addr,
+            b"POST /games HTTP/1.1\r\nContent-Length: 2\r\nConnection: close\r\n\r\n\xff\xfe",
+        );
+        assert_eq!(status, 400);
+
+        for (path, body) in [
+            ("/games", "{"),
+            ("/games/1/moves", r#"{"action": "Left"}"#),
+            ("/best-move", "[1, 2, 3]"),
+            (
+                "/best-move",
+                r#"{"board": [[3, 0, 0, 0], [0, 0, 0, 0], [0, 0, 0, 0], [0, 0, 0, 0]]}"#,
+            ),
+            ("/analysis", r#"{"board": [[2, 0, 0, 0]]}"#),
+        ] {
+            let (status, answer) = send(addr, "POST", path, body);
+            assert!(status == 400 || status == 404, "{path} {body}: {status}");
+            assert!(answer["error"].is_string(), "{path} {body}");
+        }
+
+        // strategies reading files or searching too deep are refused over the network
+        for strategy in ["distilled:file=/etc/passwd", "expectimax:depth=50", "mcts"] {
+            let request = json!({ "board": [[2, 2, 0, 0], [0, 0, 0, 0], [0, 0, 0, 0], [0, 0, 0, 0]], "strategy": strategy });
+            let (status, answer) = send(addr, "POST", "/best-move", &request.to_string());
+            assert_eq!(status, 400, "{strategy}");
+            assert!(answer["error"].is_string());
+        }
+    }
+
+    #[test]
+    fn test_remote_strategy() {
+        assert_eq!(remote_strategy("random").unwrap(), Strategy::Random);
+        assert_eq!(
+            remote_strategy(&format!("expectimax:depth={MAX_REMOTE_DEPTH}")).unwrap(),
+            Strategy::Expectimax {
+                depth: MAX_REMOTE_DEPTH
+            }
+        );
+        let error = remote_strategy("expectimax:depth=9").unwrap_err();
+        assert!(error.to_string().contains("limited"));
+        assert!(remote_strategy("distilled:file=policy.bin").is_err());
+
+        assert_eq!(remote_budget(Some(20)), Duration::from_millis(20));
+        assert_eq!(remote_budget(Some(u64::MAX)), MAX_REMOTE_BUDGET);
+        assert_eq!(remote_budget(None), MAX_REMOTE_BUDGET);
+    }
+
+    #[test]
+    fn test_read_request() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let client = std::thread::spawn(move || {
+            let mut stream = TcpStream::connect(addr).unwrap();
+            write!(
+                stream,
+                "GET /live HTTP/1.1\r\nSec-WebSocket-Key: abc\r\n\r\n"
+            )
+            .unwrap();
+            let mut stream = TcpStream::connect(addr).unwrap();
+            // headers without end, longer than the limit
+            stream.write_all(b"GET / HTTP/1.1\r\nX: ").unwrap();
+            let _ = stream.write_all(&vec![b'x'; MAX_HEAD as usize * 2]);
+        });
+        let request = read_request(&mut listener.accept().unwrap().0).unwrap();
+        assert_eq!(request.path, "/live");
+        assert_eq!(request.headers["sec-websocket-key"], "abc");
+        assert!(read_request(&mut listener.accept().unwrap().0).is_err());
+        client.join().unwrap();
+    }
+}
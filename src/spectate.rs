@@ -0,0 +1,226 @@
+//! Live spectators of the games played by `main auto` and `tournament` (`--spectate 0.0.0.0:8049`), for a classroom
+//! projector.
+//!
+//! The page served at `/` connects to the WebSocket `/live`, on which the state of every game is broadcast after each
+//! move, as a JSON text message:
+//!
+//! ```text
+//! {"action":"Left","board":[[2,0,0,0],[0,0,0,0],[0,0,0,0],[0,0,0,0]],"game":"greedy #3","lost":false,"num_moves":1,"score":0,"seed":3}
+//! ```
+//!
+//! Spectators joining late first receive the last state of every game. Serving them requires the `server` feature,
+//! while broadcasting without spectators does nothing, so that the players do not depend on the feature.
+
+use std::collections::BTreeMap;
+use std::io::Write;
+#[cfg(feature = "server")]
+use std::net::TcpListener;
+use std::net::TcpStream;
+use std::sync::{Arc, Mutex};
+
+use serde_json::{json, Value};
+
+use crate::board::Action;
+use crate::savegame::GameInProgress;
+use crate::websocket::text_frame;
+
+/// Connected spectators, and the last state of each game
+#[derive(Default)]
+pub struct Spectators {
+    clients: Mutex<Vec<TcpStream>>,
+    games: Mutex<BTreeMap<String, Value>>,
+}
+
+impl Spectators {
+    /// Broadcasts the state of the game named `name`, after `action` if any.
+    pub fn update(&self, name: &str, game: &GameInProgress, action: Option<Action>) {
+        let board = *game.board();
+        let state = json!({
+            "game": name,
+            "seed": game.seed(),
+            "board": board.board().values(),
+            "score": game.merge_score(),
+            "num_moves": game.num_moves(),
+            "action": action,
+            "lost": board.board().is_lost(),
+        });
+        let frame = text_frame(&state.to_string());
+        self.games.lock().unwrap().insert(name.to_string(), state);
+        // spectators that left are forgotten
+        self.clients
+            .lock()
+            .unwrap()
+            .retain_mut(|client| client.write_all(&frame).is_ok());
+    }
+
+    /// Adds a spectator whose WebSocket handshake is done, sending it the last state of every game.
+    #[cfg(feature = "server")]
+    fn join(&self, mut client: TcpStream) {
+        // hold the games while joining, so that no update is missed or sent twice
+        let games = self.games.lock().unwrap();
+        for state in games.values() {
+            if client.write_all(&text_frame(&state.to_string())).is_err() {
+                return;
+            }
+        }
+        self.clients.lock().unwrap().push(client);
+    }
+}
+
+/// Serves the spectators on the listener in a background thread.
+#[cfg(feature = "server")]
+pub fn listen(listener: TcpListener) -> Arc<Spectators> {
+    let spectators = Arc::new(Spectators::default());
+    let shared = spectators.clone();
+    std::thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let spectators = shared.clone();
+            std::thread::spawn(move || {
+                // a spectator failing to connect must not stop the others
+                let _ = server::handle(&spectators, stream);
+            });
+        }
+    });
+    spectators
+}
+
+/// Listens for spectators on the address, printing the URL of the page.
+#[cfg(feature = "server")]
+pub fn bind(addr: &str) -> anyhow::Result<Arc<Spectators>> {
+    use anyhow::Context;
+
+    let listener = TcpListener::bind(addr).with_context(|| format!("Cannot listen on {addr}"))?;
+    eprintln!("Spectate on http://{}", listener.local_addr()?);
+    Ok(listen(listener))
+}
+
+#[cfg(not(feature = "server"))]
+pub fn bind(_addr: &str) -> anyhow::Result<Arc<Spectators>> {
+    anyhow::bail!("Spectators are not available in this build, rebuild with `--features server`")
+}
+
+#[cfg(feature = "server")]
+mod server {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::server::read_request;
+    use crate::websocket;
+
+    /// Time after which a spectator that does not read its messages is dropped, rather than slowing down the games
+    const WRITE_TIMEOUT: Duration = Duration::from_secs(2);
+
+    pub fn handle(spectators: &Spectators, mut stream: TcpStream) -> anyhow::Result<()> {
+        let request = read_request(&mut stream)?;
+        if request.path != "/live" {
+            write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Type: text/html; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{PAGE}",
+                PAGE.len()
+            )?;
+            return Ok(());
+        }
+        websocket::accept(&mut stream, &request)?;
+        stream.set_write_timeout(Some(WRITE_TIMEOUT))?;
+        spectators.join(stream);
+        Ok(())
+    }
+
+    /// Page showing the board of every game, updated live
+    const PAGE: &str = r##"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>2048 live</title>
+<style>
+body { font-family: Helvetica, Arial, sans-serif; background: #faf8ef; color: #776e65; }
+#games { display: flex; flex-wrap: wrap; gap: 24px; }
+.game h2 { font-size: 18px; margin: 4px 0; }
+.board { display: grid; grid-template-columns: repeat(4, 64px); gap: 6px; padding: 6px; background: #bbada0; border-radius: 6px; }
+.tile { height: 64px; display: flex; align-items: center; justify-content: center; font-weight: bold; font-size: 22px; border-radius: 4px; }
+</style>
+</head>
+<body>
+<h1>2048 live</h1>
+<div id="games"></div>
+<script>
+const colors = {0: "#cdc1b4", 2: "#eee4da", 4: "#ede0c8", 8: "#f2b179", 16: "#f59563", 32: "#f67c5f", 64: "#f65e3b",
+  128: "#edcf72", 256: "#edcc61", 512: "#edc850", 1024: "#edc53f"};
+const socket = new WebSocket(`ws://${location.host}/live`);
+socket.onmessage = (message) => {
+  const state = JSON.parse(message.data);
+  let game = document.getElementById(state.game);
+  if (!game) {
+    game = document.createElement("div");
+    game.id = state.game;
+    game.className = "game";
+    document.getElementById("games").appendChild(game);
+  }
+  const tiles = state.board.flat().map((value) =>
+    `<div class="tile" style="background: ${colors[value] || "#edc22e"}">${value || ""}</div>`).join("");
+  const status = state.lost ? "game over" : (state.action || "start");
+  game.innerHTML = `<h2>${state.game}</h2><div>move ${state.num_moves}, score ${state.score} (${status})</div>
+    <div class="board">${tiles}</div>`;
+};
+</script>
+</body>
+</html>
+"##;
+}
+
+#[cfg(all(test, feature = "server"))]
+mod tests {
+    use std::io::{BufRead, BufReader, Read};
+
+    use super::*;
+
+    #[test]
+    fn test_spectate() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let spectators = listen(listener);
+        let game = GameInProgress::new(4);
+        spectators.update("early", &game, None);
+
+        let mut stream = TcpStream::connect(addr).unwrap();
+        write!(
+            stream,
+            "GET /live HTTP/1.1\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\nSec-WebSocket-Version: 13\r\n\r\n"
+        )
+        .unwrap();
+        let mut reader = BufReader::new(stream);
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        assert!(line.starts_with("HTTP/1.1 101"));
+        while line.trim() != "" {
+            line.clear();
+            reader.read_line(&mut line).unwrap();
+        }
+        let mut read_message = || {
+            let mut header = [0; 2];
+            reader.read_exact(&mut header).unwrap();
+            assert_eq!(header[0], 0x81);
+            let len = match header[1] {
+                126 => {
+                    let mut len = [0; 2];
+                    reader.read_exact(&mut len).unwrap();
+                    u16::from_be_bytes(len) as usize
+                }
+                len => len as usize,
+            };
+            let mut text = vec![0; len];
+            reader.read_exact(&mut text).unwrap();
+            serde_json::from_slice::<Value>(&text).unwrap()
+        };
+        // the state of games started before joining
+        assert_eq!(read_message()["game"], "early");
+        // wait for the spectator to be registered
+        while spectators.clients.lock().unwrap().is_empty() {
+            std::thread::yield_now();
+        }
+        spectators.update("late", &game, Some(Action::Left));
+        let message = read_message();
+        assert_eq!(message["game"], "late");
+        assert_eq!(message["action"], "Left");
+    }
+}
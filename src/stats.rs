@@ -0,0 +1,261 @@
+//! Descriptive statistics over the results of several games.
+
+use std::fmt::{Display, Formatter};
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+/// Number of resamples of bootstrap confidence intervals and of random sign flips of the paired test
+const NUM_RESAMPLES: usize = 10_000;
+
+/// Seed of the resampling, so that reports are reproducible
+const RESAMPLING_SEED: u64 = 0;
+
+/// Summary of a sample of values: mean, standard deviation and quantiles.
+#[derive(Clone, Copy, Debug, PartialEq, serde::Serialize)]
+pub struct Summary {
+    pub count: usize,
+    pub mean: f64,
+    /// Sample standard deviation (0 with less than 2 values)
+    pub std: f64,
+    pub min: f64,
+    pub p25: f64,
+    pub median: f64,
+    pub p75: f64,
+    pub p95: f64,
+    pub max: f64,
+}
+
+impl Summary {
+    /// Summarizes the values, or returns `None` if there are none.
+    pub fn of(values: &[f64]) -> Option<Summary> {
+        if values.is_empty() {
+            return None;
+        }
+        let mut sorted = values.to_vec();
+        sorted.sort_by(f64::total_cmp);
+        let count = values.len();
+        let mean = values.iter().sum::<f64>() / count as f64;
+        let variance = if count > 1 {
+            values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (count - 1) as f64
+        } else {
+            0.0
+        };
+        Some(Summary {
+            count,
+            mean,
+            std: variance.sqrt(),
+            min: sorted[0],
+            p25: quantile(&sorted, 0.25),
+            median: quantile(&sorted, 0.5),
+            p75: quantile(&sorted, 0.75),
+            p95: quantile(&sorted, 0.95),
+            max: sorted[count - 1],
+        })
+    }
+}
+
+impl Display for Summary {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "  mean:   {:>10.2} (std {:.2})", self.mean, self.std)?;
+        writeln!(f, "  min:    {:>10.2}", self.min)?;
+        writeln!(f, "  p25:    {:>10.2}", self.p25)?;
+        writeln!(f, "  median: {:>10.2}", self.median)?;
+        writeln!(f, "  p75:    {:>10.2}", self.p75)?;
+        writeln!(f, "  p95:    {:>10.2}", self.p95)?;
+        writeln!(f, "  max:    {:>10.2}", self.max)
+    }
+}
+
+/// Quantile `q` of sorted values, linearly interpolated between the two closest ranks.
+///
+/// Panics if `sorted` is empty.
+pub fn quantile(sorted: &[f64], q: f64) -> f64 {
+    let rank = q * (sorted.len() - 1) as f64;
+    let (below, above) = (rank.floor() as usize, rank.ceil() as usize);
+    let t = rank - below as f64;
+    sorted[below] * (1.0 - t) + sorted[above] * t
+}
+
+/// Mean of the values (NaN if there are none)
+pub fn mean(values: &[f64]) -> f64 {
+    values.iter().sum::<f64>() / values.len() as f64
+}
+
+/// Median of the values (NaN if there are none)
+pub fn median(values: &[f64]) -> f64 {
+    if values.is_empty() {
+        return f64::NAN;
+    }
+    let mut sorted = values.to_vec();
+    sorted.sort_by(f64::total_cmp);
+    quantile(&sorted, 0.5)
+}
+
+/// Percentile bootstrap confidence interval of the statistic, at the given level (e.g. 0.95).
+///
+/// The values are resampled with replacement, and the interval is made of the quantiles of the statistic
+/// over the resamples. Panics if `values` is empty.
+pub fn bootstrap_ci(values: &[f64], statistic: fn(&[f64]) -> f64, level: f64) -> (f64, f64) {
+    let mut rng = StdRng::seed_from_u64(RESAMPLING_SEED);
+    let mut resample = vec![0.0; values.len()];
+    let mut statistics: Vec<f64> = (0..NUM_RESAMPLES)
+        .map(|_| {
+            for value in resample.iter_mut() {
+                *value = values[rng.random_range(0..values.len())];
+            }
+            statistic(&resample)
+        })
+        .collect();
+    statistics.sort_by(f64::total_cmp);
+    let alpha = (1.0 - level) / 2.0;
+    (
+        quantile(&statistics, alpha),
+        quantile(&statistics, 1.0 - alpha),
+    )
+}
+
+/// One-sided p-value of the hypothesis that paired differences have a positive mean, by a sign-flip test.
+///
+/// Under the null hypothesis (no difference between the two sides), each difference is as likely to be positive
+/// as negative: the p-value is the fraction of random sign flips whose mean is at least the observed mean.
+pub fn paired_p_value(differences: &[f64]) -> f64 {
+    let observed = mean(differences);
+    let mut rng = StdRng::seed_from_u64(RESAMPLING_SEED);
+    let num_extreme = (0..NUM_RESAMPLES)
+        .filter(|_| {
+            let flipped: f64 = differences
+                .iter()
+                .map(|&d| if rng.random_bool(0.5) { d } else { -d })
+                .sum();
+            flipped / differences.len() as f64 >= observed
+        })
+        .count();
+    // the observed signs are one of the possible flips
+    (num_extreme + 1) as f64 / (NUM_RESAMPLES + 1) as f64
+}
+
+/// Kaplan-Meier estimate of the probability of still running beyond each value (e.g. number of moves).
+///
+/// Each sample is the value reached by a game and whether the game ended there. Games that were interrupted
+/// (e.g. by a timeout) are censored: they count as running up to their value, and are ignored afterwards.
+/// Returns the estimate right after each value at which a game ended, starting with `(0, 1)`.
+pub fn survival_curve(samples: &[(f64, bool)]) -> Vec<(f64, f64)> {
+    let mut sorted = samples.to_vec();
+    sorted.sort_by(|a, b| a.0.total_cmp(&b.0));
+    let mut curve = vec![(0.0, 1.0)];
+    let mut survival = 1.0;
+    let mut at_risk = sorted.len();
+    let mut i = 0;
+    while i < sorted.len() {
+        let value = sorted[i].0;
+        let same_value = sorted[i..].iter().take_while(|sample| sample.0 == value);
+        let (num_samples, num_ended) = same_value.fold((0, 0), |(n, ended), sample| {
+            (n + 1, ended + usize::from(sample.1))
+        });
+        if num_ended > 0 {
+            survival *= 1.0 - num_ended as f64 / at_risk as f64;
+            curve.push((value, survival));
+        }
+        at_risk -= num_samples;
+        i += num_samples;
+    }
+    curve
+}
+
+/// Number of iterations of the fit of Bradley-Terry strengths
+const BRADLEY_TERRY_ITERATIONS: usize = 1000;
+
+/// Strengths of players by a Bradley-Terry model, where player `i` beats player `j` with probability
+/// `s[i] / (s[i] + s[j])`. `wins[i][j]` is the number of times `i` beat `j` (ties count as half a win for each).
+///
+/// Each pair of players is given one additional tie, so that a player winning (or losing) all its games still gets a
+/// finite strength. Strengths are normalized to have a geometric mean of 1.
+pub fn bradley_terry(wins: &[Vec<f64>]) -> Vec<f64> {
+    let n = wins.len();
+    let wins_of = |i: usize, j: usize| wins[i][j] + 0.5;
+    let mut strengths = vec![1.0; n];
+    // minorization-maximization iterations (Hunter, 2004)
+    for _ in 0..BRADLEY_TERRY_ITERATIONS {
+        let mut next: Vec<f64> = (0..n)
+            .map(|i| {
+                let others = (0..n).filter(|&j| j != i);
+                let total_wins: f64 = others.clone().map(|j| wins_of(i, j)).sum();
+                let denominator: f64 = others
+                    .map(|j| (wins_of(i, j) + wins_of(j, i)) / (strengths[i] + strengths[j]))
+                    .sum();
+                total_wins / denominator
+            })
+            .collect();
+        let log_mean = next.iter().map(|s| s.ln()).sum::<f64>() / n as f64;
+        for s in &mut next {
+            *s /= log_mean.exp();
+        }
+        strengths = next;
+    }
+    strengths
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_summary() {
+        assert_eq!(Summary::of(&[]), None);
+        let summary = Summary::of(&[4.0, 1.0, 3.0, 2.0, 5.0]).unwrap();
+        assert_eq!(summary.count, 5);
+        assert_eq!(summary.mean, 3.0);
+        assert_eq!(summary.std, 2.5f64.sqrt());
+        assert_eq!((summary.min, summary.max), (1.0, 5.0));
+        assert_eq!((summary.p25, summary.median, summary.p75), (2.0, 3.0, 4.0));
+        assert!((summary.p95 - 4.8).abs() < 1e-9);
+        assert_eq!(Summary::of(&[7.0]).unwrap().std, 0.0);
+    }
+
+    #[test]
+    fn test_resampling() {
+        let values: Vec<f64> = (0..100).map(|i| (i % 10) as f64).collect();
+        let (low, high) = bootstrap_ci(&values, mean, 0.95);
+        assert!(low < 4.5 && 4.5 < high && high - low < 2.0);
+
+        let better: Vec<f64> = (0..30).map(|i| 1.0 + (i % 3) as f64).collect();
+        assert!(paired_p_value(&better) < 0.001);
+        let noise: Vec<f64> = (0..30)
+            .map(|i| if i % 2 == 0 { 1.0 } else { -1.0 })
+            .collect();
+        assert!(paired_p_value(&noise) > 0.3);
+    }
+
+    #[test]
+    fn test_survival_curve() {
+        let curve = survival_curve(&[(10.0, true), (30.0, true), (20.0, true), (20.0, true)]);
+        assert_eq!(
+            curve,
+            vec![(0.0, 1.0), (10.0, 0.75), (20.0, 0.25), (30.0, 0.0)]
+        );
+        // the game interrupted at 15 is no longer at risk when the other games end
+        let curve = survival_curve(&[(10.0, true), (15.0, false), (20.0, true), (30.0, true)]);
+        assert_eq!(
+            curve,
+            vec![(0.0, 1.0), (10.0, 0.75), (20.0, 0.375), (30.0, 0.0)]
+        );
+    }
+
+    #[test]
+    fn test_bradley_terry() {
+        // 0 beats 1 three times out of four, 1 beats 2 three times out of four
+        let wins = vec![
+            vec![0.0, 30.0, 40.0],
+            vec![10.0, 0.0, 30.0],
+            vec![0.0, 10.0, 0.0],
+        ];
+        let strengths = bradley_terry(&wins);
+        assert!(strengths[0] > strengths[1] && strengths[1] > strengths[2]);
+        assert!((strengths.iter().product::<f64>() - 1.0).abs() < 1e-9);
+        let p = strengths[0] / (strengths[0] + strengths[1]);
+        assert!((p - 0.75).abs() < 0.05);
+        // players that are never beaten still have a finite strength
+        assert!(strengths.iter().all(|s| s.is_finite() && *s > 0.0));
+    }
+}
@@ -0,0 +1,255 @@
+//! Registry of the action-selection strategies, selectable by name from the command line.
+//!
+//! A strategy is written as its name, optionally followed by parameters: `random`, `expectimax:depth=4`,
+//! `distilled:file=policy.bin`.
+
+use std::fmt::{Display, Formatter};
+use std::path::Path;
+use std::str::FromStr;
+use std::time::Duration;
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Instant;
+
+use anyhow::{bail, Context};
+#[cfg(target_arch = "wasm32")]
+use web_time::Instant;
+
+use crate::board::{Action, PlayableBoard, ALL_ACTIONS};
+use crate::distill::{self, LookupPolicy};
+use crate::search;
+
+/// Default depth (number of actions looked ahead) of expectimax
+const DEFAULT_DEPTH: usize = 3;
+
+/// Lower estimate of how much longer a search one action deeper takes, used to decide whether to deepen
+const DEPTH_GROWTH: u32 = 8;
+
+/// An algorithm selecting the action to play on a board.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Strategy {
+    /// Whatever `search::select_action` calls
+    Default,
+    /// A uniformly random applicable action
+    Random,
+    /// The action leading to the best evaluated afterstate
+    Greedy,
+    /// Expectimax search, looking `depth` actions ahead
+    Expectimax { depth: usize },
+    /// The applicable action preferred by a policy distilled from a search (see `distill`), loaded once for the
+    /// whole program
+    Distilled(&'static LookupPolicy),
+}
+
+/// Names and descriptions of all strategies, e.g. for help messages
+pub const STRATEGIES: [(&str, &str); 5] = [
+    ("default", "the strategy called by `search::select_action`"),
+    ("random", "a uniformly random applicable action"),
+    (
+        "greedy",
+        "the action leading to the best evaluated afterstate",
+    ),
+    (
+        "expectimax",
+        "expectimax search, with parameter `depth` (number of actions looked ahead)",
+    ),
+    (
+        "distilled",
+        "lookup policy distilled from a search by `main distill`, with parameter `file`",
+    ),
+];
+
+impl Strategy {
+    /// Selects the action to play on the board, or `None` if no action is applicable.
+    pub fn select_action(&self, board: PlayableBoard) -> Option<Action> {
+        match *self {
+            Strategy::Default => search::select_action(board),
+            Strategy::Random => search::select_action_randomly(board),
+            Strategy::Greedy => search::select_action_greedily(board),
+            Strategy::Expectimax { depth } => search::select_action_expectimax(board, depth),
+            Strategy::Distilled(policy) => policy.select_action(board),
+        }
+    }
+
+    /// Value of each action (in the order of `ALL_ACTIONS`) for the strategy, or `None` for the actions that are not
+    /// applicable: its expected value for expectimax, its preference for a distilled policy, the evaluation of its
+    /// afterstate for the random strategy (which has no values of its own), its expected value looking one action
+    /// ahead otherwise.
+    pub fn evaluate_all_actions(&self, board: PlayableBoard) -> [Option<f32>; 4] {
+        match *self {
+            Strategy::Expectimax { depth } => search::evaluate_all_actions(board, depth),
+            Strategy::Distilled(policy) => {
+                let preferences = policy.preferences(board.board());
+                std::array::from_fn(|i| board.apply(ALL_ACTIONS[i]).map(|_| preferences[i]))
+            }
+            Strategy::Random => {
+                ALL_ACTIONS.map(|action| board.apply(action).map(|after| after.evaluate()))
+            }
+            Strategy::Default | Strategy::Greedy => search::evaluate_all_actions(board, 1),
+        }
+    }
+
+    /// Parses a strategy that does not read any file (any but `distilled`), e.g. one given by a web page, where the
+    /// files of the machine are out of reach or must stay so.
+    pub fn parse_without_files(s: &str) -> anyhow::Result<Strategy> {
+        let (name, _) = s.split_once(':').unwrap_or((s, ""));
+        if name == "distilled" {
+            bail!(
+                "The strategy `distilled` reads its policy from a file, which is not possible here"
+            );
+        }
+        s.parse()
+    }
+
+    /// The same strategy looking `depth` actions ahead, or `None` if the strategy has no depth.
+    pub fn with_depth(&self, depth: usize) -> Option<Strategy> {
+        match self {
+            Strategy::Expectimax { .. } => Some(Strategy::Expectimax { depth }),
+            _ => None,
+        }
+    }
+
+    /// Same as `select_action`, but decides within the time budget.
+    ///
+    /// Expectimax is run as an anytime search: with increasing depths up to its own depth, and no deeper after Ctrl-C
+    /// (see `interrupt`). Each depth after the first is stopped when the budget runs out (see `search::with_deadline`),
+    /// and the action of the deepest completed depth is returned. A depth that is expected to take more than the rest
+    /// of the budget, at least `DEPTH_GROWTH` times as long as all the previous ones, is not started at all.
+    ///
+    /// Only the first depth, needed to have an action at all, may overrun the budget (`bench` counts these overruns).
+    /// Other strategies are not interruptible and ignore the budget. On wasm, searches cannot be stopped and the
+    /// budget is only checked between two depths.
+    pub fn select_action_within(&self, board: PlayableBoard, budget: Duration) -> Option<Action> {
+        let Strategy::Expectimax { depth: max_depth } = *self else {
+            return self.select_action(board);
+        };
+        let start = Instant::now();
+        let deadline = start + budget;
+        let mut action = search::select_action_expectimax(board, 1);
+        for depth in 2..=max_depth {
+            let elapsed = start.elapsed();
+            if action.is_none() || elapsed * (1 + DEPTH_GROWTH) > budget {
+                break;
+            }
+            // after Ctrl-C, the shallower decision is good enough to finish promptly
+            #[cfg(not(target_arch = "wasm32"))]
+            if crate::interrupt::interrupted() {
+                break;
+            }
+            match search::with_deadline(deadline, || search::select_action_expectimax(board, depth))
+            {
+                Some(deeper) => action = deeper,
+                // out of time: the shallower decision stands
+                None => break,
+            }
+        }
+        action
+    }
+}
+
+impl FromStr for Strategy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Strategy> {
+        let (name, params) = s.split_once(':').unwrap_or((s, ""));
+        let mut depth = DEFAULT_DEPTH;
+        let mut file = None;
+        for param in params.split(',').filter(|param| !param.is_empty()) {
+            let (key, value) = param
+                .split_once('=')
+                .with_context(|| format!("Expected `key=value` but got `{param}`"))?;
+            match (name, key) {
+                ("expectimax", "depth") => {
+                    depth = value
+                        .parse()
+                        .with_context(|| format!("Invalid depth: {value}"))?
+                }
+                ("distilled", "file") => file = Some(value),
+                _ => bail!("Unknown parameter `{key}` for strategy `{name}`"),
+            }
+        }
+        Ok(match name {
+            "default" => Strategy::Default,
+            "random" => Strategy::Random,
+            "greedy" => Strategy::Greedy,
+            "expectimax" => Strategy::Expectimax { depth },
+            "distilled" => {
+                let file = file.context("Missing parameter `file` of strategy `distilled`")?;
+                Strategy::Distilled(distill::load_shared(Path::new(file))?)
+            }
+            _ => {
+                let names: Vec<_> = STRATEGIES.iter().map(|(name, _)| *name).collect();
+                bail!("Unknown strategy: {name} (available: {})", names.join(", "))
+            }
+        })
+    }
+}
+
+impl Display for Strategy {
+    /// Writes the strategy in the format accepted by `from_str`, with all its parameters.
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Strategy::Default => write!(f, "default"),
+            Strategy::Random => write!(f, "random"),
+            Strategy::Greedy => write!(f, "greedy"),
+            Strategy::Expectimax { depth } => write!(f, "expectimax:depth={depth}"),
+            Strategy::Distilled(policy) => match policy.source() {
+                Some(path) => write!(f, "distilled:file={}", path.display()),
+                None => write!(f, "distilled"),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!("random".parse::<Strategy>().unwrap(), Strategy::Random);
+        assert_eq!(
+            "expectimax:depth=4".parse::<Strategy>().unwrap(),
+            Strategy::Expectimax { depth: 4 }
+        );
+        assert_eq!(
+            "expectimax".parse::<Strategy>().unwrap(),
+            Strategy::Expectimax {
+                depth: DEFAULT_DEPTH
+            }
+        );
+        for strategy in [Strategy::Greedy, Strategy::Expectimax { depth: 2 }] {
+            assert_eq!(strategy.to_string().parse::<Strategy>().unwrap(), strategy);
+        }
+        assert_eq!(
+            Strategy::Expectimax { depth: 2 }.with_depth(5),
+            Some(Strategy::Expectimax { depth: 5 })
+        );
+        assert_eq!(Strategy::Random.with_depth(5), None);
+        assert!("mcts".parse::<Strategy>().is_err());
+        assert!("random:depth=2".parse::<Strategy>().is_err());
+        assert!("expectimax:depth=deep".parse::<Strategy>().is_err());
+        assert!("distilled".parse::<Strategy>().is_err());
+    }
+
+    #[test]
+    fn test_parse_without_files() {
+        assert_eq!(
+            Strategy::parse_without_files("expectimax:depth=2").unwrap(),
+            Strategy::Expectimax { depth: 2 }
+        );
+        assert_eq!(
+            Strategy::parse_without_files("random").unwrap(),
+            Strategy::Random
+        );
+        // rejected before reading anything, even if the file exists
+        let path = std::env::temp_dir().join(format!("ai-2048-policy-{}.bin", std::process::id()));
+        crate::distill::LookupPolicy::new().save(&path).unwrap();
+        let error = Strategy::parse_without_files(&format!("distilled:file={}", path.display()))
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("distilled"), "{error}");
+        assert!(Strategy::parse_without_files("distilled").is_err());
+        std::fs::remove_file(&path).unwrap();
+        assert!(Strategy::parse_without_files("mcts").is_err());
+    }
+}
@@ -0,0 +1,234 @@
+//! Submissions to the course leaderboard (`main submit`): a standardized benchmark, the same for everyone, written to a
+//! single JSON file with the configuration, the git revision and the record of every game.
+//!
+//! The leaderboard does not trust the claimed scores: `Submission::verify` replays the record of each game (see
+//! `record`) on the standard seeds and recomputes the mean score. The digest of the file only detects edits made by
+//! hand, anyone being able to recompute it.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{ensure, Context};
+use sha2::{Digest, Sha256};
+
+use crate::eval::{self, HEURISTICS};
+use crate::record::Record;
+use crate::savegame::GameInProgress;
+use crate::search;
+use crate::strategy::Strategy;
+
+/// Version of the format of the submission files
+const FORMAT: u32 = 1;
+
+/// Seeds of the games of the standardized benchmark
+pub const SEEDS: std::ops::Range<u64> = 1_000..1_050;
+
+/// Time budget of each decision in the standardized benchmark, in milliseconds
+pub const TIME_PER_MOVE_MS: u64 = 100;
+
+/// Content of a submission file
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct Submission {
+    pub format: u32,
+    /// Name of the student or team
+    pub name: String,
+    pub strategy: String,
+    /// Weights of the evaluation function, by heuristic
+    pub weights: BTreeMap<String, f32>,
+    pub time_per_move_ms: u64,
+    /// Abbreviated hash of the git commit of the code, suffixed by `-dirty` if there were uncommitted changes
+    pub git_commit: Option<String>,
+    /// Seconds since the Unix epoch
+    pub created_at: u64,
+    /// Mean 2048 score over the games
+    pub mean_score: f64,
+    /// Record of each game, in the order of `SEEDS`
+    pub records: Vec<String>,
+    /// SHA-256 of the other fields, in hexadecimal
+    pub digest: String,
+}
+
+/// Plays the standardized benchmark with the strategy, using the weights of the default evaluation.
+pub fn run(name: &str, strategy: &Strategy) -> anyhow::Result<Submission> {
+    use rayon::prelude::*;
+
+    let records: Vec<Record> = SEEDS
+        .into_par_iter()
+        .map(|seed| {
+            let mut game = GameInProgress::new(seed);
+            // as in `bench`, random decisions of a strategy are reproducible
+            search::seed_strategy_rng(seed ^ search::STRATEGY_STREAM);
+            let budget = std::time::Duration::from_millis(TIME_PER_MOVE_MS);
+            while let Some(action) = strategy.select_action_within(*game.board(), budget) {
+                game.play(action)
+                    .context("The strategy selected an inapplicable action")?;
+            }
+            Ok(Record::of(&game))
+        })
+        .collect::<anyhow::Result<_>>()?;
+    let weights = eval::weights();
+    let mut submission = Submission {
+        format: FORMAT,
+        name: name.to_string(),
+        strategy: strategy.to_string(),
+        weights: HEURISTICS
+            .iter()
+            .zip(weights.0)
+            .map(|(heuristic, weight)| (heuristic.name.to_string(), weight))
+            .collect(),
+        time_per_move_ms: TIME_PER_MOVE_MS,
+        git_commit: git_commit(),
+        created_at: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_secs()),
+        mean_score: mean_score(&records),
+        records: records.iter().map(Record::to_string).collect(),
+        digest: String::new(),
+    };
+    submission.digest = submission.compute_digest()?;
+    Ok(submission)
+}
+
+fn mean_score(records: &[Record]) -> f64 {
+    let total: u64 = records
+        .iter()
+        .map(|record| u64::from(record.claimed_score))
+        .sum();
+    total as f64 / records.len().max(1) as f64
+}
+
+impl Submission {
+    pub fn load(path: &Path) -> anyhow::Result<Submission> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Cannot read {}", path.display()))?;
+        serde_json::from_str(&text)
+            .with_context(|| format!("Invalid submission {}", path.display()))
+    }
+
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        std::fs::write(path, format!("{:#}\n", serde_json::to_value(self)?))
+            .with_context(|| format!("Cannot write {}", path.display()))
+    }
+
+    /// SHA-256 of the submission without its digest
+    fn compute_digest(&self) -> anyhow::Result<String> {
+        let content = serde_json::to_string(&Submission {
+            digest: String::new(),
+            ..self.clone()
+        })?;
+        Ok(Sha256::digest(content.as_bytes())
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect())
+    }
+
+    /// Checks that the submission follows the standardized benchmark and was not edited, by replaying all its games:
+    /// each game must be played to its end on the standard seeds and reach its claimed score, and the mean score must
+    /// be that of the games. Returns the mean score.
+    pub fn verify(&self) -> anyhow::Result<f64> {
+        ensure!(
+            self.format == FORMAT,
+            "Unsupported format of submission: {}",
+            self.format
+        );
+        ensure!(
+            self.digest == self.compute_digest()?,
+            "The digest does not match the content, the file was edited"
+        );
+        ensure!(
+            self.time_per_move_ms == TIME_PER_MOVE_MS,
+            "The time per move is not the standard one ({TIME_PER_MOVE_MS}ms)"
+        );
+        let records: Vec<Record> = self
+            .records
+            .iter()
+            .map(|record| record.parse())
+            .collect::<anyhow::Result<_>>()?;
+        ensure!(
+            records.iter().map(|record| record.seed).eq(SEEDS),
+            "The games are not played on the standard seeds {}..{}",
+            SEEDS.start,
+            SEEDS.end
+        );
+        for record in &records {
+            let verified = record
+                .verify()
+                .with_context(|| format!("Invalid game of seed {}", record.seed))?;
+            ensure!(
+                verified.board.is_lost(),
+                "The game of seed {} was stopped before its end",
+                record.seed
+            );
+        }
+        let mean = mean_score(&records);
+        ensure!(
+            mean == self.mean_score,
+            "The submission claims a mean score of {}, but its games reach {mean}",
+            self.mean_score
+        );
+        Ok(mean)
+    }
+}
+
+/// Abbreviated hash of the current git commit, suffixed by `-dirty` if there are uncommitted changes
+/// (`None` outside of a git repository).
+pub fn git_commit() -> Option<String> {
+    let git = |args: &[&str]| Command::new("git").args(args).output().ok();
+    let head = git(&["rev-parse", "--short", "HEAD"]).filter(|output| output.status.success())?;
+    let mut commit = String::from_utf8(head.stdout).ok()?.trim().to_string();
+    if git(&["diff", "--quiet", "HEAD"]).is_some_and(|output| !output.status.success()) {
+        commit.push_str("-dirty");
+    }
+    Some(commit)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verify() {
+        let games: Vec<Record> = SEEDS
+            .map(|seed| {
+                let mut game = GameInProgress::new(seed);
+                while let Some(action) = search::select_action_randomly(*game.board()) {
+                    game.play(action);
+                }
+                Record::of(&game)
+            })
+            .collect();
+        let mut submission = Submission {
+            format: FORMAT,
+            name: "test".to_string(),
+            strategy: "random".to_string(),
+            weights: BTreeMap::new(),
+            time_per_move_ms: TIME_PER_MOVE_MS,
+            git_commit: None,
+            created_at: 0,
+            mean_score: mean_score(&games),
+            records: games.iter().map(Record::to_string).collect(),
+            digest: String::new(),
+        };
+        submission.digest = submission.compute_digest().unwrap();
+        let path = std::env::temp_dir().join(format!("submission-{}.json", std::process::id()));
+        submission.save(&path).unwrap();
+        let loaded = Submission::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.verify().unwrap(), submission.mean_score);
+
+        // a claimed score edited along with the digest is still detected by replaying the game
+        let mut edited = submission.clone();
+        let mut record: Record = edited.records[0].parse().unwrap();
+        record.claimed_score += 4;
+        edited.records[0] = record.to_string();
+        edited.mean_score += 4.0 / SEEDS.count() as f64;
+        edited.digest = edited.compute_digest().unwrap();
+        assert!(edited.verify().is_err());
+        // and edits without the digest by the digest
+        let mut edited = submission.clone();
+        edited.name = "someone else".to_string();
+        assert!(edited.verify().is_err());
+    }
+}
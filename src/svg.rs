@@ -0,0 +1,191 @@
+//! Rendering of recorded games as SVG images with the colors of the terminal, to embed runs in reports and
+//! presentations (`main export`).
+//!
+//! A game is exported either as one frame per board (`frame-0000.svg`, `frame-0001.svg`, ...), which can be
+//! assembled into a GIF or a video by other tools, or as a single animated SVG playing the whole game.
+
+use std::fmt::Write as _;
+use std::path::Path;
+
+use anyhow::Context;
+
+use crate::board::{tile_color, Board, N};
+use crate::replay::Event;
+
+/// Size of a tile and of the gaps between tiles, in pixels
+const TILE: usize = 100;
+const GAP: usize = 12;
+/// Size of the board, and height of the caption below it
+const SIZE: usize = N * TILE + (N + 1) * GAP;
+const CAPTION: usize = 40;
+
+/// A board of a game, with a caption describing how it was reached
+pub struct Frame {
+    pub board: Board,
+    pub caption: String,
+}
+
+/// Boards of a recorded game, from the initial one to the last one.
+pub fn frames(events: &[Event]) -> anyhow::Result<Vec<Frame>> {
+    let mut frames = Vec::new();
+    let mut board = Board::EMPTY;
+    let mut merge_score = 0;
+    for event in events {
+        match event {
+            Event::Start {
+                seed,
+                strategy,
+                board: start,
+            } => {
+                board = *start;
+                frames.push(Frame {
+                    board,
+                    caption: format!("{strategy}, seed {seed}"),
+                });
+            }
+            Event::Move {
+                action,
+                score,
+                spawn,
+                ..
+            } => {
+                board = board.apply(*action).with_context(|| {
+                    format!("Move {}: {action:?} is not applicable", frames.len())
+                })?;
+                board.cells[spawn.row][spawn.col] = spawn.tile;
+                merge_score += score;
+                frames.push(Frame {
+                    board,
+                    caption: format!("Move {}: {action:?}   score {merge_score}", frames.len()),
+                });
+            }
+            Event::End { .. } => {
+                if let Some(last) = frames.last_mut() {
+                    last.caption.push_str("   (game over)");
+                }
+            }
+        }
+    }
+    Ok(frames)
+}
+
+/// Tiles of the board, as SVG elements
+fn tiles(board: &Board) -> String {
+    let mut svg = String::new();
+    for (row, cells) in board.cells.iter().enumerate() {
+        for (col, &tile) in cells.iter().enumerate() {
+            let (x, y) = (GAP + col * (TILE + GAP), GAP + row * (TILE + GAP));
+            let (r, g, b) = tile_color(tile);
+            let _ = write!(
+                svg,
+                r#"<rect x="{x}" y="{y}" width="{TILE}" height="{TILE}" rx="6" fill="rgb({r},{g},{b})"/>"#
+            );
+            if tile != 0 {
+                let value = 1u32 << tile;
+                // smaller font for longer numbers, to fit in the tile
+                let font = match value.to_string().len() {
+                    1 | 2 => 48,
+                    3 => 40,
+                    4 => 32,
+                    _ => 26,
+                };
+                let _ = write!(
+                    svg,
+                    r##"<text x="{}" y="{}" font-size="{font}" text-anchor="middle" dominant-baseline="central" fill="#776e65">{value}</text>"##,
+                    x + TILE / 2,
+                    y + TILE / 2
+                );
+            }
+        }
+    }
+    svg
+}
+
+/// Caption below the board, as an SVG element
+fn caption(text: &str) -> String {
+    let escaped = text
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;");
+    format!(
+        r##"<text x="{GAP}" y="{}" font-size="20" fill="#776e65">{escaped}</text>"##,
+        SIZE + CAPTION * 2 / 3
+    )
+}
+
+/// Opening of an SVG document, with the background of the board
+fn header() -> String {
+    format!(
+        r##"<svg xmlns="http://www.w3.org/2000/svg" width="{SIZE}" height="{}" font-family="Helvetica, Arial, sans-serif" font-weight="bold"><rect width="{SIZE}" height="{}" fill="#faf8ef"/><rect width="{SIZE}" height="{SIZE}" rx="8" fill="#bbada0"/>"##,
+        SIZE + CAPTION,
+        SIZE + CAPTION
+    )
+}
+
+/// A single frame as a standalone SVG image.
+pub fn render(frame: &Frame) -> String {
+    format!(
+        "{}{}{}</svg>\n",
+        header(),
+        tiles(&frame.board),
+        caption(&frame.caption)
+    )
+}
+
+/// All frames as one animated SVG image showing each of them for `delay_ms`, and keeping the last one.
+pub fn render_animated(frames: &[Frame], delay_ms: u64) -> String {
+    let mut svg = header();
+    for (i, frame) in frames.iter().enumerate() {
+        let begin = i as u64 * delay_ms;
+        // each frame is shown at its time and hidden at the start of the next one
+        let hide = if i + 1 < frames.len() {
+            format!(
+                r#"<set attributeName="visibility" to="hidden" begin="{}ms"/>"#,
+                begin + delay_ms
+            )
+        } else {
+            String::new()
+        };
+        let _ = write!(
+            svg,
+            r#"<g visibility="hidden"><set attributeName="visibility" to="visible" begin="{begin}ms"/>{hide}{}{}</g>"#,
+            tiles(&frame.board),
+            caption(&frame.caption)
+        );
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+/// Writes each frame to `frame-<i>.svg` in the directory (created if needed), returning the number of frames.
+pub fn write_frames(frames: &[Frame], dir: &Path) -> anyhow::Result<usize> {
+    std::fs::create_dir_all(dir).with_context(|| format!("Cannot create {}", dir.display()))?;
+    for (i, frame) in frames.iter().enumerate() {
+        let path = dir.join(format!("frame-{i:04}.svg"));
+        std::fs::write(&path, render(frame))
+            .with_context(|| format!("Cannot write {}", path.display()))?;
+    }
+    Ok(frames.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::savegame::GameInProgress;
+
+    #[test]
+    fn test_frames() {
+        let mut game = GameInProgress::new(5);
+        for action in crate::board::ALL_ACTIONS.iter().cycle().take(12) {
+            game.play(*action);
+        }
+        let frames = frames(&game.to_replay("test")).unwrap();
+        assert_eq!(frames.len(), game.num_moves() + 1);
+        assert_eq!(frames.last().unwrap().board, *game.board().board());
+        let svg = render(&frames[0]);
+        assert!(svg.starts_with("<svg") && svg.ends_with("</svg>\n"));
+        assert_eq!(svg.matches("<rect").count(), 2 + N * N);
+        let animated = render_animated(&frames, 200);
+        assert_eq!(animated.matches("<g ").count(), frames.len());
+    }
+}
@@ -0,0 +1,335 @@
+//! Tabular reinforcement learning on small boards (`main tabular`): 2x2 and 3x3 boards have few enough reachable
+//! states to keep the exact value of each of them in a table, with no approximation by an evaluation function.
+//!
+//! The learner estimates the value of afterstates (the expected score still to come) by temporal differences:
+//! after each action, the value of the previous afterstate moves toward the reward of the action plus the value of
+//! the new afterstate. It is then compared to an expectimax search on the same boards and the same random tiles.
+//!
+//! The board of the rest of the crate has a fixed size of `N`x`N`, so the small boards have their own
+//! implementation of the rules here, with the same actions and the same random tiles.
+
+use hashbrown::HashMap;
+use rand::rngs::StdRng;
+use rand::seq::IndexedRandom;
+use rand::{Rng, SeedableRng};
+
+use crate::board::{Action, ALL_ACTIONS};
+
+/// Sizes of the boards supported
+pub const SIZES: [usize; 2] = [2, 3];
+
+/// A square board of `size`x`size` cells, packed 4 bits per cell (the exponent of the tile, 0 for empty cells) in
+/// reading order.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct SmallBoard {
+    size: usize,
+    cells: u64,
+}
+
+impl SmallBoard {
+    /// Returns an initial board, with a single random tile.
+    pub fn init_with(size: usize, rng: &mut impl Rng) -> SmallBoard {
+        assert!(SIZES.contains(&size), "unsupported size {size}");
+        SmallBoard { size, cells: 0 }.with_random_tile_with(rng)
+    }
+
+    /// Board with the given exponents, row by row.
+    pub fn from_exponents(size: usize, exponents: &[u8]) -> SmallBoard {
+        assert_eq!(exponents.len(), size * size);
+        let mut board = SmallBoard { size, cells: 0 };
+        for (cell, &exponent) in exponents.iter().enumerate() {
+            board.set(cell, exponent);
+        }
+        board
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Exponent of the tile of the cell, in reading order
+    pub fn get(&self, cell: usize) -> u8 {
+        (self.cells >> (4 * cell) & 0xf) as u8
+    }
+
+    fn set(&mut self, cell: usize, exponent: u8) {
+        assert!(exponent < 16, "tile too large for a small board");
+        self.cells = self.cells & !(0xf << (4 * cell)) | (exponent as u64) << (4 * cell);
+    }
+
+    pub fn max_tile(&self) -> u8 {
+        (0..self.size * self.size)
+            .map(|cell| self.get(cell))
+            .max()
+            .unwrap_or(0)
+    }
+
+    fn empty_cells(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..self.size * self.size).filter(|&cell| self.get(cell) == 0)
+    }
+
+    /// Cells of each line, in the direction of the action (the first cell is the one the tiles move to)
+    fn lines(&self, action: Action) -> Vec<Vec<usize>> {
+        let n = self.size;
+        (0..n)
+            .map(|line| {
+                (0..n)
+                    .map(|i| match action {
+                        Action::Left => line * n + i,
+                        Action::Right => line * n + n - 1 - i,
+                        Action::Up => i * n + line,
+                        Action::Down => (n - 1 - i) * n + line,
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Applies the action, returning the afterstate and the score of the merges, or `None` if no tile moves.
+    pub fn apply(&self, action: Action) -> Option<(SmallBoard, u32)> {
+        let mut after = *self;
+        let mut score = 0;
+        for cells in self.lines(action) {
+            let tiles: Vec<u8> = cells
+                .iter()
+                .map(|&cell| self.get(cell))
+                .filter(|&tile| tile != 0)
+                .collect();
+            let mut merged = Vec::with_capacity(cells.len());
+            let mut i = 0;
+            while i < tiles.len() {
+                if i + 1 < tiles.len() && tiles[i] == tiles[i + 1] {
+                    merged.push(tiles[i] + 1);
+                    score += 1 << (tiles[i] + 1);
+                    i += 2;
+                } else {
+                    merged.push(tiles[i]);
+                    i += 1;
+                }
+            }
+            for (i, &cell) in cells.iter().enumerate() {
+                after.set(cell, merged.get(i).copied().unwrap_or(0));
+            }
+        }
+        (after != *self).then_some((after, score))
+    }
+
+    /// Boards reachable by adding a random tile to this afterstate, with their probabilities.
+    pub fn successors(&self) -> Vec<(f32, SmallBoard)> {
+        let empty: Vec<usize> = self.empty_cells().collect();
+        let mut successors = Vec::with_capacity(2 * empty.len());
+        for &cell in &empty {
+            for (exponent, probability) in [(1, 0.9), (2, 0.1)] {
+                let mut next = *self;
+                next.set(cell, exponent);
+                successors.push((probability / empty.len() as f32, next));
+            }
+        }
+        successors
+    }
+
+    pub fn with_random_tile_with(&self, rng: &mut impl Rng) -> SmallBoard {
+        let empty: Vec<usize> = self.empty_cells().collect();
+        let mut next = *self;
+        let cell = *empty.choose(rng).expect("an empty cell");
+        next.set(cell, if rng.random_bool(0.9) { 1 } else { 2 });
+        next
+    }
+}
+
+/// Exact table of the values of the afterstates met, learned by TD(0)
+pub struct TabularLearner {
+    pub size: usize,
+    /// Expected score still to come after each afterstate, 0 for those never met
+    pub values: HashMap<SmallBoard, f32>,
+    /// Learning rate
+    pub alpha: f32,
+}
+
+impl TabularLearner {
+    pub fn new(size: usize, alpha: f32) -> TabularLearner {
+        TabularLearner {
+            size,
+            values: HashMap::new(),
+            alpha,
+        }
+    }
+
+    pub fn value(&self, after: &SmallBoard) -> f32 {
+        self.values.get(after).copied().unwrap_or(0.0)
+    }
+
+    /// Action maximizing its score plus the value of its afterstate
+    pub fn greedy(&self, board: SmallBoard) -> Option<(Action, SmallBoard, u32)> {
+        ALL_ACTIONS
+            .into_iter()
+            .filter_map(|action| {
+                board
+                    .apply(action)
+                    .map(|(after, score)| (action, after, score))
+            })
+            .max_by(|(_, a1, s1), (_, a2, s2)| {
+                (*s1 as f32 + self.value(a1)).total_cmp(&(*s2 as f32 + self.value(a2)))
+            })
+    }
+
+    /// Plays a training game, with a random action with probability `epsilon`, learning after each action.
+    /// Returns the score of the game.
+    pub fn train_episode(&mut self, rng: &mut impl Rng, epsilon: f32) -> u32 {
+        let mut board = SmallBoard::init_with(self.size, rng);
+        let mut previous: Option<SmallBoard> = None;
+        let mut total = 0;
+        loop {
+            let step = if rng.random::<f32>() < epsilon {
+                let moves: Vec<_> = ALL_ACTIONS
+                    .into_iter()
+                    .filter_map(|action| board.apply(action).map(|(a, s)| (action, a, s)))
+                    .collect();
+                moves.choose(rng).copied()
+            } else {
+                self.greedy(board)
+            };
+            let Some((_, after, score)) = step else {
+                // no action after the last afterstate: nothing more to come
+                if let Some(previous) = previous {
+                    self.update(previous, 0.0);
+                }
+                return total;
+            };
+            if let Some(previous) = previous {
+                let target = score as f32 + self.value(&after);
+                self.update(previous, target);
+            }
+            total += score;
+            previous = Some(after);
+            board = after.with_random_tile_with(rng);
+        }
+    }
+
+    fn update(&mut self, after: SmallBoard, target: f32) {
+        let value = self.values.entry(after).or_insert(0.0);
+        *value += self.alpha * (target - *value);
+    }
+}
+
+/// Action of an expectimax search looking `depth` actions ahead, maximizing the expected score of these actions.
+pub fn expectimax(board: SmallBoard, depth: usize) -> Option<Action> {
+    let mut memo = HashMap::new();
+    ALL_ACTIONS
+        .into_iter()
+        .filter_map(|action| {
+            let (after, score) = board.apply(action)?;
+            Some((action, score as f32 + chance(after, depth - 1, &mut memo)))
+        })
+        .max_by(|(_, v1), (_, v2)| v1.total_cmp(v2))
+        .map(|(action, _)| action)
+}
+
+/// Expected score of the best `depth` actions after the random tile of the afterstate
+fn chance(after: SmallBoard, depth: usize, memo: &mut HashMap<(SmallBoard, usize), f32>) -> f32 {
+    if depth == 0 {
+        return 0.0;
+    }
+    if let Some(&value) = memo.get(&(after, depth)) {
+        return value;
+    }
+    let value = after
+        .successors()
+        .into_iter()
+        .map(|(probability, next)| {
+            let best = ALL_ACTIONS
+                .into_iter()
+                .filter_map(|action| {
+                    let (after, score) = next.apply(action)?;
+                    Some(score as f32 + chance(after, depth - 1, memo))
+                })
+                .fold(0.0, f32::max);
+            probability * best
+        })
+        .sum();
+    memo.insert((after, depth), value);
+    value
+}
+
+/// Plays a game whose random tiles are drawn from a generator seeded with `seed`. Returns its score and the
+/// exponent of its largest tile.
+pub fn play(
+    size: usize,
+    seed: u64,
+    mut policy: impl FnMut(SmallBoard) -> Option<Action>,
+) -> (u32, u8) {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut board = SmallBoard::init_with(size, &mut rng);
+    let mut total = 0;
+    while let Some((after, score)) = policy(board).and_then(|action| board.apply(action)) {
+        total += score;
+        board = after.with_random_tile_with(&mut rng);
+    }
+    (total, board.max_tile())
+}
+
+/// Mean score and mean largest tile of a policy over the seeds `0..num_games`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Results {
+    pub mean_score: f64,
+    pub mean_max_tile: f64,
+}
+
+pub fn evaluate(
+    size: usize,
+    num_games: u64,
+    mut policy: impl FnMut(SmallBoard) -> Option<Action>,
+) -> Results {
+    let (mut score, mut max_tile) = (0.0, 0.0);
+    for seed in 0..num_games {
+        let (s, m) = play(size, seed, &mut policy);
+        score += s as f64;
+        max_tile += (1u32 << m) as f64;
+    }
+    let n = num_games.max(1) as f64;
+    Results {
+        mean_score: score / n,
+        mean_max_tile: max_tile / n,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tabular() {
+        // 2 2 4 / 0 0 0 / 0 0 2
+        let board = SmallBoard::from_exponents(3, &[1, 1, 2, 0, 0, 0, 0, 0, 1]);
+        let (after, score) = board.apply(Action::Left).unwrap();
+        assert_eq!(
+            after,
+            SmallBoard::from_exponents(3, &[2, 2, 0, 0, 0, 0, 1, 0, 0])
+        );
+        assert_eq!(score, 4);
+        let (after, _) = board.apply(Action::Down).unwrap();
+        assert_eq!(
+            after,
+            SmallBoard::from_exponents(3, &[0, 0, 0, 0, 0, 2, 1, 1, 1])
+        );
+        assert!(SmallBoard::from_exponents(2, &[1, 2, 2, 1])
+            .apply(Action::Up)
+            .is_none());
+        let probabilities: f32 = after.successors().iter().map(|(p, _)| p).sum();
+        assert!((probabilities - 1.0).abs() < 1e-5);
+
+        let mut learner = TabularLearner::new(2, 0.1);
+        let mut rng = StdRng::seed_from_u64(0);
+        for _ in 0..2000 {
+            learner.train_episode(&mut rng, 0.1);
+        }
+        // a 2x2 board has few afterstates
+        assert!(!learner.values.is_empty() && learner.values.len() < 1000);
+        let learned = evaluate(2, 100, |board| learner.greedy(board).map(|(a, _, _)| a));
+        let random = evaluate(2, 100, |board| {
+            ALL_ACTIONS.into_iter().find(|&a| board.apply(a).is_some())
+        });
+        assert!(learned.mean_score >= random.mean_score);
+        assert!(evaluate(2, 10, |board| expectimax(board, 2)).mean_score > 0.0);
+    }
+}
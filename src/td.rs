@@ -0,0 +1,153 @@
+//! Temporal-difference learning of n-tuple networks on afterstates, as run by the `train` binary.
+//!
+//! Games are played greedily with respect to the network, and the value of each afterstate is moved toward its
+//! λ-return: the value of an afterstate is the expected number of actions that can still be played from it.
+//!
+//! ```rust
+//! let mut network = NTupleNetwork::new(&FOUR_TUPLES);
+//! let trajectory = td::self_play(&network, &mut rng);
+//! td::learn(&mut network, &trajectory, 0.1, 0.0);
+//! println!("greedy average: {}", td::evaluate(&network, 100)?);
+//! ```
+
+use anyhow::ensure;
+use rand::Rng;
+use rayon::prelude::*;
+
+use crate::board::{Board, PlayableBoard, RandableBoard, ALL_ACTIONS};
+use crate::eval::ntuple::NTupleNetwork;
+
+/// Returns the afterstate with the highest value, or `None` if no action is applicable.
+pub fn best_afterstate(network: &NTupleNetwork, board: PlayableBoard) -> Option<RandableBoard> {
+    ALL_ACTIONS
+        .into_iter()
+        .filter_map(|action| board.apply(action))
+        .map(|after| (network.eval(after.board()), after))
+        .max_by(|(v1, _), (v2, _)| v1.total_cmp(v2))
+        .map(|(_, after)| after)
+}
+
+/// Plays a full game by greedily following the network and returns the sequence of afterstates encountered.
+pub fn self_play(network: &NTupleNetwork, rng: &mut impl Rng) -> Vec<Board> {
+    let mut trajectory = Vec::new();
+    let mut board = PlayableBoard::init_with(rng);
+    while let Some(after) = best_afterstate(network, board) {
+        trajectory.push(*after.board());
+        board = after.with_random_tile_with(rng);
+    }
+    trajectory
+}
+
+/// Updates the network toward the λ-returns of the afterstates of a finished game.
+///
+/// Each action yields a reward of 1, and the last afterstate (after which no action was applicable) has value 0.
+/// Updates are made backward, so that each target uses the already updated value of its successor.
+pub fn learn(network: &mut NTupleNetwork, trajectory: &[Board], alpha: f32, lambda: f32) {
+    let step = alpha / network.num_features() as f32;
+    // value and λ-return of the successor of the current afterstate
+    let mut next: Option<(f32, f32)> = None;
+    for afterstate in trajectory.iter().rev() {
+        let target = match next {
+            None => 0.0,
+            Some((value, ret)) => 1.0 + (1.0 - lambda) * value + lambda * ret,
+        };
+        let error = target - network.eval(afterstate);
+        network.update(afterstate, step * error);
+        next = Some((network.eval(afterstate), target));
+    }
+}
+
+/// Average number of actions of the greedy policy over several games, played in parallel.
+///
+/// Fails if no game is to be played.
+pub fn evaluate(network: &NTupleNetwork, num_games: u64) -> anyhow::Result<f32> {
+    ensure!(
+        num_games > 0,
+        "at least one game is needed to evaluate the network"
+    );
+    let total: usize = (0..num_games)
+        .into_par_iter()
+        .map(|_| {
+            let mut board = PlayableBoard::init();
+            let mut num_moves = 0;
+            while let Some(after) = best_afterstate(network, board) {
+                num_moves += 1;
+                board = after.with_random_tile();
+            }
+            num_moves
+        })
+        .sum();
+    Ok(total as f32 / num_games as f32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::eval::ntuple::FOUR_TUPLES;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    /// Afterstates of a game of 3 actions, whose values are 2, 1 and 0
+    fn trajectory() -> [Board; 3] {
+        let board = Board {
+            cells: [[0, 1, 2, 3], [4, 5, 6, 7], [8, 9, 10, 11], [12, 13, 14, 15]],
+        };
+        let (mut second, mut third) = (board, board);
+        second.cells[0].rotate_left(1);
+        third.cells[2].rotate_left(1);
+        [board, second, third]
+    }
+
+    #[test]
+    fn test_terminal() {
+        // the last afterstate is moved to 0 (with all its weights distinct, in a single step when alpha is 1)
+        let [.., last] = trajectory();
+        let mut network = NTupleNetwork::new(&FOUR_TUPLES);
+        network.update(&last, 0.25);
+        assert!(network.eval(&last) > 0.0);
+        learn(&mut network, &[last], 1.0, 0.0);
+        assert!(network.eval(&last).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_convergence() {
+        for lambda in [0.0, 0.5, 1.0] {
+            let mut network = NTupleNetwork::new(&FOUR_TUPLES);
+            for _ in 0..200 {
+                learn(&mut network, &trajectory(), 0.2, lambda);
+            }
+            let values = trajectory().map(|board| network.eval(&board));
+            for (value, expected) in values.into_iter().zip([2.0, 1.0, 0.0]) {
+                assert!((value - expected).abs() < 0.05, "λ = {lambda}: {values:?}");
+            }
+        }
+        // with λ = 1, the targets are the number of remaining actions whatever the values of the successors
+        let mut network = NTupleNetwork::new(&FOUR_TUPLES);
+        let [first, ..] = trajectory();
+        network.update(&first, 100.0);
+        let mut monte_carlo = network.clone();
+        learn(&mut network, &trajectory(), 0.1, 0.0);
+        learn(&mut monte_carlo, &trajectory(), 0.1, 1.0);
+        assert!(monte_carlo.eval(&first) < network.eval(&first));
+    }
+
+    #[test]
+    fn test_self_play() {
+        let network = NTupleNetwork::new(&FOUR_TUPLES);
+        let trajectory = self_play(&network, &mut StdRng::seed_from_u64(3));
+        assert!(!trajectory.is_empty());
+        assert_eq!(
+            self_play(&network, &mut StdRng::seed_from_u64(3)),
+            trajectory
+        );
+        // the game ends when no action is applicable after the random tile, never on an afterstate
+        assert!(trajectory.iter().all(|board| !board.is_lost()));
+    }
+
+    #[test]
+    fn test_evaluate() {
+        let network = NTupleNetwork::new(&FOUR_TUPLES);
+        assert!(evaluate(&network, 0).is_err());
+        assert!(evaluate(&network, 2).unwrap() > 0.0);
+    }
+}
@@ -0,0 +1,381 @@
+//! Tournament between agents on a shared set of games, for the end-of-lab competition.
+//!
+//! An agent is either a strategy of this crate (`expectimax:depth=3`) or an external engine (`engine:<command>`),
+//! a program speaking the JSON-lines protocol of `main engine`. All agents play the same seeds, so that they face
+//! the same random tiles, and are ranked by their mean 2048 score.
+//!
+//! ```text
+//! cargo run --release --bin tournament -- -a greedy -a alice=expectimax:depth=3 -a "bob=engine:./bob-engine --fast"
+//! ```
+
+use std::io::{BufRead, BufReader, Write};
+use std::path::PathBuf;
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+use ai_2048::board::{Action, PlayableBoard, WIN_TILE};
+use ai_2048::savegame::GameInProgress;
+use ai_2048::spectate::Spectators;
+use ai_2048::strategy::Strategy;
+use ai_2048::{search, spectate, stats};
+use anyhow::{bail, ensure, Context};
+use clap::{Parser, ValueEnum};
+use rayon::prelude::*;
+
+#[derive(Parser, Debug)]
+#[command(version, about, long_about = None)]
+struct Args {
+    /// Agent taking part, as `[name=]<strategy>` or `[name=]engine:<command>` (repeat the flag for each agent)
+    #[arg(short, long = "agent")]
+    agents: Vec<Agent>,
+
+    /// File with one agent per line, in the same form as `--agent` (`#` starts a comment)
+    #[arg(short, long)]
+    file: Option<PathBuf>,
+
+    /// Number of games played by each agent
+    #[arg(short, long, default_value = "20")]
+    num_games: u64,
+
+    /// Seed of the first game (game `i` uses the seed `seed + i`)
+    #[arg(long, default_value = "0")]
+    seed: u64,
+
+    /// Time budget in milliseconds for each decision, given to the agents able to use one
+    #[arg(long)]
+    time_per_move: Option<u64>,
+
+    /// Time in seconds allowed for a single game
+    #[arg(short, long, default_value = "600")]
+    timeout: u64,
+
+    /// Format of the leaderboard
+    #[arg(long, value_enum, default_value = "text")]
+    format: Format,
+
+    /// Broadcasts the games to spectators of the page served at this address, e.g. `0.0.0.0:8049` (requires
+    /// building with `--features server`)
+    #[arg(long)]
+    spectate: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum Format {
+    Text,
+    Md,
+    Json,
+}
+
+/// A participant of the tournament
+#[derive(Clone, Debug)]
+struct Agent {
+    name: String,
+    player: Player,
+}
+
+#[derive(Clone, Debug)]
+enum Player {
+    Strategy(Strategy),
+    /// Command line of an external engine
+    Engine(Vec<String>),
+}
+
+impl FromStr for Agent {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Agent> {
+        let s = s.trim();
+        // the name is optional, and an engine command may contain `=`
+        let (name, spec) = match s.split_once('=') {
+            Some((name, spec)) if !name.contains(':') && !name.contains(' ') => {
+                (name.to_string(), spec)
+            }
+            _ => (s.to_string(), s),
+        };
+        let player = match spec.strip_prefix("engine:") {
+            Some(command) => {
+                let command: Vec<String> = command.split_whitespace().map(str::to_string).collect();
+                ensure!(!command.is_empty(), "Missing command of the engine");
+                Player::Engine(command)
+            }
+            None => Player::Strategy(spec.parse()?),
+        };
+        Ok(Agent { name, player })
+    }
+}
+
+/// A running external engine
+struct Engine {
+    child: Child,
+    input: ChildStdin,
+    output: BufReader<ChildStdout>,
+}
+
+impl Engine {
+    fn start(command: &[String]) -> anyhow::Result<Engine> {
+        let mut child = Command::new(&command[0])
+            .args(&command[1..])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .with_context(|| format!("Cannot start the engine `{}`", command.join(" ")))?;
+        let input = child.stdin.take().expect("piped stdin");
+        let output = BufReader::new(child.stdout.take().expect("piped stdout"));
+        Ok(Engine {
+            child,
+            input,
+            output,
+        })
+    }
+
+    fn select_action(
+        &mut self,
+        board: PlayableBoard,
+        budget: Option<Duration>,
+    ) -> anyhow::Result<Option<Action>> {
+        let request = serde_json::json!({
+            "board": board.board().values(),
+            "budget_ms": budget.map(|budget| budget.as_millis() as u64),
+        });
+        writeln!(self.input, "{request}")?;
+        self.input.flush()?;
+        let mut line = String::new();
+        ensure!(
+            self.output.read_line(&mut line)? > 0,
+            "The engine stopped answering"
+        );
+        let answer: serde_json::Value =
+            serde_json::from_str(&line).with_context(|| format!("Invalid answer: {line}"))?;
+        if let Some(error) = answer.get("error") {
+            bail!("The engine failed: {error}");
+        }
+        serde_json::from_value(answer["action"].clone())
+            .with_context(|| format!("Invalid action in the answer: {line}"))
+    }
+}
+
+impl Drop for Engine {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// Outcome of a game of an agent
+struct GameOutcome {
+    num_moves: usize,
+    merge_score: u32,
+    max_tile: u8,
+    move_times: Vec<f64>,
+    timed_out: bool,
+}
+
+fn play(
+    agent: &Agent,
+    seed: u64,
+    args: &Args,
+    spectators: Option<&Spectators>,
+) -> anyhow::Result<GameOutcome> {
+    let budget = args.time_per_move.map(Duration::from_millis);
+    let mut engine = match &agent.player {
+        Player::Engine(command) => Some(Engine::start(command)?),
+        Player::Strategy(_) => None,
+    };
+    let mut game = GameInProgress::new(seed);
+    // as in `bench`, random decisions of a strategy are reproducible
+    search::seed_strategy_rng(seed ^ search::STRATEGY_STREAM);
+    let name = format!("{} #{seed}", agent.name);
+    if let Some(spectators) = spectators {
+        spectators.update(&name, &game, None);
+    }
+    let mut move_times = Vec::new();
+    let start = Instant::now();
+    let timed_out = loop {
+        if start.elapsed() > Duration::from_secs(args.timeout) {
+            break true;
+        }
+        let board = *game.board();
+        let start_selection = Instant::now();
+        let action = match (&agent.player, &mut engine) {
+            (_, Some(engine)) => engine.select_action(board, budget)?,
+            // a panicking agent loses its game, not the whole tournament
+            (Player::Strategy(strategy), None) => std::panic::catch_unwind(|| match budget {
+                Some(budget) => strategy.select_action_within(board, budget),
+                None => strategy.select_action(board),
+            })
+            .map_err(|_| anyhow::anyhow!("The strategy panicked (seed {seed})"))?,
+            (Player::Engine(_), None) => unreachable!("engines are started before the game"),
+        };
+        move_times.push(start_selection.elapsed().as_secs_f64());
+        let Some(action) = action else {
+            break false;
+        };
+        game.play(action)
+            .with_context(|| format!("Inapplicable action {action:?} (seed {seed})\n{board}"))?;
+        if let Some(spectators) = spectators {
+            spectators.update(&name, &game, Some(action));
+        }
+    };
+    Ok(GameOutcome {
+        num_moves: game.num_moves(),
+        merge_score: game.merge_score(),
+        max_tile: game.board().board().max_tile(),
+        move_times,
+        timed_out,
+    })
+}
+
+/// Line of the leaderboard
+#[derive(serde::Serialize)]
+struct Standing {
+    rank: usize,
+    agent: String,
+    games: usize,
+    /// Games that failed (e.g. an engine crashing), counted with a score of 0
+    failures: usize,
+    timeouts: usize,
+    mean_score: f64,
+    median_score: f64,
+    mean_moves: f64,
+    /// Fraction of the games reaching the 2048 tile
+    win_rate: f64,
+    ms_per_move: f64,
+}
+
+fn standing(agent: &Agent, outcomes: &[anyhow::Result<GameOutcome>]) -> Standing {
+    let played: Vec<&GameOutcome> = outcomes.iter().filter_map(|x| x.as_ref().ok()).collect();
+    let scores: Vec<f64> = outcomes
+        .iter()
+        .map(|outcome| outcome.as_ref().map_or(0.0, |game| game.merge_score as f64))
+        .collect();
+    let moves: Vec<f64> = played.iter().map(|game| game.num_moves as f64).collect();
+    let times: Vec<f64> = played
+        .iter()
+        .flat_map(|game| game.move_times.iter().copied())
+        .collect();
+    let wins = played
+        .iter()
+        .filter(|game| game.max_tile >= WIN_TILE)
+        .count();
+    Standing {
+        rank: 0,
+        agent: agent.name.clone(),
+        games: outcomes.len(),
+        failures: outcomes.len() - played.len(),
+        timeouts: played.iter().filter(|game| game.timed_out).count(),
+        mean_score: stats::mean(&scores),
+        median_score: stats::median(&scores),
+        mean_moves: stats::mean(&moves),
+        win_rate: wins as f64 / outcomes.len().max(1) as f64,
+        ms_per_move: stats::mean(&times) * 1000.0,
+    }
+}
+
+fn read_agents(args: &Args) -> anyhow::Result<Vec<Agent>> {
+    let mut agents = args.agents.clone();
+    if let Some(path) = &args.file {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Cannot read {}", path.display()))?;
+        for (i, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if !line.is_empty() {
+                agents.push(
+                    line.parse()
+                        .with_context(|| format!("{}:{}", path.display(), i + 1))?,
+                );
+            }
+        }
+    }
+    ensure!(agents.len() >= 2, "At least two agents are needed");
+    Ok(agents)
+}
+
+fn main() -> anyhow::Result<()> {
+    let args = Args::parse();
+    let agents = read_agents(&args)?;
+    let seeds: Vec<u64> = (args.seed..args.seed + args.num_games).collect();
+    let spectators = args.spectate.as_deref().map(spectate::bind).transpose()?;
+
+    // every game of every agent is independent
+    let games: Vec<(usize, u64)> = (0..agents.len())
+        .flat_map(|i| seeds.iter().map(move |&seed| (i, seed)))
+        .collect();
+    let mut outcomes: Vec<Vec<anyhow::Result<GameOutcome>>> =
+        agents.iter().map(|_| Vec::new()).collect();
+    let results: Vec<_> = games
+        .into_par_iter()
+        .map(|(i, seed)| {
+            (
+                i,
+                seed,
+                play(&agents[i], seed, &args, spectators.as_deref()),
+            )
+        })
+        .collect();
+    for (i, seed, result) in results {
+        if let Err(e) = &result {
+            eprintln!("{} failed on seed {seed}: {e:#}", agents[i].name);
+        }
+        outcomes[i].push(result);
+    }
+
+    let mut standings: Vec<Standing> = agents
+        .iter()
+        .zip(&outcomes)
+        .map(|(agent, outcomes)| standing(agent, outcomes))
+        .collect();
+    standings.sort_by(|a, b| b.mean_score.total_cmp(&a.mean_score));
+    for (rank, standing) in standings.iter_mut().enumerate() {
+        standing.rank = rank + 1;
+    }
+
+    match args.format {
+        Format::Json => println!("{:#}", serde_json::to_value(&standings)?),
+        Format::Md => {
+            println!(
+                "| rank | agent | mean score | median | moves | win rate | ms/move | failures |"
+            );
+            println!("| ---: | :--- | ---: | ---: | ---: | ---: | ---: | ---: |");
+            for s in &standings {
+                println!(
+                    "| {} | {} | {:.1} | {:.1} | {:.1} | {:.1}% | {:.3} | {} |",
+                    s.rank,
+                    s.agent.replace('|', "\\|"),
+                    s.mean_score,
+                    s.median_score,
+                    s.mean_moves,
+                    s.win_rate * 100.0,
+                    s.ms_per_move,
+                    s.failures
+                );
+            }
+        }
+        Format::Text => {
+            println!(
+                "Leaderboard over {} games (seeds {}..{})\n",
+                seeds.len(),
+                args.seed,
+                args.seed + args.num_games
+            );
+            println!(
+                "{:>4}  {:<24} {:>10} {:>10} {:>8} {:>8} {:>10} {:>8}",
+                "rank", "agent", "mean score", "median", "moves", "win rate", "ms/move", "failures"
+            );
+            for s in &standings {
+                println!(
+                    "{:>4}  {:<24} {:>10.1} {:>10.1} {:>8.1} {:>7.1}% {:>10.3} {:>8}",
+                    s.rank,
+                    s.agent,
+                    s.mean_score,
+                    s.median_score,
+                    s.mean_moves,
+                    s.win_rate * 100.0,
+                    s.ms_per_move,
+                    s.failures
+                );
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,116 @@
+use std::path::PathBuf;
+use std::time::Instant;
+
+use ai_2048::eval;
+use ai_2048::td::{evaluate, learn, self_play};
+use clap::{Parser, ValueEnum};
+use eval::ntuple::{NTupleNetwork, FOUR_TUPLES, SIX_TUPLES};
+use eval::params::{self, Metadata};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+/// Learns the weights of an n-tuple network by self-play, using temporal difference learning on afterstates.
+///
+/// The value of an afterstate is the expected number of actions that can still be played from it.
+/// The learned network evaluates the leaves of the search of `bench` and `main` with `--evaluator ntuple:file=<output>`.
+#[derive(Parser, Debug)]
+#[command(version, about, long_about = None)]
+struct Args {
+    /// Number of training games to play
+    #[arg(short, long, default_value = "100000")]
+    episodes: u64,
+
+    /// Learning rate, divided among all weights involved in an evaluation
+    #[arg(short, long, default_value = "0.1")]
+    alpha: f32,
+
+    /// Trace decay parameter of TD(λ). 0 corresponds to TD(0)
+    #[arg(short, long, default_value = "0")]
+    lambda: f32,
+
+    /// Tuples of the network (ignored when starting from existing weights)
+    #[arg(long, value_enum, default_value = "four")]
+    network: Network,
+
+    /// Start from the network in this file (a checkpoint or a bare network) instead of an empty network
+    #[arg(long)]
+    init: Option<PathBuf>,
+
+    /// Checkpoint file where the learned network is written, with the number of training games and the last
+    /// greedy average
+    #[arg(short, long, default_value = "ntuple.weights")]
+    output: PathBuf,
+
+    /// Number of training games between two saves of the weights
+    #[arg(long, default_value = "1000", value_parser = clap::value_parser!(u64).range(1..))]
+    checkpoint_every: u64,
+
+    /// Number of training games between two evaluations of the greedy policy
+    #[arg(long, default_value = "5000", value_parser = clap::value_parser!(u64).range(1..))]
+    eval_every: u64,
+
+    /// Number of games played in each evaluation
+    #[arg(long, default_value = "100", value_parser = clap::value_parser!(u64).range(1..))]
+    eval_games: u64,
+
+    /// Seed of the random number generator used for training games
+    #[arg(long, default_value = "0")]
+    seed: u64,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum Network {
+    /// Straight 4-tuples and 2x2 squares
+    Four,
+    /// The four 6-tuples of Yeh et al. (requires ~270MB of memory)
+    Six,
+}
+
+fn main() -> anyhow::Result<()> {
+    let args: Args = Args::parse();
+
+    let mut steps = 0;
+    let mut bench_score = None;
+    let mut network = match (&args.init, args.network) {
+        (Some(path), _) => {
+            let (network, metadata) = params::load_ntuple(path)?;
+            steps = metadata.training_steps;
+            bench_score = metadata.bench_score;
+            network
+        }
+        (None, Network::Four) => NTupleNetwork::new(&FOUR_TUPLES),
+        (None, Network::Six) => NTupleNetwork::new(&SIX_TUPLES),
+    };
+
+    let mut rng = StdRng::seed_from_u64(args.seed);
+    let start = Instant::now();
+    // number of actions in each training game since the last report
+    let mut lengths: Vec<usize> = Vec::new();
+
+    for episode in 1..=args.episodes {
+        let trajectory = self_play(&network, &mut rng);
+        lengths.push(trajectory.len());
+        learn(&mut network, &trajectory, args.alpha, args.lambda);
+
+        if episode % args.eval_every == 0 || episode == args.episodes {
+            let average_training = lengths.iter().sum::<usize>() as f32 / lengths.len() as f32;
+            lengths.clear();
+            let average_greedy = evaluate(&network, args.eval_games)?;
+            bench_score = Some(average_greedy as f64);
+            println!(
+                "[{:>7.1}s] episode {episode:>8}   training avg (#actions): {average_training:>8.1}   greedy avg (#actions): {average_greedy:>8.1}",
+                start.elapsed().as_secs_f32()
+            );
+        }
+        if episode % args.checkpoint_every == 0 || episode == args.episodes {
+            let metadata = Metadata {
+                trainer: "train".into(),
+                training_steps: steps + episode,
+                bench_score,
+            };
+            params::save_ntuple(&args.output, &network, &metadata)?;
+        }
+    }
+    println!("Weights written to {}", args.output.display());
+    Ok(())
+}
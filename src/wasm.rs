@@ -0,0 +1,98 @@
+//! The game and the AI exposed to JavaScript, for the page of `web/`.
+//!
+//! Built with `wasm-pack build --target web --out-dir web/pkg` (see `web/index.html`).
+
+use wasm_bindgen::prelude::*;
+
+use crate::board::{Action, ALL_ACTIONS, N};
+use crate::savegame::GameInProgress;
+use crate::strategy::Strategy;
+
+/// Name of an action, as used by the page
+fn action_name(action: Action) -> &'static str {
+    match action {
+        Action::Up => "up",
+        Action::Down => "down",
+        Action::Left => "left",
+        Action::Right => "right",
+    }
+}
+
+/// A game played in the browser, by the user or by the engine.
+#[wasm_bindgen]
+pub struct Game {
+    game: GameInProgress,
+}
+
+#[wasm_bindgen]
+impl Game {
+    /// Starts a game whose random tiles are drawn from a generator seeded with `seed`.
+    #[wasm_bindgen(constructor)]
+    pub fn new(seed: u32) -> Game {
+        Game {
+            game: GameInProgress::new(seed.into()),
+        }
+    }
+
+    /// Exponents of the tiles row by row (0 for empty cells, 1 for a 2, 2 for a 4, ...)
+    pub fn cells(&self) -> Vec<u8> {
+        self.game
+            .board()
+            .board()
+            .cells
+            .iter()
+            .flatten()
+            .copied()
+            .collect()
+    }
+
+    pub fn size(&self) -> usize {
+        N
+    }
+
+    pub fn num_moves(&self) -> usize {
+        self.game.num_moves()
+    }
+
+    /// Score of the classic 2048 game
+    pub fn score(&self) -> u32 {
+        self.game.merge_score()
+    }
+
+    pub fn is_lost(&self) -> bool {
+        self.game.board().board().is_lost()
+    }
+
+    /// Plays the action (`up`, `down`, `left` or `right`), returning false if it does not move any tile.
+    pub fn play(&mut self, action: &str) -> bool {
+        let action = ALL_ACTIONS
+            .into_iter()
+            .find(|&candidate| action_name(candidate) == action);
+        action.is_some_and(|action| self.game.play(action).is_some())
+    }
+
+    /// Action selected by the strategy (e.g. `expectimax:depth=2`), or `undefined` if the game is lost.
+    ///
+    /// Fails for an invalid strategy, and for the strategies reading a file (`distilled`) since the page has no
+    /// access to files.
+    pub fn best_action(&self, strategy: &str) -> Result<Option<String>, JsError> {
+        let strategy = Strategy::parse_without_files(strategy)
+            .map_err(|e: anyhow::Error| JsError::new(&e.to_string()))?;
+        let action = strategy.select_action(*self.game.board());
+        Ok(action.map(|action| action_name(action).to_string()))
+    }
+
+    /// Evaluation of the afterstate of each action (up, down, left, right), `NaN` for the actions that do not move
+    /// any tile
+    pub fn action_values(&self) -> Vec<f32> {
+        ALL_ACTIONS
+            .into_iter()
+            .map(|action| {
+                self.game
+                    .board()
+                    .apply(action)
+                    .map_or(f32::NAN, |after| after.evaluate())
+            })
+            .collect()
+    }
+}
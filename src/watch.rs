@@ -0,0 +1,300 @@
+//! Full-screen view of a game played by the AI (`main auto --tui`): the board on the left, and on the right the
+//! progress of the game, the statistics of the search of the last move and the contribution of each heuristic to the
+//! evaluation of the board, to follow what the strategy is "thinking".
+//!
+//! The content of the panel is always available, while drawing it in the terminal requires the `tui` feature
+//! (`cargo run --release --features tui -- auto --tui`).
+
+use std::time::Duration;
+
+use crate::board::{Action, PlayableBoard};
+use crate::eval;
+use crate::savegame::GameInProgress;
+use crate::search::SearchTotals;
+
+#[cfg(feature = "tui")]
+pub use tui::play;
+
+/// Decision of the strategy for the last move
+#[derive(Clone, Copy, Debug)]
+pub struct LastMove {
+    pub action: Action,
+    /// Time taken to select the action
+    pub time: Duration,
+    /// Statistics of the searches made to select the action (all zero for strategies that do not search)
+    pub search: SearchTotals,
+}
+
+/// Lines describing the game and the last move
+pub fn game_lines(game: &GameInProgress, last: Option<&LastMove>) -> Vec<String> {
+    let mut lines = vec![
+        format!("Seed:      {}", game.seed()),
+        format!("Move:      {}", game.num_moves()),
+        format!("Score:     {}", game.merge_score()),
+        format!("Max tile:  {}", 1u32 << game.board().board().max_tile()),
+    ];
+    let Some(last) = last else {
+        return lines;
+    };
+    lines.push(String::new());
+    lines.push(format!(
+        "Last move: {:?} in {:.2}ms",
+        last.action,
+        last.time.as_secs_f64() * 1000.0
+    ));
+    lines.extend(search_lines(&last.search));
+    lines
+}
+
+/// Lines of the statistics of the searches made for a decision
+pub fn search_lines(search: &SearchTotals) -> Vec<String> {
+    if search.num_searches == 0 {
+        return vec!["No search statistics (not reported by the strategy)".to_string()];
+    }
+    vec![
+        format!(
+            "Depth:     {:.1} ({} searches)",
+            search.total_depth as f64 / search.num_searches as f64,
+            search.num_searches
+        ),
+        format!("Nodes:     {}", search.num_nodes),
+        format!("Evals:     {}", search.num_evals),
+        format!(
+            "Cache:     {} hits ({:.1}%)",
+            search.num_cache_hits,
+            search.num_cache_hits as f64 / search.num_evals.max(1) as f64 * 100.0
+        ),
+    ]
+}
+
+/// Lines of the contribution of each active heuristic to the evaluation of the board, and of the total
+pub fn eval_lines(board: &PlayableBoard) -> Vec<String> {
+    let breakdown = eval::explain(board.board());
+    let mut lines: Vec<String> = breakdown
+        .terms
+        .iter()
+        .filter(|term| term.weight != 0.0)
+        .map(|term| format!("{:<14} {:>12.1}", term.name, term.contribution))
+        .collect();
+    let base = if breakdown.lost {
+        "base (lost)"
+    } else {
+        "base"
+    };
+    lines.push(format!("{base:<14} {:>12.1}", breakdown.base));
+    lines.push(format!("{:<14} {:>12.1}", "total", breakdown.total()));
+    lines
+}
+
+#[cfg(feature = "tui")]
+mod tui {
+    use std::path::Path;
+    use std::time::Instant;
+
+    use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+    use ratatui::layout::{Constraint, Layout};
+    use ratatui::style::{Color, Style, Stylize};
+    use ratatui::text::{Line, Span};
+    use ratatui::widgets::{Block, Paragraph};
+    use ratatui::Frame;
+
+    use super::*;
+    use crate::board::{tile_color, N};
+    use crate::search;
+    use crate::strategy::Strategy;
+
+    /// Width and height of a tile, in characters
+    const TILE_WIDTH: usize = 8;
+    const TILE_HEIGHT: usize = 3;
+
+    /// Time between two checks of the keyboard while the game does not progress
+    const REFRESH: Duration = Duration::from_millis(100);
+
+    /// Plays the game with the strategy, showing each board for `delay`, until the game is lost or the user quits
+    /// (`q` or `Esc`). The space bar pauses and resumes the game.
+    ///
+    /// With `save`, the game is saved to this file after each move. Returns the game if it is interrupted with
+    /// Ctrl-C before its end, to be saved by the caller.
+    pub fn play(
+        mut game: GameInProgress,
+        strategy: &Strategy,
+        time_per_move: Option<Duration>,
+        delay: Duration,
+        save: Option<&Path>,
+    ) -> anyhow::Result<Option<GameInProgress>> {
+        let mut terminal = ratatui::init();
+        let result = (|| {
+            let mut last = None;
+            let mut paused = false;
+            let mut over = false;
+            loop {
+                terminal.draw(|frame| draw(frame, &game, last.as_ref(), paused, over))?;
+                // wait for `delay` while handling the keys, and forever when paused or at the end of the game
+                let deadline = Instant::now() + delay;
+                loop {
+                    let timeout = deadline.saturating_duration_since(Instant::now());
+                    if !paused && !over && timeout.is_zero() {
+                        break;
+                    }
+                    let wait = if paused || over {
+                        delay.max(REFRESH)
+                    } else {
+                        timeout
+                    };
+                    if !event::poll(wait)? {
+                        continue;
+                    }
+                    let Event::Key(key) = event::read()? else {
+                        continue;
+                    };
+                    if key.kind != KeyEventKind::Press {
+                        continue;
+                    }
+                    // in raw mode, Ctrl-C is a key rather than a signal
+                    if key.code == KeyCode::Char('c')
+                        && key.modifiers.contains(KeyModifiers::CONTROL)
+                    {
+                        return Ok((!over).then_some(game));
+                    }
+                    match key.code {
+                        KeyCode::Char('q') | KeyCode::Esc => return Ok(None),
+                        KeyCode::Char(' ') if !over => {
+                            paused = !paused;
+                            terminal
+                                .draw(|frame| draw(frame, &game, last.as_ref(), paused, over))?;
+                        }
+                        _ => {}
+                    }
+                }
+
+                let board = *game.board();
+                // discard the statistics of previous searches, to only report those of this move
+                search::take_search_totals();
+                let start = Instant::now();
+                let action = match time_per_move {
+                    Some(budget) => strategy.select_action_within(board, budget),
+                    None => strategy.select_action(board),
+                };
+                let Some(action) = action else {
+                    over = true;
+                    continue;
+                };
+                last = Some(LastMove {
+                    action,
+                    time: start.elapsed(),
+                    search: search::take_search_totals(),
+                });
+                game.play(action).expect("invalid action");
+                if let Some(path) = save {
+                    game.save(path)?;
+                }
+            }
+        })();
+        ratatui::restore();
+        result
+    }
+
+    fn draw(
+        frame: &mut Frame,
+        game: &GameInProgress,
+        last: Option<&LastMove>,
+        paused: bool,
+        over: bool,
+    ) {
+        let board_width = (N * TILE_WIDTH + 2) as u16;
+        let board_height = (N * TILE_HEIGHT + 2) as u16;
+        let [left, right] =
+            Layout::horizontal([Constraint::Length(board_width), Constraint::Fill(1)])
+                .areas(frame.area());
+        let [board_area, help] =
+            Layout::vertical([Constraint::Length(board_height), Constraint::Fill(1)]).areas(left);
+        let [stats, evals] =
+            Layout::vertical([Constraint::Length(13), Constraint::Fill(1)]).areas(right);
+
+        let title = match (over, paused) {
+            (true, _) => " GAME OVER ",
+            (false, true) => " Paused ",
+            (false, false) => " 2048 ",
+        };
+        frame.render_widget(
+            Paragraph::new(board_lines(game.board())).block(Block::bordered().title(title)),
+            board_area,
+        );
+        frame.render_widget(
+            Paragraph::new("space: pause/resume\nq, Esc: quit\nCtrl-C: save and quit").dim(),
+            help,
+        );
+        frame.render_widget(
+            Paragraph::new(game_lines(game, last).join("\n"))
+                .block(Block::bordered().title(" Game ")),
+            stats,
+        );
+        frame.render_widget(
+            Paragraph::new(eval_lines(game.board()).join("\n"))
+                .block(Block::bordered().title(" Evaluation ")),
+            evals,
+        );
+    }
+
+    /// Lines drawing the tiles of the board with their colors
+    fn board_lines(board: &PlayableBoard) -> Vec<Line<'static>> {
+        let mut lines = Vec::new();
+        for row in board.board().cells {
+            for line in 0..TILE_HEIGHT {
+                let spans: Vec<Span> = row
+                    .iter()
+                    .map(|&tile| {
+                        let (r, g, b) = tile_color(tile);
+                        let text = if tile != 0 && line == TILE_HEIGHT / 2 {
+                            format!("{:^TILE_WIDTH$}", 1u32 << tile)
+                        } else {
+                            " ".repeat(TILE_WIDTH)
+                        };
+                        // dark text on light tiles, as in the original game
+                        let fg = if tile <= 2 {
+                            Color::Rgb(119, 110, 101)
+                        } else {
+                            Color::White
+                        };
+                        Span::styled(text, Style::new().bg(Color::Rgb(r, g, b)).fg(fg).bold())
+                    })
+                    .collect();
+                lines.push(Line::from(spans));
+            }
+        }
+        lines
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_panel() {
+        let mut game = GameInProgress::new(3);
+        assert_eq!(game_lines(&game, None).len(), 4);
+        let action = crate::board::ALL_ACTIONS
+            .into_iter()
+            .find(|&action| game.board().apply(action).is_some())
+            .unwrap();
+        game.play(action);
+        let last = LastMove {
+            action,
+            time: Duration::from_millis(2),
+            search: SearchTotals {
+                num_searches: 2,
+                num_nodes: 100,
+                num_evals: 50,
+                num_cache_hits: 10,
+                total_depth: 5,
+            },
+        };
+        let lines = game_lines(&game, Some(&last));
+        assert!(lines.contains(&"Move:      1".to_string()));
+        assert!(lines.contains(&"Depth:     2.5 (2 searches)".to_string()));
+        assert!(lines.contains(&"Cache:     10 hits (20.0%)".to_string()));
+        let evals = eval_lines(game.board());
+        assert!(evals.last().unwrap().starts_with("total"));
+    }
+}
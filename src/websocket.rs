@@ -0,0 +1,192 @@
+//! Just enough of the WebSocket protocol (RFC 6455) for the pages of `spectate` and `driver`: the handshake answering
+//! a request read by `server`, and text messages.
+//!
+//! Messages are small and never fragmented. The handshake requires the `server` feature, for the base64 encoding of
+//! its answer.
+
+use std::io::{Read, Write};
+use std::net::TcpStream;
+
+use anyhow::{bail, ensure};
+
+/// Frame of a text message, unmasked as sent by a server
+pub fn text_frame(text: &str) -> Vec<u8> {
+    let mut frame = vec![0x81];
+    let len = text.len();
+    if len < 126 {
+        frame.push(len as u8);
+    } else if len <= u16::MAX as usize {
+        frame.push(126);
+        frame.extend((len as u16).to_be_bytes());
+    } else {
+        frame.push(127);
+        frame.extend((len as u64).to_be_bytes());
+    }
+    frame.extend(text.as_bytes());
+    frame
+}
+
+/// Reads the next text message sent by a client, answering its pings. Returns `None` when the client closes the
+/// connection.
+pub fn read_text(stream: &mut TcpStream) -> anyhow::Result<Option<String>> {
+    loop {
+        let mut header = [0; 2];
+        stream.read_exact(&mut header)?;
+        let opcode = header[0] & 0x0f;
+        ensure!(
+            header[0] & 0x80 != 0,
+            "Fragmented messages are not supported"
+        );
+        let len = match header[1] & 0x7f {
+            126 => {
+                let mut len = [0; 2];
+                stream.read_exact(&mut len)?;
+                u16::from_be_bytes(len) as usize
+            }
+            127 => {
+                let mut len = [0; 8];
+                stream.read_exact(&mut len)?;
+                u64::from_be_bytes(len) as usize
+            }
+            len => len as usize,
+        };
+        // the messages of clients are always masked
+        ensure!(header[1] & 0x80 != 0, "Unmasked message from a client");
+        let mut mask = [0; 4];
+        stream.read_exact(&mut mask)?;
+        let mut payload = vec![0; len];
+        stream.read_exact(&mut payload)?;
+        for (i, byte) in payload.iter_mut().enumerate() {
+            *byte ^= mask[i % 4];
+        }
+        match opcode {
+            0x1 => return Ok(Some(String::from_utf8(payload)?)),
+            0x8 => return Ok(None),
+            // ping, answered by a pong with the same payload
+            0x9 => {
+                let mut pong = vec![0x8a, payload.len() as u8];
+                pong.extend(payload);
+                stream.write_all(&pong)?;
+            }
+            0xa => {}
+            _ => bail!("Unsupported WebSocket frame (opcode {opcode})"),
+        }
+    }
+}
+
+#[cfg(feature = "server")]
+pub(crate) use handshake::accept;
+
+#[cfg(feature = "server")]
+mod handshake {
+    use std::io::Write;
+    use std::net::TcpStream;
+
+    use anyhow::Context;
+    use base64::Engine;
+
+    use crate::server::Request;
+
+    /// GUID appended to the key of the client to accept a WebSocket connection
+    const WEBSOCKET_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+    /// Accepts the WebSocket connection asked by the request.
+    pub(crate) fn accept(stream: &mut TcpStream, request: &Request) -> anyhow::Result<()> {
+        let key = request
+            .headers
+            .get("sec-websocket-key")
+            .context("Not a WebSocket request")?;
+        write!(
+            stream,
+            "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: {}\r\n\r\n",
+            accept_key(key)
+        )?;
+        Ok(())
+    }
+
+    /// Answer of the server to the key of a WebSocket handshake
+    pub(super) fn accept_key(key: &str) -> String {
+        base64::engine::general_purpose::STANDARD
+            .encode(sha1(format!("{key}{WEBSOCKET_GUID}").as_bytes()))
+    }
+
+    /// SHA-1 digest, only used by the handshake (no crate of the dependencies provides it)
+    fn sha1(data: &[u8]) -> [u8; 20] {
+        let mut h: [u32; 5] = [0x67452301, 0xEFCDAB89, 0x98BADCFE, 0x10325476, 0xC3D2E1F0];
+        let mut message = data.to_vec();
+        message.push(0x80);
+        while message.len() % 64 != 56 {
+            message.push(0);
+        }
+        message.extend((data.len() as u64 * 8).to_be_bytes());
+        for block in message.chunks(64) {
+            let mut w = [0u32; 80];
+            for i in 0..16 {
+                w[i] = u32::from_be_bytes(block[4 * i..4 * i + 4].try_into().unwrap());
+            }
+            for i in 16..80 {
+                w[i] = (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1);
+            }
+            let [mut a, mut b, mut c, mut d, mut e] = h;
+            for (i, &wi) in w.iter().enumerate() {
+                let (f, k) = match i {
+                    0..=19 => ((b & c) | (!b & d), 0x5A827999),
+                    20..=39 => (b ^ c ^ d, 0x6ED9EBA1),
+                    40..=59 => ((b & c) | (b & d) | (c & d), 0x8F1BBCDC),
+                    _ => (b ^ c ^ d, 0xCA62C1D6),
+                };
+                let temp = a
+                    .rotate_left(5)
+                    .wrapping_add(f)
+                    .wrapping_add(e)
+                    .wrapping_add(k)
+                    .wrapping_add(wi);
+                (e, d, c, b, a) = (d, c, b.rotate_left(30), a, temp);
+            }
+            for (h, x) in h.iter_mut().zip([a, b, c, d, e]) {
+                *h = h.wrapping_add(x);
+            }
+        }
+        let mut digest = [0; 20];
+        for (chunk, h) in digest.chunks_mut(4).zip(h) {
+            chunk.copy_from_slice(&h.to_be_bytes());
+        }
+        digest
+    }
+}
+
+#[cfg(all(test, feature = "server"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_websocket() {
+        // example of RFC 6455
+        assert_eq!(
+            handshake::accept_key("dGhlIHNhbXBsZSBub25jZQ=="),
+            "s3pPLMBiTxaQ9kYGzzhZRbK+xOo="
+        );
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (mut server, _) = listener.accept().unwrap();
+        // a masked ping, then a masked text message
+        let mask = [1, 2, 3, 4];
+        let masked = |text: &[u8]| -> Vec<u8> {
+            text.iter()
+                .enumerate()
+                .map(|(i, b)| b ^ mask[i % 4])
+                .collect()
+        };
+        let mut frames = vec![0x89, 0x82];
+        frames.extend(mask);
+        frames.extend(masked(b"hi"));
+        frames.extend([0x81, 0x85]);
+        frames.extend(mask);
+        frames.extend(masked(b"hello"));
+        client.write_all(&frames).unwrap();
+        assert_eq!(read_text(&mut server).unwrap().as_deref(), Some("hello"));
+        let mut pong = [0; 4];
+        client.read_exact(&mut pong).unwrap();
+        assert_eq!(pong, [0x8a, 2, b'h', b'i']);
+    }
+}